@@ -7,14 +7,17 @@ use franklin_crypto::bellman::{
 use franklin_crypto::{
     bellman::plonk::better_better_cs::cs::{TrivialAssembly, Width4MainGateWithDNext},
     bellman::Engine,
-    plonk::circuit::Width4WithCustomGates,
+    plonk::circuit::{allocated_num::{AllocatedNum, Num}, Width4WithCustomGates},
 };
 
 use rand::{Rand, SeedableRng, XorShiftRng};
-use rescue_poseidon::generic_round_function;
+use rescue_poseidon::{circuit_generic_hash, generic_round_function, GenericSponge};
+#[cfg(feature = "poseidon2-experimental-widths")]
+use rescue_poseidon::{ConstantsSource, State};
 use rescue_poseidon::{
     PoseidonParams, RescueParams, RescuePrimeParams,
 };
+use rescue_poseidon::poseidon2::{poseidon2_hash_bytes_fast, Poseidon2Params};
 
 fn init_rng() -> XorShiftRng {
     const TEST_SEED: [u32; 4] = [0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654];
@@ -40,6 +43,14 @@ fn test_state_inputs() -> [Fr; 3] {
     }
     inputs
 }
+fn test_wide_state_inputs() -> [Fr; 12] {
+    let rng = &mut init_rng();
+    let mut inputs = [Fr::zero(); 12];
+    for i in 0..12 {
+        inputs[i] = Fr::rand(rng);
+    }
+    inputs
+}
 
 // fn bench_poseidon_round_function_comparison(crit: &mut Criterion) {
 //     let params = PoseidonParams::<Bn256, 2, 3>::default();
@@ -97,6 +108,48 @@ fn bench_poseidon_round_function(crit: &mut Criterion) {
     });
 }
 
+// Lockstep inverse sbox against the per element one it replaced, for both
+// plain exponent and addition chain.
+#[cfg(feature = "bench-internals")]
+fn bench_inverse_sbox(crit: &mut Criterion) {
+    use rescue_poseidon::bench_internals::{inverse_sbox, inverse_sbox_per_element};
+
+    let mut group = crit.benchmark_group("Rescue Inverse Sbox");
+    let params = [
+        ("Exponent", RescueParams::<Bn256, 2, 3>::default()),
+        ("Addition Chain", RescueParams::<Bn256, 2, 3>::specialized_for_num_rounds(8, 120)),
+    ];
+    for (name, params) in params.iter() {
+        group.bench_function(format!("{} Lockstep", name), |b| {
+            let mut state = test_state_inputs();
+            b.iter(|| inverse_sbox(params, &mut state));
+        });
+        group.bench_function(format!("{} Per Element", name), |b| {
+            let mut state = test_state_inputs();
+            b.iter(|| inverse_sbox_per_element(params, &mut state));
+        });
+    }
+    group.finish();
+}
+
+#[cfg(feature = "bench-internals")]
+fn bench_scalar_product(crit: &mut Criterion) {
+    use rescue_poseidon::bench_internals::{scalar_product, scalar_product_single_accumulator};
+
+    let mut group = crit.benchmark_group("Scalar Product");
+    let inputs = test_wide_state_inputs();
+    for len in [3, 12] {
+        let (a, b) = (&inputs[..len], &inputs[12 - len..]);
+        group.bench_function(format!("Width {} Two Accumulators", len), |bencher| {
+            bencher.iter(|| scalar_product::<Bn256>(a, b));
+        });
+        group.bench_function(format!("Width {} Single Accumulator", len), |bencher| {
+            bencher.iter(|| scalar_product_single_accumulator::<Bn256>(a, b));
+        });
+    }
+    group.finish();
+}
+
 fn bench_rescue_prime_round_function(crit: &mut Criterion) {
     let params = RescuePrimeParams::<Bn256, 2, 3>::default();
     crit.bench_function("RescuePrime Round Function", |b| {
@@ -104,11 +157,117 @@ fn bench_rescue_prime_round_function(crit: &mut Criterion) {
     });
 }
 
+fn bench_poseidon2_round_function(crit: &mut Criterion) {
+    let params = Poseidon2Params::<Bn256, 2, 3>::default();
+    crit.bench_function("Poseidon2 Round Function", |b| {
+        b.iter(|| generic_round_function(&params, &mut test_state_inputs()));
+    });
+}
+
+fn bench_poseidon2_hash_bytes(crit: &mut Criterion) {
+    let bytes: Vec<u8> = (0..1024).map(|i| i as u8).collect();
+    crit.bench_function("Poseidon2 Hash 1KiB", |b| {
+        b.iter(|| poseidon2_hash_bytes_fast::<Bn256>(&bytes));
+    });
+}
+
+// Width 12 state takes 384 bytes, plain arrays in a vector straddle 7 cache
+// lines every other element while aligned states always take 6.
+#[cfg(feature = "poseidon2-experimental-widths")]
+fn bench_wide_state(crit: &mut Criterion) {
+    const NUM_STATES: usize = 64;
+    let params = Poseidon2Params::<Bn256, 8, 12>::default();
+    let inputs = test_wide_state_inputs();
+    let mut group = crit.benchmark_group("Width 12 State");
+
+    group.bench_function("Poseidon2 Round Function Array", |b| {
+        let mut state = inputs;
+        b.iter(|| generic_round_function(&params, &mut state));
+    });
+    group.bench_function("Poseidon2 Round Function State", |b| {
+        let mut state = State::<Bn256, 12>::from(inputs);
+        b.iter(|| state.permute(&params));
+    });
+
+    group.bench_function("Poseidon2 Round Function 64 Arrays", |b| {
+        let mut states = vec![inputs; NUM_STATES];
+        b.iter(|| {
+            for state in states.iter_mut() {
+                generic_round_function(&params, state);
+            }
+        });
+    });
+    group.bench_function("Poseidon2 Round Function 64 States", |b| {
+        let mut states = vec![State::<Bn256, 12>::from(inputs); NUM_STATES];
+        b.iter(|| {
+            for state in states.iter_mut() {
+                state.permute(&params);
+            }
+        });
+    });
+
+    let input: Vec<Fr> = (0..NUM_STATES).flat_map(|_| inputs).collect();
+    group.bench_function("Poseidon2 Sponge 768 Elements", |b| {
+        b.iter(|| GenericSponge::<Bn256, 8, 12>::hash(&input, &params, None));
+    });
+    group.finish();
+}
+
+#[cfg(feature = "poseidon2-experimental-widths")]
+fn bench_params_generation(crit: &mut Criterion) {
+    let mut group = crit.benchmark_group("Params Generation");
+    group.sample_size(10);
+    group.bench_function("Poseidon2 Width 12", |b| {
+        b.iter(|| Poseidon2Params::<Bn256, 8, 12>::with_constants_source(ConstantsSource::Blake2s).unwrap());
+    });
+    #[cfg(feature = "blake3")]
+    group.bench_function("Poseidon2 Width 12 Blake3", |b| {
+        b.iter(|| Poseidon2Params::<Bn256, 8, 12>::with_constants_source(ConstantsSource::Blake3Xof).unwrap());
+    });
+    group.finish();
+}
+
+// Synthesis time of a circuit with many hashes is dominated by witness
+// generation and state bookkeeping rather than by the constraint system itself.
+fn bench_circuit_poseidon_hash_synthesis(crit: &mut Criterion) {
+    const NUM_HASHES: usize = 10_000;
+    let params = PoseidonParams::<Bn256, 2, 3>::default();
+    let inputs = test_inputs();
+    let mut group = crit.benchmark_group("Circuit Synthesis");
+    group.sample_size(10);
+    group.bench_function("Poseidon 10k Hashes", |b| {
+        b.iter(|| {
+            let cs = &mut init_cs::<Bn256>();
+            let mut input = [Num::zero(); 2];
+            for (dst, src) in input.iter_mut().zip(inputs.iter()) {
+                *dst = Num::Variable(AllocatedNum::alloc(cs, || Ok(*src)).unwrap());
+            }
+            for _ in 0..NUM_HASHES {
+                let output = circuit_generic_hash(cs, &input, &params, None, None).unwrap();
+                for (dst, lc) in input.iter_mut().zip(output) {
+                    *dst = lc.into_num(cs).unwrap();
+                }
+            }
+        });
+    });
+}
+
 pub fn group(crit: &mut Criterion) {
     bench_rescue_round_function(crit);
     bench_poseidon_round_function(crit);
     bench_rescue_round_function_comparison(crit);
     bench_rescue_round_function_via_addition_chain(crit);
+    #[cfg(feature = "bench-internals")]
+    bench_inverse_sbox(crit);
+    #[cfg(feature = "bench-internals")]
+    bench_scalar_product(crit);
     // bench_poseidon_round_function_comparison(crit);
     bench_rescue_prime_round_function(crit);
+    bench_poseidon2_round_function(crit);
+    bench_poseidon2_hash_bytes(crit);
+    #[cfg(feature = "poseidon2-experimental-widths")]
+    bench_wide_state(crit);
+    #[cfg(feature = "poseidon2-experimental-widths")]
+    bench_params_generation(crit);
+    bench_circuit_poseidon_hash_synthesis(crit);
 }
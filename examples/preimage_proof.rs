@@ -0,0 +1,64 @@
+//! Proves knowledge of a preimage of a public digest with PLONK, from setup
+//! to verification. Circuits with other hashes only differ in params.
+use franklin_crypto::bellman::bn256::{Bn256, Fr};
+use franklin_crypto::bellman::kate_commitment::{Crs, CrsForMonomialForm};
+use franklin_crypto::bellman::plonk::better_better_cs::cs::{
+    Circuit, ProvingAssembly, SetupAssembly, Width4MainGateWithDNext,
+};
+use franklin_crypto::bellman::plonk::better_better_cs::setup::VerificationKey;
+use franklin_crypto::bellman::plonk::better_better_cs::verifier::verify;
+use franklin_crypto::bellman::plonk::commitments::transcript::keccak_transcript::RollingKeccakTranscript;
+use franklin_crypto::bellman::worker::Worker;
+use franklin_crypto::bellman::SynthesisError;
+use franklin_crypto::plonk::circuit::Width4WithCustomGates;
+use rand::{Rand, SeedableRng, XorShiftRng};
+use rescue_poseidon::poseidon2::Poseidon2Params;
+use rescue_poseidon::{HashParams, PreimageCircuit, RescueParams};
+
+const RATE: usize = 2;
+const WIDTH: usize = 3;
+const PREIMAGE_LENGTH: usize = 4;
+
+fn main() {
+    let rng = &mut XorShiftRng::from_seed([0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654]);
+    let preimage = [0; PREIMAGE_LENGTH].map(|_| Fr::rand(rng));
+
+    prove_and_verify(RescueParams::<Bn256, RATE, WIDTH>::default(), preimage).expect("rescue proof");
+    prove_and_verify(Poseidon2Params::<Bn256, RATE, WIDTH>::default(), preimage).expect("poseidon2 proof");
+}
+
+fn prove_and_verify<P: HashParams<Bn256, RATE, WIDTH>>(
+    params: P,
+    preimage: [Fr; PREIMAGE_LENGTH],
+) -> Result<(), SynthesisError> {
+    type Preimage<H> = PreimageCircuit<Bn256, H, RATE, WIDTH, PREIMAGE_LENGTH>;
+    let worker = Worker::new();
+    let family = params.hash_family();
+
+    // setup only needs the shape of the circuit
+    let setup_circuit = Preimage::<P>::new(params.clone());
+    let mut assembly = SetupAssembly::<Bn256, Width4WithCustomGates, Width4MainGateWithDNext>::new();
+    setup_circuit.synthesize(&mut assembly)?;
+    assembly.finalize();
+    let setup = assembly.create_setup::<Preimage<P>>(&worker)?;
+
+    // trusted setup of the real deployment should be used instead
+    let crs = Crs::<Bn256, CrsForMonomialForm>::crs_42(setup.permutation_monomials[0].size(), &worker);
+    let vk = VerificationKey::from_setup(&setup, &worker, &crs)?;
+
+    // prover knows the preimage
+    let circuit = Preimage::<P>::with_preimage(params, preimage);
+    let digest = circuit.digest().expect("preimage is known");
+    let mut assembly = ProvingAssembly::<Bn256, Width4WithCustomGates, Width4MainGateWithDNext>::new();
+    circuit.synthesize(&mut assembly)?;
+    assembly.finalize();
+    let proof = assembly.create_proof::<Preimage<P>, RollingKeccakTranscript<Fr>>(&worker, &setup, &crs, None)?;
+
+    // verifier only sees the digest as public input
+    assert_eq!(proof.inputs, vec![digest]);
+    let valid = verify::<Bn256, Preimage<P>, RollingKeccakTranscript<Fr>>(&vk, &proof, None)?;
+    assert!(valid, "proof should be valid");
+    println!("{:?} preimage proof is valid", family);
+
+    Ok(())
+}
@@ -0,0 +1,7 @@
+#![no_main]
+use libfuzzer_sys::fuzz_target;
+
+// Native and circuit sboxes under every custom gate, see `sbox_differential`.
+fuzz_target!(|data: &[u8]| {
+    rescue_poseidon::fuzzing::sbox_differential(data);
+});
@@ -0,0 +1,236 @@
+use crate::common::matrix::mmul_assign;
+use crate::common::sbox::sbox;
+use crate::sponge::generic_hash;
+use crate::traits::{HashParams, NoopMiddleware, RoundFunctionMiddleware};
+use alloc::vec::Vec;
+use franklin_crypto::bellman::{Engine, Field};
+use super::params::AnemoiParams;
+
+/// Receives inputs whose length `known` prior(fixed-length).
+/// Also uses custom domain strategy which basically sets value of capacity element to
+/// length of input and applies a padding rule which makes input size equals to multiple of
+/// rate parameter.
+/// Uses pre-defined state-width=2 and rate=1.
+pub fn anemoi_hash<E: Engine, const L: usize>(input: &[E::Fr; L]) -> [E::Fr; 1] {
+    const WIDTH: usize = 2;
+    const RATE: usize = 1;
+
+    let params = AnemoiParams::<E, RATE, WIDTH>::default();
+    generic_hash(&params, input, None)
+}
+
+/// Jive 2-to-1 compression with state-width=2, i.e. `x + y + u + v` where
+/// `(u, v)` is the permutation of `(x, y)`. Costs a single permutation, unlike
+/// absorbing both elements into a sponge, so it's meant for Merkle trees.
+pub fn anemoi_jive_compress<E: Engine>(left: &E::Fr, right: &E::Fr) -> E::Fr {
+    let params = AnemoiParams::<E, 1, 2>::default();
+    jive_compress(&params, &[*left, *right])[0]
+}
+
+/// Jive compression of the whole state into `WIDTH / 2` elements: element
+/// `i` of the output is the sum of `X[i]` and `Y[i]` of both the input and
+/// its permutation.
+pub fn jive_compress<E: Engine, const RATE: usize, const WIDTH: usize>(
+    params: &AnemoiParams<E, RATE, WIDTH>,
+    input: &[E::Fr; WIDTH],
+) -> Vec<E::Fr> {
+    let mut state = *input;
+    anemoi_round_function(&mut state, params);
+
+    let columns = WIDTH / 2;
+    (0..columns)
+        .map(|i| {
+            let mut sum = input[i];
+            sum.add_assign(&input[columns + i]);
+            sum.add_assign(&state[i]);
+            sum.add_assign(&state[columns + i]);
+            sum
+        })
+        .collect()
+}
+
+pub fn anemoi_round_function<E: Engine, const RATE: usize, const WIDTH: usize>(
+    state: &mut [E::Fr; WIDTH],
+    params: &AnemoiParams<E, RATE, WIDTH>,
+) {
+    anemoi_round_function_with_middleware(state, params, &mut NoopMiddleware)
+}
+
+pub(crate) fn anemoi_round_function_with_middleware<
+    E: Engine,
+    M: RoundFunctionMiddleware<E, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    state: &mut [E::Fr; WIDTH],
+    params: &AnemoiParams<E, RATE, WIDTH>,
+    middleware: &mut M,
+) {
+    let columns = WIDTH / 2;
+    for round in 0..params.number_of_full_rounds() {
+        state
+            .iter_mut()
+            .zip(params.constants_of_round(round).iter())
+            .for_each(|(s, c)| s.add_assign(c));
+        mmul_assign::<E, WIDTH>(params.mds_matrix(), state);
+
+        for column in 0..columns {
+            let (x, y) = flystel(params, state[column], state[columns + column], |inverse| {
+                middleware.after_inverse_sbox(round, &[inverse])
+            });
+            state[column] = x;
+            state[columns + column] = y;
+        }
+
+        middleware.after_round(round, state);
+    }
+
+    // linear layer at the end
+    mmul_assign::<E, WIDTH>(params.mds_matrix(), state);
+}
+
+// Open Flystel: u = x - Q_gamma(y), v = y - u^(1/d), x' = u + Q_delta(v),
+// y' = v. Inverse sbox output is reported to `on_inverse`.
+fn flystel<E: Engine, const RATE: usize, const WIDTH: usize>(
+    params: &AnemoiParams<E, RATE, WIDTH>,
+    x: E::Fr,
+    y: E::Fr,
+    mut on_inverse: impl FnMut(E::Fr),
+) -> (E::Fr, E::Fr) {
+    let (beta, gamma, delta) = params.flystel_constants();
+    let quadratic = |value: E::Fr, constant: &E::Fr| {
+        let mut result = value;
+        result.square();
+        result.mul_assign(beta);
+        result.add_assign(constant);
+        result
+    };
+
+    let mut u = x;
+    u.sub_assign(&quadratic(y, gamma));
+
+    let mut inverse = [u];
+    sbox::<E>(params.alpha_inv(), &mut inverse);
+    on_inverse(inverse[0]);
+
+    let mut v = y;
+    v.sub_assign(&inverse[0]);
+
+    let mut x = u;
+    x.add_assign(&quadratic(v, delta));
+
+    (x, v)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use franklin_crypto::bellman::{PrimeField, PrimeFieldRepr};
+
+    fn fr_from_hex(value: &str) -> Fr {
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.read_be(&hex::decode(value).unwrap()[..]).unwrap();
+        Fr::from_repr(repr).unwrap()
+    }
+
+    #[test]
+    fn test_anemoi_number_of_rounds() {
+        use crate::common::params::anemoi_number_of_rounds;
+
+        // round numbers of the paper for 128 bits of security
+        for (alpha, rounds) in [(3, [21, 14, 12, 12, 10]), (5, [21, 14, 12, 12, 10]), (7, [20, 13, 12, 11, 10])] {
+            for (columns, rounds) in [1, 2, 3, 4, 6].into_iter().zip(rounds) {
+                assert_eq!(anemoi_number_of_rounds(columns, alpha, 128), rounds);
+            }
+        }
+    }
+
+    #[test]
+    fn test_anemoi_params() {
+        let params = AnemoiParams::<Bn256, 1, 2>::default();
+        assert_eq!(params.number_of_full_rounds(), 21);
+        assert_eq!(params.round_constants.len(), 21);
+        assert!(AnemoiParams::<Bn256, 2, 3>::try_default().is_err());
+
+        // single column linear layer is the pseudo-Hadamard transform
+        let two = Fr::from_str("2").unwrap();
+        assert_eq!(params.mds_matrix, [[two, Fr::one()], [Fr::one(), Fr::one()]]);
+
+        let (beta, gamma, delta) = params.flystel_constants();
+        assert_eq!(*beta, Fr::from_str("5").unwrap());
+        assert!(gamma.is_zero());
+        let mut product = *beta;
+        product.mul_assign(delta);
+        assert_eq!(product, Fr::one());
+
+        // constants of the first round are `C = g + 2^alpha` and `D = C + g^-1`
+        let first = Fr::from_str("37").unwrap();
+        let mut second = first;
+        second.add_assign(delta);
+        assert_eq!(params.round_constants[0], [first, second]);
+
+        let params = AnemoiParams::<Bn256, 2, 4>::default();
+        assert_eq!(params.number_of_full_rounds(), 14);
+        let expected = [
+            "1d851aa3a1ad5cfa398a47d33b9c614f0879e1c79891ea5a21ff8ece689f96a9",
+            "2e6e9ebabafe968e2ccfb7ecedc70edeef126d839c6ddff45bac444d8358ecc1",
+            "0b9600e2b2bdd62adf15f55c26da154bd4ca3e19ff98c7fc971568d5215b9e2a",
+            "2483f03382e005595489a92ec80ce985b3da102902c588898d5f648854fcd7fb",
+        ];
+        assert_eq!(params.round_constants[1], expected.map(fr_from_hex));
+
+        assert_eq!(params.fingerprint(), AnemoiParams::<Bn256, 2, 4>::default().fingerprint());
+    }
+
+    #[test]
+    fn test_anemoi_permutation_kat() {
+        let params = AnemoiParams::<Bn256, 1, 2>::default();
+        let mut state = [Fr::zero(), Fr::one()];
+        anemoi_round_function(&mut state, &params);
+        let expected = [
+            "0808e3921fc7a9cc2158eab2c805f80d33ff254237fe6b2ce06f83572b833eab",
+            "0107063a755b95efa530e745b35b8fbcce2a26d3b92bb12ee2c34b3a92719d01",
+        ];
+        assert_eq!(state, expected.map(fr_from_hex));
+
+        let params = AnemoiParams::<Bn256, 2, 4>::default();
+        let mut state = [0, 1, 2, 3].map(|value| Fr::from_str(&value.to_string()).unwrap());
+        anemoi_round_function(&mut state, &params);
+        let expected = [
+            "2cb43c79daf0f8fb5e76e76711d860311b0926ffe297b8315c87710eb31864d9",
+            "1c01ee71abcbc1adeb777fdd5fcb24fd4e2293d9eb632a54ec63721f381bd2ad",
+            "1acd84307c0d7207d8866dbe05090f8a3fa0cde918a2985e92f27820317d652d",
+            "1057e76e5f1f4890261614f8f471240616d8c6a1245bff093d36b10f1161dfb3",
+        ];
+        assert_eq!(state, expected.map(fr_from_hex));
+    }
+
+    #[test]
+    fn test_anemoi_hash_kat() {
+        // permutation of the reference in the sponge of this crate, capacity is
+        // specialized with input length
+        let input = [Fr::one()];
+        let expected = "041bde7a5fe8a34ac21279946c1a6acb9bd0dc4d6b7f5e3e5ae95b33a39aa600";
+        assert_eq!(anemoi_hash::<Bn256, 1>(&input), [fr_from_hex(expected)]);
+
+        let input = [1, 2, 3].map(|value| Fr::from_str(&value.to_string()).unwrap());
+        let expected = "0a2174fa40078889bcf8d63d859876b8faa61563795928e548af56d4e04626fd";
+        assert_eq!(anemoi_hash::<Bn256, 3>(&input), [fr_from_hex(expected)]);
+    }
+
+    #[test]
+    fn test_anemoi_jive_compress_kat() {
+        let (left, right) = (Fr::one(), Fr::from_str("2").unwrap());
+        let expected = "1858ff7072240adc41b63d1bef2acdc623fea99100cfabed2f283c98a7d80470";
+        assert_eq!(anemoi_jive_compress::<Bn256>(&left, &right), fr_from_hex(expected));
+
+        let params = AnemoiParams::<Bn256, 2, 4>::default();
+        let input = [0, 1, 2, 3].map(|value| Fr::from_str(&value.to_string()).unwrap());
+        let expected = [
+            "171d723775cccad97ead0f6e9560175e32760ca08180dffeab97f39af495ca07",
+            "2c59d5e00aeb0a3e118d94d6543c490364fb5a7b0fbf295e299a232e497db264",
+        ];
+        assert_eq!(jive_compress(&params, &input), expected.map(fr_from_hex).to_vec());
+    }
+}
@@ -0,0 +1,5 @@
+pub(crate) mod anemoi;
+pub mod params;
+
+pub use self::anemoi::*;
+pub use self::params::{AnemoiParams, AnemoiParamsError, ANEMOI_SUPPORTED_WIDTHS};
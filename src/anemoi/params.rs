@@ -0,0 +1,268 @@
+use alloc::vec::Vec;
+use franklin_crypto::bellman::pairing::bn256::Bn256;
+use franklin_crypto::bellman::{Engine, Field, PrimeField, ScalarEngine};
+
+use crate::common::fingerprint::Fingerprint;
+use crate::common::params::anemoi_number_of_rounds;
+use crate::traits::{CustomGate, HashFamily, HashParams, Sbox};
+
+/// First and second hundred decimal digits of pi after the point, round
+/// constants are derived from their powers as in the reference implementation.
+pub(crate) const PI_0: &str = "1415926535897932384626433832795028841971693993751058209749445923078164062862089986280348253421170679";
+pub(crate) const PI_1: &str = "8214808651328230664709384460955058223172535940812848111745028410270193852110555964462294895493038196";
+
+/// State widths Anemoi params can be constructed for, i.e. one or two
+/// columns of Flystel.
+pub const ANEMOI_SUPPORTED_WIDTHS: &[usize] = &[2, 4];
+
+/// Params of the Anemoi permutation https://eprint.iacr.org/2022/840. State is
+/// split into halves `X = state[..WIDTH / 2]` and `Y = state[WIDTH / 2..]`,
+/// each column `(X[i], Y[i])` goes through the open Flystel with
+/// `Q_gamma(y) = beta * y^2 + gamma` and `Q_delta(y) = beta * y^2 + delta`.
+/// Constants are derived as by the reference implementation.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct AnemoiParams<E: Engine, const RATE: usize, const WIDTH: usize> {
+    #[serde(serialize_with = "crate::serialize_array_of_arrays")]
+    #[serde(deserialize_with = "crate::deserialize_array_of_arrays")]
+    pub(crate) mds_matrix: [[E::Fr; WIDTH]; WIDTH],
+    #[serde(serialize_with = "crate::serialize_vec_of_arrays")]
+    #[serde(deserialize_with = "crate::deserialize_vec_of_arrays")]
+    pub(crate) round_constants: Vec<[E::Fr; WIDTH]>,
+    // beta, gamma and delta of the Flystel quadratics
+    pub(crate) flystel_constants: (E::Fr, E::Fr, E::Fr),
+    pub(crate) alpha: Sbox,
+    pub(crate) alpha_inv: Sbox,
+    pub(crate) rounds: usize,
+    pub(crate) custom_gate: CustomGate,
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> PartialEq for AnemoiParams<E, RATE, WIDTH> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash_family() == other.hash_family()
+    }
+}
+
+/// Errors of constructing Anemoi params.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum AnemoiParamsError {
+    UnsupportedWidth(usize),
+}
+
+impl core::fmt::Display for AnemoiParamsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnsupportedWidth(width) => write!(
+                f,
+                "Anemoi is not supported for width {}, supported widths are {:?}",
+                width, ANEMOI_SUPPORTED_WIDTHS
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for AnemoiParamsError {}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> Default for AnemoiParams<E, RATE, WIDTH> {
+    /// Panics for widths out of `ANEMOI_SUPPORTED_WIDTHS`, see `try_default`.
+    fn default() -> Self {
+        Self::try_default().unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> AnemoiParams<E, RATE, WIDTH> {
+    /// Default params, unsupported width is reported here instead of in the
+    /// middle of the first hash.
+    pub fn try_default() -> Result<Self, AnemoiParamsError> {
+        if !ANEMOI_SUPPORTED_WIDTHS.contains(&WIDTH) {
+            return Err(AnemoiParamsError::UnsupportedWidth(WIDTH));
+        }
+        let security_level = 128;
+        let alpha = 5u64;
+        let rounds = anemoi_number_of_rounds(WIDTH / 2, alpha, security_level);
+        let alpha_inv = crate::common::utils::compute_gcd_vec::<E>(alpha).expect("inverse of alpha");
+
+        let beta = flystel_generator::<E>();
+        let gamma = E::Fr::zero();
+        let delta = beta.inverse().expect("generator is not zero");
+        let flystel_constants = (beta, gamma, delta);
+
+        Ok(Self {
+            mds_matrix: linear_layer_matrix::<E, WIDTH>(&beta),
+            round_constants: round_constants::<E, WIDTH>(rounds, alpha, &flystel_constants),
+            flystel_constants,
+            alpha: Sbox::Alpha(alpha),
+            alpha_inv: Sbox::AlphaInverse(alpha_inv, alpha),
+            rounds,
+            custom_gate: CustomGate::None,
+        })
+    }
+
+    /// `beta`, `gamma` and `delta` of the Flystel quadratics.
+    pub fn flystel_constants(&self) -> &(E::Fr, E::Fr, E::Fr) {
+        &self.flystel_constants
+    }
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> HashParams<E, RATE, WIDTH> for AnemoiParams<E, RATE, WIDTH> {
+    fn hash_family(&self) -> HashFamily {
+        HashFamily::Anemoi
+    }
+
+    /// Constants added at the beginning of the round, `X` gets the first half
+    /// and `Y` the second one.
+    fn constants_of_round(&self, round: usize) -> &[E::Fr; WIDTH] {
+        &self.round_constants[round]
+    }
+
+    /// Whole linear layer, i.e. the column mixing of both halves followed by
+    /// the pseudo-Hadamard transform, as a single matrix.
+    fn mds_matrix(&self) -> &[[E::Fr; WIDTH]; WIDTH] {
+        &self.mds_matrix
+    }
+
+    fn number_of_full_rounds(&self) -> usize {
+        self.rounds
+    }
+
+    fn number_of_partial_rounds(&self) -> usize {
+        0
+    }
+
+    fn alpha(&self) -> &Sbox {
+        &self.alpha
+    }
+
+    fn alpha_inv(&self) -> &Sbox {
+        &self.alpha_inv
+    }
+
+    fn optimized_round_constants(&self) -> &[[E::Fr; WIDTH]] {
+        unimplemented!("Anemoi doesn't use optimized constants")
+    }
+
+    fn optimized_mds_matrixes(&self) -> (&[[E::Fr; WIDTH]; WIDTH], &[[[E::Fr; WIDTH]; WIDTH]]) {
+        unimplemented!("Anemoi doesn't use optimized matrixes")
+    }
+
+    fn custom_gate(&self) -> CustomGate {
+        self.custom_gate
+    }
+
+    fn use_custom_gate(&mut self, custom_gate: CustomGate) {
+        self.custom_gate = custom_gate;
+    }
+
+    fn fingerprint(&self) -> [u8; 32] {
+        let mut fingerprint = Fingerprint::new(self.hash_family(), RATE, WIDTH);
+        fingerprint.update_usize(self.rounds);
+        fingerprint.update_matrix::<E, WIDTH>(&self.mds_matrix);
+        for constants in self.round_constants.iter() {
+            fingerprint.update_frs::<E>(constants);
+        }
+        let (beta, gamma, delta) = &self.flystel_constants;
+        fingerprint.update_frs::<E>(&[*beta, *gamma, *delta]);
+        fingerprint.update_sbox(&self.alpha);
+        fingerprint.update_sbox(&self.alpha_inv);
+
+        fingerprint.finalize()
+    }
+
+    fn try_to_anemoi_params(&self) -> Option<&AnemoiParams<E, RATE, WIDTH>> {
+        Some(self)
+    }
+}
+
+// The reference takes the multiplicative generator from Sage, i.e. the
+// smallest primitive root. It is 5 for BN254 where `ff` has 7, for BLS12-381
+// both are 7, other fields use the generator of `ff`.
+fn flystel_generator<E: Engine>() -> E::Fr {
+    if E::Fr::char().as_ref() == <Bn256 as ScalarEngine>::Fr::char().as_ref() {
+        E::Fr::from_str("5").unwrap()
+    } else {
+        E::Fr::multiplicative_generator()
+    }
+}
+
+// `C[r][i] = Q_gamma(pi_0^r) + (pi_0^r + pi_1^i)^alpha` for `X` and
+// `D[r][i] = Q_delta(pi_1^i) + (pi_0^r + pi_1^i)^alpha` for `Y`, pi digits are
+// reduced modulo the field characteristic.
+fn round_constants<E: Engine, const WIDTH: usize>(
+    rounds: usize,
+    alpha: u64,
+    (beta, gamma, delta): &(E::Fr, E::Fr, E::Fr),
+) -> Vec<[E::Fr; WIDTH]> {
+    let pi_0 = E::Fr::from_str(PI_0).unwrap();
+    let pi_1 = E::Fr::from_str(PI_1).unwrap();
+    let quadratic = |value: &E::Fr, constant: &E::Fr| {
+        let mut result = *value;
+        result.square();
+        result.mul_assign(beta);
+        result.add_assign(constant);
+        result
+    };
+
+    let columns = WIDTH / 2;
+    (0..rounds)
+        .map(|round| {
+            let pi_0_r = pi_0.pow(&[round as u64]);
+            let mut constants = [E::Fr::zero(); WIDTH];
+            for i in 0..columns {
+                let pi_1_i = pi_1.pow(&[i as u64]);
+                let mut power = pi_0_r;
+                power.add_assign(&pi_1_i);
+                let power = power.pow(&[alpha]);
+
+                constants[i] = quadratic(&pi_0_r, gamma);
+                constants[i].add_assign(&power);
+                constants[columns + i] = quadratic(&pi_1_i, delta);
+                constants[columns + i].add_assign(&power);
+            }
+            constants
+        })
+        .collect()
+}
+
+// Columns of the matrix are images of unit vectors under the linear layer:
+// each half is multiplied by [[1, g], [g, g^2 + 1]] (identity for a single
+// column), `Y` rotated left by one first, then `Y += X` and `X += Y`.
+fn linear_layer_matrix<E: Engine, const WIDTH: usize>(generator: &E::Fr) -> [[E::Fr; WIDTH]; WIDTH] {
+    let columns = WIDTH / 2;
+    let mix = |half: &mut [E::Fr]| {
+        if columns == 2 {
+            let mut first = half[1];
+            first.mul_assign(generator);
+            first.add_assign(&half[0]);
+
+            let mut second = half[1];
+            second.mul_assign(generator);
+            second.add_assign(&half[0]);
+            second.mul_assign(generator);
+            second.add_assign(&half[1]);
+
+            half[0] = first;
+            half[1] = second;
+        }
+    };
+
+    let mut matrix = [[E::Fr::zero(); WIDTH]; WIDTH];
+    for col in 0..WIDTH {
+        let mut state = [E::Fr::zero(); WIDTH];
+        state[col] = E::Fr::one();
+
+        let (x, y) = state.split_at_mut(columns);
+        mix(x);
+        y.rotate_left(1);
+        mix(y);
+        for (x, y) in x.iter_mut().zip(y.iter_mut()) {
+            y.add_assign(x);
+            x.add_assign(y);
+        }
+
+        for (row, value) in state.iter().enumerate() {
+            matrix[row][col] = *value;
+        }
+    }
+
+    matrix
+}
@@ -0,0 +1,303 @@
+//! Native Poseidon and Rescue permutations over the Bn256 scalar field with
+//! Montgomery multiplication and squaring in x86_64 assembly, generated by
+//! the `asm_derive` feature of `ff_ce`. The assembly relies on ADX and BMI2
+//! instructions, so it is only taken on CPUs which have them. Other CPUs,
+//! engines and hash families keep the portable arithmetic.
+//!
+//! Both fields keep elements in Montgomery form with the same `R = 2^256`,
+//! so elements and constants are converted by copying limbs.
+use crate::common::sbox::{sbox_alpha, sbox_alpha_inv, sbox_alpha_inv_via_add_chain_lockstep};
+use crate::ff::{Field, PrimeField};
+use crate::traits::{HashFamily, HashParams, Sbox};
+use core::any::Any;
+use franklin_crypto::bellman::pairing::bn256;
+use franklin_crypto::bellman::Engine;
+
+// code of the derive isn't held to clippy lints
+#[allow(clippy::all)]
+mod fr {
+    use crate::ff::{Field, PrimeField, PrimeFieldAsm, PrimeFieldRepr};
+
+    #[derive(PrimeFieldAsm)]
+    #[PrimeFieldModulus = "21888242871839275222246405745257275088548364400416034343698204186575808495617"]
+    #[PrimeFieldGenerator = "7"]
+    #[UseADX = "true"]
+    pub struct Fr(FrRepr);
+
+    impl Fr {
+        // Limbs must be a reduced element in Montgomery form.
+        #[inline(always)]
+        pub(super) fn from_reduced_limbs(limbs: [u64; 4]) -> Self {
+            Fr(FrRepr(limbs))
+        }
+    }
+}
+
+use self::fr::Fr;
+
+fn cpu_supports_asm() -> bool {
+    std::is_x86_feature_detected!("adx") && std::is_x86_feature_detected!("bmi2")
+}
+
+/// Applies the permutation of `params` to `state` with the assembly
+/// arithmetic. Returns `false` without touching `state` if either the CPU,
+/// the engine or the hash family isn't supported.
+pub(crate) fn try_round_function<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    state: &mut [E::Fr; WIDTH],
+) -> bool {
+    let Some(state) = (state as &mut dyn Any).downcast_mut::<[bn256::Fr; WIDTH]>() else {
+        return false;
+    };
+    let round_function = match params.hash_family() {
+        HashFamily::Poseidon => poseidon_round_function::<E, P, RATE, WIDTH>,
+        HashFamily::Rescue => rescue_round_function::<E, P, RATE, WIDTH>,
+        _ => return false,
+    };
+    if !cpu_supports_asm() {
+        return false;
+    }
+
+    let mut asm_state = state.map(|el| to_asm(&el));
+    round_function(params, &mut asm_state);
+    *state = asm_state.map(|el| from_asm(&el));
+
+    true
+}
+
+// Only called for elements of the Bn256 scalar field.
+#[inline(always)]
+fn to_asm<F: PrimeField>(el: &F) -> Fr {
+    let mut limbs = [0u64; 4];
+    limbs.copy_from_slice(el.into_raw_repr().as_ref());
+
+    Fr::from_reduced_limbs(limbs)
+}
+
+#[inline(always)]
+fn from_asm(el: &Fr) -> bn256::Fr {
+    bn256::Fr::from_raw_repr(bn256::FrRepr(el.into_raw_repr().0)).expect("element of Bn256 scalar field")
+}
+
+fn sbox(power: &Sbox, state: &mut [Fr]) {
+    match power {
+        Sbox::Alpha(alpha) => sbox_alpha(alpha, state),
+        Sbox::AlphaInverse(alpha_inv, _) => sbox_alpha_inv(alpha_inv, state),
+        Sbox::AddChain(chain, _) => sbox_alpha_inv_via_add_chain_lockstep(chain, state),
+    }
+}
+
+fn add_constants<E: Engine, const WIDTH: usize>(state: &mut [Fr; WIDTH], constants: &[E::Fr; WIDTH]) {
+    for (s, c) in state.iter_mut().zip(constants.iter()) {
+        s.add_assign(&to_asm(c));
+    }
+}
+
+fn scalar_product<E: Engine>(a: &[Fr], b: &[E::Fr]) -> Fr {
+    let mut acc = Fr::zero();
+    for (a, b) in a.iter().zip(b.iter()) {
+        let mut tmp = to_asm(b);
+        tmp.mul_assign(a);
+        acc.add_assign(&tmp);
+    }
+
+    acc
+}
+
+fn mmul_assign<E: Engine, const WIDTH: usize>(matrix: &[[E::Fr; WIDTH]; WIDTH], state: &mut [Fr; WIDTH]) {
+    let mut result = [Fr::zero(); WIDTH];
+    for (r, row) in result.iter_mut().zip(matrix.iter()) {
+        *r = scalar_product::<E>(state, row);
+    }
+    *state = result;
+}
+
+// circ(2, 1, 1) adds the sum of all elements to each of them
+fn circular_mmul_assign<const WIDTH: usize>(state: &mut [Fr; WIDTH]) {
+    debug_assert_eq!(WIDTH, 3);
+    let mut sum = Fr::zero();
+    state.iter().for_each(|el| sum.add_assign(el));
+    state.iter_mut().for_each(|el| el.add_assign(&sum));
+}
+
+// Same rounds as `poseidon_round_function_with_middleware`.
+fn poseidon_round_function<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    state: &mut [Fr; WIDTH],
+) {
+    debug_assert!(params.number_of_full_rounds() & 1 == 0);
+    let half_of_full_rounds = params.number_of_full_rounds() / 2;
+    let circular = params.uses_circular_full_rounds_mds();
+    let full_round_matmul = |state: &mut [Fr; WIDTH]| {
+        if circular {
+            circular_mmul_assign(state);
+        } else {
+            mmul_assign::<E, WIDTH>(params.mds_matrix(), state);
+        }
+    };
+
+    let optimized_round_constants = params.optimized_round_constants();
+    let (m_prime, sparse_matrixes) = params.optimized_mds_matrixes();
+
+    for (round, constants) in optimized_round_constants[..half_of_full_rounds].iter().enumerate() {
+        add_constants::<E, WIDTH>(state, constants);
+        sbox(params.alpha(), state);
+        // circular matrix of the last round is folded into M'
+        if !circular || round + 1 < half_of_full_rounds {
+            full_round_matmul(state);
+        }
+    }
+
+    add_constants::<E, WIDTH>(state, &optimized_round_constants[half_of_full_rounds]);
+    mmul_assign::<E, WIDTH>(m_prime, state);
+
+    let zero_constants = [E::Fr::zero(); WIDTH];
+    for (round_constants, sparse_matrix) in optimized_round_constants
+        [half_of_full_rounds + 1..half_of_full_rounds + params.number_of_partial_rounds()]
+        .iter()
+        .chain(core::iter::once(&zero_constants))
+        .zip(sparse_matrixes.iter())
+    {
+        let mut quad = state[0];
+        quad.square();
+        quad.square();
+        state[0].mul_assign(&quad);
+
+        state[0].add_assign(&to_asm(&round_constants[0]));
+
+        // sparse matrix is the first row and column over the identity
+        let first = state[0];
+        state[0] = scalar_product::<E>(state, &sparse_matrix[0]);
+        for (s, row) in state[1..].iter_mut().zip(sparse_matrix[1..].iter()) {
+            let mut tmp = to_asm(&row[0]);
+            tmp.mul_assign(&first);
+            s.add_assign(&tmp);
+        }
+    }
+
+    for constants in &optimized_round_constants[params.number_of_partial_rounds() + half_of_full_rounds
+        ..params.number_of_partial_rounds() + params.number_of_full_rounds()]
+    {
+        add_constants::<E, WIDTH>(state, constants);
+        sbox(params.alpha(), state);
+        full_round_matmul(state);
+    }
+}
+
+// Same rounds as `rescue_round_function_with_middleware`, specialized params
+// multiply by circ(2, 1, 1).
+fn rescue_round_function<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    state: &mut [Fr; WIDTH],
+) {
+    add_constants::<E, WIDTH>(state, params.constants_of_round(0));
+
+    for round in 0..2 * params.number_of_full_rounds() {
+        if round & 1 == 0 {
+            sbox(params.alpha_inv(), state);
+        } else {
+            sbox(params.alpha(), state);
+        }
+
+        if params.allows_specialization() {
+            circular_mmul_assign(state);
+        } else {
+            mmul_assign::<E, WIDTH>(params.mds_matrix(), state);
+        }
+        add_constants::<E, WIDTH>(state, params.constants_of_round(round + 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon::params::PoseidonParams;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::rescue::params::RescueParams;
+    use crate::sponge::generic_round_function_with_middleware;
+    use crate::tests::init_rng;
+    use crate::traits::NoopMiddleware;
+    use franklin_crypto::bellman::pairing::bls12_381::{self, Bls12};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::{Rand, Rng};
+
+    fn random_state<F: Rand, R: Rng, const WIDTH: usize>(rng: &mut R) -> [F; WIDTH] {
+        [(); WIDTH].map(|_| F::rand(rng))
+    }
+
+    // compares the assembly permutation with the portable one, it is only
+    // taken on CPUs with ADX and BMI2
+    fn check_against_portable<P: HashParams<Bn256, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(params: &P) {
+        let rng = &mut init_rng();
+        for _ in 0..16 {
+            let mut expected: [Fr; WIDTH] = random_state(rng);
+            let mut actual = expected;
+            generic_round_function_with_middleware(params, &mut expected, &mut NoopMiddleware);
+            assert_eq!(try_round_function(params, &mut actual), cpu_supports_asm());
+            if cpu_supports_asm() {
+                assert_eq!(actual, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_asm_field_arithmetic() {
+        let rng = &mut init_rng();
+        for _ in 0..1000 {
+            let (a, b) = (Fr::rand(rng), Fr::rand(rng));
+            assert_eq!(from_asm(&to_asm(&a)), a);
+            if !cpu_supports_asm() {
+                continue;
+            }
+
+            let (mut asm_a, asm_b) = (to_asm(&a), to_asm(&b));
+            let mut expected = a;
+            expected.mul_assign(&b);
+            asm_a.mul_assign(&asm_b);
+            assert_eq!(from_asm(&asm_a), expected);
+
+            expected.square();
+            asm_a.square();
+            assert_eq!(from_asm(&asm_a), expected);
+
+            expected.add_assign(&b);
+            asm_a.add_assign(&asm_b);
+            assert_eq!(from_asm(&asm_a), expected);
+
+            expected.sub_assign(&a);
+            asm_a.sub_assign(&to_asm(&a));
+            assert_eq!(from_asm(&asm_a), expected);
+        }
+        let mut minus_one = Fr::one();
+        minus_one.negate();
+        assert_eq!(from_asm(&to_asm(&minus_one)), minus_one);
+    }
+
+    #[test]
+    fn test_asm_poseidon_round_function() {
+        check_against_portable(&PoseidonParams::<Bn256, 2, 3>::default());
+        check_against_portable(&PoseidonParams::<Bn256, 2, 3>::with_circular_mds());
+        check_against_portable(&PoseidonParams::<Bn256, 4, 5>::default());
+    }
+
+    #[test]
+    fn test_asm_rescue_round_function() {
+        check_against_portable(&RescueParams::<Bn256, 2, 3>::default());
+        check_against_portable(&RescueParams::<Bn256, 2, 3>::specialized_for_num_rounds(8, 120));
+        check_against_portable(&RescueParams::<Bn256, 3, 4>::default());
+    }
+
+    #[test]
+    fn test_asm_falls_back_to_portable() {
+        let rng = &mut init_rng();
+        let mut state: [Fr; 3] = random_state(rng);
+        let copy = state;
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        assert!(!try_round_function(&params, &mut state));
+        assert_eq!(state, copy);
+
+        let params = PoseidonParams::<Bls12, 2, 3>::default();
+        let mut state: [bls12_381::Fr; 3] = random_state(rng);
+        assert!(!try_round_function(&params, &mut state));
+    }
+}
@@ -0,0 +1,122 @@
+//! Commit-reveal randomness beacon over the sponge.
+//!
+//! Each participant publishes `commit_reveal(reveal)` first and the reveal
+//! itself later. Reveals of an epoch are folded by a hash chain
+//! `acc_0 = H(FOLD_TAG)`, `acc_{i+1} = H(acc_i, i, reveal_i)` where `H` is
+//! a fixed length hash, and the last accumulator is the beacon output. Index
+//! of a reveal is part of the step, so a reveal can not be moved to another
+//! position.
+use crate::common::utils::{ct_eq_digest, fe_from_u64};
+use crate::sponge::GenericSponge;
+use crate::traits::HashParams;
+use alloc::vec::Vec;
+use franklin_crypto::bellman::Engine;
+
+/// Domain tag of reveal commitments.
+pub const BEACON_COMMITMENT_DOMAIN_TAG: u64 = u64::from_be_bytes(*b"BcnCommt");
+
+/// Domain tag of the initial accumulator of the fold.
+pub const BEACON_FOLD_DOMAIN_TAG: u64 = u64::from_be_bytes(*b"BcnFold\0");
+
+/// Proof that a reveal is folded into a beacon output at a given position.
+#[derive(Clone, Debug)]
+pub struct RevealInclusionProof<E: Engine> {
+    pub index: usize,
+    /// Accumulator right before the reveal is folded.
+    pub prefix: E::Fr,
+    /// Reveals folded after the reveal.
+    pub suffix: Vec<E::Fr>,
+}
+
+/// Commitment to a reveal which is published before the reveal itself.
+pub fn commit_reveal<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    reveal: &E::Fr,
+) -> E::Fr {
+    let tag = fe_from_u64::<E>(BEACON_COMMITMENT_DOMAIN_TAG);
+
+    GenericSponge::<E, RATE, WIDTH>::hash(&[tag, *reveal], params, None)[0]
+}
+
+/// Checks that reveal opens the commitment.
+pub fn verify_reveal<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    commitment: &E::Fr,
+    reveal: &E::Fr,
+) -> bool {
+    ct_eq_digest::<E, 1>(&[commit_reveal::<E, P, RATE, WIDTH>(params, reveal)], &[*commitment])
+}
+
+/// Folds reveals of an epoch into the beacon output.
+pub fn fold_reveals<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    reveals: &[E::Fr],
+) -> E::Fr {
+    fold_from::<E, P, RATE, WIDTH>(params, initial_accumulator::<E, P, RATE, WIDTH>(params), 0, reveals)
+}
+
+/// Produces a proof that `reveals[index]` is folded into `fold_reveals(reveals)`.
+pub fn prove_reveal_inclusion<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    reveals: &[E::Fr],
+    index: usize,
+) -> RevealInclusionProof<E> {
+    assert!(index < reveals.len(), "index is out of range");
+    let prefix = fold_from::<E, P, RATE, WIDTH>(
+        params,
+        initial_accumulator::<E, P, RATE, WIDTH>(params),
+        0,
+        &reveals[..index],
+    );
+
+    RevealInclusionProof {
+        index,
+        prefix,
+        suffix: reveals[index + 1..].to_vec(),
+    }
+}
+
+/// Checks that `reveal` is folded into `beacon` at position `proof.index`.
+pub fn verify_reveal_inclusion<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    beacon: &E::Fr,
+    reveal: &E::Fr,
+    proof: &RevealInclusionProof<E>,
+) -> bool {
+    let current = fold_step::<E, P, RATE, WIDTH>(params, &proof.prefix, proof.index, reveal);
+
+    let folded = fold_from::<E, P, RATE, WIDTH>(params, current, proof.index + 1, &proof.suffix);
+
+    ct_eq_digest::<E, 1>(&[folded], &[*beacon])
+}
+
+pub(crate) fn initial_accumulator<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+) -> E::Fr {
+    GenericSponge::<E, RATE, WIDTH>::hash(&[fe_from_u64::<E>(BEACON_FOLD_DOMAIN_TAG)], params, None)[0]
+}
+
+fn fold_from<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    mut acc: E::Fr,
+    first_index: usize,
+    reveals: &[E::Fr],
+) -> E::Fr {
+    for (i, reveal) in reveals.iter().enumerate() {
+        acc = fold_step::<E, P, RATE, WIDTH>(params, &acc, first_index + i, reveal);
+    }
+
+    acc
+}
+
+fn fold_step<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    acc: &E::Fr,
+    index: usize,
+    reveal: &E::Fr,
+) -> E::Fr {
+    let index = fe_from_u64::<E>(index as u64);
+
+    GenericSponge::<E, RATE, WIDTH>::hash(&[*acc, index, *reveal], params, None)[0]
+}
+
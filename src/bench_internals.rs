@@ -0,0 +1,32 @@
+//! Optimized internals next to the straightforward versions they replaced,
+//! so `benches/` can compare both. Not a stable API.
+use crate::common::sbox::{sbox, sbox_alpha_inv_per_element};
+use crate::common::utils;
+use crate::traits::HashParams;
+use franklin_crypto::bellman::Engine;
+
+/// Inverse sbox of `params`, as applied by round functions.
+pub fn inverse_sbox<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    state: &mut [E::Fr; WIDTH],
+) {
+    sbox::<E>(params.alpha_inv(), state);
+}
+
+/// Inverse sbox of `params` applied to one element after another.
+pub fn inverse_sbox_per_element<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    state: &mut [E::Fr; WIDTH],
+) {
+    sbox_alpha_inv_per_element::<E>(params.alpha_inv(), state);
+}
+
+/// Scalar product used by matrix multiplications.
+pub fn scalar_product<E: Engine>(a: &[E::Fr], b: &[E::Fr]) -> E::Fr {
+    utils::scalar_product::<E>(a, b)
+}
+
+/// Scalar product with a single accumulator.
+pub fn scalar_product_single_accumulator<E: Engine>(a: &[E::Fr], b: &[E::Fr]) -> E::Fr {
+    utils::scalar_product_single_accumulator::<E>(a, b)
+}
@@ -0,0 +1,139 @@
+use crate::common::utils::fe_from_u64;
+use crate::merkle::{hash_leaf, verify_path, MerkleLayers};
+use crate::traits::HashParams;
+use alloc::vec::Vec;
+use franklin_crypto::bellman::Engine;
+
+/// Commitment to a vector split into fixed size chunks.
+/// Each chunk is hashed together with its index into a leaf of a Merkle tree,
+/// so updating a single chunk only recomputes the path from its leaf to the
+/// root. Number of chunks must be a power of two.
+#[derive(Clone, Debug)]
+pub struct ChunkedCommitment<E: Engine, const RATE: usize, const WIDTH: usize> {
+    chunk_size: usize,
+    chunks: Vec<Vec<E::Fr>>,
+    tree: MerkleLayers<E>,
+}
+
+/// Opening of a single chunk of a committed vector.
+#[derive(Clone, Debug)]
+pub struct ChunkOpening<E: Engine> {
+    pub index: usize,
+    pub chunk: Vec<E::Fr>,
+    pub path: Vec<E::Fr>,
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> ChunkedCommitment<E, RATE, WIDTH> {
+    pub fn new<P: HashParams<E, RATE, WIDTH>>(values: &[E::Fr], chunk_size: usize, params: &P) -> Self {
+        assert!(chunk_size > 0, "chunk size should be non-zero");
+        assert_eq!(values.len() % chunk_size, 0, "vector length should be a multiple of chunk size");
+        let chunks: Vec<_> = values.chunks(chunk_size).map(|chunk| chunk.to_vec()).collect();
+        assert!(chunks.len().is_power_of_two(), "number of chunks should be a power of two");
+
+        let leaves = chunks
+            .iter()
+            .enumerate()
+            .map(|(index, chunk)| hash_chunk::<E, P, RATE, WIDTH>(index, chunk, params))
+            .collect();
+
+        Self {
+            chunk_size,
+            chunks,
+            tree: MerkleLayers::new(leaves, params),
+        }
+    }
+
+    pub fn root(&self) -> E::Fr {
+        self.tree.root()
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn num_chunks(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn chunk(&self, index: usize) -> &[E::Fr] {
+        &self.chunks[index]
+    }
+
+    /// Replaces a chunk and recomputes only the hashes on its path to the root.
+    pub fn update_chunk<P: HashParams<E, RATE, WIDTH>>(&mut self, index: usize, new_data: &[E::Fr], params: &P) {
+        assert!(index < self.chunks.len(), "index is out of range");
+        assert_eq!(new_data.len(), self.chunk_size, "chunk size mismatch");
+
+        self.chunks[index].copy_from_slice(new_data);
+        let leaf_hash = hash_chunk::<E, P, RATE, WIDTH>(index, new_data, params);
+        self.tree.update_leaf(index, leaf_hash, params);
+    }
+
+    pub fn open(&self, index: usize) -> ChunkOpening<E> {
+        ChunkOpening {
+            index,
+            path: self.tree.path(index),
+            chunk: self.chunks[index].clone(),
+        }
+    }
+}
+
+impl<E: Engine> ChunkOpening<E> {
+    pub fn verify<P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+        &self,
+        root: &E::Fr,
+        params: &P,
+    ) -> bool {
+        if self.chunk.is_empty() {
+            return false;
+        }
+        let leaf_hash = hash_chunk::<E, P, RATE, WIDTH>(self.index, &self.chunk, params);
+
+        verify_path::<E, P, RATE, WIDTH>(root, leaf_hash, self.index, &self.path, params)
+    }
+}
+
+// Chunk is hashed as (index, chunk) by fixed length sponge, so both position
+// and chunk size are bound into the leaf.
+fn hash_chunk<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    index: usize,
+    chunk: &[E::Fr],
+    params: &P,
+) -> E::Fr {
+    let mut input = Vec::with_capacity(chunk.len() + 1);
+    input.push(fe_from_u64::<E>(index as u64));
+    input.extend_from_slice(chunk);
+
+    hash_leaf::<E, P, RATE, WIDTH>(&input, params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::tests::init_rng;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_chunked_commitment_update() {
+        let rng = &mut init_rng();
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        let mut values: Vec<_> = (0..24).map(|_| Fr::rand(rng)).collect();
+
+        let mut commitment = ChunkedCommitment::<Bn256, 2, 3>::new(&values, 3, &params);
+        let opening = commitment.open(5);
+        assert!(opening.verify(&commitment.root(), &params));
+
+        let new_data: Vec<_> = (0..3).map(|_| Fr::rand(rng)).collect();
+        commitment.update_chunk(5, &new_data, &params);
+        values[15..18].copy_from_slice(&new_data);
+
+        // incremental update matches commitment from scratch
+        let expected = ChunkedCommitment::<Bn256, 2, 3>::new(&values, 3, &params);
+        assert_eq!(commitment.root(), expected.root());
+        assert!(!opening.verify(&commitment.root(), &params));
+        assert!(commitment.open(5).verify(&commitment.root(), &params));
+        assert!(commitment.open(2).verify(&commitment.root(), &params));
+    }
+}
@@ -0,0 +1,246 @@
+use super::error::{Gadget, GadgetError};
+use super::matrix::matrix_vector_product;
+use super::sbox::sbox_with_witness;
+use super::sponge::circuit_generic_hash_num;
+use crate::anemoi::{jive_compress, AnemoiParams};
+use crate::traits::{CustomGate, HashFamily, HashParams};
+use crate::DomainStrategy;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Field, SynthesisError};
+use franklin_crypto::{
+    bellman::Engine,
+    plonk::circuit::{allocated_num::Num, linear_combination::LinearCombination},
+};
+
+/// Receives inputs whose length `known` prior(fixed-length).
+/// Also uses custom domain strategy which basically sets value of capacity element to
+/// length of input and applies a padding rule which makes input size equals to multiple of
+/// rate parameter.
+/// Uses pre-defined state-width=2 and rate=1.
+pub fn circuit_anemoi_hash<E: Engine, CS: ConstraintSystem<E>, const L: usize>(
+    cs: &mut CS,
+    input: &[Num<E>; L],
+    domain_strategy: Option<DomainStrategy>,
+) -> Result<[Num<E>; 1], SynthesisError> {
+    const WIDTH: usize = 2;
+    const RATE: usize = 1;
+    let params = AnemoiParams::<E, RATE, WIDTH>::default();
+    circuit_generic_hash_num(cs, input, &params, domain_strategy)
+}
+
+/// Circuit counterpart of `anemoi_jive_compress`.
+pub fn circuit_anemoi_jive_compress<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    left: &Num<E>,
+    right: &Num<E>,
+) -> Result<Num<E>, SynthesisError> {
+    let params = AnemoiParams::<E, 1, 2>::default();
+    Ok(circuit_jive_compress(cs, &params, &[*left, *right])?[0])
+}
+
+/// Circuit counterpart of `jive_compress`, constant inputs are compressed
+/// natively.
+pub fn circuit_jive_compress<E: Engine, CS: ConstraintSystem<E>, const RATE: usize, const WIDTH: usize>(
+    cs: &mut CS,
+    params: &AnemoiParams<E, RATE, WIDTH>,
+    input: &[Num<E>; WIDTH],
+) -> Result<Vec<Num<E>>, SynthesisError> {
+    if input.iter().all(|el| el.is_constant()) {
+        let values = input.map(|el| el.get_value().expect("constant has a value"));
+        return Ok(jive_compress(params, &values).into_iter().map(Num::Constant).collect());
+    }
+
+    let mut state = input.map(LinearCombination::from);
+    circuit_anemoi_round_function(cs, params, &mut state)?;
+
+    let columns = WIDTH / 2;
+    let one = E::Fr::one();
+    (0..columns)
+        .map(|i| {
+            let mut sum = state[i].clone();
+            sum.add_assign(&state[columns + i]);
+            sum.add_assign_number_with_coeff(&input[i], one);
+            sum.add_assign_number_with_coeff(&input[columns + i], one);
+            sum.into_num(cs)
+        })
+        .collect()
+}
+
+pub fn circuit_anemoi_round_function<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &AnemoiParams<E, RATE, WIDTH>,
+    state: &mut [LinearCombination<E>; WIDTH],
+) -> Result<(), SynthesisError> {
+    try_circuit_anemoi_round_function(cs, params, state, params.custom_gate(), &mut [].iter()).map_err(GadgetError::log_and_convert)
+}
+
+pub(crate) fn try_circuit_anemoi_round_function<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &AnemoiParams<E, RATE, WIDTH>,
+    state: &mut [LinearCombination<E>; WIDTH],
+    custom_gate: CustomGate,
+    precomputed: &mut std::slice::Iter<E::Fr>,
+) -> Result<(), GadgetError> {
+    let context = |gadget, round| GadgetError::context(HashFamily::Anemoi, gadget, round, custom_gate);
+
+    let columns = WIDTH / 2;
+    let rounds = params.number_of_full_rounds();
+    for round in 0..rounds {
+        let constants = params.constants_of_round(round);
+        for (s, c) in state.iter_mut().zip(constants.iter().cloned()) {
+            s.add_assign_constant(c);
+        }
+
+        matrix_vector_product(params.mds_matrix(), state)
+            .map_err(context(Gadget::LinearLayer, round))?;
+
+        for column in 0..columns {
+            let (x, y) = flystel(
+                cs,
+                params,
+                state[column].clone(),
+                state[columns + column].clone(),
+                custom_gate,
+                precomputed,
+            )
+            .map_err(|(gadget, err)| context(gadget, round)(err))?;
+            state[column] = x;
+            state[columns + column] = y;
+        }
+    }
+
+    // Linear layer at the end
+    matrix_vector_product(params.mds_matrix(), state).map_err(context(Gadget::LinearLayer, rounds))?;
+
+    Ok(())
+}
+
+// Open Flystel: u = x - Q_gamma(y), v = y - u^(1/d), x' = u + Q_delta(v),
+// y' = v. Only the inverse sbox is reported as such, the quadratics are
+// reported as sbox.
+fn flystel<E: Engine, CS: ConstraintSystem<E>, const RATE: usize, const WIDTH: usize>(
+    cs: &mut CS,
+    params: &AnemoiParams<E, RATE, WIDTH>,
+    x: LinearCombination<E>,
+    y: LinearCombination<E>,
+    custom_gate: CustomGate,
+    precomputed: &mut std::slice::Iter<E::Fr>,
+) -> Result<(LinearCombination<E>, LinearCombination<E>), (Gadget, SynthesisError)> {
+    let sbox_err = |err| (Gadget::Sbox, err);
+    let (beta, gamma, delta) = params.flystel_constants();
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+
+    let y = y.into_num(cs).map_err(sbox_err)?;
+    let y_squared = y.mul(cs, &y).map_err(sbox_err)?;
+    let mut u = x;
+    let mut minus_beta = *beta;
+    minus_beta.negate();
+    u.add_assign_number_with_coeff(&y_squared, minus_beta);
+    let mut minus_gamma = *gamma;
+    minus_gamma.negate();
+    u.add_assign_constant(minus_gamma);
+
+    let mut inverse = [u.clone()];
+    sbox_with_witness(cs, params.alpha_inv(), &mut inverse, None, custom_gate, precomputed)
+        .map_err(|err| (Gadget::InverseSbox, err))?;
+    let [inverse] = inverse;
+    let inverse = inverse.into_num(cs).map_err(sbox_err)?;
+
+    let mut v = LinearCombination::from(y);
+    v.add_assign_number_with_coeff(&inverse, minus_one);
+    let v = v.into_num(cs).map_err(sbox_err)?;
+    let v_squared = v.mul(cs, &v).map_err(sbox_err)?;
+
+    let mut x = u;
+    x.add_assign_number_with_coeff(&v_squared, *beta);
+    x.add_assign_constant(*delta);
+
+    Ok((x, LinearCombination::from(v)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::anemoi::{anemoi_hash, anemoi_jive_compress, anemoi_round_function};
+    use crate::tests::{init_cs, init_cs_no_custom_gate, init_rng};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_circuit_anemoi_round_function() {
+        fn check<const RATE: usize, const WIDTH: usize>() {
+            let params = AnemoiParams::<Bn256, RATE, WIDTH>::default();
+            let rng = &mut init_rng();
+
+            let cs = &mut init_cs::<Bn256>();
+            let mut state = [0; WIDTH].map(|_| Fr::rand(rng));
+            let mut circuit_state = state.map(|x| Num::alloc(cs, Some(x)).unwrap().into());
+
+            anemoi_round_function::<Bn256, RATE, WIDTH>(&mut state, &params);
+            circuit_anemoi_round_function(cs, &params, &mut circuit_state).unwrap();
+
+            assert_eq!(state, circuit_state.map(|x| x.get_value().unwrap()));
+            assert!(cs.is_satisfied());
+        }
+
+        check::<1, 2>();
+        check::<2, 4>();
+    }
+
+    #[test]
+    fn test_circuit_anemoi_hash() {
+        const NUM_ELEMENTS: usize = 3;
+        let rng = &mut init_rng();
+        let input = [0; NUM_ELEMENTS].map(|_| Fr::rand(rng));
+        let expected = anemoi_hash::<Bn256, NUM_ELEMENTS>(&input);
+
+        let cs = &mut init_cs_no_custom_gate::<Bn256>();
+        let num_input = input.map(|x| Num::alloc(cs, Some(x)).unwrap());
+        let actual = circuit_anemoi_hash(cs, &num_input, None).unwrap();
+
+        assert_eq!(actual.map(|x| x.get_value().unwrap()), expected);
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_circuit_anemoi_jive_compress() {
+        let rng = &mut init_rng();
+        let (left, right) = (Fr::rand(rng), Fr::rand(rng));
+        let expected = anemoi_jive_compress::<Bn256>(&left, &right);
+
+        let cs = &mut init_cs::<Bn256>();
+        let left_num = Num::alloc(cs, Some(left)).unwrap();
+        let right_num = Num::alloc(cs, Some(right)).unwrap();
+
+        let before = cs.n();
+        let actual = circuit_anemoi_jive_compress(cs, &left_num, &right_num).unwrap();
+        let jive_gates = cs.n() - before;
+        assert_eq!(actual.get_value().unwrap(), expected);
+
+        let before = cs.n();
+        crate::CircuitGenericSponge::<Bn256, 1, 2>::hash_num(
+            cs,
+            &[left_num, right_num],
+            &AnemoiParams::<Bn256, 1, 2>::default(),
+            None,
+        )
+        .unwrap();
+        // sponge with rate 1 permutes once per element
+        assert!(jive_gates < cs.n() - before);
+        assert!(cs.is_satisfied());
+
+        let constant = circuit_anemoi_jive_compress(cs, &Num::Constant(left), &Num::Constant(right)).unwrap();
+        assert_eq!(constant.get_value().unwrap(), expected);
+    }
+}
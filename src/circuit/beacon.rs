@@ -0,0 +1,143 @@
+use super::sponge::CircuitGenericSponge;
+use crate::beacon::{initial_accumulator, BEACON_COMMITMENT_DOMAIN_TAG};
+use crate::common::utils::fe_from_u64;
+use crate::traits::HashParams;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Engine, SynthesisError};
+use franklin_crypto::plonk::circuit::{allocated_num::Num, boolean::Boolean};
+
+/// Circuit counterpart of `beacon::verify_reveal`.
+pub fn circuit_verify_reveal<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &P,
+    commitment: &Num<E>,
+    reveal: &Num<E>,
+) -> Result<Boolean, SynthesisError> {
+    let tag = Num::Constant(fe_from_u64::<E>(BEACON_COMMITMENT_DOMAIN_TAG));
+    let actual = CircuitGenericSponge::<E, RATE, WIDTH>::hash_num(cs, &[tag, *reveal], params, None)?[0];
+
+    Num::equals(cs, &actual, commitment)
+}
+
+/// Circuit counterpart of `beacon::fold_reveals` for a statically known
+/// number of reveals.
+pub fn circuit_fold_reveals<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &P,
+    reveals: &[Num<E>],
+) -> Result<Num<E>, SynthesisError> {
+    let acc = Num::Constant(initial_accumulator::<E, P, RATE, WIDTH>(params));
+
+    circuit_fold_from(cs, params, acc, 0, reveals)
+}
+
+/// Circuit counterpart of `beacon::verify_reveal_inclusion`. Position of the
+/// reveal and number of reveals after it are static.
+pub fn circuit_verify_reveal_inclusion<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &P,
+    beacon: &Num<E>,
+    reveal: &Num<E>,
+    index: usize,
+    prefix: &Num<E>,
+    suffix: &[Num<E>],
+) -> Result<Boolean, SynthesisError> {
+    let current = circuit_fold_from(cs, params, *prefix, index, std::slice::from_ref(reveal))?;
+    let actual = circuit_fold_from(cs, params, current, index + 1, suffix)?;
+
+    Num::equals(cs, &actual, beacon)
+}
+
+fn circuit_fold_from<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &P,
+    mut acc: Num<E>,
+    first_index: usize,
+    reveals: &[Num<E>],
+) -> Result<Num<E>, SynthesisError> {
+    for (i, reveal) in reveals.iter().enumerate() {
+        let index = Num::Constant(fe_from_u64::<E>((first_index + i) as u64));
+        acc = CircuitGenericSponge::<E, RATE, WIDTH>::hash_num(cs, &[acc, index, *reveal], params, None)?[0];
+    }
+
+    Ok(acc)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::beacon::{commit_reveal, fold_reveals, prove_reveal_inclusion, verify_reveal, verify_reveal_inclusion};
+    use crate::poseidon2::Poseidon2Params;
+    use crate::tests::{init_cs, init_rng};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_beacon() {
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        let reveals: Vec<_> = (0..4).map(|_| Fr::rand(rng)).collect();
+
+        let commitment = commit_reveal(&params, &reveals[1]);
+        assert!(verify_reveal(&params, &commitment, &reveals[1]));
+        assert!(!verify_reveal(&params, &commitment, &reveals[2]));
+
+        let beacon = fold_reveals(&params, &reveals);
+        let proof = prove_reveal_inclusion(&params, &reveals, 1);
+        assert!(verify_reveal_inclusion(&params, &beacon, &reveals[1], &proof));
+        assert!(!verify_reveal_inclusion(&params, &beacon, &reveals[2], &proof));
+
+        // reveal can not be moved to another position
+        let mut moved = proof.clone();
+        moved.index = 2;
+        assert!(!verify_reveal_inclusion(&params, &beacon, &reveals[1], &moved));
+
+        let reveals_num: Vec<_> = reveals.iter().map(|el| Num::alloc(cs, Some(*el)).unwrap()).collect();
+        let commitment_num = Num::alloc(cs, Some(commitment)).unwrap();
+        let is_valid = circuit_verify_reveal(cs, &params, &commitment_num, &reveals_num[1]).unwrap();
+        assert!(is_valid.get_value().unwrap());
+
+        let beacon_num = circuit_fold_reveals(cs, &params, &reveals_num).unwrap();
+        assert_eq!(beacon_num.get_value().unwrap(), beacon);
+
+        let prefix = Num::alloc(cs, Some(proof.prefix)).unwrap();
+        let is_included = circuit_verify_reveal_inclusion(
+            cs,
+            &params,
+            &beacon_num,
+            &reveals_num[1],
+            proof.index,
+            &prefix,
+            &reveals_num[2..],
+        )
+        .unwrap();
+        assert!(is_included.get_value().unwrap());
+
+        assert!(cs.is_satisfied());
+    }
+}
@@ -0,0 +1,91 @@
+use super::merkle::{circuit_compute_root, circuit_hash_leaf};
+use crate::traits::HashParams;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Engine, SynthesisError};
+use franklin_crypto::plonk::circuit::{allocated_num::Num, boolean::Boolean};
+
+/// Circuit counterpart of `ChunkOpening::verify`. Index is a witness which is
+/// constrained to fit into `path.len()` bits. Empty chunk is rejected with a
+/// constant `false`, same as the native verification does.
+pub fn circuit_verify_chunk_opening<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &P,
+    root: &Num<E>,
+    index: &Num<E>,
+    chunk: &[Num<E>],
+    path: &[Num<E>],
+) -> Result<Boolean, SynthesisError> {
+    if chunk.is_empty() {
+        return Ok(Boolean::constant(false));
+    }
+    let index_bits = index.into_bits_le(cs, Some(path.len()))?;
+
+    let mut input = Vec::with_capacity(chunk.len() + 1);
+    input.push(*index);
+    input.extend_from_slice(chunk);
+    let leaf_hash = circuit_hash_leaf(cs, &input, params)?;
+    let computed_root = circuit_compute_root(cs, leaf_hash, path, &index_bits, params)?;
+
+    Num::equals(cs, &computed_root, root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunked_commitment::ChunkedCommitment;
+    use crate::common::utils::fe_from_u64;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::tests::{init_cs, init_rng};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_circuit_verify_chunk_opening() {
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        let values: Vec<_> = (0..16).map(|_| Fr::rand(rng)).collect();
+
+        let commitment = ChunkedCommitment::<Bn256, 2, 3>::new(&values, 2, &params);
+        let root = Num::alloc(cs, Some(commitment.root())).unwrap();
+
+        for (index, claimed_index) in [(3, 3), (6, 6), (6, 7)] {
+            let opening = commitment.open(index);
+            let index_num = Num::alloc(cs, Some(fe_from_u64::<Bn256>(claimed_index as u64))).unwrap();
+            let chunk: Vec<_> = opening.chunk.iter().map(|el| Num::alloc(cs, Some(*el)).unwrap()).collect();
+            let path: Vec<_> = opening.path.iter().map(|el| Num::alloc(cs, Some(*el)).unwrap()).collect();
+
+            let is_valid = circuit_verify_chunk_opening(cs, &params, &root, &index_num, &chunk, &path).unwrap();
+            assert_eq!(is_valid.get_value().unwrap(), index == claimed_index);
+        }
+
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_circuit_verify_empty_chunk() {
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        let values: Vec<_> = (0..16).map(|_| Fr::rand(rng)).collect();
+
+        let commitment = ChunkedCommitment::<Bn256, 2, 3>::new(&values, 2, &params);
+        let mut opening = commitment.open(3);
+        opening.chunk.clear();
+        assert!(!opening.verify(&commitment.root(), &params));
+
+        let root = Num::alloc(cs, Some(commitment.root())).unwrap();
+        let index = Num::alloc(cs, Some(fe_from_u64::<Bn256>(3))).unwrap();
+        let path: Vec<_> = opening.path.iter().map(|el| Num::alloc(cs, Some(*el)).unwrap()).collect();
+
+        let is_valid = circuit_verify_chunk_opening(cs, &params, &root, &index, &[], &path).unwrap();
+        assert_eq!(is_valid.get_value(), Some(false));
+        assert!(cs.is_satisfied());
+    }
+}
@@ -0,0 +1,97 @@
+use crate::traits::{CustomGate, HashFamily};
+use franklin_crypto::bellman::SynthesisError;
+
+/// Part of the round function which failed to synthesize.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Gadget {
+    Sbox,
+    InverseSbox,
+    LinearLayer,
+}
+
+impl std::fmt::Display for Gadget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sbox => write!(f, "sbox"),
+            Self::InverseSbox => write!(f, "inverse sbox"),
+            Self::LinearLayer => write!(f, "linear layer"),
+        }
+    }
+}
+
+/// `SynthesisError` raised by a gadget of the round function together with
+/// the place where it happened. Converts back into the original error, so
+/// context is only lost when crossing `SynthesisError` based APIs.
+#[derive(Debug)]
+pub struct GadgetError {
+    pub family: HashFamily,
+    pub gadget: Gadget,
+    /// Round of the permutation, numbered as in `HashParams::constants_of_round`.
+    pub round: usize,
+    pub custom_gate: CustomGate,
+    pub source: SynthesisError,
+}
+
+impl GadgetError {
+    pub(crate) fn context(
+        family: HashFamily,
+        gadget: Gadget,
+        round: usize,
+        custom_gate: CustomGate,
+    ) -> impl FnOnce(SynthesisError) -> Self {
+        move |source| Self {
+            family,
+            gadget,
+            round,
+            custom_gate,
+            source,
+        }
+    }
+
+    // Keeps context in logs when error is converted into a bare `SynthesisError`.
+    pub(crate) fn log_and_convert(self) -> SynthesisError {
+        log::error!("{}", self);
+
+        self.into()
+    }
+}
+
+impl std::fmt::Display for GadgetError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} of {} round {} (custom gate {:?}) failed: {}",
+            self.gadget, self.family, self.round, self.custom_gate, self.source
+        )
+    }
+}
+
+impl std::error::Error for GadgetError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.source)
+    }
+}
+
+impl From<GadgetError> for SynthesisError {
+    fn from(err: GadgetError) -> Self {
+        err.source
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gadget_error_context() {
+        let err = GadgetError::context(HashFamily::Poseidon, Gadget::Sbox, 7, CustomGate::QuinticWidth4)(
+            SynthesisError::AssignmentMissing,
+        );
+        let message = err.to_string();
+        assert!(message.contains("sbox of poseidon round 7"), "{}", message);
+        assert!(message.contains("QuinticWidth4"), "{}", message);
+
+        let err: SynthesisError = err.into();
+        assert!(matches!(err, SynthesisError::AssignmentMissing));
+    }
+}
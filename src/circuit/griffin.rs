@@ -0,0 +1,156 @@
+use super::error::{Gadget, GadgetError};
+use super::matrix::matrix_vector_product;
+use super::sbox::{sbox, sbox_with_witness};
+use super::sponge::circuit_generic_hash_num;
+use crate::griffin::GriffinParams;
+use crate::traits::{CustomGate, HashFamily, HashParams};
+use crate::DomainStrategy;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::SynthesisError;
+use franklin_crypto::{
+    bellman::Engine,
+    plonk::circuit::{allocated_num::Num, linear_combination::LinearCombination},
+};
+
+/// Receives inputs whose length `known` prior(fixed-length).
+/// Also uses custom domain strategy which basically sets value of capacity element to
+/// length of input and applies a padding rule which makes input size equals to multiple of
+/// rate parameter.
+/// Uses pre-defined state-width=3 and rate=2.
+pub fn circuit_griffin_hash<E: Engine, CS: ConstraintSystem<E>, const L: usize>(
+    cs: &mut CS,
+    input: &[Num<E>; L],
+    domain_strategy: Option<DomainStrategy>,
+) -> Result<[Num<E>; 2], SynthesisError> {
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+    let params = GriffinParams::<E, RATE, WIDTH>::default();
+    circuit_generic_hash_num(cs, input, &params, domain_strategy)
+}
+
+pub fn circuit_griffin_round_function<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &GriffinParams<E, RATE, WIDTH>,
+    state: &mut [LinearCombination<E>; WIDTH],
+) -> Result<(), SynthesisError> {
+    try_circuit_griffin_round_function(cs, params, state, params.custom_gate(), &mut [].iter()).map_err(GadgetError::log_and_convert)
+}
+
+pub(crate) fn try_circuit_griffin_round_function<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &GriffinParams<E, RATE, WIDTH>,
+    state: &mut [LinearCombination<E>; WIDTH],
+    custom_gate: CustomGate,
+    precomputed: &mut std::slice::Iter<E::Fr>,
+) -> Result<(), GadgetError> {
+    let context = |gadget, round| GadgetError::context(HashFamily::Griffin, gadget, round, custom_gate);
+
+    // Linear layer at beginning
+    matrix_vector_product(params.mds_matrix(), state).map_err(context(Gadget::LinearLayer, 0))?;
+
+    let rounds = params.number_of_full_rounds();
+    for round in 0..rounds {
+        // y0 = x0^(1/d), inverse sbox is applied to the whole array it is given
+        let mut first = [state[0].clone()];
+        sbox_with_witness(cs, params.alpha_inv(), &mut first, None, custom_gate, precomputed)
+            .map_err(context(Gadget::InverseSbox, round))?;
+        let [first] = first;
+        state[0] = first;
+
+        nonlinear_layer(cs, params, state, custom_gate).map_err(context(Gadget::Sbox, round))?;
+
+        matrix_vector_product(params.mds_matrix(), state)
+            .map_err(context(Gadget::LinearLayer, round))?;
+
+        if round + 1 < rounds {
+            let constants = params.constants_of_round(round);
+            for (s, c) in state.iter_mut().zip(constants.iter().cloned()) {
+                s.add_assign_constant(c);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+// Rest of the native layer after the inverse sbox: y1 = x1^d and
+// y2 = x2 * (L^2 + alpha * L + beta) where L = y0 + y1.
+fn nonlinear_layer<E: Engine, CS: ConstraintSystem<E>, const RATE: usize, const WIDTH: usize>(
+    cs: &mut CS,
+    params: &GriffinParams<E, RATE, WIDTH>,
+    state: &mut [LinearCombination<E>; WIDTH],
+    custom_gate: CustomGate,
+) -> Result<(), SynthesisError> {
+    assert_eq!(WIDTH, 3);
+    sbox(cs, params.alpha(), state, Some(1..2), custom_gate)?;
+
+    let (alpha, beta) = params.alpha_beta();
+    let mut linear = state[0].clone();
+    linear.add_assign(&state[1]);
+    let linear = linear.into_num(cs)?;
+
+    let mut factor = LinearCombination::from(linear.mul(cs, &linear)?);
+    factor.add_assign_number_with_coeff(&linear, *alpha);
+    factor.add_assign_constant(*beta);
+    let factor = factor.into_num(cs)?;
+
+    let last = state[2].clone().into_num(cs)?;
+    state[2] = LinearCombination::from(last.mul(cs, &factor)?);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::griffin::{griffin_hash, griffin_round_function};
+    use crate::tests::{init_cs, init_cs_no_custom_gate, init_rng};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_circuit_griffin_round_function() {
+        let params = GriffinParams::<Bn256, 2, 3>::default();
+        let rng = &mut init_rng();
+
+        let cs = &mut init_cs::<Bn256>();
+        let mut state = [0; 3].map(|_| Fr::rand(rng));
+        let mut circuit_state = state.map(|x| Num::alloc(cs, Some(x)).unwrap().into());
+
+        griffin_round_function::<Bn256, 2, 3>(&mut state, &params);
+        circuit_griffin_round_function(cs, &params, &mut circuit_state).unwrap();
+
+        assert_eq!(state, circuit_state.map(|x| x.get_value().unwrap()));
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_circuit_griffin_hash() {
+        const NUM_ELEMENTS: usize = 5;
+        let rng = &mut init_rng();
+        let input = [0; NUM_ELEMENTS].map(|_| Fr::rand(rng));
+        let expected = griffin_hash::<Bn256, NUM_ELEMENTS>(&input);
+
+        let cs = &mut init_cs_no_custom_gate::<Bn256>();
+        let num_input = input.map(|x| Num::alloc(cs, Some(x)).unwrap());
+        let actual = circuit_griffin_hash(cs, &num_input, None).unwrap();
+
+        assert_eq!(actual.map(|x| x.get_value().unwrap()), expected);
+        assert!(cs.is_satisfied());
+
+        // constant inputs are folded into constants
+        let cs = &mut init_cs::<Bn256>();
+        let actual = circuit_griffin_hash(cs, &input.map(Num::Constant), None).unwrap();
+        assert_eq!(actual.map(|x| x.get_value().unwrap()), expected);
+    }
+}
@@ -0,0 +1,24 @@
+use franklin_crypto::bellman::Engine;
+use franklin_crypto::plonk::circuit::linear_combination::LinearCombination;
+
+// Helpers for handling sponge state without round-tripping through heap
+// allocated vectors. An empty linear combination does not allocate, so
+// zeroing and moving state elements is free, while the terms buffer of the
+// moved element is reused by whoever takes it.
+
+// Returns a state where every element is an empty linear combination.
+pub(crate) fn zero_state<E: Engine, const DIM: usize>() -> [LinearCombination<E>; DIM] {
+    core::array::from_fn(|_| LinearCombination::zero())
+}
+
+// Moves a linear combination out and leaves an empty one in its place.
+pub(crate) fn take_lc<E: Engine>(lc: &mut LinearCombination<E>) -> LinearCombination<E> {
+    core::mem::replace(lc, LinearCombination::zero())
+}
+
+// Moves the whole state out and leaves empty linear combinations in its place.
+pub(crate) fn take_state<E: Engine, const DIM: usize>(
+    state: &mut [LinearCombination<E>; DIM],
+) -> [LinearCombination<E>; DIM] {
+    core::mem::replace(state, zero_state())
+}
@@ -1,16 +1,18 @@
 use franklin_crypto::bellman::{Engine, SynthesisError};
 use franklin_crypto::plonk::circuit::linear_combination::LinearCombination;
+
+use super::lc_state::take_state;
 // Computes matrix vector product and assigns result into same vector.
 pub(crate) fn matrix_vector_product<E: Engine, const DIM: usize>(
     matrix: &[[E::Fr; DIM]; DIM],
     vector: &mut [LinearCombination<E>; DIM],
 ) -> Result<(), SynthesisError> {
-    let vec_cloned = vector.clone();
+    // move the inputs out instead of cloning them, result rows start empty
+    let vec_taken = take_state(vector);
 
     for (idx, row) in matrix.iter().enumerate() {
         // [fr, fr, fr] * [lc, lc, lc]
-        vector[idx] = LinearCombination::zero();
-        for (factor, lc) in row.iter().zip(&vec_cloned) {
+        for (factor, lc) in row.iter().zip(&vec_taken) {
             vector[idx].add_assign_scaled(lc, *factor)
         }
     }
@@ -23,24 +25,18 @@ pub(crate) fn mul_by_sparse_matrix<E: Engine, const DIM: usize>(
     matrix: &[[E::Fr; DIM]; DIM],
     vector: &mut [LinearCombination<E>; DIM],
 ) {
-    assert_eq!(DIM, 3, "valid only for 3x3 matrix");
-
-    let vec_cloned = vector.clone();
-
-    // we will assign result into input vector so set each to zero
-    for lc in vector.iter_mut() {
-        *lc = LinearCombination::zero();
-    }    
+    // we will assign result into input vector, moving inputs out leaves each
+    // element as zero
+    let vec_taken = take_state(vector);
 
-    for (a, b) in vec_cloned.iter().zip(matrix[0].iter()) {
+    for (a, b) in vec_taken.iter().zip(matrix[0].iter()) {
         vector[0].add_assign_scaled(a, *b);
     }
 
-    vector[1].add_assign_scaled(&vec_cloned[0], matrix[1][0]);
-    vector[1].add_assign(&vec_cloned[1]);
-
-    vector[2].add_assign_scaled(&vec_cloned[0], matrix[2][0]);
-    vector[2].add_assign(&vec_cloned[2]);
+    for i in 1..DIM {
+        vector[i].add_assign_scaled(&vec_taken[0], matrix[i][0]);
+        vector[i].add_assign(&vec_taken[i]);
+    }
 }
 
 #[cfg(test)]
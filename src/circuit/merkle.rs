@@ -0,0 +1,113 @@
+use super::sponge::CircuitGenericSponge;
+use crate::common::utils::fe_from_u64;
+use crate::merkle::{MERKLE_LEAF_DOMAIN_TAG, MERKLE_NODE_DOMAIN_TAG};
+use crate::traits::HashParams;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Engine, SynthesisError};
+use franklin_crypto::plonk::circuit::{allocated_num::Num, boolean::Boolean};
+
+/// Circuit counterpart of `merkle::verify_proof`, enforces that `leaf` is
+/// in the tree with the given `root`. `index_bits` are little endian bits of
+/// the leaf index, one per element of `path`.
+pub fn verify_merkle_path<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    root: &Num<E>,
+    leaf: Num<E>,
+    path: &[Num<E>],
+    index_bits: &[Boolean],
+    params: &P,
+) -> Result<(), SynthesisError> {
+    let leaf_hash = circuit_hash_leaf(cs, &[leaf], params)?;
+    let computed_root = circuit_compute_root(cs, leaf_hash, path, index_bits, params)?;
+
+    computed_root.enforce_equal(cs, root)
+}
+
+/// Hashes leaf `input` prefixed by the leaf domain tag.
+pub(crate) fn circuit_hash_leaf<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    input: &[Num<E>],
+    params: &P,
+) -> Result<Num<E>, SynthesisError> {
+    let mut tagged = Vec::with_capacity(input.len() + 1);
+    tagged.push(Num::Constant(fe_from_u64::<E>(MERKLE_LEAF_DOMAIN_TAG)));
+    tagged.extend_from_slice(input);
+
+    Ok(CircuitGenericSponge::<E, RATE, WIDTH>::hash_num(cs, &tagged, params, None)?[0])
+}
+
+/// Root of the tree computed from a leaf hash and its authentication path.
+pub(crate) fn circuit_compute_root<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    leaf_hash: Num<E>,
+    path: &[Num<E>],
+    index_bits: &[Boolean],
+    params: &P,
+) -> Result<Num<E>, SynthesisError> {
+    assert_eq!(path.len(), index_bits.len(), "each level of the path needs an index bit");
+
+    let tag = Num::Constant(fe_from_u64::<E>(MERKLE_NODE_DOMAIN_TAG));
+    let mut current = leaf_hash;
+    for (sibling, is_right) in path.iter().zip(index_bits.iter()) {
+        let left = Num::conditionally_select(cs, is_right, sibling, &current)?;
+        let right = Num::conditionally_select(cs, is_right, &current, sibling)?;
+        current = CircuitGenericSponge::<E, RATE, WIDTH>::hash_num(cs, &[tag, left, right], params, None)?[0];
+    }
+
+    Ok(current)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::MerkleTree;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::tests::{init_cs, init_rng};
+    use crate::RescueParams;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    fn check_merkle_path<P: HashParams<Bn256, 2, 3>>(params: &P) {
+        let rng = &mut init_rng();
+        let leaves: Vec<_> = (0..8).map(|_| Fr::rand(rng)).collect();
+        let tree = MerkleTree::new(&leaves, params);
+
+        for (index, claimed_index) in [(0, 0), (5, 5), (5, 4)] {
+            let cs = &mut init_cs::<Bn256>();
+            let proof = tree.get_proof(index);
+            let root = Num::alloc(cs, Some(tree.root())).unwrap();
+            let leaf = Num::alloc(cs, Some(leaves[index])).unwrap();
+            let path: Vec<_> = proof.path.iter().map(|el| Num::alloc(cs, Some(*el)).unwrap()).collect();
+            let index_bits: Vec<_> = (0..path.len())
+                .map(|i| Boolean::alloc(cs, Some(claimed_index >> i & 1 == 1)).unwrap())
+                .collect();
+
+            verify_merkle_path(cs, &root, leaf, &path, &index_bits, params).unwrap();
+            assert_eq!(cs.is_satisfied(), index == claimed_index);
+        }
+    }
+
+    #[test]
+    fn test_verify_merkle_path() {
+        check_merkle_path(&Poseidon2Params::<Bn256, 2, 3>::default());
+        check_merkle_path(&RescueParams::<Bn256, 2, 3>::default());
+    }
+}
@@ -1,9 +1,23 @@
 pub(crate) mod sponge;
+pub mod anemoi;
+pub mod error;
+pub mod griffin;
 pub(crate) mod poseidon;
 pub mod poseidon2;
+pub mod poseidon2_sponge;
+pub mod poseidon2_transcript;
 pub(crate) mod rescue;
 pub(crate) mod rescue_prime;
-mod sbox;
+pub mod safe;
+pub mod public_inputs;
+pub mod beacon;
+pub mod chunked_commitment;
+pub mod merkle;
+pub mod point_encoding;
+pub mod transcript_protocol;
+pub mod wots;
+pub(crate) mod sbox;
 mod matrix;
+mod lc_state;
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,196 @@
+//! Circuit counterpart of `crate::point_encoding` for BN254 points given by
+//! chunks of their encoding, i.e. 128-bit limbs of compressed x-coordinate.
+//! Chunks are checked to be the canonical encoding of some x-coordinate with
+//! consistent flags. Whether the x-coordinate belongs to a point of the curve
+//! is not checked, it needs y-coordinate and is left to the gadget which
+//! uses the point.
+use super::public_inputs::enforce_bits_less_than;
+use super::transcript_protocol::CircuitSpongeTranscript;
+use crate::point_encoding::CHUNK_BYTES;
+use crate::traits::HashParams;
+use franklin_crypto::bellman::pairing::bn256::Fq;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Engine, PrimeField, SynthesisError};
+use franklin_crypto::plonk::circuit::{allocated_num::Num, boolean::Boolean};
+
+const CHUNK_BITS: usize = CHUNK_BYTES * 8;
+
+/// Enforces that chunks are the canonical encoding of a G1 point.
+pub fn circuit_enforce_canonical_g1_encoding<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    encoding: &[Num<E>; 2],
+) -> Result<(), SynthesisError> {
+    enforce_canonical_encoding(cs, encoding)
+}
+
+/// Enforces that chunks are the canonical encoding of a G2 point.
+pub fn circuit_enforce_canonical_g2_encoding<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    encoding: &[Num<E>; 4],
+) -> Result<(), SynthesisError> {
+    enforce_canonical_encoding(cs, encoding)
+}
+
+/// Circuit counterpart of `commit_point` for G1 points.
+pub fn circuit_commit_g1<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    transcript: &mut CircuitSpongeTranscript<E, P, RATE, WIDTH>,
+    encoding: &[Num<E>; 2],
+) -> Result<(), SynthesisError> {
+    circuit_enforce_canonical_g1_encoding(cs, encoding)?;
+
+    transcript.commit_proof_element(cs, encoding)
+}
+
+/// Circuit counterpart of `commit_point` for G2 points.
+pub fn circuit_commit_g2<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    transcript: &mut CircuitSpongeTranscript<E, P, RATE, WIDTH>,
+    encoding: &[Num<E>; 4],
+) -> Result<(), SynthesisError> {
+    circuit_enforce_canonical_g2_encoding(cs, encoding)?;
+
+    transcript.commit_proof_element(cs, encoding)
+}
+
+// Each base field element of the x-coordinate takes two chunks, the first
+// element additionally has infinity and sign of y flags in its top bits.
+fn enforce_canonical_encoding<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    encoding: &[Num<E>],
+) -> Result<(), SynthesisError> {
+    assert!(E::Fr::CAPACITY as usize >= CHUNK_BITS, "field is too small for a chunk");
+    assert_eq!(Fq::NUM_BITS as usize + 2, 2 * CHUNK_BITS);
+    assert_eq!(encoding.len() % 2, 0);
+
+    let mut flags = None;
+    let mut coordinate_is_zero = Boolean::constant(true);
+    for (idx, pair) in encoding.chunks_exact(2).enumerate() {
+        let mut bits = chunk_bits(cs, &pair[1])?;
+        bits.extend(chunk_bits(cs, &pair[0])?);
+        let top_bits = bits.split_off(Fq::NUM_BITS as usize);
+        if idx == 0 {
+            flags = Some((top_bits[0], top_bits[1]));
+        } else {
+            for bit in top_bits.iter() {
+                Boolean::enforce_equal(cs, bit, &Boolean::constant(false))?;
+            }
+        }
+
+        enforce_bits_less_than::<E, CS, _>(cs, &bits, Fq::char())?;
+        for bit in bits.iter() {
+            coordinate_is_zero = Boolean::and(cs, &coordinate_is_zero, &bit.not())?;
+        }
+    }
+
+    // point at infinity is encoded by its flag alone
+    let (is_infinity, sign) = flags.expect("at least one coordinate");
+    let only_flag = Boolean::and(cs, &coordinate_is_zero, &sign.not())?;
+    let extra_bits_at_infinity = Boolean::and(cs, &is_infinity, &only_flag.not())?;
+
+    Boolean::enforce_equal(cs, &extra_bits_at_infinity, &Boolean::constant(false))
+}
+
+fn chunk_bits<E: Engine, CS: ConstraintSystem<E>>(cs: &mut CS, chunk: &Num<E>) -> Result<Vec<Boolean>, SynthesisError> {
+    match chunk {
+        Num::Constant(constant) => {
+            let repr = constant.into_repr();
+            assert!(repr.as_ref()[2..].iter().all(|limb| *limb == 0), "constant chunk is too large");
+
+            Ok((0..CHUNK_BITS)
+                .map(|i| Boolean::constant((repr.as_ref()[i / 64] >> (i % 64)) & 1 == 1))
+                .collect())
+        }
+        Num::Variable(chunk) => chunk.into_bits_le(cs, Some(CHUNK_BITS)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::point_encoding::{commit_point, encode_compressed_point};
+    use crate::poseidon2::Poseidon2Params;
+    use crate::tests::{init_cs, init_rng};
+    use crate::transcript_protocol::{SpongeTranscript, TranscriptProtocol};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr, G1Affine, G1, G2};
+    use franklin_crypto::bellman::pairing::{CurveAffine, CurveProjective};
+    use franklin_crypto::bellman::Field;
+    use rand::Rand;
+
+    fn alloc_encoding<CS: ConstraintSystem<Bn256>, const N: usize>(cs: &mut CS, encoding: &[Fr]) -> [Num<Bn256>; N] {
+        let nums: Vec<_> = encoding.iter().map(|el| Num::alloc(cs, Some(*el)).unwrap()).collect();
+
+        nums.try_into().unwrap_or_else(|_| panic!("encoding of {} elements", N))
+    }
+
+    #[test]
+    fn test_circuit_commit_points() {
+        let cs = &mut init_cs::<Bn256>();
+        let rng = &mut init_rng();
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        let g1 = G1::rand(rng).into_affine();
+        let g2 = G2::rand(rng).into_affine();
+
+        let mut transcript = SpongeTranscript::<Bn256, _, 2, 3>::new(params.clone());
+        commit_point(&mut transcript, &g1);
+        commit_point(&mut transcript, &G1Affine::zero());
+        commit_point(&mut transcript, &g2);
+
+        let mut circuit_transcript = CircuitSpongeTranscript::<Bn256, _, 2, 3>::new(params);
+        let encoding = alloc_encoding(cs, &encode_compressed_point::<Bn256, _>(&g1));
+        circuit_commit_g1(cs, &mut circuit_transcript, &encoding).unwrap();
+        let encoding = alloc_encoding(cs, &encode_compressed_point::<Bn256, _>(&G1Affine::zero()));
+        circuit_commit_g1(cs, &mut circuit_transcript, &encoding).unwrap();
+        let encoding = alloc_encoding(cs, &encode_compressed_point::<Bn256, _>(&g2));
+        circuit_commit_g2(cs, &mut circuit_transcript, &encoding).unwrap();
+
+        let expected = transcript.get_challenge();
+        let actual = circuit_transcript.get_challenge(cs).unwrap();
+        assert_eq!(actual.get_value().unwrap(), expected);
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_circuit_rejects_non_canonical_encodings() {
+        let rng = &mut init_rng();
+        let g1 = G1::rand(rng).into_affine();
+        let encoding = encode_compressed_point::<Bn256, _>(&g1);
+        let mut two_pow_126 = Fr::one();
+        for _ in 0..126 {
+            two_pow_126.double();
+        }
+
+        // x-coordinate with all bits set is not less than the modulus
+        let mut two_pow_128 = two_pow_126;
+        two_pow_128.double();
+        two_pow_128.double();
+        let mut all_ones_high = two_pow_126;
+        all_ones_high.sub_assign(&Fr::one());
+        let mut all_ones_low = two_pow_128;
+        all_ones_low.sub_assign(&Fr::one());
+        let cs = &mut init_cs::<Bn256>();
+        let nums = alloc_encoding(cs, &[all_ones_high, all_ones_low]);
+        circuit_enforce_canonical_g1_encoding(cs, &nums).unwrap();
+        assert!(!cs.is_satisfied());
+
+        // infinity flag together with a non zero x-coordinate
+        let mut flagged = encoding.clone();
+        flagged[0].add_assign(&two_pow_126);
+        let cs = &mut init_cs::<Bn256>();
+        let nums = alloc_encoding(cs, &flagged);
+        circuit_enforce_canonical_g1_encoding(cs, &nums).unwrap();
+        assert!(!cs.is_satisfied());
+    }
+}
@@ -1,9 +1,12 @@
 use super::sbox::sbox;
 use super::sponge::circuit_generic_hash_num;
 use super::matrix::{matrix_vector_product, mul_by_sparse_matrix};
+use super::lc_state::take_lc;
 use crate::{DomainStrategy, poseidon::params::PoseidonParams};
-use crate::traits::{HashFamily, HashParams};
+use crate::common::params::circular_mds_matrix;
+use crate::traits::{CustomGate, HashFamily, HashParams};
 use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use super::error::{Gadget, GadgetError};
 use franklin_crypto::bellman::{Field, SynthesisError};
 use franklin_crypto::{
     bellman::Engine,
@@ -26,6 +29,22 @@ pub fn circuit_poseidon_hash<E: Engine, CS: ConstraintSystem<E>, const L: usize>
     circuit_generic_hash_num(cs, input, &params, domain_strategy)
 }
 
+fn full_round_matrix_vector_product<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    params: &P,
+    state: &mut [LinearCombination<E>; WIDTH],
+) -> Result<(), SynthesisError> {
+    if params.uses_circular_full_rounds_mds() {
+        matrix_vector_product(&circular_mds_matrix::<E, WIDTH>(), state)
+    } else {
+        matrix_vector_product(&params.mds_matrix(), state)
+    }
+}
+
 pub(crate) fn circuit_poseidon_round_function<
     E: Engine,
     CS: ConstraintSystem<E>,
@@ -36,12 +55,14 @@ pub(crate) fn circuit_poseidon_round_function<
     cs: &mut CS,
     params: &P,
     state: &mut [LinearCombination<E>; WIDTH],
-) -> Result<(), SynthesisError> {
+    custom_gate: CustomGate,
+) -> Result<(), GadgetError> {
     assert_eq!(
         params.hash_family(),
         HashFamily::Poseidon,
         "Incorrect hash family!"
     );
+    let context = |gadget, round| GadgetError::context(HashFamily::Poseidon, gadget, round, custom_gate);
     assert!(params.number_of_full_rounds() % 2 == 0);
 
     let half_of_full_rounds = params.number_of_full_rounds() / 2;
@@ -63,11 +84,15 @@ pub(crate) fn circuit_poseidon_round_function<
             params.alpha(),
             state,
             Some(0..WIDTH),
-            params.custom_gate(),
-        )?;
-
-        // mul state by mds
-        matrix_vector_product(&params.mds_matrix(), state)?;
+            custom_gate,
+        )
+        .map_err(context(Gadget::Sbox, round))?;
+
+        // mul state by mds, circular matrix of the last round is folded into M'
+        if !params.uses_circular_full_rounds_mds() || round + 1 < half_of_full_rounds {
+            full_round_matrix_vector_product(params, state)
+                .map_err(context(Gadget::LinearLayer, round))?;
+        }
     }
 
     state
@@ -75,7 +100,8 @@ pub(crate) fn circuit_poseidon_round_function<
         .zip(optimized_round_constants[half_of_full_rounds].iter())
         .for_each(|(a, b)| a.add_assign_constant(*b));
 
-    matrix_vector_product(&m_prime, state)?;
+    matrix_vector_product(&m_prime, state)
+        .map_err(context(Gadget::LinearLayer, half_of_full_rounds))?;
 
     let mut constants_for_partial_rounds = optimized_round_constants
         [half_of_full_rounds + 1..half_of_full_rounds + params.number_of_partial_rounds()]
@@ -84,28 +110,34 @@ pub(crate) fn circuit_poseidon_round_function<
     // in order to reduce gate number we merge two consecutive iteration
     // which costs 2 gates per each
 
-    for (round_constant, sparse_matrix) in constants_for_partial_rounds
+    for (pair, (round_constant, sparse_matrix)) in constants_for_partial_rounds
         [..constants_for_partial_rounds.len() - 1]
         .chunks(2)
         .zip(sparse_matrixes[..sparse_matrixes.len() - 1].chunks(2))
+        .enumerate()
     {
+        let round = half_of_full_rounds + 2 * pair;
         // first
-        sbox(cs, params.alpha(), state, Some(0..1), params.custom_gate())?;
+        sbox(cs, params.alpha(), state, Some(0..1), custom_gate)
+            .map_err(context(Gadget::Sbox, round))?;
         state[0].add_assign_constant(round_constant[0][0]);
         mul_by_sparse_matrix(&sparse_matrix[0], state);
 
         // second
-        sbox(cs, params.alpha(), state, Some(0..1), params.custom_gate())?;
+        sbox(cs, params.alpha(), state, Some(0..1), custom_gate)
+            .map_err(context(Gadget::Sbox, round + 1))?;
         state[0].add_assign_constant(round_constant[1][0]);
         mul_by_sparse_matrix(&sparse_matrix[1], state);
         // reduce gate cost: LC -> Num -> LC
         for state in state.iter_mut() {
-            let num = state.clone().into_num(cs).expect("a num");
+            let num = take_lc(state).into_num(cs).map_err(context(Gadget::LinearLayer, round + 1))?;
             *state = LinearCombination::from(num.get_variable());
         }
     }
 
-    sbox(cs, params.alpha(), state, Some(0..1), params.custom_gate())?;
+    let last_partial_round = half_of_full_rounds + params.number_of_partial_rounds() - 1;
+    sbox(cs, params.alpha(), state, Some(0..1), custom_gate)
+        .map_err(context(Gadget::Sbox, last_partial_round))?;
     state[0].add_assign_constant(constants_for_partial_rounds.last().unwrap()[0]);
     mul_by_sparse_matrix(&sparse_matrixes.last().unwrap(), state);
 
@@ -125,11 +157,13 @@ pub(crate) fn circuit_poseidon_round_function<
             params.alpha(),
             state,
             Some(0..WIDTH),
-            params.custom_gate(),
-        )?;
+            custom_gate,
+        )
+        .map_err(context(Gadget::Sbox, round))?;
 
         // mul state by mds
-        matrix_vector_product(&params.mds_matrix(), state)?;
+        full_round_matrix_vector_product(params, state)
+            .map_err(context(Gadget::LinearLayer, round))?;
     }
 
     Ok(())
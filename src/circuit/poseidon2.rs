@@ -2,15 +2,25 @@ use super::sbox::sbox;
 use super::sponge::circuit_generic_hash_num;
 use super::matrix::{matrix_vector_product, mul_by_sparse_matrix};
 use crate::{DomainStrategy, poseidon::params::PoseidonParams};
-use crate::poseidon2::Poseidon2Params;
-use crate::traits::{HashFamily, HashParams};
+use crate::poseidon2::{
+    fr_into_u64_limbs, get_challenges_from_fr, num_challenges_per_fr, Poseidon2Params, CHALLENGE_LIMBS,
+};
+use crate::common::utils::fe_from_u64;
+use crate::traits::{CustomGate, HashFamily, HashParams};
 use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
-use franklin_crypto::bellman::{Field, SynthesisError};
+use super::error::{Gadget, GadgetError};
+use super::public_inputs::enforce_bits_are_canonical;
+use franklin_crypto::bellman::{Field, PrimeField, SynthesisError};
+use franklin_crypto::boojum::field::goldilocks::GoldilocksField;
+use franklin_crypto::boojum::field::SmallField;
 use franklin_crypto::{
     bellman::Engine,
-    plonk::circuit::{allocated_num::Num, linear_combination::LinearCombination},
+    plonk::circuit::{allocated_num::Num, boolean::Boolean, linear_combination::LinearCombination},
 };
 
+pub(crate) const GOLDILOCKS_CHAR_BITS: usize = 64;
+const GOLDILOCKS_CHAR: u64 = 0xFFFF_FFFF_0000_0001;
+
 /// Receives inputs whose length `known` prior(fixed-length).
 /// Also uses custom domain strategy which basically sets value of capacity element to
 /// length of input and applies a padding rule which makes input size equals to multiple of
@@ -23,8 +33,260 @@ pub fn circuit_poseidon2_hash<E: Engine, CS: ConstraintSystem<E>, const L: usize
 ) -> Result<[Num<E>; 2], SynthesisError> {
     const WIDTH: usize = 3;
     const RATE: usize = 2;
-    let params = Poseidon2Params::<E, RATE, WIDTH>::default();
-    circuit_generic_hash_num(cs, input, &params, domain_strategy)
+    let params = Poseidon2Params::<E, RATE, WIDTH>::shared();
+    circuit_poseidon2_hash_with_params(cs, input, params.as_ref(), domain_strategy)
+}
+
+/// Same as `circuit_poseidon2_hash` with params supplied by the caller, e.g.
+/// an `Arc` shared with native sponges, so circuits hashing many times don't
+/// derive constants again for each hash.
+pub fn circuit_poseidon2_hash_with_params<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const RATE: usize,
+    const WIDTH: usize,
+    const L: usize,
+>(
+    cs: &mut CS,
+    input: &[Num<E>; L],
+    params: &Poseidon2Params<E, RATE, WIDTH>,
+    domain_strategy: Option<DomainStrategy>,
+) -> Result<[Num<E>; RATE], SynthesisError> {
+    circuit_generic_hash_num(cs, input, params, domain_strategy)
+}
+
+/// Circuit counterpart of `Poseidon2Sponge` over Goldilocks in overwrite
+/// absorption mode with state-width=3 and rate=2. Limbs are packed into
+/// field elements exactly as native sponge does, `CAPACITY / 64` limbs per
+/// element, and padded with a single `one` limb. Limbs aren't range checked
+/// here, callers should constrain them to be canonical Goldilocks elements by
+/// `enforce_goldilocks_range`, as `circuit_poseidon2_hash_small_field` does.
+pub fn circuit_poseidon2_absorb_small_field<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    limbs: &[Num<E>],
+) -> Result<[Num<E>; 2], SynthesisError> {
+    let params = Poseidon2Params::<E, 2, 3>::shared();
+    circuit_poseidon2_absorb_small_field_with_params(cs, limbs, params.as_ref())
+}
+
+/// Same as `circuit_poseidon2_absorb_small_field` with params supplied by
+/// the caller.
+pub fn circuit_poseidon2_absorb_small_field_with_params<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    limbs: &[Num<E>],
+    params: &Poseidon2Params<E, 2, 3>,
+) -> Result<[Num<E>; 2], SynthesisError> {
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+    let capasity_per_element = E::Fr::CAPACITY as usize / GOLDILOCKS_CHAR_BITS;
+
+    let mut limbs_with_padding = limbs.to_vec();
+    limbs_with_padding.push(Num::Constant(E::Fr::one()));
+
+    let mut state: [LinearCombination<E>; WIDTH] = [
+        LinearCombination::zero(),
+        LinearCombination::zero(),
+        LinearCombination::zero(),
+    ];
+    for chunk in limbs_with_padding.chunks(RATE * capasity_per_element) {
+        for (pos, s) in state[..RATE].iter_mut().enumerate() {
+            let start = (pos * capasity_per_element).min(chunk.len());
+            let end = (start + capasity_per_element).min(chunk.len());
+            // overwrite mode
+            *s = pack_limbs(&chunk[start..end]);
+        }
+        circuit_poseidon2_round_function(cs, params, &mut state)?;
+    }
+
+    let [first, second, _] = state;
+
+    Ok([first.into_num(cs)?, second.into_num(cs)?])
+}
+
+/// Hashes Goldilocks limbs by `circuit_poseidon2_absorb_small_field` and
+/// splits each output element into Goldilocks challenges the same way as
+/// native `Poseidon2Transcript` does, see `circuit_get_challenges_from_fr`.
+/// Each limb is range checked by `enforce_goldilocks_range`, so packings the
+/// native transcript can't produce are rejected.
+pub fn circuit_poseidon2_hash_small_field<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    limbs: &[Num<E>],
+) -> Result<Vec<Num<E>>, SynthesisError> {
+    for limb in limbs.iter() {
+        enforce_goldilocks_range(cs, limb)?;
+    }
+    let output = circuit_poseidon2_absorb_small_field(cs, limbs)?;
+
+    let mut challenges = vec![];
+    for el in output.iter() {
+        challenges.extend(circuit_get_challenges_from_fr(cs, el)?);
+    }
+
+    Ok(challenges)
+}
+
+/// Enforces that `value` is a canonical Goldilocks element, i.e. less than
+/// Goldilocks modulus and so fits into 64 bits. Native packing absorbs
+/// reduced values, so each limb packed in circuit should pass this check.
+pub fn enforce_goldilocks_range<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    value: &Num<E>,
+) -> Result<(), SynthesisError> {
+    match value {
+        Num::Constant(constant) => {
+            let repr = constant.into_repr();
+            assert!(
+                repr.as_ref()[1..].iter().all(|limb| *limb == 0) && repr.as_ref()[0] < GOLDILOCKS_CHAR,
+                "value should be a canonical Goldilocks element"
+            );
+        }
+        Num::Variable(value) => {
+            let bits = value.into_bits_le(cs, Some(GOLDILOCKS_CHAR_BITS))?;
+            let overflows = goldilocks_word_overflows(cs, &bits)?;
+            Boolean::enforce_equal(cs, &overflows, &Boolean::constant(false))?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Range checks each limb by `enforce_goldilocks_range` and packs them into
+/// a single element with the same layout as `Poseidon2Sponge` does, i.e.
+/// `sum(limbs[i] * 2^(64 * i))`. At most `CAPACITY / 64` limbs fit.
+pub fn pack_goldilocks_limbs<E: Engine, CS: ConstraintSystem<E>, const K: usize>(
+    cs: &mut CS,
+    limbs: &[Num<E>; K],
+) -> Result<Num<E>, SynthesisError> {
+    assert!(
+        K <= E::Fr::CAPACITY as usize / GOLDILOCKS_CHAR_BITS,
+        "too many limbs to pack into a single element"
+    );
+    for limb in limbs.iter() {
+        enforce_goldilocks_range(cs, limb)?;
+    }
+
+    pack_limbs(limbs).into_num(cs)
+}
+
+// Packs limbs without range checks.
+fn pack_limbs<E: Engine>(limbs: &[Num<E>]) -> LinearCombination<E> {
+    let mut shift = E::Fr::one();
+    for _ in 0..GOLDILOCKS_CHAR_BITS {
+        shift.double();
+    }
+
+    let mut packed = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+    for limb in limbs.iter() {
+        packed.add_assign_number_with_coeff(limb, coeff);
+        coeff.mul_assign(&shift);
+    }
+
+    packed
+}
+
+// Word is not less than Goldilocks modulus iff upper half is all ones and
+// lower half is non zero.
+fn goldilocks_word_overflows<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    word: &[Boolean],
+) -> Result<Boolean, SynthesisError> {
+    assert_eq!(word.len(), GOLDILOCKS_CHAR_BITS);
+    let mut upper_is_all_ones = Boolean::constant(true);
+    for bit in word[32..].iter() {
+        upper_is_all_ones = Boolean::and(cs, &upper_is_all_ones, bit)?;
+    }
+    let mut lower_is_zero = Boolean::constant(true);
+    for bit in word[..32].iter() {
+        lower_is_zero = Boolean::and(cs, &lower_is_zero, &bit.not())?;
+    }
+
+    Boolean::and(cs, &upper_is_all_ones, &lower_is_zero.not())
+}
+
+/// Circuit counterpart of `fr_into_u64_limbs`. Limbs come from the canonical
+/// bit decomposition of `value`, so each of them is range checked to 64 bits
+/// and prover can't split `value + modulus` instead.
+pub fn circuit_fr_into_u64_limbs<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    value: &Num<E>,
+) -> Result<[Num<E>; CHALLENGE_LIMBS], SynthesisError> {
+    let words = match canonical_words(cs, value)? {
+        Some(words) => words,
+        None => {
+            let limbs = fr_into_u64_limbs::<E>(&value.get_value().expect("constant has a value"));
+            return Ok(limbs.map(|limb| Num::Constant(fe_from_u64::<E>(limb))));
+        }
+    };
+
+    let mut limbs = [Num::Constant(E::Fr::zero()); CHALLENGE_LIMBS];
+    for (limb, word) in limbs.iter_mut().zip(words.iter()) {
+        *limb = pack_bits(word).into_num(cs)?;
+    }
+
+    Ok(limbs)
+}
+
+/// Circuit counterpart of `get_challenges_from_fr` for Goldilocks: lower
+/// `CAPACITY / 64` limbs of `circuit_fr_into_u64_limbs`, each reduced by
+/// Goldilocks modulus.
+pub fn circuit_get_challenges_from_fr<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    value: &Num<E>,
+) -> Result<Vec<Num<E>>, SynthesisError> {
+    let num_challenges = num_challenges_per_fr::<E, GoldilocksField>();
+    let words = match canonical_words(cs, value)? {
+        Some(words) => words,
+        None => {
+            let challenges = get_challenges_from_fr::<E, GoldilocksField>(value.get_value().expect("constant has a value"));
+            return Ok(challenges
+                .into_iter()
+                .map(|challenge| Num::Constant(fe_from_u64::<E>(challenge.as_u64_reduced())))
+                .collect());
+        }
+    };
+
+    let mut minus_char = fe_from_u64::<E>(GOLDILOCKS_CHAR);
+    minus_char.negate();
+
+    let mut challenges = Vec::with_capacity(num_challenges);
+    for word in words.iter().take(num_challenges) {
+        let overflows = goldilocks_word_overflows(cs, word)?;
+
+        let mut lc = pack_bits(word);
+        lc.add_assign_boolean_with_coeff(&overflows, minus_char);
+        challenges.push(lc.into_num(cs)?);
+    }
+
+    Ok(challenges)
+}
+
+// Canonical bits of a variable split into 64-bit words, `None` for constants.
+// Last word is shorter than 64 bits.
+fn canonical_words<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    value: &Num<E>,
+) -> Result<Option<Vec<Vec<Boolean>>>, SynthesisError> {
+    let value = match value {
+        Num::Constant(_) => return Ok(None),
+        Num::Variable(value) => value,
+    };
+
+    let bits = value.into_bits_le(cs, Some(E::Fr::NUM_BITS as usize))?;
+    enforce_bits_are_canonical::<E, CS>(cs, &bits)?;
+
+    Ok(Some(bits.chunks(GOLDILOCKS_CHAR_BITS).map(|word| word.to_vec()).collect()))
+}
+
+fn pack_bits<E: Engine>(bits: &[Boolean]) -> LinearCombination<E> {
+    let mut lc = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+    for bit in bits.iter() {
+        lc.add_assign_boolean_with_coeff(bit, coeff);
+        coeff.double();
+    }
+
+    lc
 }
 
 pub fn circuit_poseidon2_round_function<
@@ -37,12 +299,28 @@ pub fn circuit_poseidon2_round_function<
     params: &Poseidon2Params<E, RATE, WIDTH>,
     state: &mut [LinearCombination<E>; WIDTH],
 ) -> Result<(), SynthesisError> {
+    try_circuit_poseidon2_round_function(cs, params, state, params.custom_gate()).map_err(GadgetError::log_and_convert)
+}
+
+pub(crate) fn try_circuit_poseidon2_round_function<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &Poseidon2Params<E, RATE, WIDTH>,
+    state: &mut [LinearCombination<E>; WIDTH],
+    custom_gate: CustomGate,
+) -> Result<(), GadgetError> {
     assert!(params.number_of_full_rounds() % 2 == 0);
+    let context = |gadget, round| GadgetError::context(HashFamily::Poseidon2, gadget, round, custom_gate);
 
     let half_of_full_rounds = params.number_of_full_rounds() / 2;
 
     // Linear layer at beginning
-    matrix_vector_product(&params.mds_external_matrix, state)?;
+    matrix_vector_product(&params.mds_external_matrix, state)
+        .map_err(context(Gadget::LinearLayer, 0))?;
 
     // first full rounds
     for round in 0..half_of_full_rounds {
@@ -58,11 +336,13 @@ pub fn circuit_poseidon2_round_function<
             params.alpha(),
             state,
             Some(0..WIDTH),
-            params.custom_gate(),
-        )?;
+            custom_gate,
+        )
+        .map_err(context(Gadget::Sbox, round))?;
 
         // mul state by mds
-        matrix_vector_product(&params.mds_external_matrix, state)?;
+        matrix_vector_product(&params.mds_external_matrix, state)
+            .map_err(context(Gadget::LinearLayer, round))?;
     }
 
     let mut diag_internal_matrix_decreased = params.diag_internal_matrix.clone();
@@ -76,7 +356,8 @@ pub fn circuit_poseidon2_round_function<
         state[0].add_assign_constant(round_constant);
 
         // non linear sbox
-        sbox(cs, params.alpha(), state, Some(0..1), params.custom_gate())?;
+        sbox(cs, params.alpha(), state, Some(0..1), custom_gate)
+            .map_err(context(Gadget::Sbox, round))?;
 
         // mul state by internal matrix
         let mut sum = state[0].clone();
@@ -106,11 +387,13 @@ pub fn circuit_poseidon2_round_function<
             params.alpha(),
             state,
             Some(0..WIDTH),
-            params.custom_gate(),
-        )?;
+            custom_gate,
+        )
+        .map_err(context(Gadget::Sbox, round))?;
 
         // mul state by mds
-        matrix_vector_product(&params.mds_external_matrix, state)?;
+        matrix_vector_product(&params.mds_external_matrix, state)
+            .map_err(context(Gadget::LinearLayer, round))?;
     }
 
     Ok(())
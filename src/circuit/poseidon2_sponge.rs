@@ -0,0 +1,233 @@
+use super::lc_state::{take_lc, zero_state};
+use super::poseidon2::{circuit_poseidon2_round_function, enforce_goldilocks_range, GOLDILOCKS_CHAR_BITS};
+use crate::poseidon2::Poseidon2Params;
+use crate::sponge::absorb_into_rate;
+use alloc::sync::Arc;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Engine, Field, PrimeField, SynthesisError};
+use franklin_crypto::boojum::algebraic_props::round_function::{
+    AbsorptionModeAdd, AbsorptionModeOverwrite, AbsorptionModeTrait,
+};
+use franklin_crypto::plonk::circuit::{allocated_num::Num, linear_combination::LinearCombination};
+
+/// Circuit counterpart of an absorption mode of `Poseidon2Sponge`.
+pub trait CircuitAbsorptionMode<E: Engine>: AbsorptionModeTrait<E::Fr> {
+    fn absorb_lc(dst: &mut LinearCombination<E>, src: &LinearCombination<E>);
+}
+
+impl<E: Engine> CircuitAbsorptionMode<E> for AbsorptionModeOverwrite {
+    fn absorb_lc(dst: &mut LinearCombination<E>, src: &LinearCombination<E>) {
+        *dst = src.clone();
+    }
+}
+
+impl<E: Engine> CircuitAbsorptionMode<E> for AbsorptionModeAdd {
+    fn absorb_lc(dst: &mut LinearCombination<E>, src: &LinearCombination<E>) {
+        dst.add_assign(src);
+    }
+}
+
+/// Circuit counterpart of `Poseidon2Sponge` over Goldilocks. Small field
+/// elements are range checked by `enforce_goldilocks_range` and packed into
+/// the buffer exactly as native sponge does, so recursive verifiers can
+/// recompute commitments and transcript states of the native sponge.
+pub struct CircuitPoseidon2Sponge<
+    E: Engine,
+    M: CircuitAbsorptionMode<E>,
+    const RATE: usize,
+    const WIDTH: usize,
+> {
+    pub(crate) state: [LinearCombination<E>; WIDTH],
+    buffer: [LinearCombination<E>; RATE],
+    pub(crate) filled: usize,
+    params: Arc<Poseidon2Params<E, RATE, WIDTH>>,
+    _marker: core::marker::PhantomData<M>,
+}
+
+impl<E: Engine, M: CircuitAbsorptionMode<E>, const RATE: usize, const WIDTH: usize>
+    CircuitPoseidon2Sponge<E, M, RATE, WIDTH>
+{
+    pub fn new() -> Self {
+        Self::new_with_params(Poseidon2Params::<E, RATE, WIDTH>::shared())
+    }
+
+    /// Same as `new` with params supplied by the caller.
+    pub fn new_with_params(params: Arc<Poseidon2Params<E, RATE, WIDTH>>) -> Self {
+        assert!(Self::capasity_per_element() > 0);
+
+        Self {
+            state: zero_state(),
+            buffer: zero_state(),
+            filled: 0,
+            params,
+            _marker: core::marker::PhantomData,
+        }
+    }
+
+    pub fn capasity_per_element() -> usize {
+        (E::Fr::CAPACITY as usize) / GOLDILOCKS_CHAR_BITS
+    }
+
+    pub fn run_round_function<CS: ConstraintSystem<E>>(&mut self, cs: &mut CS) -> Result<(), SynthesisError> {
+        circuit_poseidon2_round_function(cs, self.params.as_ref(), &mut self.state)
+    }
+
+    pub fn absorb_buffer_to_state<CS: ConstraintSystem<E>>(&mut self, cs: &mut CS) -> Result<(), SynthesisError> {
+        absorb_into_rate::<_, _, RATE, WIDTH>(&mut self.state, &self.buffer, M::absorb_lc);
+        self.buffer = zero_state();
+
+        self.run_round_function(cs)?;
+        self.filled = 0;
+
+        Ok(())
+    }
+
+    /// Circuit counterpart of `Poseidon2Sponge::absorb_single_small_field`.
+    /// Enforces `value` to be a canonical Goldilocks element.
+    pub fn absorb_single_small_field<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: &mut CS,
+        value: &Num<E>,
+    ) -> Result<(), SynthesisError> {
+        enforce_goldilocks_range(cs, value)?;
+
+        let capasity_per_element = Self::capasity_per_element();
+        debug_assert!(self.filled < RATE * capasity_per_element);
+        let pos = self.filled / capasity_per_element;
+        let exp = self.filled % capasity_per_element;
+
+        let mut coeff = E::Fr::one();
+        for _ in 0..(exp * GOLDILOCKS_CHAR_BITS) {
+            coeff.double();
+        }
+        self.buffer[pos].add_assign_number_with_coeff(value, coeff);
+        self.filled += 1;
+
+        if self.filled == RATE * capasity_per_element {
+            self.absorb_buffer_to_state(cs)?;
+        }
+
+        Ok(())
+    }
+
+    /// Circuit counterpart of `Poseidon2Sponge::absorb_single`.
+    pub fn absorb_single<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: &mut CS,
+        value: &Num<E>,
+    ) -> Result<(), SynthesisError> {
+        self.absorb_single_lc(cs, LinearCombination::from(*value))
+    }
+
+    // Same as `absorb_single` for elements that are not collapsed into nums,
+    // e.g. packed small field elements.
+    pub(crate) fn absorb_single_lc<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: &mut CS,
+        value: LinearCombination<E>,
+    ) -> Result<(), SynthesisError> {
+        let capasity_per_element = Self::capasity_per_element();
+        debug_assert!(self.filled < RATE * capasity_per_element);
+        let pos = self.filled / capasity_per_element;
+        let exp = self.filled % capasity_per_element;
+
+        match exp {
+            0 => {
+                self.filled += capasity_per_element;
+                self.buffer[pos] = value;
+            }
+            _ => {
+                self.filled = (pos + 1) * capasity_per_element;
+
+                if self.filled == RATE * capasity_per_element {
+                    self.absorb_buffer_to_state(cs)?;
+
+                    self.buffer[0] = value;
+                    self.filled = capasity_per_element;
+                } else {
+                    self.filled += capasity_per_element;
+                    self.buffer[pos + 1] = value;
+                }
+            }
+        }
+
+        if self.filled == RATE * capasity_per_element {
+            self.absorb_buffer_to_state(cs)?;
+        }
+
+        Ok(())
+    }
+
+    /// Circuit counterpart of `Poseidon2Sponge::absorb`.
+    pub fn absorb<CS: ConstraintSystem<E>>(&mut self, cs: &mut CS, values: &[Num<E>]) -> Result<(), SynthesisError> {
+        for value in values.iter() {
+            self.absorb_single(cs, value)?;
+        }
+
+        Ok(())
+    }
+
+    /// Circuit counterpart of `Poseidon2Sponge::flush`.
+    pub fn flush<CS: ConstraintSystem<E>>(&mut self, cs: &mut CS) -> Result<(), SynthesisError> {
+        if self.filled == 0 {
+            return Ok(());
+        }
+        self.absorb_single_small_field(cs, &Num::Constant(E::Fr::one()))?;
+        if self.filled > 0 {
+            self.absorb_buffer_to_state(cs)?;
+        }
+
+        Ok(())
+    }
+
+    /// Circuit counterpart of `Poseidon2Sponge::committed_state`.
+    pub fn committed_state<CS: ConstraintSystem<E>>(&mut self, cs: &mut CS) -> Result<[Num<E>; RATE], SynthesisError> {
+        self.flush(cs)?;
+
+        self.rate_into_nums(cs)
+    }
+
+    /// Circuit counterpart of `Poseidon2Sponge::try_get_committment`.
+    pub fn try_get_committment<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: &mut CS,
+    ) -> Result<Option<[Num<E>; RATE]>, SynthesisError> {
+        if self.filled != 0 {
+            return Ok(None);
+        }
+
+        self.rate_into_nums(cs).map(Some)
+    }
+
+    /// Circuit counterpart of `Poseidon2Sponge::finalize`.
+    pub fn finalize<CS: ConstraintSystem<E>>(&mut self, cs: &mut CS) -> Result<[Num<E>; RATE], SynthesisError> {
+        // padding
+        self.absorb_single_small_field(cs, &Num::Constant(E::Fr::one()))?;
+
+        if self.filled > 0 {
+            self.absorb_buffer_to_state(cs)?;
+        }
+
+        self.rate_into_nums(cs)
+    }
+
+    // Collapses rate part of the state into nums and keeps them as state, so
+    // following absorptions don't pay for long linear combinations again.
+    pub(crate) fn rate_into_nums<CS: ConstraintSystem<E>>(&mut self, cs: &mut CS) -> Result<[Num<E>; RATE], SynthesisError> {
+        let mut output = [Num::Constant(E::Fr::zero()); RATE];
+        for (lc, num) in self.state[..RATE].iter_mut().zip(output.iter_mut()) {
+            *num = take_lc(lc).into_num(cs)?;
+            *lc = LinearCombination::from(*num);
+        }
+
+        Ok(output)
+    }
+}
+
+impl<E: Engine, M: CircuitAbsorptionMode<E>, const RATE: usize, const WIDTH: usize> Default
+    for CircuitPoseidon2Sponge<E, M, RATE, WIDTH>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,204 @@
+use super::poseidon2::{circuit_get_challenges_from_fr, enforce_goldilocks_range, GOLDILOCKS_CHAR_BITS};
+use super::poseidon2_sponge::{CircuitAbsorptionMode, CircuitPoseidon2Sponge};
+use crate::common::utils::fe_from_u64;
+use crate::poseidon2::TranscriptMode;
+use crate::sponge::absorb_into_rate;
+use crate::traits::HashParams;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Engine, Field, SynthesisError};
+use franklin_crypto::plonk::circuit::{allocated_num::Num, linear_combination::LinearCombination};
+use std::collections::VecDeque;
+
+/// Circuit counterpart of `Poseidon2Transcript` over Goldilocks, so circuits
+/// can recompute challenges of transcripts produced natively, e.g. a Bn256
+/// wrapper verifying a Boojum proof. Witnessed small field elements are
+/// range checked by `enforce_goldilocks_range` and packed `CAPACITY / 64`
+/// per element exactly as the native transcript does.
+pub struct CircuitPoseidon2Transcript<
+    E: Engine,
+    M: CircuitAbsorptionMode<E>,
+    const RATE: usize,
+    const WIDTH: usize,
+> {
+    buffer: Vec<LinearCombination<E>>,
+    last_filled: usize,
+    available_challenges: VecDeque<Num<E>>,
+    mode: TranscriptMode,
+    sponge: CircuitPoseidon2Sponge<E, M, RATE, WIDTH>,
+}
+
+impl<E: Engine, M: CircuitAbsorptionMode<E>, const RATE: usize, const WIDTH: usize>
+    CircuitPoseidon2Transcript<E, M, RATE, WIDTH>
+{
+    pub fn new() -> Self {
+        Self::new_with_mode(TranscriptMode::Compatible)
+    }
+
+    pub fn new_with_mode(mode: TranscriptMode) -> Self {
+        Self {
+            buffer: Vec::new(),
+            last_filled: 0,
+            available_challenges: VecDeque::new(),
+            mode,
+            sponge: CircuitPoseidon2Sponge::new(),
+        }
+    }
+
+    /// Circuit counterpart of `Poseidon2Transcript::new_bound_to_params`.
+    pub fn new_bound_to_params<CS: ConstraintSystem<E>>(cs: &mut CS) -> Result<Self, SynthesisError> {
+        let mut transcript = Self::new();
+        let fingerprint = crate::poseidon2::Poseidon2Params::<E, RATE, WIDTH>::shared().fingerprint();
+        let elements: Vec<_> = fingerprint
+            .chunks_exact(4)
+            .map(|chunk| Num::Constant(fe_from_u64::<E>(u32::from_le_bytes(chunk.try_into().unwrap()) as u64)))
+            .collect();
+        transcript.witness_field_elements(cs, &elements)?;
+
+        Ok(transcript)
+    }
+
+    /// Circuit counterpart of `Transcript::witness_field_elements`. Each
+    /// element is enforced to be a canonical Goldilocks element.
+    pub fn witness_field_elements<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: &mut CS,
+        field_els: &[Num<E>],
+    ) -> Result<(), SynthesisError> {
+        for el in field_els.iter() {
+            enforce_goldilocks_range(cs, el)?;
+        }
+
+        let capasity_per_element = CircuitPoseidon2Sponge::<E, M, RATE, WIDTH>::capasity_per_element();
+        debug_assert!(self.last_filled < capasity_per_element);
+
+        let add_to_last = field_els.len().min((capasity_per_element - self.last_filled) % capasity_per_element);
+
+        if add_to_last != 0 {
+            let last = self.buffer.last_mut().unwrap();
+            pack_into(last, &field_els[..add_to_last], self.last_filled);
+        }
+
+        for chunk in field_els[add_to_last..].chunks(capasity_per_element) {
+            let mut packed = LinearCombination::zero();
+            pack_into(&mut packed, chunk, 0);
+            self.buffer.push(packed);
+        }
+
+        self.last_filled = (self.last_filled + field_els.len()) % capasity_per_element;
+
+        self.available_challenges = VecDeque::new();
+
+        Ok(())
+    }
+
+    /// Circuit counterpart of `Transcript::witness_merkle_tree_cap`.
+    pub fn witness_merkle_tree_cap(&mut self, cap: &[Num<E>]) {
+        self.last_filled = 0;
+        self.buffer.extend(cap.iter().map(|el| LinearCombination::from(*el)));
+
+        self.available_challenges = VecDeque::new();
+    }
+
+    /// Circuit counterpart of `Transcript::get_challenge`. Challenges are
+    /// canonical Goldilocks elements.
+    pub fn get_challenge<CS: ConstraintSystem<E>>(&mut self, cs: &mut CS) -> Result<Num<E>, SynthesisError> {
+        assert_eq!(self.sponge.filled, 0);
+
+        if self.buffer.is_empty() {
+            if let Some(challenge) = self.available_challenges.pop_front() {
+                return Ok(challenge);
+            }
+
+            self.sponge.run_round_function(cs)?;
+            let commitment = self
+                .sponge
+                .try_get_committment(cs)?
+                .expect("must have no pending elements in the buffer");
+            self.extend_challenges(cs, &commitment)?;
+
+            return self.get_challenge(cs);
+        }
+
+        let to_absorb = core::mem::take(&mut self.buffer);
+        self.last_filled = 0;
+        self.available_challenges = VecDeque::new();
+
+        let commitment = match self.mode {
+            TranscriptMode::Lazy if to_absorb.len() % RATE == 0 => {
+                let mut to_absorb = to_absorb;
+                let last_chunk = to_absorb.split_off(to_absorb.len() - RATE);
+                for el in to_absorb.into_iter() {
+                    self.sponge.absorb_single_lc(cs, el)?;
+                }
+                absorb_into_rate::<_, _, RATE, WIDTH>(&mut self.sponge.state, &last_chunk, M::absorb_lc);
+                self.sponge.state[WIDTH - 1].add_assign_constant(E::Fr::one());
+                self.sponge.run_round_function(cs)?;
+
+                self.sponge
+                    .try_get_committment(cs)?
+                    .expect("must have no pending elements in the buffer")
+            }
+            TranscriptMode::Compatible | TranscriptMode::Lazy => {
+                for el in to_absorb.into_iter() {
+                    self.sponge.absorb_single_lc(cs, el)?;
+                }
+                self.sponge.finalize(cs)?
+            }
+        };
+        self.extend_challenges(cs, &commitment)?;
+
+        // to avoid duplication
+        self.get_challenge(cs)
+    }
+
+    /// Circuit counterpart of `Poseidon2Transcript::get_extension_challenge`.
+    pub fn get_extension_challenge<CS: ConstraintSystem<E>, const N: usize>(
+        &mut self,
+        cs: &mut CS,
+    ) -> Result<[Num<E>; N], SynthesisError> {
+        let mut challenges = [Num::Constant(E::Fr::zero()); N];
+        for challenge in challenges.iter_mut() {
+            *challenge = self.get_challenge(cs)?;
+        }
+
+        Ok(challenges)
+    }
+
+    fn extend_challenges<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: &mut CS,
+        commitment: &[Num<E>; RATE],
+    ) -> Result<(), SynthesisError> {
+        for el in commitment.iter() {
+            self.available_challenges.extend(circuit_get_challenges_from_fr(cs, el)?);
+        }
+
+        Ok(())
+    }
+}
+
+impl<E: Engine, M: CircuitAbsorptionMode<E>, const RATE: usize, const WIDTH: usize> Default
+    for CircuitPoseidon2Transcript<E, M, RATE, WIDTH>
+{
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// Adds `els` to `dst` as limbs starting from limb `offset`, without range checks.
+fn pack_into<E: Engine>(dst: &mut LinearCombination<E>, els: &[Num<E>], offset: usize) {
+    let mut shift = E::Fr::one();
+    for _ in 0..GOLDILOCKS_CHAR_BITS {
+        shift.double();
+    }
+
+    let mut coeff = E::Fr::one();
+    for _ in 0..offset {
+        coeff.mul_assign(&shift);
+    }
+    for el in els.iter() {
+        dst.add_assign_number_with_coeff(el, coeff);
+        coeff.mul_assign(&shift);
+    }
+}
+
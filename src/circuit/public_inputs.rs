@@ -0,0 +1,116 @@
+use super::sponge::CircuitGenericSponge;
+use crate::poseidon2::Poseidon2Params;
+use crate::public_inputs::{public_inputs_domain_tag, truncate_to_capacity};
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::pairing::ff::BitIterator;
+use franklin_crypto::bellman::{Engine, Field, PrimeField, SynthesisError};
+use franklin_crypto::plonk::circuit::{
+    allocated_num::Num, boolean::Boolean, linear_combination::LinearCombination,
+};
+
+/// Circuit counterpart of `hash_public_inputs`. Uses same domain tag and
+/// truncation rule so result matches native one.
+pub fn circuit_hash_public_inputs<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    inputs: &[Num<E>],
+) -> Result<Num<E>, SynthesisError> {
+    let params = Poseidon2Params::<E, 2, 3>::default();
+
+    let mut input_with_tag = Vec::with_capacity(inputs.len() + 1);
+    input_with_tag.push(Num::Constant(public_inputs_domain_tag::<E>()));
+    input_with_tag.extend_from_slice(inputs);
+
+    let output = CircuitGenericSponge::<E, 2, 3>::hash_num(cs, &input_with_tag, &params, None)?;
+
+    circuit_truncate_to_capacity(cs, &output[0])
+}
+
+// Decomposes value into canonical bits and recombines lower CAPACITY bits.
+fn circuit_truncate_to_capacity<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    value: &Num<E>,
+) -> Result<Num<E>, SynthesisError> {
+    let value = match value {
+        Num::Constant(constant) => return Ok(Num::Constant(truncate_to_capacity::<E>(*constant))),
+        Num::Variable(value) => value,
+    };
+
+    let bits = value.into_bits_le(cs, Some(E::Fr::NUM_BITS as usize))?;
+    enforce_bits_are_canonical::<E, CS>(cs, &bits)?;
+
+    let mut lc = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+    for bit in bits[..E::Fr::CAPACITY as usize].iter() {
+        lc.add_assign_boolean_with_coeff(bit, coeff);
+        coeff.double();
+    }
+
+    lc.into_num(cs)
+}
+
+// Bit decomposition of NUM_BITS length is not unique so we enforce that
+// decomposed value is strictly less than modulus.
+pub(crate) fn enforce_bits_are_canonical<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    bits: &[Boolean],
+) -> Result<(), SynthesisError> {
+    assert_eq!(bits.len(), E::Fr::NUM_BITS as usize);
+
+    enforce_bits_less_than::<E, CS, _>(cs, bits, E::Fr::char())
+}
+
+// Enforces that value of little endian bits is strictly less than the bound,
+// which shouldn't be longer than the bits.
+pub(crate) fn enforce_bits_less_than<E: Engine, CS: ConstraintSystem<E>, R: AsRef<[u64]>>(
+    cs: &mut CS,
+    bits: &[Boolean],
+    bound: R,
+) -> Result<(), SynthesisError> {
+    let modulus_bits: Vec<bool> = BitIterator::new(bound).collect();
+    let (high_bits, modulus_bits) = modulus_bits.split_at(modulus_bits.len() - bits.len());
+    assert!(high_bits.iter().all(|bit| !bit), "bound is longer than the bits");
+
+    // walk from the most significant bit, value is less than modulus
+    // when first differing bit is zero in value and one in modulus
+    let mut equal_so_far = Boolean::constant(true);
+    let mut less_than = Boolean::constant(false);
+    for (bit, modulus_bit) in bits.iter().rev().zip(modulus_bits.iter()) {
+        if *modulus_bit {
+            let becomes_less = Boolean::and(cs, &equal_so_far, &bit.not())?;
+            let not_less = Boolean::and(cs, &less_than.not(), &becomes_less.not())?;
+            less_than = not_less.not();
+            equal_so_far = Boolean::and(cs, &equal_so_far, bit)?;
+        } else {
+            equal_so_far = Boolean::and(cs, &equal_so_far, &bit.not())?;
+        }
+    }
+
+    Boolean::enforce_equal(cs, &less_than, &Boolean::constant(true))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::public_inputs::hash_public_inputs;
+    use crate::tests::{init_cs, init_rng};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_circuit_hash_public_inputs() {
+        let cs = &mut init_cs::<Bn256>();
+        let rng = &mut init_rng();
+
+        let inputs: Vec<_> = (0..5).map(|_| Fr::rand(rng)).collect();
+        let inputs_as_num: Vec<_> = inputs
+            .iter()
+            .map(|el| Num::alloc(cs, Some(*el)).unwrap())
+            .collect();
+
+        let expected = hash_public_inputs::<Bn256>(&inputs);
+        let actual = circuit_hash_public_inputs(cs, &inputs_as_num).unwrap();
+
+        assert_eq!(actual.get_value().unwrap(), expected);
+        assert!(cs.is_satisfied());
+    }
+}
@@ -1,6 +1,7 @@
-use super::sbox::sbox;
+use super::sbox::{sbox, sbox_with_witness};
 use super::matrix::matrix_vector_product;
-use crate::{DomainStrategy, circuit::sponge::circuit_generic_hash_num, traits::{HashFamily, HashParams}};
+use super::error::{Gadget, GadgetError};
+use crate::{DomainStrategy, circuit::sponge::circuit_generic_hash_num, traits::{CustomGate, HashFamily, HashParams}};
 use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
 
 use crate::rescue::params::RescueParams;
@@ -36,12 +37,15 @@ pub(crate) fn circuit_rescue_round_function<
     cs: &mut CS,
     params: &P,
     state: &mut [LinearCombination<E>; WIDTH],
-) -> Result<(), SynthesisError> {
+    custom_gate: CustomGate,
+    precomputed: &mut std::slice::Iter<E::Fr>,
+) -> Result<(), GadgetError> {
     assert_eq!(
         params.hash_family(),
         HashFamily::Rescue,
         "Incorrect hash family!"
     );
+    let context = |gadget, round| GadgetError::context(HashFamily::Rescue, gadget, round, custom_gate);
     state
         .iter_mut()
         .zip(params.constants_of_round(0).iter())
@@ -50,24 +54,28 @@ pub(crate) fn circuit_rescue_round_function<
     for round in 0..2 * params.number_of_full_rounds() {
         // apply sbox
         if round & 1 == 0 {
-            sbox(
+            sbox_with_witness(
                 cs,
                 params.alpha_inv(),
                 state,
                 None,
-                params.custom_gate(),
-            )?;
+                custom_gate,
+                precomputed,
+            )
+            .map_err(context(Gadget::InverseSbox, round))?;
         } else {
             sbox(
                 cs,
                 params.alpha(),
                 state,
                 None,
-                params.custom_gate(),
-            )?;
+                custom_gate,
+            )
+            .map_err(context(Gadget::Sbox, round))?;
         }
         // mds row
-        matrix_vector_product(&params.mds_matrix(), state)?;
+        matrix_vector_product(&params.mds_matrix(), state)
+            .map_err(context(Gadget::LinearLayer, round))?;
 
         // round constants
         for (s, c) in state
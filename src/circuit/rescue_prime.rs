@@ -1,8 +1,9 @@
 use super::sbox::*;
 use super::sponge::circuit_generic_hash_num;
 use super::matrix::matrix_vector_product;
+use super::error::{Gadget, GadgetError};
 use crate::{DomainStrategy, rescue_prime::params::RescuePrimeParams};
-use crate::traits::{HashFamily, HashParams};
+use crate::traits::{CustomGate, HashFamily, HashParams};
 use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
 use franklin_crypto::bellman::SynthesisError;
 use franklin_crypto::{
@@ -36,12 +37,15 @@ pub(crate) fn gadget_rescue_prime_round_function<
     cs: &mut CS,
     params: &P,
     state: &mut [LinearCombination<E>; WIDTH],
-) -> Result<(), SynthesisError> {
+    custom_gate: CustomGate,
+    precomputed: &mut std::slice::Iter<E::Fr>,
+) -> Result<(), GadgetError> {
     assert_eq!(
         params.hash_family(),
         HashFamily::RescuePrime,
         "Incorrect hash family!"
     );
+    let context = |gadget, round| GadgetError::context(HashFamily::RescuePrime, gadget, round, custom_gate);
 
     for round in 0..params.number_of_full_rounds() - 1 {
         // apply sbox
@@ -52,11 +56,13 @@ pub(crate) fn gadget_rescue_prime_round_function<
             params.alpha(),
             state,
             None,
-            params.custom_gate(),
-        )?;
+            custom_gate,
+        )
+        .map_err(context(Gadget::Sbox, round))?;
 
         // mul by mds
-        matrix_vector_product(&params.mds_matrix(), state)?;
+        matrix_vector_product(&params.mds_matrix(), state)
+            .map_err(context(Gadget::LinearLayer, round))?;
 
         // round constants
         let constants = params.constants_of_round(round);
@@ -64,16 +70,19 @@ pub(crate) fn gadget_rescue_prime_round_function<
             s.add_assign_constant(c);
         }
         // apply inverse sbox
-        sbox(
+        sbox_with_witness(
             cs,
             params.alpha_inv(),
             state,
             None,
-            params.custom_gate(),
-        )?;
+            custom_gate,
+            precomputed,
+        )
+        .map_err(context(Gadget::InverseSbox, round))?;
 
         // mul by mds
-        matrix_vector_product(&params.mds_matrix(), state)?;
+        matrix_vector_product(&params.mds_matrix(), state)
+            .map_err(context(Gadget::LinearLayer, round))?;
 
         // round constants
         let constants = params.constants_of_round(round + 1);
@@ -0,0 +1,89 @@
+use super::lc_state::take_lc;
+use super::sponge::circuit_generic_round_function;
+use crate::safe::{op_len, IOPattern, IOTracker, SpongeOp};
+use crate::traits::HashParams;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Engine, Field, SynthesisError};
+use franklin_crypto::plonk::circuit::{allocated_num::Num, linear_combination::LinearCombination};
+
+/// Circuit counterpart of `SafeSponge`. IO pattern is fixed at synthesis
+/// time, so calls not following it panic instead of returning an error.
+pub struct CircuitSafeSponge<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize> {
+    state: [LinearCombination<E>; WIDTH],
+    params: P,
+    tracker: IOTracker,
+    absorb_pos: usize,
+    squeeze_pos: usize,
+}
+
+impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>
+    CircuitSafeSponge<E, P, RATE, WIDTH>
+{
+    /// SAFE `START`, tag is a constant of the circuit.
+    pub fn new(params: P, pattern: IOPattern, domain_separator: &[u8]) -> Self {
+        assert!(RATE < WIDTH, "SAFE requires a capacity element");
+        let mut state: [LinearCombination<E>; WIDTH] = core::array::from_fn(|_| LinearCombination::zero());
+        state[RATE].add_assign_constant(pattern.tag::<E>(domain_separator));
+
+        Self {
+            state,
+            params,
+            tracker: IOTracker::new(pattern),
+            absorb_pos: 0,
+            squeeze_pos: 0,
+        }
+    }
+
+    /// SAFE `ABSORB` of `input.len()` elements.
+    pub fn absorb<CS: ConstraintSystem<E>>(&mut self, cs: &mut CS, input: &[Num<E>]) -> Result<(), SynthesisError> {
+        self.consume(SpongeOp::Absorb(op_len(input.len())));
+
+        for el in input.iter() {
+            if self.absorb_pos == RATE {
+                circuit_generic_round_function(cs, &mut self.state, &self.params)?;
+                self.absorb_pos = 0;
+            }
+            self.state[self.absorb_pos].add_assign_number_with_coeff(el, E::Fr::one());
+            self.absorb_pos += 1;
+        }
+        self.squeeze_pos = RATE;
+
+        Ok(())
+    }
+
+    /// SAFE `SQUEEZE` of `length` elements.
+    pub fn squeeze<CS: ConstraintSystem<E>>(&mut self, cs: &mut CS, length: usize) -> Result<Vec<Num<E>>, SynthesisError> {
+        self.consume(SpongeOp::Squeeze(op_len(length)));
+
+        let mut output = Vec::with_capacity(length);
+        for _ in 0..length {
+            if self.squeeze_pos == RATE {
+                circuit_generic_round_function(cs, &mut self.state, &self.params)?;
+                self.squeeze_pos = 0;
+                self.absorb_pos = 0;
+            }
+            // squeezed element stays in the state as a num, so absorbing
+            // into it doesn't pay for the long combination again
+            let lc = &mut self.state[self.squeeze_pos];
+            let num = take_lc(lc).into_num(cs)?;
+            *lc = LinearCombination::from(num);
+            output.push(num);
+            self.squeeze_pos += 1;
+        }
+
+        Ok(output)
+    }
+
+    /// SAFE `FINISH`, panics unless every operation of the pattern is done.
+    pub fn finish(self) {
+        if let Err(e) = self.tracker.finish() {
+            panic!("{}", e);
+        }
+    }
+
+    fn consume(&mut self, op: SpongeOp) {
+        if let Err(e) = self.tracker.consume(op) {
+            panic!("{}", e);
+        }
+    }
+}
@@ -16,6 +16,7 @@ use franklin_crypto::{
 use franklin_crypto::plonk::circuit::Assignment;
 
 use crate::traits::{CustomGate, Sbox};
+use super::lc_state::take_lc;
 
 // Substitution box is non-linear part of permutation function.
 // It basically computes 5th power of each element in the state.
@@ -29,6 +30,19 @@ pub(crate) fn sbox<E: Engine, CS: ConstraintSystem<E>, const WIDTH: usize>(
     prev_state: &mut [LinearCombination<E>; WIDTH],
     use_partial_state: Option<std::ops::Range<usize>>,
     custom_gate: CustomGate,
+) -> Result<(), SynthesisError> {
+    sbox_with_witness(cs, power, prev_state, use_partial_state, custom_gate, &mut [].iter())
+}
+
+// Inverse sbox takes its outputs from `precomputed`, one per state element,
+// and computes them only if it is exhausted. Forward sbox ignores it.
+pub(crate) fn sbox_with_witness<E: Engine, CS: ConstraintSystem<E>, const WIDTH: usize>(
+    cs: &mut CS,
+    power: &Sbox,
+    prev_state: &mut [LinearCombination<E>; WIDTH],
+    use_partial_state: Option<std::ops::Range<usize>>,
+    custom_gate: CustomGate,
+    precomputed: &mut std::slice::Iter<E::Fr>,
 ) -> Result<(), SynthesisError> {
     let state_range = if let Some(partial_range) = use_partial_state{
         partial_range
@@ -45,11 +59,11 @@ pub(crate) fn sbox<E: Engine, CS: ConstraintSystem<E>, const WIDTH: usize>(
             custom_gate,
         ),
         Sbox::AlphaInverse(alpha_inv, alpha) => {           
-            sbox_alpha_inv(cs, alpha_inv, alpha, prev_state, custom_gate)
+            sbox_alpha_inv(cs, alpha_inv, alpha, prev_state, custom_gate, precomputed)
         },
         Sbox::AddChain(chain, alpha) => {         
             // in circuit there is no difference  
-            sbox_alpha_inv_via_add_chain(cs, chain, alpha, prev_state, custom_gate)
+            sbox_alpha_inv_via_add_chain(cs, chain, alpha, prev_state, custom_gate, precomputed)
         },
     }
 }
@@ -72,7 +86,7 @@ fn sbox_alpha<E: Engine, CS: ConstraintSystem<E>, const WIDTH: usize>(
         unimplemented!("only 5th power is supported!")
     }
     for lc in prev_state[state_range].iter_mut() {
-        match lc.clone().into_num(cs)? {
+        match take_lc(lc).into_num(cs)? {
             Num::Constant(value) => {
                 let result = value.pow(&[*alpha]);
                 *lc = LinearCombination::zero();
@@ -104,6 +118,7 @@ fn sbox_alpha_inv<E: Engine, CS: ConstraintSystem<E>, const WIDTH: usize>(
     alpha: &u64,
     prev_state: &mut [LinearCombination<E>; WIDTH],
     custom_gate: CustomGate,
+    precomputed: &mut std::slice::Iter<E::Fr>,
 ) -> Result<(), SynthesisError> {
     let use_custom_gate = match custom_gate {
         CustomGate::None => false,
@@ -115,17 +130,18 @@ fn sbox_alpha_inv<E: Engine, CS: ConstraintSystem<E>, const WIDTH: usize>(
     }
 
     for lc in prev_state.iter_mut() {
-        match lc.clone().into_num(cs)? {
+        let precomputed_value = precomputed.next().cloned();
+        match take_lc(lc).into_num(cs)? {
             Num::Constant(value) => {
                 let result = value.pow(alpha_inv);
                 *lc = LinearCombination::zero();
                 lc.add_assign_constant(result);
             }
             Num::Variable(ref value) => {
-                let wit: Option<E::Fr> = value.get_value().map(|base| {
+                let wit: Option<E::Fr> = precomputed_value.or_else(|| value.get_value().map(|base| {
                     let result = base.pow(alpha_inv);
                     result
-                });
+                }));
 
                 let powered = AllocatedNum::alloc(cs, || wit.grab())?;
 
@@ -162,6 +178,7 @@ fn sbox_alpha_inv_via_add_chain<E: Engine, CS: ConstraintSystem<E>, const WIDTH:
     alpha: &u64,
     prev_state: &mut [LinearCombination<E>; WIDTH],
     custom_gate: CustomGate,
+    precomputed: &mut std::slice::Iter<E::Fr>,
 ) -> Result<(), SynthesisError> {
     let use_custom_gate = match custom_gate {
         CustomGate::None => false,
@@ -173,7 +190,8 @@ fn sbox_alpha_inv_via_add_chain<E: Engine, CS: ConstraintSystem<E>, const WIDTH:
     }
 
     for lc in prev_state.iter_mut() {
-        match lc.clone().into_num(cs)? {
+        let precomputed_value = precomputed.next().cloned();
+        match take_lc(lc).into_num(cs)? {
             Num::Constant(value) => {
                 let mut scratch = smallvec::SmallVec::<[E::Fr; 512]>::new();
                 let result = crate::add_chain_pow_smallvec(value, addition_chain, &mut scratch);
@@ -181,12 +199,12 @@ fn sbox_alpha_inv_via_add_chain<E: Engine, CS: ConstraintSystem<E>, const WIDTH:
                 lc.add_assign_constant(result);
             }
             Num::Variable(ref value) => {
-                let wit: Option<E::Fr> = value.get_value().map(|el| {
+                let wit: Option<E::Fr> = precomputed_value.or_else(|| value.get_value().map(|el| {
                     let mut scratch = smallvec::SmallVec::<[E::Fr; 512]>::new();
                     let result = crate::add_chain_pow_smallvec(el, addition_chain, &mut scratch);
 
                     result
-                });
+                }));
 
                 let powered = AllocatedNum::alloc(cs, || wit.grab())?;
 
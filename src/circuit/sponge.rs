@@ -1,17 +1,23 @@
 use crate::{
     common::domain_strategy::DomainStrategy,
-    traits::{HashFamily, HashParams}, poseidon2::Poseidon2Params,
+    traits::{CustomGate, HashFamily, HashParams}, poseidon2::Poseidon2Params,
 };
 use franklin_crypto::{
     bellman::plonk::better_better_cs::cs::ConstraintSystem, plonk::circuit::allocated_num::Num,
 };
-use franklin_crypto::{bellman::Field, plonk::circuit::boolean::Boolean};
+use franklin_crypto::{bellman::{Field, PrimeField}, plonk::circuit::boolean::Boolean};
 use franklin_crypto::{
     bellman::{Engine, SynthesisError},
     plonk::circuit::linear_combination::LinearCombination,
 };
-use std::convert::TryInto;
-
+use crate::sponge::{absorb_into_rate, validate_initial_state, OutputConvention, SpongeStateError};
+use crate::witness::HashWitness;
+use crate::common::utils::try_slice_into_fixed;
+use super::error::GadgetError;
+use super::lc_state::{take_lc, zero_state};
+
+/// Custom gate of `params` is overridden by `custom_gate`. If it is not given
+/// the best gate supported by constraint system is selected.
 pub fn circuit_generic_hash<
     E: Engine,
     CS: ConstraintSystem<E>,
@@ -24,8 +30,70 @@ pub fn circuit_generic_hash<
     input: &[Num<E>; LENGTH],
     params: &P,
     domain_strategy: Option<DomainStrategy>,
+    custom_gate: Option<CustomGate>,
+) -> Result<[LinearCombination<E>; RATE], SynthesisError> {
+    let custom_gate = custom_gate.unwrap_or_else(|| CustomGate::select_for::<E, CS>());
+
+    circuit_generic_hash_with_gate(cs, input, params, custom_gate, domain_strategy)
+}
+
+/// Same as `circuit_generic_hash` with an explicit gate. Params are only
+/// read, so the same params can be shared by circuits using different gates.
+pub fn circuit_generic_hash_with_gate<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+    const LENGTH: usize,
+>(
+    cs: &mut CS,
+    input: &[Num<E>; LENGTH],
+    params: &P,
+    custom_gate: CustomGate,
+    domain_strategy: Option<DomainStrategy>,
+) -> Result<[LinearCombination<E>; RATE], SynthesisError> {
+    assert!(
+        custom_gate.is_supported_by::<E, CS>(),
+        "{:?} is not supported by constraint system",
+        custom_gate
+    );
+
+    CircuitGenericSponge::hash_with_gate(cs, input, params, domain_strategy, OutputConvention::Rate, custom_gate, None)
+}
+
+/// Same as `circuit_generic_hash` with default domain strategy but outputs of
+/// inverse sboxes are taken from `witness` instead of being computed during
+/// synthesis. Witness should be computed by `precompute_witnesses` for the
+/// same params and values of `input`, otherwise circuit is not satisfied.
+pub fn circuit_generic_hash_with_witness<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+    const LENGTH: usize,
+>(
+    cs: &mut CS,
+    input: &[Num<E>; LENGTH],
+    params: &P,
+    witness: &HashWitness<E>,
 ) -> Result<[LinearCombination<E>; RATE], SynthesisError> {
-    CircuitGenericSponge::hash(cs, input, params, domain_strategy)
+    assert_eq!(
+        witness.num_permutations(),
+        (LENGTH + RATE - 1) / RATE,
+        "witness is computed for an input of different length"
+    );
+
+    CircuitGenericSponge::hash_with_gate(
+        cs,
+        input,
+        params,
+        None,
+        OutputConvention::Rate,
+        params.custom_gate(),
+        Some(witness),
+    )
 }
 
 pub fn circuit_generic_hash_num<
@@ -55,6 +123,8 @@ pub struct CircuitGenericSponge<E: Engine, const RATE: usize, const WIDTH: usize
     state: [LinearCombination<E>; WIDTH],
     mode: SpongeMode<E, RATE>,
     domain_strategy: DomainStrategy,
+    // state has no variables yet, so permutation can be computed natively
+    state_is_constant: bool,
 }
 
 impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> CircuitGenericSponge<E, RATE, WIDTH> {
@@ -67,37 +137,84 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> CircuitGenericSponge<
             DomainStrategy::CustomVariableLength | DomainStrategy::VariableLength => (),
             _ => panic!("only variable length domain strategies allowed"),
         }
-        let state = (0..WIDTH)
-            .map(|_| LinearCombination::zero())
-            .collect::<Vec<_>>()
-            .try_into()
-            .expect("constant array");
+        let state = zero_state();
         Self {
             state,
             mode: SpongeMode::Absorb([None; RATE]),
             domain_strategy: domain_strategy,
+            state_is_constant: true,
         }
     }
 
+    /// Circuit counterpart of `GenericSponge::new_with_initial_state`.
+    /// Initial state is a constant.
+    pub fn new_with_initial_state(
+        initial_state: [E::Fr; WIDTH],
+        domain_strategy: DomainStrategy,
+    ) -> Result<Self, SpongeStateError> {
+        validate_initial_state::<E, RATE, WIDTH>(&initial_state, &domain_strategy)?;
+        let state = core::array::from_fn(|i| {
+            let mut lc = LinearCombination::zero();
+            lc.add_assign_constant(initial_state[i]);
+            lc
+        });
+
+        Ok(Self {
+            state,
+            mode: SpongeMode::Absorb([None; RATE]),
+            domain_strategy,
+            state_is_constant: true,
+        })
+    }
+
+    pub fn rate(&self) -> usize {
+        RATE
+    }
+
+    pub fn capacity(&self) -> usize {
+        WIDTH - RATE
+    }
+
     pub fn hash<CS: ConstraintSystem<E>, P: HashParams<E, RATE, WIDTH>>(
         cs: &mut CS,
         input: &[Num<E>],
         params: &P,
         domain_strategy: Option<DomainStrategy>,
     ) -> Result<[LinearCombination<E>; RATE], SynthesisError> {
+        Self::hash_with_output_convention(cs, input, params, domain_strategy, OutputConvention::Rate)
+    }
+
+    /// Circuit counterpart of `GenericSponge::hash_with_output_convention`.
+    pub fn hash_with_output_convention<CS: ConstraintSystem<E>, P: HashParams<E, RATE, WIDTH>>(
+        cs: &mut CS,
+        input: &[Num<E>],
+        params: &P,
+        domain_strategy: Option<DomainStrategy>,
+        output_convention: OutputConvention,
+    ) -> Result<[LinearCombination<E>; RATE], SynthesisError> {
+        Self::hash_with_gate(cs, input, params, domain_strategy, output_convention, params.custom_gate(), None)
+    }
+
+    // Permutations use `custom_gate` instead of the one of params and take
+    // inverse sbox outputs from `witness` if it is given.
+    fn hash_with_gate<CS: ConstraintSystem<E>, P: HashParams<E, RATE, WIDTH>>(
+        cs: &mut CS,
+        input: &[Num<E>],
+        params: &P,
+        domain_strategy: Option<DomainStrategy>,
+        output_convention: OutputConvention,
+        custom_gate: CustomGate,
+        witness: Option<&HashWitness<E>>,
+    ) -> Result<[LinearCombination<E>; RATE], SynthesisError> {
+        assert!(!input.is_empty(), "empty input");
         let domain_strategy = domain_strategy.unwrap_or(DomainStrategy::CustomFixedLength);
         match domain_strategy {
-            DomainStrategy::CustomFixedLength | DomainStrategy::FixedLength => (),
+            DomainStrategy::CustomFixedLength | DomainStrategy::FixedLength | DomainStrategy::NoPadding => (),
             _ => panic!("only fixed length domain strategies allowed"),
         }
         // init state
-        let mut state: [LinearCombination<E>; WIDTH] = (0..WIDTH)
-            .map(|_| LinearCombination::zero())
-            .collect::<Vec<LinearCombination<E>>>()
-            .try_into()
-            .expect("constant array of LCs");
+        let mut state: [LinearCombination<E>; WIDTH] = zero_state();
 
-        let domain_strategy = DomainStrategy::CustomFixedLength;
         // specialize capacity
         let capacity_value = domain_strategy
             .compute_capacity::<E>(input.len(), RATE)
@@ -107,34 +224,56 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> CircuitGenericSponge<
             .expect("last element")
             .add_assign_constant(capacity_value);
 
-        // compute padding values
-        let padding_values = domain_strategy
-            .generate_padding_values::<E>(input.len(), RATE)
-            .iter()
-            .map(|el| Num::Constant(*el))
-            .collect::<Vec<Num<E>>>();
-
-        // chain all values
-        let mut padded_input = smallvec::SmallVec::<[_; 9]>::new();
-        padded_input.extend_from_slice(input);
-        padded_input.extend_from_slice(&padding_values);
-
-        assert!(padded_input.len() % RATE == 0);
+        // process each full chunk of input directly, no intermediate buffer needed
+        let mut state_is_constant = true;
+        let precomputed = |permutation: usize| witness.map_or(&[][..], |w| w.permutation(permutation));
+        let mut chunks = input.chunks_exact(RATE);
+        let mut permutation = 0;
+        for values in &mut chunks {
+            absorb(
+                cs,
+                &mut state,
+                &mut state_is_constant,
+                &try_slice_into_fixed(values).expect("constant array"),
+                params,
+                custom_gate,
+                precomputed(permutation),
+            )?;
+            permutation += 1;
+        }
 
-        // process each chunk of input
-        for values in padded_input.chunks_exact(RATE) {
+        // last chunk is completed with padding values on the stack
+        let remainder = chunks.remainder();
+        if !remainder.is_empty() {
+            let mut padding_values = [E::Fr::zero(); RATE];
+            domain_strategy.fill_padding_values::<E>(
+                input.len(),
+                RATE,
+                &mut padding_values[remainder.len()..],
+            );
+            let mut last_chunk = [Num::Constant(E::Fr::zero()); RATE];
+            last_chunk[..remainder.len()].copy_from_slice(remainder);
+            for (dst, src) in last_chunk[remainder.len()..]
+                .iter_mut()
+                .zip(padding_values[remainder.len()..].iter())
+            {
+                *dst = Num::Constant(*src);
+            }
             absorb(
                 cs,
                 &mut state,
-                values.try_into().expect("constant array"),
+                &mut state_is_constant,
+                &last_chunk,
                 params,
+                custom_gate,
+                precomputed(permutation),
             )?;
         }
 
         // prepare output
         let mut output = arrayvec::ArrayVec::<_, RATE>::new();
-        for s in state[..RATE].iter() {
-            output.push(s.clone());
+        for i in 0..RATE {
+            output.push(take_lc(&mut state[output_convention.state_index(i, RATE, WIDTH)]));
         }
 
         Ok(output.into_inner().expect("array"))
@@ -146,7 +285,17 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> CircuitGenericSponge<
         params: &P,
         domain_strategy: Option<DomainStrategy>
     ) -> Result<[Num<E>; RATE], SynthesisError> {
-        let result = Self::hash(cs, input, params, domain_strategy)?;
+        Self::hash_num_with_output_convention(cs, input, params, domain_strategy, OutputConvention::Rate)
+    }
+
+    pub fn hash_num_with_output_convention<CS: ConstraintSystem<E>, P: HashParams<E, RATE, WIDTH>>(
+        cs: &mut CS,
+        input: &[Num<E>],
+        params: &P,
+        domain_strategy: Option<DomainStrategy>,
+        output_convention: OutputConvention,
+    ) -> Result<[Num<E>; RATE], SynthesisError> {
+        let result = Self::hash_with_output_convention(cs, input, params, domain_strategy, output_convention)?;
         // prepare output
         let mut output = [Num::Constant(E::Fr::zero()); RATE];
         for (o, s) in output.iter_mut().zip(result.into_iter()) {
@@ -156,14 +305,20 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> CircuitGenericSponge<
         Ok(output)
     }
 
+    /// Same padding rule as `GenericSponge::absorb_multiple`.
     pub fn absorb_multiple<CS: ConstraintSystem<E>, P: HashParams<E, RATE, WIDTH>>(
         &mut self,
         cs: &mut CS,
         input: &[Num<E>],
         params: &P,
     ) -> Result<(), SynthesisError> {
-        for inp in input.into_iter() {
-            self.absorb(cs, *inp, params)?
+        if input.is_empty() {
+            return Ok(());
+        }
+        let padding_values = self.domain_strategy.generate_padding_values::<E>(input.len(), RATE);
+
+        for inp in input.iter().cloned().chain(padding_values.into_iter().map(Num::Constant)) {
+            self.absorb(cs, inp, params)?
         }
 
         Ok(())
@@ -196,7 +351,15 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> CircuitGenericSponge<
                 }
 
                 // here we can absorb values. run round function implicitly there
-                absorb::<_, _, P, RATE, WIDTH>(cs, &mut self.state, &mut unwrapped_buffer, params)?;
+                absorb::<_, _, P, RATE, WIDTH>(
+                    cs,
+                    &mut self.state,
+                    &mut self.state_is_constant,
+                    &mut unwrapped_buffer,
+                    params,
+                    params.custom_gate(),
+                    &[],
+                )?;
 
                 // absorb value
                 buf[0] = Some(input);
@@ -212,15 +375,68 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> CircuitGenericSponge<
         Ok(())
     }
 
+    /// Circuit counterpart of `GenericSponge::absorb_u256`. Expects value as
+    /// low and high 128-bit halves and range checks each of them.
+    pub fn absorb_uint256<CS: ConstraintSystem<E>, P: HashParams<E, RATE, WIDTH>>(
+        &mut self,
+        cs: &mut CS,
+        halves: &[Num<E>; 2],
+        params: &P,
+    ) -> Result<(), SynthesisError> {
+        for half in halves.iter() {
+            match half {
+                Num::Constant(value) => {
+                    let repr = value.into_repr();
+                    assert!(
+                        repr.as_ref()[2..].iter().all(|limb| *limb == 0),
+                        "half of uint256 should fit into 128 bits"
+                    );
+                }
+                Num::Variable(value) => {
+                    let _ = value.into_bits_le(cs, Some(128))?;
+                }
+            }
+            self.absorb(cs, *half, params)?;
+        }
+
+        Ok(())
+    }
+
+    /// Circuit counterpart of `GenericSponge::absorb_bits` with the same layout.
+    pub fn absorb_bits<CS: ConstraintSystem<E>, P: HashParams<E, RATE, WIDTH>>(
+        &mut self,
+        cs: &mut CS,
+        bits: &[Boolean],
+        params: &P,
+    ) -> Result<(), SynthesisError> {
+        let capacity = E::Fr::CAPACITY as usize;
+        for chunk in bits.chunks(capacity) {
+            let mut lc = LinearCombination::zero();
+            let mut coeff = E::Fr::one();
+            for bit in chunk.iter() {
+                lc.add_assign_boolean_with_coeff(bit, coeff);
+                coeff.double();
+            }
+            let packed = lc.into_num(cs)?;
+            self.absorb(cs, packed, params)?;
+        }
+
+        Ok(())
+    }
+
     /// Apply padding manually especially when single absorb called single/many times
+    /// Same as `GenericSponge::pad_if_necessary`, empty buffer is left as is
+    /// and squeezing from a sponge which absorbed nothing returns `None`.
     pub fn pad_if_necessary(&mut self) {
         match self.mode {
             SpongeMode::Absorb(ref mut buf) => {
                 let unwrapped_buffer_len = buf.iter().filter(|el| el.is_some()).count();
+                if unwrapped_buffer_len == 0 {
+                    return;
+                }
                 // compute padding values
-                let padding_strategy = DomainStrategy::CustomVariableLength;
                 let padding_values =
-                    padding_strategy.generate_padding_values::<E>(unwrapped_buffer_len, RATE);
+                    self.domain_strategy.generate_padding_values::<E>(unwrapped_buffer_len, RATE);
                 let mut padding_values_it = padding_values.iter().cloned();
 
                 for b in buf {
@@ -234,6 +450,77 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> CircuitGenericSponge<
         }
     }
 
+    /// Circuit counterpart of `GenericSponge::ratchet`.
+    pub fn ratchet<CS: ConstraintSystem<E>, P: HashParams<E, RATE, WIDTH>>(
+        &mut self,
+        cs: &mut CS,
+        params: &P,
+    ) -> Result<(), SynthesisError> {
+        self.pad_if_necessary();
+        let mut input = [Num::Constant(E::Fr::zero()); RATE];
+        if let SpongeMode::Absorb(ref buf) = self.mode {
+            for (i, b) in input.iter_mut().zip(buf.iter()) {
+                if let Some(value) = b {
+                    *i = *value;
+                }
+            }
+        }
+        absorb(
+            cs,
+            &mut self.state,
+            &mut self.state_is_constant,
+            &input,
+            params,
+            params.custom_gate(),
+            &[],
+        )?;
+        self.state[..RATE].iter_mut().for_each(|s| *s = LinearCombination::zero());
+        self.mode = SpongeMode::Absorb([None; RATE]);
+
+        Ok(())
+    }
+
+    /// Circuit counterpart of `GenericSponge::duplex_call`.
+    pub fn duplex_call<CS: ConstraintSystem<E>, P: HashParams<E, RATE, WIDTH>>(
+        &mut self,
+        cs: &mut CS,
+        input: &[Num<E>],
+        params: &P,
+    ) -> Result<[Num<E>; RATE], SynthesisError> {
+        assert!(input.len() <= RATE, "at most {} elements are absorbed per duplex call", RATE);
+        if let SpongeMode::Absorb(ref buf) = self.mode {
+            assert!(buf.iter().all(|el| el.is_none()), "duplex call with pending absorbed elements");
+        }
+
+        let mut block = [Num::Constant(E::Fr::zero()); RATE];
+        block[..input.len()].copy_from_slice(input);
+        if input.len() < RATE {
+            block[input.len()] = Num::Constant(E::Fr::one());
+        } else {
+            self.state[WIDTH - 1].add_assign_constant(E::Fr::one());
+        }
+        absorb(
+            cs,
+            &mut self.state,
+            &mut self.state_is_constant,
+            &block,
+            params,
+            params.custom_gate(),
+            &[],
+        )?;
+        self.mode = SpongeMode::Absorb([None; RATE]);
+
+        // outputs are kept as the rate part, so the next call doesn't pay for
+        // long linear combinations again
+        let mut output = [Num::Constant(E::Fr::zero()); RATE];
+        for (s, o) in self.state[..RATE].iter_mut().zip(output.iter_mut()) {
+            *o = take_lc(s).into_num(cs)?;
+            *s = LinearCombination::from(*o);
+        }
+
+        Ok(output)
+    }
+
     pub fn squeeze<CS: ConstraintSystem<E>, P: HashParams<E, RATE, WIDTH>>(
         &mut self,
         cs: &mut CS,
@@ -262,7 +549,15 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> CircuitGenericSponge<
                     }
 
                     // permute state
-                    absorb(cs, &mut self.state, &all_inputs, params)?;
+                    absorb(
+                        cs,
+                        &mut self.state,
+                        &mut self.state_is_constant,
+                        &all_inputs,
+                        params,
+                        params.custom_gate(),
+                        &[],
+                    )?;
 
                     // we are switching squeezing mode so we can ignore to reset absorbing buffer
                     let mut squeezed_buffer = arrayvec::ArrayVec::<_, RATE>::new();
@@ -283,6 +578,91 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> CircuitGenericSponge<
         }
     }
 
+    /// Replaces state of the sponge by the state of `other` if `flag` is set,
+    /// otherwise keeps current one. Both sponges should have absorbed or squeezed
+    /// same number of elements since their buffers are selected element-wise.
+    pub fn conditionally_replace<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: &mut CS,
+        flag: &Boolean,
+        other: &Self,
+    ) -> Result<(), SynthesisError> {
+        assert!(
+            std::mem::discriminant(&self.domain_strategy) == std::mem::discriminant(&other.domain_strategy),
+            "domain strategies should match"
+        );
+
+        for (a, b) in self.state.iter_mut().zip(other.state.iter()) {
+            let a_num = a.clone().into_num(cs)?;
+            let b_num = b.clone().into_num(cs)?;
+            let selected = Num::conditionally_select(cs, flag, &b_num, &a_num)?;
+            *a = LinearCombination::from(selected);
+        }
+        self.state_is_constant = false;
+
+        match (&mut self.mode, &other.mode) {
+            (SpongeMode::Absorb(ref mut a_buf), SpongeMode::Absorb(ref b_buf)) => {
+                for (a, b) in a_buf.iter_mut().zip(b_buf.iter()) {
+                    match (a.as_mut(), b) {
+                        (Some(a), Some(b)) => *a = Num::conditionally_select(cs, flag, b, a)?,
+                        (None, None) => (),
+                        _ => panic!("sponges should have same number of absorbed elements"),
+                    }
+                }
+            }
+            (SpongeMode::Squeeze(ref mut a_buf), SpongeMode::Squeeze(ref b_buf)) => {
+                for (a, b) in a_buf.iter_mut().zip(b_buf.iter()) {
+                    match (a.as_mut(), b) {
+                        (Some(a), Some(b)) => {
+                            let a_num = a.clone().into_num(cs)?;
+                            let b_num = b.clone().into_num(cs)?;
+                            let selected = Num::conditionally_select(cs, flag, &b_num, &a_num)?;
+                            *a = LinearCombination::from(selected);
+                        }
+                        (None, None) => (),
+                        _ => panic!("sponges should have same number of squeezed elements"),
+                    }
+                }
+            }
+            _ => panic!("sponges should be in the same mode"),
+        }
+
+        Ok(())
+    }
+
+    /// Circuit counterpart of `GenericSponge::squeeze_or_permute`, returns
+    /// `None` only if buffer is not padded.
+    pub fn squeeze_or_permute<CS: ConstraintSystem<E>, P: HashParams<E, RATE, WIDTH>>(
+        &mut self,
+        cs: &mut CS,
+        params: &P,
+    ) -> Result<Option<LinearCombination<E>>, SynthesisError> {
+        if let Some(value) = self.squeeze(cs, params)? {
+            return Ok(Some(value));
+        }
+        if let SpongeMode::Absorb(_) = self.mode {
+            return Ok(None);
+        }
+
+        // all elements are squeezed, permute state without any input
+        absorb(
+            cs,
+            &mut self.state,
+            &mut self.state_is_constant,
+            &[Num::Constant(E::Fr::zero()); RATE],
+            params,
+            params.custom_gate(),
+            &[],
+        )?;
+        let mut squeezed_buffer = arrayvec::ArrayVec::<_, RATE>::new();
+        for s in self.state[..RATE].iter() {
+            squeezed_buffer.push(Some(s.clone()));
+        }
+        self.mode = SpongeMode::Squeeze(squeezed_buffer.into_inner().expect("length must match"));
+
+        self.squeeze(cs, params)
+    }
+
     pub fn squeeze_num<CS: ConstraintSystem<E>, P: HashParams<E, RATE, WIDTH>>(
         &mut self,
         cs: &mut CS,
@@ -305,15 +685,39 @@ fn absorb<
 >(
     cs: &mut CS,
     state: &mut [LinearCombination<E>; WIDTH],
+    state_is_constant: &mut bool,
     input: &[Num<E>; RATE],
     params: &P,
+    custom_gate: CustomGate,
+    precomputed: &[E::Fr],
 ) -> Result<(), SynthesisError> {
-    for (v, s) in input.iter().zip(state.iter_mut()) {
-        s.add_assign_number_with_coeff(v, E::Fr::one());
+    absorb_into_rate::<_, _, RATE, WIDTH>(state, input, |s, v| {
+        s.add_assign_number_with_coeff(v, E::Fr::one())
+    });
+
+    *state_is_constant &= input.iter().all(|v| v.is_constant());
+    if *state_is_constant {
+        // constant prefixes(e.g domain tags) are permuted natively without any gate
+        let mut native_state = [E::Fr::zero(); WIDTH];
+        for (n, s) in native_state.iter_mut().zip(state.iter()) {
+            *n = s.get_value().expect("value of a constant LC");
+        }
+        crate::sponge::generic_round_function(params, &mut native_state);
+        for (s, n) in state.iter_mut().zip(native_state.iter()) {
+            let mut lc = LinearCombination::zero();
+            lc.add_assign_constant(*n);
+            *s = lc;
+        }
+
+        return Ok(());
     }
-    circuit_generic_round_function(cs, state, params)
+
+    try_circuit_generic_round_function_with_witness(cs, state, params, custom_gate, precomputed)
+        .map_err(GadgetError::log_and_convert)
 }
 
+/// Errors are logged together with the failed gadget and round, see
+/// `try_circuit_generic_round_function` to get them as `GadgetError`.
 pub fn circuit_generic_round_function<
     E: Engine,
     CS: ConstraintSystem<E>,
@@ -325,19 +729,116 @@ pub fn circuit_generic_round_function<
     state: &mut [LinearCombination<E>; WIDTH],
     params: &P,
 ) -> Result<(), SynthesisError> {
+    try_circuit_generic_round_function(cs, state, params).map_err(GadgetError::log_and_convert)
+}
+
+/// Applies round function to each of `states` independently, e.g. to hash
+/// leaves of a tree. Custom gate and hash family are resolved once for the
+/// whole batch. Gates of independent permutations can't share rows, so they
+/// are synthesized one permutation after another in order of `states`.
+pub fn circuit_generic_round_function_many<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    states: &mut [[LinearCombination<E>; WIDTH]],
+    params: &P,
+) -> Result<(), SynthesisError> {
+    let custom_gate = params.custom_gate();
+    for state in states.iter_mut() {
+        try_circuit_generic_round_function_with_witness(cs, state, params, custom_gate, &[])
+            .map_err(GadgetError::log_and_convert)?;
+    }
+
+    Ok(())
+}
+
+/// Same as `circuit_generic_round_function` but reports which gadget of
+/// which round failed.
+pub fn try_circuit_generic_round_function<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    state: &mut [LinearCombination<E>; WIDTH],
+    params: &P,
+) -> Result<(), GadgetError> {
+    try_circuit_generic_round_function_with_gate(cs, state, params, params.custom_gate())
+}
+
+/// Same as `try_circuit_generic_round_function` but gadgets use `custom_gate`
+/// instead of the one of params, so shared params are never mutated.
+pub fn try_circuit_generic_round_function_with_gate<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    state: &mut [LinearCombination<E>; WIDTH],
+    params: &P,
+    custom_gate: CustomGate,
+) -> Result<(), GadgetError> {
+    try_circuit_generic_round_function_with_witness(cs, state, params, custom_gate, &[])
+}
+
+// Inverse sboxes take their outputs from `precomputed` in allocation order.
+pub(crate) fn try_circuit_generic_round_function_with_witness<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    state: &mut [LinearCombination<E>; WIDTH],
+    params: &P,
+    custom_gate: CustomGate,
+    precomputed: &[E::Fr],
+) -> Result<(), GadgetError> {
+    let precomputed = &mut precomputed.iter();
     match params.hash_family() {
-        HashFamily::Rescue => super::rescue::circuit_rescue_round_function(cs, params, state),
-        HashFamily::Poseidon => super::poseidon::circuit_poseidon_round_function(cs, params, state),
+        HashFamily::Rescue => {
+            super::rescue::circuit_rescue_round_function(cs, params, state, custom_gate, precomputed)
+        }
+        HashFamily::Poseidon => super::poseidon::circuit_poseidon_round_function(cs, params, state, custom_gate),
         HashFamily::RescuePrime => {
-            super::rescue_prime::gadget_rescue_prime_round_function(cs, params, state)
+            super::rescue_prime::gadget_rescue_prime_round_function(cs, params, state, custom_gate, precomputed)
         }
         HashFamily::Poseidon2 => {
-            super::poseidon2::circuit_poseidon2_round_function(
+            super::poseidon2::try_circuit_poseidon2_round_function(
                 cs, 
                 params.try_to_poseidon2_params().unwrap(), 
-                state
+                state,
+                custom_gate,
+            )
+        }
+        HashFamily::Griffin => {
+            super::griffin::try_circuit_griffin_round_function(
+                cs,
+                params.try_to_griffin_params().unwrap(),
+                state,
+                custom_gate,
+                precomputed,
             )
         }
+        HashFamily::Anemoi => {
+            super::anemoi::try_circuit_anemoi_round_function(
+                cs,
+                params.try_to_anemoi_params().unwrap(),
+                state,
+                custom_gate,
+                precomputed,
+            )
+        }
+        HashFamily::Monolith => unreachable!("monolith params don't implement HashParams"),
     }
 }
 
@@ -357,26 +858,12 @@ pub fn circuit_generic_round_function_conditional<
     for (lc, s) in state.iter().zip(old_state_nums.iter_mut()) {
         *s = lc.clone().into_num(cs)?;
     }
-    let tmp = match params.hash_family() {
-        HashFamily::Rescue => super::rescue::circuit_rescue_round_function(cs, params, state),
-        HashFamily::Poseidon => super::poseidon::circuit_poseidon_round_function(cs, params, state),
-        HashFamily::RescuePrime => {
-            super::rescue_prime::gadget_rescue_prime_round_function(cs, params, state)
-        }
-        HashFamily::Poseidon2 => {
-            super::poseidon2::circuit_poseidon2_round_function(
-                cs, 
-                params.try_to_poseidon2_params().unwrap(), 
-                state
-            )
-        }
-    };
 
-    let _ = tmp?;
+    circuit_generic_round_function(cs, state, params)?;
 
     let mut new_state_nums = [Num::zero(); WIDTH];
-    for (lc, s) in state.iter().zip(new_state_nums.iter_mut()) {
-        *s = lc.clone().into_num(cs)?;
+    for (lc, s) in state.iter_mut().zip(new_state_nums.iter_mut()) {
+        *s = take_lc(lc).into_num(cs)?;
     }
 
     for ((old, new), lc) in old_state_nums.iter().zip(new_state_nums.iter()).zip(state.iter_mut()) {
@@ -6,10 +6,11 @@ use crate::tests::init_cs;
 use crate::tests::init_rng;
 use crate::traits::{CustomGate, HashParams};
 use crate::{circuit::sponge::CircuitGenericSponge, tests::init_cs_no_custom_gate};
-use franklin_crypto::bellman::pairing::bn256::Bn256;
+use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
 use franklin_crypto::bellman::Field;
 use franklin_crypto::plonk::circuit::allocated_num::AllocatedNum;
 use franklin_crypto::plonk::circuit::allocated_num::Num;
+use franklin_crypto::plonk::circuit::linear_combination::LinearCombination;
 use franklin_crypto::{bellman::plonk::better_better_cs::cs::ConstraintSystem, bellman::Engine};
 use rand::Rand;
 
@@ -383,3 +384,601 @@ fn test_circuit_var_len_rescue_prime_hasher() {
         assert!(cs.is_satisfied());
     }
 }
+
+#[test]
+fn test_circuit_sponge_conditionally_replace() {
+    use franklin_crypto::plonk::circuit::boolean::Boolean;
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+    const INPUT_LENGTH: usize = 3;
+
+    let cs = &mut init_cs::<Bn256>();
+    let params = RescuePrimeParams::<Bn256, RATE, WIDTH>::default();
+    let (_, first_input) = test_inputs::<Bn256, _, INPUT_LENGTH>(cs, true);
+    let mut second_input = first_input;
+    second_input.reverse();
+
+    for flag in [true, false] {
+        let mut first = CircuitGenericSponge::<Bn256, RATE, WIDTH>::new();
+        first.absorb_multiple(cs, &first_input, &params).unwrap();
+        let mut second = CircuitGenericSponge::<Bn256, RATE, WIDTH>::new();
+        second.absorb_multiple(cs, &second_input, &params).unwrap();
+
+        let mut expected = if flag { second.clone() } else { first.clone() };
+        expected.pad_if_necessary();
+        let expected = expected.squeeze(cs, &params).unwrap().unwrap();
+
+        let flag = Boolean::alloc(cs, Some(flag)).unwrap();
+        first.conditionally_replace(cs, &flag, &second).unwrap();
+        first.pad_if_necessary();
+        let actual = first.squeeze(cs, &params).unwrap().unwrap();
+
+        assert_eq!(actual.get_value().unwrap(), expected.get_value().unwrap());
+    }
+
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_circuit_absorb_uint256() {
+    use franklin_crypto::bellman::{PrimeField, PrimeFieldRepr};
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+
+    let cs = &mut init_cs::<Bn256>();
+    let params = PoseidonParams::<Bn256, RATE, WIDTH>::default();
+    // value above the modulus
+    let limbs = [u64::MAX; 4];
+
+    let mut sponge = GenericSponge::<Bn256, RATE, WIDTH>::new();
+    sponge.absorb_u256(limbs, &params);
+    let expected = sponge.squeeze(&params).unwrap();
+
+    let mut halves = [Num::zero(); 2];
+    for (half, limbs) in halves.iter_mut().zip(limbs.chunks_exact(2)) {
+        let mut repr = <<Bn256 as Engine>::Fr as PrimeField>::Repr::default();
+        repr.as_mut()[..2].copy_from_slice(limbs);
+        let value = <Bn256 as Engine>::Fr::from_repr(repr).unwrap();
+        *half = Num::Variable(AllocatedNum::alloc(cs, || Ok(value)).unwrap());
+    }
+    let mut circuit_sponge = CircuitGenericSponge::<Bn256, RATE, WIDTH>::new();
+    circuit_sponge.absorb_uint256(cs, &halves, &params).unwrap();
+    let actual = circuit_sponge.squeeze(cs, &params).unwrap().unwrap();
+
+    assert_eq!(actual.get_value().unwrap(), expected);
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_custom_gate_selection() {
+    use crate::circuit_generic_hash;
+    use franklin_crypto::bellman::plonk::better_better_cs::cs::{PlonkCsWidth4WithNextStepParams, TrivialAssembly, Width4MainGateWithDNext};
+    use franklin_crypto::plonk::circuit::Width4WithCustomGates;
+    type CsWithGates = TrivialAssembly<Bn256, Width4WithCustomGates, Width4MainGateWithDNext>;
+    type CsWithoutGates = TrivialAssembly<Bn256, PlonkCsWidth4WithNextStepParams, Width4MainGateWithDNext>;
+
+    assert!(matches!(CustomGate::select_for::<Bn256, CsWithGates>(), CustomGate::QuinticWidth4));
+    assert!(matches!(CustomGate::select_for::<Bn256, CsWithoutGates>(), CustomGate::None));
+    assert!(!CustomGate::QuinticWidth4.is_supported_by::<Bn256, CsWithoutGates>());
+
+    let cs = &mut init_cs_no_custom_gate::<Bn256>();
+    let params = RescuePrimeParams::<Bn256, 2, 3>::new_with_width4_custom_gate();
+    let (inputs, inputs_as_num) = test_inputs::<Bn256, _, 2>(cs, true);
+    let expected = GenericSponge::<_, 2, 3>::hash(&inputs, &params, None);
+    let actual = circuit_generic_hash(cs, &inputs_as_num, &params, None, None).unwrap();
+
+    assert_eq!(actual[0].get_value().unwrap(), expected[0]);
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_circuit_hash_with_gate_keeps_params() {
+    use crate::circuit_generic_hash_with_gate;
+    use std::sync::Arc;
+
+    let params = Arc::new(PoseidonParams::<Bn256, 2, 3>::default());
+    let mut gate_counts = vec![];
+    for custom_gate in [CustomGate::None, CustomGate::QuinticWidth4] {
+        let cs = &mut init_cs::<Bn256>();
+        let params = Arc::clone(&params);
+        let (inputs, inputs_as_num) = test_inputs::<Bn256, _, 2>(cs, true);
+        let expected = GenericSponge::<_, 2, 3>::hash(&inputs, params.as_ref(), None);
+        let actual = circuit_generic_hash_with_gate(cs, &inputs_as_num, params.as_ref(), custom_gate, None).unwrap();
+
+        assert_eq!(actual[0].get_value().unwrap(), expected[0]);
+        assert!(cs.is_satisfied());
+        gate_counts.push(cs.n());
+    }
+
+    // gate is used without being stored in the shared params
+    assert!(gate_counts[1] < gate_counts[0]);
+    assert!(matches!(params.custom_gate(), CustomGate::None));
+}
+
+fn check_circuit_hash_with_witness<P: HashParams<Bn256, 2, 3>>(params: &P) {
+    use crate::{circuit_generic_hash_with_witness, precompute_witnesses};
+
+    let cs = &mut init_cs::<Bn256>();
+    let (inputs, inputs_as_num) = test_inputs::<Bn256, _, 3>(cs, true);
+    let start = cs.n();
+    let expected = CircuitGenericSponge::hash(cs, &inputs_as_num, params, None).unwrap();
+    let gates = cs.n() - start;
+
+    let witness = precompute_witnesses(params, &inputs);
+    let start = cs.n();
+    let actual = circuit_generic_hash_with_witness(cs, &inputs_as_num, params, &witness).unwrap();
+    assert_eq!(cs.n() - start, gates);
+    for (a, e) in actual.iter().zip(expected.iter()) {
+        assert_eq!(a.get_value(), e.get_value());
+    }
+    assert!(cs.is_satisfied());
+
+    // witness of other input doesn't satisfy inverse sbox constraints
+    let mut other_inputs = inputs;
+    other_inputs[0].add_assign(&Fr::one());
+    let other_witness = precompute_witnesses(params, &other_inputs);
+    let _ = circuit_generic_hash_with_witness(cs, &inputs_as_num, params, &other_witness).unwrap();
+    assert!(!cs.is_satisfied());
+}
+
+#[test]
+fn test_circuit_hash_with_witness() {
+    check_circuit_hash_with_witness(&RescueParams::<Bn256, 2, 3>::default());
+    check_circuit_hash_with_witness(&RescuePrimeParams::<Bn256, 2, 3>::default());
+    check_circuit_hash_with_witness(&crate::GriffinParams::<Bn256, 2, 3>::default());
+}
+
+#[test]
+fn test_circuit_sponge_ratchet() {
+    let params = RescueParams::<Bn256, 2, 3>::default();
+    let cs = &mut init_cs::<Bn256>();
+    let (inputs, inputs_as_num) = test_inputs::<Bn256, _, 3>(cs, true);
+
+    let mut sponge = CircuitGenericSponge::<Bn256, 2, 3>::new();
+    sponge.absorb_multiple(cs, &inputs_as_num, &params).unwrap();
+    sponge.absorb(cs, inputs_as_num[0], &params).unwrap();
+    sponge.ratchet(cs, &params).unwrap();
+    sponge.absorb(cs, inputs_as_num[1], &params).unwrap();
+    sponge.pad_if_necessary();
+    let actual = sponge.squeeze(cs, &params).unwrap().unwrap();
+
+    let mut native = GenericSponge::<Bn256, 2, 3>::new();
+    native.absorb_multiple(&inputs, &params);
+    native.absorb(inputs[0], &params);
+    native.ratchet(&params);
+    native.absorb(inputs[1], &params);
+    native.pad_if_necessary();
+    assert_eq!(actual.get_value().unwrap(), native.squeeze(&params).unwrap());
+
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_circuit_sponge_constant_prefix() {
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+    const INPUT_LENGTH: usize = 6;
+
+    let params = PoseidonParams::<Bn256, RATE, WIDTH>::default();
+    let cs = &mut init_cs::<Bn256>();
+    let (inputs, variables) = test_inputs::<Bn256, _, INPUT_LENGTH>(cs, true);
+
+    // first chunk is a compile-time constant, e.g. a domain tag
+    let mut mixed = variables;
+    for (m, i) in mixed[..RATE].iter_mut().zip(inputs.iter()) {
+        *m = Num::Constant(*i);
+    }
+
+    let expected = GenericSponge::<Bn256, RATE, WIDTH>::hash(&inputs, &params, None);
+
+    let mut gates = vec![];
+    for input in [variables, mixed].iter() {
+        let n = cs.n();
+        let actual = CircuitGenericSponge::<Bn256, RATE, WIDTH>::hash(cs, input, &params, None).unwrap();
+        gates.push(cs.n() - n);
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert_eq!(a.get_value().unwrap(), *e);
+        }
+
+        let mut sponge = CircuitGenericSponge::<Bn256, RATE, WIDTH>::new();
+        sponge.absorb_multiple(cs, input, &params).unwrap();
+        sponge.pad_if_necessary();
+        let actual = sponge.squeeze(cs, &params).unwrap().unwrap();
+        let mut native = GenericSponge::<Bn256, RATE, WIDTH>::new();
+        native.absorb_multiple(&inputs, &params);
+        native.pad_if_necessary();
+        assert_eq!(actual.get_value().unwrap(), native.squeeze(&params).unwrap());
+    }
+    assert!(gates[1] < gates[0]);
+
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_circuit_absorb_bits() {
+    use franklin_crypto::bellman::PrimeField;
+    use franklin_crypto::plonk::circuit::boolean::Boolean;
+    use rand::Rng;
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+
+    let rng = &mut init_rng();
+    let cs = &mut init_cs::<Bn256>();
+    let params = PoseidonParams::<Bn256, RATE, WIDTH>::default();
+    let num_bits = franklin_crypto::bellman::pairing::bn256::Fr::CAPACITY as usize + 3;
+    let bits: Vec<bool> = (0..num_bits).map(|_| rng.gen()).collect();
+
+    let mut sponge = GenericSponge::<Bn256, RATE, WIDTH>::new();
+    sponge.absorb_bits(&bits, &params);
+    sponge.pad_if_necessary();
+    let expected = sponge.squeeze(&params).unwrap();
+
+    let circuit_bits: Vec<_> = bits
+        .iter()
+        .map(|bit| Boolean::alloc(cs, Some(*bit)).unwrap())
+        .collect();
+    let mut sponge = CircuitGenericSponge::<Bn256, RATE, WIDTH>::new();
+    sponge.absorb_bits(cs, &circuit_bits, &params).unwrap();
+    sponge.pad_if_necessary();
+    let actual = sponge.squeeze(cs, &params).unwrap().unwrap();
+
+    assert_eq!(actual.get_value().unwrap(), expected);
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_circuit_squeeze_or_permute() {
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+    const INPUT_LENGTH: usize = 3;
+
+    let cs = &mut init_cs::<Bn256>();
+    let params = RescuePrimeParams::<Bn256, RATE, WIDTH>::default();
+    let (inputs, inputs_as_num) = test_inputs::<Bn256, _, INPUT_LENGTH>(cs, true);
+
+    let mut sponge = GenericSponge::<Bn256, RATE, WIDTH>::new();
+    let mut circuit_sponge = CircuitGenericSponge::<Bn256, RATE, WIDTH>::new();
+    for (el, num) in inputs.iter().zip(inputs_as_num.iter()) {
+        sponge.absorb(*el, &params);
+        circuit_sponge.absorb(cs, *num, &params).unwrap();
+    }
+    assert!(circuit_sponge.squeeze_or_permute(cs, &params).unwrap().is_none());
+    sponge.pad_if_necessary();
+    circuit_sponge.pad_if_necessary();
+
+    for _ in 0..2 * RATE + 1 {
+        let expected = sponge.squeeze_or_permute(&params).unwrap();
+        let actual = circuit_sponge.squeeze_or_permute(cs, &params).unwrap().unwrap();
+        assert_eq!(actual.get_value().unwrap(), expected);
+    }
+
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_circuit_squeeze_after_padding_empty_buffer() {
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+
+    let cs = &mut init_cs::<Bn256>();
+    let params = PoseidonParams::<Bn256, RATE, WIDTH>::default();
+    let (inputs, inputs_as_num) = test_inputs::<Bn256, _, 1>(cs, true);
+
+    let mut circuit_sponge = CircuitGenericSponge::<Bn256, RATE, WIDTH>::new();
+    circuit_sponge.pad_if_necessary();
+    assert!(circuit_sponge.squeeze(cs, &params).unwrap().is_none());
+    assert!(circuit_sponge.squeeze_or_permute(cs, &params).unwrap().is_none());
+
+    circuit_sponge.absorb(cs, inputs_as_num[0], &params).unwrap();
+    circuit_sponge.pad_if_necessary();
+    let mut sponge = GenericSponge::<Bn256, RATE, WIDTH>::new();
+    sponge.absorb(inputs[0], &params);
+    sponge.pad_if_necessary();
+
+    let expected = sponge.squeeze(&params).unwrap();
+    let actual = circuit_sponge.squeeze(cs, &params).unwrap().unwrap();
+    assert_eq!(actual.get_value().unwrap(), expected);
+
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_hash_output_conventions() {
+    use crate::poseidon2::Poseidon2Params;
+    use crate::sponge::generic_round_function;
+    use crate::OutputConvention;
+    use franklin_crypto::bellman::pairing::bn256::Fr;
+    use franklin_crypto::bellman::PrimeField;
+
+    let cs = &mut init_cs::<Bn256>();
+    let params = Poseidon2Params::<Bn256, 2, 3>::default();
+    let (native_input, input) = test_inputs::<Bn256, _, 2>(cs, true);
+
+    // single chunk without padding
+    let mut state = [native_input[0], native_input[1], Fr::from_str("2").unwrap()];
+    generic_round_function(&params, &mut state);
+
+    for (convention, expected) in [
+        (OutputConvention::Rate, [state[0], state[1]]),
+        (OutputConvention::Tail, [state[1], state[2]]),
+        (OutputConvention::ReversedRate, [state[1], state[0]]),
+    ] {
+        let actual = GenericSponge::<Bn256, 2, 3>::hash_with_output_convention(&native_input, &params, None, convention);
+        assert_eq!(actual, expected);
+
+        let actual = CircuitGenericSponge::<Bn256, 2, 3>::hash_num_with_output_convention(cs, &input, &params, None, convention).unwrap();
+        assert_eq!(actual.map(|el| el.get_value().unwrap()), expected);
+    }
+    assert_eq!(
+        GenericSponge::<Bn256, 2, 3>::hash(&native_input, &params, None),
+        GenericSponge::<Bn256, 2, 3>::hash_with_output_convention(&native_input, &params, None, OutputConvention::default()),
+    );
+
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_circuit_absorb_multiple_padding_rule() {
+    use crate::common::domain_strategy::DomainStrategy;
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+
+    let cs = &mut init_cs::<Bn256>();
+    let params = RescuePrimeParams::<Bn256, RATE, WIDTH>::default();
+    let (inputs, inputs_as_num) = test_inputs::<Bn256, _, 5>(cs, true);
+
+    for domain_strategy in [DomainStrategy::CustomVariableLength, DomainStrategy::VariableLength] {
+        // rate-aligned and unaligned messages in different order
+        for split in [(1, 3), (2, 4), (2, 5), (3, 5), (4, 5)] {
+            let mut native = GenericSponge::<Bn256, RATE, WIDTH>::new_from_domain_strategy(domain_strategy.clone());
+            native.absorb_multiple(&inputs[..split.0], &params);
+            native.absorb_multiple(&inputs[split.0..split.1], &params);
+            native.pad_if_necessary();
+
+            let mut circuit = CircuitGenericSponge::<Bn256, RATE, WIDTH>::new_from_domain_strategy(domain_strategy.clone());
+            circuit.absorb_multiple(cs, &inputs_as_num[..split.0], &params).unwrap();
+            circuit.absorb_multiple(cs, &inputs_as_num[split.0..split.1], &params).unwrap();
+            circuit.pad_if_necessary();
+
+            for _ in 0..RATE {
+                let expected = native.squeeze(&params).unwrap();
+                let actual = circuit.squeeze(cs, &params).unwrap().unwrap();
+                assert_eq!(actual.get_value().unwrap(), expected, "split {:?}", split);
+            }
+        }
+    }
+
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_sponge_with_initial_state() {
+    use crate::common::domain_strategy::DomainStrategy;
+    use crate::sponge::{generic_round_function, SpongeStateError};
+    use franklin_crypto::bellman::pairing::bn256::Fr;
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+
+    let cs = &mut init_cs::<Bn256>();
+    let params = RescuePrimeParams::<Bn256, RATE, WIDTH>::default();
+    let (inputs, inputs_as_num) = test_inputs::<Bn256, _, RATE>(cs, true);
+    let rng = &mut init_rng();
+    let initial_state = [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)];
+
+    let mut expected = initial_state;
+    for (s, el) in expected.iter_mut().zip(inputs.iter()) {
+        s.add_assign(el);
+    }
+    generic_round_function(&params, &mut expected);
+
+    let mut native = GenericSponge::<Bn256, RATE, WIDTH>::new_with_initial_state(
+        initial_state,
+        DomainStrategy::CustomVariableLength,
+    )
+    .unwrap();
+    native.absorb_multiple(&inputs, &params);
+    assert_eq!(native.squeeze(&params).unwrap(), expected[0]);
+
+    let mut circuit = CircuitGenericSponge::<Bn256, RATE, WIDTH>::new_with_initial_state(
+        initial_state,
+        DomainStrategy::CustomVariableLength,
+    )
+    .unwrap();
+    circuit.absorb_multiple(cs, &inputs_as_num, &params).unwrap();
+    let actual = circuit.squeeze(cs, &params).unwrap().unwrap();
+    assert_eq!(actual.get_value().unwrap(), expected[0]);
+    assert!(cs.is_satisfied());
+
+    // capacity element of variable length strategy is reserved
+    assert!(matches!(
+        GenericSponge::<Bn256, RATE, WIDTH>::new_with_initial_state(initial_state, DomainStrategy::VariableLength),
+        Err(SpongeStateError::CapacityIsSpecialized)
+    ));
+    let mut personalized = initial_state;
+    personalized[WIDTH - 1] = Fr::zero();
+    assert!(GenericSponge::<Bn256, RATE, WIDTH>::new_with_initial_state(personalized, DomainStrategy::VariableLength).is_ok());
+    assert!(matches!(
+        CircuitGenericSponge::<Bn256, RATE, WIDTH>::new_with_initial_state(initial_state, DomainStrategy::FixedLength),
+        Err(SpongeStateError::UnsupportedDomainStrategy)
+    ));
+}
+
+#[test]
+fn test_duplex_call() {
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+
+    let cs = &mut init_cs::<Bn256>();
+    let params = PoseidonParams::<Bn256, RATE, WIDTH>::default();
+    let (inputs, inputs_as_num) = test_inputs::<Bn256, _, RATE>(cs, true);
+    let constant = Fr::rand(&mut init_rng());
+
+    let mut native = GenericSponge::<Bn256, RATE, WIDTH>::new();
+    let mut circuit = CircuitGenericSponge::<Bn256, RATE, WIDTH>::new();
+
+    // constant first call is permuted natively in circuit, then empty, short
+    // and full blocks
+    let calls: [(&[Fr], &[Num<Bn256>]); 4] = [
+        (&[constant], &[Num::Constant(constant)]),
+        (&[], &[]),
+        (&inputs[..1], &inputs_as_num[..1]),
+        (&inputs, &inputs_as_num),
+    ];
+    for (native_input, circuit_input) in calls {
+        let expected = native.duplex_call(native_input, &params);
+        let actual = circuit.duplex_call(cs, circuit_input, &params).unwrap();
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert_eq!(a.get_value().unwrap(), *e);
+        }
+    }
+    assert!(cs.is_satisfied());
+
+    // short input padded by one is not confused with a longer input
+    let duplex_once = |input: &[Fr]| GenericSponge::<Bn256, RATE, WIDTH>::new().duplex_call(input, &params);
+    let one = Fr::one();
+    assert_ne!(duplex_once(&[inputs[0]]), duplex_once(&[inputs[0], one]));
+    assert_ne!(duplex_once(&[]), duplex_once(&[one]));
+}
+
+#[test]
+fn test_circuit_round_function_many() {
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+    const N: usize = 4;
+
+    let cs = &mut init_cs::<Bn256>();
+    let params = PoseidonParams::<Bn256, RATE, WIDTH>::default();
+    let (inputs, inputs_as_num) = test_inputs::<Bn256, _, { N * WIDTH }>(cs, true);
+
+    let mut states: Vec<[LinearCombination<Bn256>; WIDTH]> = inputs_as_num
+        .chunks(WIDTH)
+        .map(|chunk| core::array::from_fn(|i| LinearCombination::from(chunk[i])))
+        .collect();
+    crate::circuit::sponge::circuit_generic_round_function_many(cs, &mut states, &params).unwrap();
+    assert!(cs.is_satisfied());
+
+    for (state, input) in states.into_iter().zip(inputs.chunks(WIDTH)) {
+        let mut expected: [Fr; WIDTH] = input.try_into().unwrap();
+        crate::sponge::generic_round_function(&params, &mut expected);
+        for (lc, e) in state.iter().zip(expected.iter()) {
+            assert_eq!(lc.get_value().unwrap(), *e);
+        }
+    }
+}
+
+#[test]
+fn test_circuit_safe_sponge() {
+    use crate::safe::{IOPattern, SafeSponge, SpongeOp};
+
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+
+    let cs = &mut init_cs::<Bn256>();
+    let params = PoseidonParams::<Bn256, RATE, WIDTH>::default();
+    let (inputs, inputs_as_num) = test_inputs::<Bn256, _, 5>(cs, true);
+    let pattern = IOPattern::new(&[
+        SpongeOp::Absorb(3),
+        SpongeOp::Squeeze(3),
+        SpongeOp::Absorb(2),
+        SpongeOp::Squeeze(1),
+    ]);
+
+    let mut native = SafeSponge::<Bn256, _, RATE, WIDTH>::new(params.clone(), pattern.clone(), b"test");
+    native.absorb(&inputs[..3]).unwrap();
+    let mut expected = native.squeeze(3).unwrap();
+    native.absorb(&inputs[3..]).unwrap();
+    expected.extend(native.squeeze(1).unwrap());
+    native.finish().unwrap();
+
+    let mut circuit =
+        crate::circuit::safe::CircuitSafeSponge::<Bn256, _, RATE, WIDTH>::new(params, pattern, b"test");
+    circuit.absorb(cs, &inputs_as_num[..3]).unwrap();
+    let mut actual = circuit.squeeze(cs, 3).unwrap();
+    circuit.absorb(cs, &inputs_as_num[3..]).unwrap();
+    actual.extend(circuit.squeeze(cs, 1).unwrap());
+    circuit.finish();
+
+    assert!(cs.is_satisfied());
+    for (a, e) in actual.iter().zip(expected.iter()) {
+        assert_eq!(a.get_value().unwrap(), *e);
+    }
+}
+
+#[test]
+#[should_panic(expected = "IO pattern expects")]
+fn test_circuit_safe_sponge_rejects_unexpected_op() {
+    use crate::safe::{IOPattern, SpongeOp};
+
+    let cs = &mut init_cs::<Bn256>();
+    let params = PoseidonParams::<Bn256, 2, 3>::default();
+    let pattern = IOPattern::new(&[SpongeOp::Absorb(1), SpongeOp::Squeeze(1)]);
+    let mut circuit = crate::circuit::safe::CircuitSafeSponge::<Bn256, _, 2, 3>::new(params, pattern, b"test");
+    circuit.squeeze(cs, 1).unwrap();
+}
+
+// Old gadget is going to be retired downstream, these tests track that both
+// gadgets agree and how many gates each of them takes.
+#[cfg(feature = "franklin-compat")]
+mod franklin_compat {
+    use super::*;
+    use franklin_crypto::plonk::circuit::rescue::StatefulRescueGadget;
+    use franklin_crypto::rescue::bn256::Bn256RescueParams;
+    use franklin_crypto::rescue::RescueHashParams;
+
+    // returns gates taken by franklin gadget and by this crate
+    fn compare_with_franklin_gadget(use_custom_gate: bool) -> (usize, usize) {
+        const WIDTH: usize = 3;
+        const RATE: usize = 2;
+        const LENGTH: usize = 4;
+
+        let mut params = RescueParams::<Bn256, RATE, WIDTH>::default();
+        let mut old_params = Bn256RescueParams::new_checked_2_into_1();
+        if use_custom_gate {
+            params.use_custom_gate(CustomGate::QuinticWidth4);
+            old_params.set_allow_custom_gate(true);
+        }
+
+        let cs = &mut init_cs::<Bn256>();
+        let (inputs, inputs_as_num) = test_inputs::<Bn256, _, LENGTH>(cs, true);
+        let allocated: Vec<_> = inputs_as_num.iter().map(|num| num.get_variable()).collect();
+
+        let start = cs.n();
+        let mut old_gadget = StatefulRescueGadget::<Bn256>::new(&old_params);
+        old_gadget.absorb(cs, &allocated, &old_params).unwrap();
+        let expected = old_gadget.squeeze_out_single(cs, &old_params).unwrap();
+        let old_gates = cs.n() - start;
+
+        let start = cs.n();
+        let mut sponge = CircuitGenericSponge::<Bn256, RATE, WIDTH>::new();
+        sponge.absorb_multiple(cs, &inputs_as_num, &params).unwrap();
+        let actual = sponge.squeeze(cs, &params).unwrap().unwrap();
+        let new_gates = cs.n() - start;
+
+        assert!(
+            new_gates <= old_gates,
+            "gadget takes {} gates, more than {} gates of franklin gadget",
+            new_gates,
+            old_gates
+        );
+
+        let mut native = GenericSponge::<Bn256, RATE, WIDTH>::new();
+        native.absorb_multiple(&inputs, &params);
+        let native = native.squeeze(&params).unwrap();
+
+        assert_eq!(expected.get_value().unwrap(), native);
+        assert_eq!(actual.get_value().unwrap(), native);
+        assert!(cs.is_satisfied());
+
+        (old_gates, new_gates)
+    }
+
+    #[test]
+    fn test_compare_with_franklin_rescue_gadget() {
+        let (old_gates, new_gates) = compare_with_franklin_gadget(false);
+        let (old_gates_with_gate, new_gates_with_gate) = compare_with_franklin_gadget(true);
+
+        // custom gate pays off in both gadgets
+        assert!(old_gates_with_gate < old_gates);
+        assert!(new_gates_with_gate < new_gates);
+    }
+}
@@ -0,0 +1,136 @@
+use super::public_inputs::enforce_bits_are_canonical;
+use super::sponge::CircuitGenericSponge;
+use crate::common::utils::fe_from_u64;
+use crate::transcript_protocol::{truncate_challenge_128, ProtocolLabel};
+use crate::traits::HashParams;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Engine, Field, PrimeField, SynthesisError};
+use franklin_crypto::plonk::circuit::{allocated_num::Num, linear_combination::LinearCombination};
+
+/// Circuit counterpart of `SpongeTranscript`. Pending elements are padded
+/// before each challenge.
+#[derive(Clone)]
+pub struct CircuitSpongeTranscript<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize> {
+    sponge: CircuitGenericSponge<E, RATE, WIDTH>,
+    params: P,
+}
+
+impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>
+    CircuitSpongeTranscript<E, P, RATE, WIDTH>
+{
+    pub fn new(params: P) -> Self {
+        Self {
+            sponge: CircuitGenericSponge::new(),
+            params,
+        }
+    }
+
+    pub fn commit_field_element<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: &mut CS,
+        element: &Num<E>,
+    ) -> Result<(), SynthesisError> {
+        self.sponge.absorb(cs, *element, &self.params)
+    }
+
+    /// Circuit counterpart of `TranscriptProtocol::commit_proof_element`.
+    pub fn commit_proof_element<CS: ConstraintSystem<E>>(
+        &mut self,
+        cs: &mut CS,
+        encoding: &[Num<E>],
+    ) -> Result<(), SynthesisError> {
+        self.commit_field_element(cs, &Num::Constant(ProtocolLabel::ProofElement.as_fe::<E>()))?;
+        self.commit_field_element(cs, &Num::Constant(fe_from_u64::<E>(encoding.len() as u64)))?;
+        for el in encoding.iter() {
+            self.commit_field_element(cs, el)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_challenge<CS: ConstraintSystem<E>>(&mut self, cs: &mut CS) -> Result<Num<E>, SynthesisError> {
+        self.sponge.pad_if_necessary();
+        let challenge = self
+            .sponge
+            .squeeze_or_permute(cs, &self.params)?
+            .expect("buffer is padded");
+
+        challenge.into_num(cs)
+    }
+
+    /// Circuit counterpart of `TranscriptProtocol::get_challenge_128`.
+    pub fn get_challenge_128<CS: ConstraintSystem<E>>(&mut self, cs: &mut CS) -> Result<Num<E>, SynthesisError> {
+        let challenge = self.get_challenge(cs)?;
+
+        circuit_truncate_challenge_128(cs, &challenge)
+    }
+}
+
+/// Circuit counterpart of `RescueTranscript`.
+pub type CircuitRescueTranscript<E> = CircuitSpongeTranscript<E, crate::RescueParams<E, 2, 3>, 2, 3>;
+
+/// Circuit counterpart of `PoseidonTranscript`.
+pub type CircuitPoseidonTranscript<E> = CircuitSpongeTranscript<E, crate::PoseidonParams<E, 2, 3>, 2, 3>;
+
+/// Lower 128 bits of the canonical representation of a challenge.
+pub fn circuit_truncate_challenge_128<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    challenge: &Num<E>,
+) -> Result<Num<E>, SynthesisError> {
+    let challenge = match challenge {
+        Num::Constant(constant) => return Ok(Num::Constant(truncate_challenge_128::<E>(constant))),
+        Num::Variable(challenge) => challenge,
+    };
+
+    // decomposition should be canonical, otherwise prover could pick lower
+    // bits of `challenge + modulus`
+    let bits = challenge.into_bits_le(cs, Some(E::Fr::NUM_BITS as usize))?;
+    enforce_bits_are_canonical::<E, CS>(cs, &bits)?;
+
+    let mut lc = LinearCombination::zero();
+    let mut coeff = E::Fr::one();
+    for bit in bits[..128].iter() {
+        lc.add_assign_boolean_with_coeff(bit, coeff);
+        coeff.double();
+    }
+
+    lc.into_num(cs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::tests::{init_cs, init_rng};
+    use crate::transcript_protocol::{SpongeTranscript, TranscriptProtocol};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_circuit_get_challenge_128() {
+        let cs = &mut init_cs::<Bn256>();
+        let rng = &mut init_rng();
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        let elements: Vec<_> = (0..3).map(|_| Fr::rand(rng)).collect();
+
+        let mut transcript = SpongeTranscript::<Bn256, _, 2, 3>::new(params.clone());
+        let mut circuit_transcript = CircuitSpongeTranscript::<Bn256, _, 2, 3>::new(params);
+        for el in elements.iter() {
+            transcript.commit_field_element(el);
+            let num = Num::alloc(cs, Some(*el)).unwrap();
+            circuit_transcript.commit_field_element(cs, &num).unwrap();
+        }
+
+        for _ in 0..2 {
+            let expected = transcript.get_challenge_128();
+            let actual = circuit_transcript.get_challenge_128(cs).unwrap();
+            assert_eq!(actual.get_value().unwrap(), expected);
+
+            let expected = transcript.get_challenge();
+            let actual = circuit_transcript.get_challenge(cs).unwrap();
+            assert_eq!(actual.get_value().unwrap(), expected);
+        }
+
+        assert!(cs.is_satisfied());
+    }
+}
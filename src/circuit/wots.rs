@@ -0,0 +1,116 @@
+use super::sponge::CircuitGenericSponge;
+use crate::common::utils::fe_from_u64;
+use crate::traits::HashParams;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Engine, Field, SynthesisError};
+use franklin_crypto::plonk::circuit::{
+    allocated_num::Num, boolean::Boolean, linear_combination::LinearCombination,
+};
+
+/// Circuit counterpart of `wots::chain` for a statically known number of steps.
+pub fn circuit_chain<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &P,
+    start: &Num<E>,
+    steps: usize,
+) -> Result<Num<E>, SynthesisError> {
+    let mut current = *start;
+    for _ in 0..steps {
+        current = CircuitGenericSponge::<E, RATE, WIDTH>::hash_num(cs, &[current], params, None)?[0];
+    }
+
+    Ok(current)
+}
+
+/// Checks that `end` is reached from `value` after `steps` steps where number
+/// of steps is a witness, e.g. a complement of a message digit. All
+/// `max_steps` steps are computed and `steps` is enforced to be at most
+/// `max_steps`.
+pub fn circuit_verify_chain<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &P,
+    value: &Num<E>,
+    steps: &Num<E>,
+    max_steps: usize,
+    end: &Num<E>,
+) -> Result<Boolean, SynthesisError> {
+    let mut current = *value;
+    let mut selected = *value;
+    // exactly one of the positions should match the number of steps
+    let mut num_matches = LinearCombination::zero();
+    for position in 0..=max_steps {
+        if position > 0 {
+            current =
+                CircuitGenericSponge::<E, RATE, WIDTH>::hash_num(cs, &[current], params, None)?[0];
+        }
+        let position = Num::Constant(fe_from_u64::<E>(position as u64));
+
+        let is_here = Num::equals(cs, steps, &position)?;
+        selected = Num::conditionally_select(cs, &is_here, &current, &selected)?;
+        num_matches.add_assign_boolean_with_coeff(&is_here, E::Fr::one());
+    }
+    let mut minus_one = E::Fr::one();
+    minus_one.negate();
+    num_matches.add_assign_constant(minus_one);
+    num_matches.enforce_zero(cs)?;
+
+    Num::equals(cs, &selected, end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::tests::{init_cs, init_rng};
+    use crate::wots::{chain, verify_chain};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_wots_chain() {
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        const MAX_STEPS: usize = 3;
+
+        let secret = Fr::rand(rng);
+        let public = chain(&params, &secret, MAX_STEPS);
+        let digit = 1;
+        let signature = chain(&params, &secret, digit);
+        assert!(verify_chain(&params, &signature, MAX_STEPS - digit, &public));
+        assert!(!verify_chain(&params, &signature, MAX_STEPS - digit + 1, &public));
+
+        let signature_num = Num::alloc(cs, Some(signature)).unwrap();
+        let public_num = Num::alloc(cs, Some(public)).unwrap();
+        let start = circuit_chain(cs, &params, &signature_num, MAX_STEPS - digit).unwrap();
+        assert_eq!(start.get_value().unwrap(), public);
+
+        for (steps, expected) in [(MAX_STEPS - digit, true), (MAX_STEPS, false)] {
+            let steps = Num::alloc(cs, Some(fe_from_u64::<Bn256>(steps as u64))).unwrap();
+            let is_valid = circuit_verify_chain(
+                cs,
+                &params,
+                &signature_num,
+                &steps,
+                MAX_STEPS,
+                &public_num,
+            )
+            .unwrap();
+            assert_eq!(is_valid.get_value().unwrap(), expected);
+        }
+
+        assert!(cs.is_satisfied());
+    }
+}
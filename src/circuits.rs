@@ -0,0 +1,120 @@
+//! Complete circuits built from the gadgets of `circuit`. They can be set up
+//! and proven as is, see `examples/preimage_proof.rs`, or embedded into
+//! larger circuits.
+use crate::circuit::sponge::circuit_generic_hash_num;
+use crate::sponge::generic_hash;
+use crate::traits::HashParams;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::{
+    Circuit, ConstraintSystem, Gate, GateInternal, Width4MainGateWithDNext,
+};
+use franklin_crypto::bellman::{Engine, Field, SynthesisError};
+use franklin_crypto::plonk::circuit::allocated_num::{AllocatedNum, Num};
+use franklin_crypto::plonk::circuit::custom_rescue_gate::Rescue5CustomGate;
+
+/// Proves knowledge of `L` elements whose fixed length digest, i.e. the first
+/// element of `generic_hash` output with default domain strategy, is the
+/// only public input. Params may use no custom gate or `QuinticWidth4`.
+pub struct PreimageCircuit<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize, const L: usize> {
+    params: P,
+    preimage: Option<[E::Fr; L]>,
+}
+
+impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize, const L: usize>
+    PreimageCircuit<E, P, RATE, WIDTH, L>
+{
+    /// Circuit without witness, enough for setup.
+    pub fn new(params: P) -> Self {
+        Self { params, preimage: None }
+    }
+
+    /// Circuit with witness for proving.
+    pub fn with_preimage(params: P, preimage: [E::Fr; L]) -> Self {
+        Self {
+            params,
+            preimage: Some(preimage),
+        }
+    }
+
+    /// Public input of the circuit, `None` if preimage is not known.
+    pub fn digest(&self) -> Option<E::Fr> {
+        self.preimage
+            .as_ref()
+            .map(|preimage| generic_hash(&self.params, preimage, None)[0])
+    }
+
+    /// Allocates preimage as witness and returns its digest without making it
+    /// public, so the circuit can be embedded into other circuits.
+    pub fn synthesize_digest<CS: ConstraintSystem<E>>(&self, cs: &mut CS) -> Result<Num<E>, SynthesisError> {
+        let mut preimage = [Num::Constant(E::Fr::zero()); L];
+        for (i, el) in preimage.iter_mut().enumerate() {
+            *el = Num::alloc(cs, self.preimage.map(|preimage| preimage[i]))?;
+        }
+        let digest = circuit_generic_hash_num(cs, &preimage, &self.params, None)?;
+
+        Ok(digest[0])
+    }
+}
+
+impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize, const L: usize> Circuit<E>
+    for PreimageCircuit<E, P, RATE, WIDTH, L>
+{
+    type MainGate = Width4MainGateWithDNext;
+
+    fn synthesize<CS: ConstraintSystem<E>>(&self, cs: &mut CS) -> Result<(), SynthesisError> {
+        let public_digest = AllocatedNum::alloc_input(cs, || self.digest().ok_or(SynthesisError::AssignmentMissing))?;
+        let digest = self.synthesize_digest(cs)?;
+
+        digest.enforce_equal(cs, &Num::Variable(public_digest))
+    }
+
+    fn declare_used_gates() -> Result<Vec<Box<dyn GateInternal<E>>>, SynthesisError> {
+        Ok(vec![
+            Self::MainGate::default().into_internal(),
+            Rescue5CustomGate::default().into_internal(),
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::tests::{init_cs, init_rng};
+    use crate::{CustomGate, RescueParams};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_preimage_circuit() {
+        fn check<P: HashParams<Bn256, 2, 3>>(params: P) {
+            let rng = &mut init_rng();
+            let preimage = [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)];
+            let circuit = PreimageCircuit::<Bn256, P, 2, 3, 3>::with_preimage(params, preimage);
+
+            let cs = &mut init_cs::<Bn256>();
+            circuit.synthesize(cs).unwrap();
+            assert!(cs.is_satisfied());
+
+            let cs = &mut init_cs::<Bn256>();
+            let digest = circuit.synthesize_digest(cs).unwrap();
+            assert_eq!(digest.get_value(), circuit.digest());
+        }
+
+        check(RescueParams::<Bn256, 2, 3>::default());
+        check(Poseidon2Params::<Bn256, 2, 3>::default());
+
+        let mut params = RescueParams::<Bn256, 2, 3>::default();
+        params.use_custom_gate(CustomGate::QuinticWidth4);
+        check(params);
+    }
+
+    #[test]
+    fn test_preimage_circuit_digest() {
+        let params = RescueParams::<Bn256, 2, 3>::default();
+        assert_eq!(PreimageCircuit::<Bn256, _, 2, 3, 1>::new(params.clone()).digest(), None);
+
+        let preimage = [Fr::one()];
+        let circuit = PreimageCircuit::<Bn256, _, 2, 3, 1>::with_preimage(params.clone(), preimage);
+        assert_eq!(circuit.digest(), Some(generic_hash(&params, &preimage, None)[0]));
+    }
+}
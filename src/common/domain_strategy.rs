@@ -1,3 +1,4 @@
+use alloc::{vec, vec::Vec};
 use franklin_crypto::bellman::{Engine, Field, PrimeField};
 
 /// Padding prevents trivial collisions.
@@ -5,7 +6,7 @@ use franklin_crypto::bellman::{Engine, Field, PrimeField};
 /// The only difference is that Rescue Prime requires no padding for
 /// fixed length input. Rescue and Poseidon require same padding rule
 /// for variable length input.
-#[derive(Clone)]
+#[derive(Clone, serde::Serialize, serde::Deserialize)]
 pub enum DomainStrategy {
     // The capacity value is length x (2^64 ) + (o − 1)
     // where o the output length. The padding consists of the field elements being 0.
@@ -20,100 +21,188 @@ pub enum DomainStrategy {
     // element is being set to input length.
     CustomFixedLength,
     CustomVariableLength,
-    // No specialization and padding rule.
+    /// Fixed length strategy of the Rescue Prime specification: capacity is
+    /// not specialized and input is not padded, so its length should be a
+    /// multiple of rate.
     NoPadding,
 }
 
+/// Errors of capacity computation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DomainStrategyError {
+    /// Capacity value doesn't fit into the field and would wrap around modulus.
+    CapacityOverflowsField,
+}
+
+impl core::fmt::Display for DomainStrategyError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::CapacityOverflowsField => write!(f, "capacity value overflows the field"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for DomainStrategyError {}
+
 impl DomainStrategy {
     /// Computes capacity value for specialization and domain seperation.
+    /// Panics if value doesn't fit into the field, see `try_compute_capacity`.
     pub(crate) fn compute_capacity<E: Engine>(
         &self,
         input_len: usize,
         rate: usize,
     ) -> Option<E::Fr> {
-        let mut repr = <E::Fr as PrimeField>::Repr::default();
-        repr.as_mut()[1] = 1u64; // 2^64 corresponds second le limb
-        let mut el = E::Fr::from_repr(repr).unwrap();
+        self.try_compute_capacity::<E>(input_len, rate)
+            .expect("capacity value fits into the field")
+    }
 
-        let mut out_repr = <E::Fr as PrimeField>::Repr::default();
-        out_repr.as_mut()[0] = (rate - 1) as u64;
-        let out_el = E::Fr::from_repr(out_repr).unwrap();
+    /// Computes capacity value for specialization and domain seperation.
+    /// Value is computed as an integer first and rejected if it can wrap
+    /// around the modulus, so it doesn't rely on the limb layout of the field.
+    pub fn try_compute_capacity<E: Engine>(
+        &self,
+        input_len: usize,
+        rate: usize,
+    ) -> Result<Option<E::Fr>, DomainStrategyError> {
+        // we always use output length equals rate
+        let out_len_minus_one = (rate - 1) as u128;
 
-        match &self {
-            Self::FixedLength => {
-                // length * 2^64 + (o-1)
-                // since we always use output length equals rate
-                let length_as_fe = E::Fr::from_str(&input_len.to_string()).unwrap();
-                el.mul_assign(&length_as_fe);
-                el.add_assign(&out_el);
+        let value = match &self {
+            // length * 2^64 + (o-1)
+            Self::FixedLength => ((input_len as u128) << 64) | out_len_minus_one,
+            // 2^64 + (o-1)
+            Self::VariableLength => (1u128 << 64) | out_len_minus_one,
+            Self::CustomFixedLength => input_len as u128,
+            Self::CustomVariableLength | Self::NoPadding => return Ok(None),
+        };
 
-                Some(el)
+        fe_from_u128::<E>(value).map(Some)
+    }
+    /// Computes number of padding elements required for a given input length.
+    pub(crate) fn padding_len(&self, input_len: usize, rate: usize) -> usize {
+        match self {
+            Self::FixedLength
+            | Self::VariableLength
+            | Self::CustomFixedLength
+            | Self::CustomVariableLength => {
+                if input_len % rate == 0 {
+                    // input doesn't need padding
+                    0
+                } else {
+                    rate - input_len % rate
+                }
             }
-            Self::VariableLength => {
-                // 2^64 + (o-1)
-                el.add_assign(&out_el);
-
-                Some(el)
+            Self::NoPadding => {
+                assert!(input_len % rate == 0, "input length should be a multiple of rate without padding");
+                0
             }
-            Self::CustomFixedLength => {
-                let mut repr = <E::Fr as PrimeField>::Repr::default();
-                repr.as_mut()[0] = input_len as u64;
+        }
+    }
 
-                E::Fr::from_repr(repr).ok()
+    /// Writes padding values into `dst` without allocating.
+    /// Length of `dst` should be equal to `padding_len`.
+    pub(crate) fn fill_padding_values<E: Engine>(
+        &self,
+        input_len: usize,
+        rate: usize,
+        dst: &mut [E::Fr],
+    ) {
+        assert_eq!(dst.len(), self.padding_len(input_len, rate));
+        match self {
+            Self::FixedLength => {
+                dst.iter_mut().for_each(|el| *el = E::Fr::zero());
+            }
+            Self::VariableLength => {
+                for (idx, el) in dst.iter_mut().enumerate() {
+                    *el = if idx == 0 { E::Fr::one() } else { E::Fr::zero() };
+                }
             }
-            Self::CustomVariableLength => None,
-            _ => unimplemented!("unknown domain strategy"),
+            Self::CustomFixedLength | Self::CustomVariableLength => {
+                dst.iter_mut().for_each(|el| *el = E::Fr::one());
+            }
+            // input is never padded
+            Self::NoPadding => (),
         }
     }
+
     /// Computes values for padding.
     pub(crate) fn generate_padding_values<E: Engine>(
         &self,
         input_len: usize,
         rate: usize
     ) -> Vec<E::Fr> {
-        assert!(input_len != 0, "empty input");
-        if input_len % rate == 0 {
-            // input doesn't need padding
-            return vec![];
-        }
-        let mut values_for_padding = Vec::with_capacity(rate);
-        match self {
-            Self::FixedLength => {
-                values_for_padding.resize(rate - input_len, E::Fr::zero());
+        let mut values_for_padding = vec![E::Fr::zero(); self.padding_len(input_len, rate)];
+        self.fill_padding_values::<E>(input_len, rate, &mut values_for_padding);
 
-                values_for_padding
-            }
-            Self::VariableLength => {
-                values_for_padding.push(E::Fr::one());
-                while (values_for_padding.len() + input_len) % rate != 0 {
-                    values_for_padding.push(E::Fr::zero());
-                }
-                values_for_padding
-            }
+        values_for_padding
+    }
+}
 
-            Self::CustomFixedLength => {
-                let mut cycle = input_len / rate;
+// Integer is accepted only if it is less than 2^(NUM_BITS-1) which is
+// less than modulus for any field.
+fn fe_from_u128<E: Engine>(value: u128) -> Result<E::Fr, DomainStrategyError> {
+    let num_bits = 128 - value.leading_zeros();
+    if num_bits >= E::Fr::NUM_BITS {
+        return Err(DomainStrategyError::CapacityOverflowsField);
+    }
 
-                if input_len % rate != 0 {
-                    cycle += 1;
-                }
+    let fe_from_u32 = |value: u32| {
+        let mut repr = <E::Fr as PrimeField>::Repr::default();
+        repr.as_mut()[0] = value as u64;
+        E::Fr::from_repr(repr).expect("a field element")
+    };
+    let mut shift = fe_from_u32(1 << 16);
+    shift.square();
 
-                let padding_len = cycle * rate - input_len;
+    // sum of 32-bit words from the most significant one
+    let mut result = E::Fr::zero();
+    for word in (0..4).rev().map(|i| (value >> (32 * i)) as u32) {
+        result.mul_assign(&shift);
+        result.add_assign(&fe_from_u32(word));
+    }
 
-                for _ in 0..padding_len {
-                    values_for_padding.push(E::Fr::one());
-                }
+    Ok(result)
+}
 
-                values_for_padding
-            }
-            Self::CustomVariableLength => {
-                values_for_padding.push(E::Fr::one());
-                while (values_for_padding.len() + input_len) % rate != 0 {
-                    values_for_padding.push(E::Fr::one());
-                }
-                values_for_padding
-            }
-            _ => unimplemented!("unknown domain strategy"),
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+
+    #[test]
+    fn test_capacity_values() {
+        let rate = 2;
+        let input_len = 5;
+
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.as_mut()[0] = 1;
+        repr.as_mut()[1] = input_len as u64;
+        let expected = Fr::from_repr(repr).unwrap();
+        let actual = DomainStrategy::FixedLength.compute_capacity::<Bn256>(input_len, rate);
+        assert_eq!(actual, Some(expected));
+
+        repr.as_mut()[1] = 1;
+        let expected = Fr::from_repr(repr).unwrap();
+        let actual = DomainStrategy::VariableLength.compute_capacity::<Bn256>(input_len, rate);
+        assert_eq!(actual, Some(expected));
+
+        let actual = DomainStrategy::CustomFixedLength.compute_capacity::<Bn256>(usize::MAX, rate);
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.as_mut()[0] = usize::MAX as u64;
+        assert_eq!(actual, Some(Fr::from_repr(repr).unwrap()));
+
+        let actual = DomainStrategy::CustomVariableLength.try_compute_capacity::<Bn256>(input_len, rate);
+        assert_eq!(actual, Ok(None));
+
+        let actual = DomainStrategy::NoPadding.try_compute_capacity::<Bn256>(input_len, rate);
+        assert_eq!(actual, Ok(None));
+        assert_eq!(DomainStrategy::NoPadding.padding_len(4, rate), 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "input length should be a multiple of rate without padding")]
+    fn test_no_padding_rejects_unaligned_input() {
+        DomainStrategy::NoPadding.padding_len(3, 2);
     }
 }
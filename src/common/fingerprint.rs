@@ -0,0 +1,89 @@
+use alloc::vec::Vec;
+use blake2::{Blake2s256, Digest};
+use franklin_crypto::bellman::{Engine, PrimeField, PrimeFieldRepr};
+
+use crate::traits::{HashFamily, Sbox, Step};
+
+// Canonical byte encoding of a parameter set. Only values which define the
+// permutation are fed in, circuit related knobs like custom gate selection
+// are intentionally left out so that native and circuit params match.
+pub(crate) struct Fingerprint {
+    hasher: Blake2s256,
+}
+
+impl Fingerprint {
+    pub(crate) fn new(family: HashFamily, rate: usize, width: usize) -> Self {
+        let mut hasher = Blake2s256::new();
+        hasher.update(b"rescue_poseidon_params");
+        let mut this = Self { hasher };
+        this.update_u64(family as u64);
+        this.update_usize(rate);
+        this.update_usize(width);
+
+        this
+    }
+
+    pub(crate) fn update_u64(&mut self, value: u64) {
+        self.hasher.update(value.to_le_bytes());
+    }
+
+    pub(crate) fn update_usize(&mut self, value: usize) {
+        self.update_u64(value as u64);
+    }
+
+    pub(crate) fn update_fr<E: Engine>(&mut self, el: &E::Fr) {
+        let mut bytes = Vec::with_capacity(32);
+        el.into_repr().write_le(&mut bytes).expect("write into vec");
+        self.hasher.update(&bytes);
+    }
+
+    pub(crate) fn update_frs<E: Engine>(&mut self, elements: &[E::Fr]) {
+        self.update_usize(elements.len());
+        for el in elements.iter() {
+            self.update_fr::<E>(el);
+        }
+    }
+
+    pub(crate) fn update_matrix<E: Engine, const DIM: usize>(&mut self, matrix: &[[E::Fr; DIM]; DIM]) {
+        for row in matrix.iter() {
+            self.update_frs::<E>(row);
+        }
+    }
+
+    pub(crate) fn update_sbox(&mut self, sbox: &Sbox) {
+        match sbox {
+            Sbox::Alpha(alpha) => {
+                self.update_u64(0);
+                self.update_u64(*alpha);
+            }
+            Sbox::AlphaInverse(alpha_inv, alpha) => {
+                self.update_u64(1);
+                self.update_u64(*alpha);
+                self.update_usize(alpha_inv.len());
+                alpha_inv.iter().for_each(|limb| self.update_u64(*limb));
+            }
+            Sbox::AddChain(chain, alpha) => {
+                self.update_u64(2);
+                self.update_u64(*alpha);
+                self.update_usize(chain.len());
+                for step in chain.iter() {
+                    match step {
+                        Step::Double { index } => {
+                            self.update_u64(0);
+                            self.update_usize(*index);
+                        }
+                        Step::Add { left, right } => {
+                            self.update_u64(1);
+                            self.update_usize(*left);
+                            self.update_usize(*right);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn finalize(self) -> [u8; 32] {
+        self.hasher.finalize().into()
+    }
+}
@@ -1,13 +1,68 @@
 use franklin_crypto::bellman::{Engine, Field};
-use std::ops::Range;
+use derivative::*;
+use alloc::{vec, vec::Vec};
+use core::ops::Range;
+
+/// Factorization of a single partial round matrix `M = M' x M''` where `M'`
+/// holds `m_hat` in its lower right corner and `M''` is sparse.
+/// Values are given for the transposed MDS matrix, which is what the
+/// factorization operates on.
+#[derive(Derivative)]
+#[derivative(Clone, Debug, PartialEq(bound = ""), Eq(bound = ""))]
+pub struct SparseDecomposition<E: Engine, const SUBDIM: usize> {
+    pub m_00: E::Fr,
+    pub m_hat: [[E::Fr; SUBDIM]; SUBDIM],
+    /// First row of the matrix without its first element.
+    pub v: [E::Fr; SUBDIM],
+    /// First column of the matrix without its first element.
+    pub w: [E::Fr; SUBDIM],
+    /// `m_hat^-1 x w`
+    pub w_hat: [E::Fr; SUBDIM],
+}
+
+/// Output of `compute_optimized_matrixes` together with the per round
+/// factorizations, in the order rounds are processed, i.e. from the last
+/// partial round to the first one.
+#[derive(Derivative)]
+#[derivative(Clone, Debug, PartialEq(bound = ""), Eq(bound = ""))]
+pub struct OptimizedMatrixes<E: Engine, const DIM: usize, const SUBDIM: usize> {
+    pub m_prime: [[E::Fr; DIM]; DIM],
+    pub sparse_matrixes: Vec<[[E::Fr; DIM]; DIM]>,
+    pub decompositions: Vec<SparseDecomposition<E, SUBDIM>>,
+}
 
 // We can reduce cost of each partial round by using an optimization from
 // original Poseidon paper. Appendix-B explains details.
-pub(crate) fn compute_optimized_matrixes<E: Engine, const DIM: usize, const SUBDIM: usize>(
+pub fn compute_optimized_matrixes<E: Engine, const DIM: usize, const SUBDIM: usize>(
     number_of_rounds: usize,
     original_mds: &[[E::Fr; DIM]; DIM],
 ) -> ([[E::Fr; DIM]; DIM], Vec<[[E::Fr; DIM]; DIM]>) {
-    let original_mds = transpose::<E, DIM>(original_mds);
+    let result = compute_optimized_matrixes_with_artifacts::<E, DIM, SUBDIM>(number_of_rounds, original_mds);
+
+    (result.m_prime, result.sparse_matrixes)
+}
+
+/// Same as `compute_optimized_matrixes` but also returns intermediate
+/// factorizations, e.g. for external analysis of the matrixes in use.
+pub fn compute_optimized_matrixes_with_artifacts<E: Engine, const DIM: usize, const SUBDIM: usize>(
+    number_of_rounds: usize,
+    original_mds: &[[E::Fr; DIM]; DIM],
+) -> OptimizedMatrixes<E, DIM, SUBDIM> {
+    compute_optimized_matrixes_of_transposed::<E, DIM, SUBDIM>(
+        number_of_rounds,
+        &transpose::<E, DIM>(original_mds),
+    )
+}
+
+// Factorization is done for the transposed MDS matrix, so params pass the
+// transposed copy they keep instead of transposing it here.
+pub(crate) fn compute_optimized_matrixes_of_transposed<E: Engine, const DIM: usize, const SUBDIM: usize>(
+    number_of_rounds: usize,
+    transposed_mds: &[[E::Fr; DIM]; DIM],
+) -> OptimizedMatrixes<E, DIM, SUBDIM> {
+    assert_eq!(DIM, SUBDIM + 1, "sub matrix should be one dimension lower");
+    let original_mds = *transposed_mds;
+    let mut decompositions = Vec::with_capacity(number_of_rounds);
     let mut matrix = original_mds;
     let mut m_prime = identity::<E, DIM>();
     let mut sparse_matrixes = vec![[[E::Fr::zero(); DIM]; DIM]; number_of_rounds];
@@ -23,6 +78,13 @@ pub(crate) fn compute_optimized_matrixes<E: Engine, const DIM: usize, const SUBD
 
         let m_hat_inv = try_inverse::<E, SUBDIM>(&m_hat).expect("inverse");
         let w_hat = multiply::<E, SUBDIM>(&m_hat_inv, &w);
+        decompositions.push(SparseDecomposition {
+            m_00: matrix[0][0],
+            m_hat,
+            v: v[0],
+            w: core::array::from_fn(|i| w[i][0]),
+            w_hat: core::array::from_fn(|i| w_hat[i][0]),
+        });
 
         let mut sparse_matrix = identity::<E, DIM>();
         sparse_matrix[0][0] = matrix[0][0];
@@ -39,21 +101,61 @@ pub(crate) fn compute_optimized_matrixes<E: Engine, const DIM: usize, const SUBD
     }
 
     sparse_matrixes.reverse();
-    sparse_matrixes
+    let all_matrixes: Vec<_> = sparse_matrixes
         .iter()
         .chain(&[m_prime.clone()])
-        .for_each(|matrix| {
-            let _ = try_inverse::<E, DIM>(matrix).expect("should have inverse");
-        });
+        .cloned()
+        .collect();
+    let _ = try_batch_inverse::<E, DIM>(&all_matrixes).expect("should have inverse");
+
+    OptimizedMatrixes {
+        m_prime: transpose::<E, DIM>(&m_prime),
+        sparse_matrixes,
+        decompositions,
+    }
+}
 
-    (transpose::<E, DIM>(&m_prime), sparse_matrixes)
+/// Same as `compute_optimized_matrixes` for any state width from 2 to 16,
+/// dimension of the sub matrix is derived from `DIM`.
+pub fn compute_optimized_matrixes_for_width<E: Engine, const DIM: usize>(
+    number_of_rounds: usize,
+    original_mds: &[[E::Fr; DIM]; DIM],
+) -> ([[E::Fr; DIM]; DIM], Vec<[[E::Fr; DIM]; DIM]>) {
+    compute_optimized_matrixes_of_transposed_for_width::<E, DIM>(
+        number_of_rounds,
+        &transpose::<E, DIM>(original_mds),
+    )
+}
+
+// SUBDIM can't be computed from DIM as a const generic, so supported widths
+// are dispatched one by one.
+pub(crate) fn compute_optimized_matrixes_of_transposed_for_width<E: Engine, const DIM: usize>(
+    number_of_rounds: usize,
+    transposed_mds: &[[E::Fr; DIM]; DIM],
+) -> ([[E::Fr; DIM]; DIM], Vec<[[E::Fr; DIM]; DIM]>) {
+    macro_rules! dispatch {
+        ($($subdim:literal),*) => {
+            match DIM - 1 {
+                $($subdim => {
+                    let result = compute_optimized_matrixes_of_transposed::<E, DIM, $subdim>(
+                        number_of_rounds,
+                        transposed_mds,
+                    );
+                    (result.m_prime, result.sparse_matrixes)
+                })*
+                _ => unimplemented!("optimized matrixes are not supported for state width {}", DIM),
+            }
+        };
+    }
+
+    dispatch!(1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15)
 }
 
 // Decontructs a sub matrix
 pub(crate) fn sub_matrix<E: Engine, const DIM: usize, const SUBDIM: usize>(
     matrix: &[[E::Fr; DIM]; DIM],
-    row_range: std::ops::Range<usize>,
-    col_range: std::ops::Range<usize>,
+    row_range: core::ops::Range<usize>,
+    col_range: core::ops::Range<usize>,
 ) -> [[E::Fr; SUBDIM]; SUBDIM] {
     // we need following decompositions for optimized matrixes
     //          row     col
@@ -95,10 +197,11 @@ pub(crate) fn mmul_assign<E: Engine, const DIM: usize>(
     matrix: &[[E::Fr; DIM]; DIM],
     vector: &mut [E::Fr; DIM],
 ) {
-    // [M]xv
+    // [M]xv, matrix is stored by rows so each output is a scalar product
+    // with a contiguous row
     let mut result = [E::Fr::zero(); DIM];
-    for col in 0..DIM {
-        result[col] = crate::common::utils::scalar_product::<E>(vector, &matrix[col]);
+    for (row_idx, row) in matrix.iter().enumerate() {
+        result[row_idx] = crate::common::utils::scalar_product::<E>(vector, row);
     }
     vector.copy_from_slice(&result[..]);
 }
@@ -134,76 +237,181 @@ pub(crate) fn transpose<E: Engine, const DIM: usize>(
     values
 }
 
-// Computes inverse of 2-d or 3-d matrixes.
-// We need inverse of matrix for optimized poseidon 
-pub(crate) fn try_inverse<E: Engine, const DIM: usize>(
+/// Computes inverse of a matrix of any dimension, `None` if it is singular.
+/// 2-d and 3-d matrixes use closed forms and larger ones Gauss-Jordan
+/// elimination. Optimized Poseidon params invert the MDS matrix this way, so
+/// custom MDS matrixes can be checked upfront.
+pub fn try_inverse<E: Engine, const DIM: usize>(
     m: &[[E::Fr; DIM]; DIM],
 ) -> Option<[[E::Fr; DIM]; DIM]> {
+    let det_inv = determinant::<E, DIM>(m).inverse()?;
+
+    Some(inverse_with_det_inv::<E, DIM>(m, &det_inv))
+}
+
+// Computes inverses of many matrixes, 2-d and 3-d ones with a single field
+// inversion.
+pub(crate) fn try_batch_inverse<E: Engine, const DIM: usize>(
+    matrixes: &[[[E::Fr; DIM]; DIM]],
+) -> Option<Vec<[[E::Fr; DIM]; DIM]>> {
+    let mut det_invs: Vec<_> = matrixes.iter().map(|m| determinant::<E, DIM>(m)).collect();
+    if det_invs.iter().any(|det| det.is_zero()) {
+        return None;
+    }
+    crate::common::utils::batch_inversion::<E>(&mut det_invs);
+
+    Some(
+        matrixes
+            .iter()
+            .zip(det_invs.iter())
+            .map(|(m, det_inv)| inverse_with_det_inv::<E, DIM>(m, det_inv))
+            .collect(),
+    )
+}
+
+fn determinant<E: Engine, const DIM: usize>(m: &[[E::Fr; DIM]; DIM]) -> E::Fr {
+    match DIM {
+        2 => determinant_dim_2::<E, DIM>(m),
+        3 => determinant_dim_3::<E, DIM>(m),
+        _ => determinant_by_elimination::<E, DIM>(m),
+    }
+}
+
+fn inverse_with_det_inv<E: Engine, const DIM: usize>(
+    m: &[[E::Fr; DIM]; DIM],
+    det_inv: &E::Fr,
+) -> [[E::Fr; DIM]; DIM] {
     match DIM {
-        2 => try_inverse_dim_2::<E, DIM>(m),
-        3 => try_inverse_dim_3::<E, DIM>(m),
-        _ => unimplemented!("unsupported matrix dimension"),
+        2 => inverse_dim_2::<E, DIM>(m, det_inv),
+        3 => inverse_dim_3::<E, DIM>(m, det_inv),
+        // elimination doesn't need the determinant
+        _ => inverse_by_elimination::<E, DIM>(m).expect("matrix with non-zero determinant"),
     }
 }
 
-// Computes inverse of 2x2 matrix.
-fn try_inverse_dim_2<E: Engine, const DIM: usize>(
+// Computes determinant as the product of pivots of the row echelon form.
+fn determinant_by_elimination<E: Engine, const DIM: usize>(m: &[[E::Fr; DIM]; DIM]) -> E::Fr {
+    let mut m = *m;
+    let mut det = E::Fr::one();
+    for col in 0..DIM {
+        let pivot = match (col..DIM).find(|row| !m[*row][col].is_zero()) {
+            Some(pivot) => pivot,
+            None => return E::Fr::zero(),
+        };
+        if pivot != col {
+            m.swap(pivot, col);
+            det.negate();
+        }
+        det.mul_assign(&m[col][col]);
+
+        let pivot_inv = m[col][col].inverse().expect("non-zero pivot");
+        for row in col + 1..DIM {
+            let mut factor = m[row][col];
+            factor.mul_assign(&pivot_inv);
+            for j in col..DIM {
+                let mut tmp = m[col][j];
+                tmp.mul_assign(&factor);
+                m[row][j].sub_assign(&tmp);
+            }
+        }
+    }
+
+    det
+}
+
+// Computes inverse by Gauss-Jordan elimination of `[m | I]`.
+fn inverse_by_elimination<E: Engine, const DIM: usize>(
     m: &[[E::Fr; DIM]; DIM],
 ) -> Option<[[E::Fr; DIM]; DIM]> {
+    let mut m = *m;
+    let mut result = identity::<E, DIM>();
+    for col in 0..DIM {
+        let pivot = (col..DIM).find(|row| !m[*row][col].is_zero())?;
+        m.swap(pivot, col);
+        result.swap(pivot, col);
+
+        // normalize pivot row
+        let pivot_inv = m[col][col].inverse().expect("non-zero pivot");
+        for j in 0..DIM {
+            m[col][j].mul_assign(&pivot_inv);
+            result[col][j].mul_assign(&pivot_inv);
+        }
+
+        // eliminate the column from all other rows
+        for row in 0..DIM {
+            if row == col || m[row][col].is_zero() {
+                continue;
+            }
+            let factor = m[row][col];
+            for j in 0..DIM {
+                let mut tmp = m[col][j];
+                tmp.mul_assign(&factor);
+                m[row][j].sub_assign(&tmp);
+
+                let mut tmp = result[col][j];
+                tmp.mul_assign(&factor);
+                result[row][j].sub_assign(&tmp);
+            }
+        }
+    }
+
+    Some(result)
+}
+
+// Computes determinant of 2x2 matrix.
+fn determinant_dim_2<E: Engine, const DIM: usize>(m: &[[E::Fr; DIM]; DIM]) -> E::Fr {
     assert_eq!(DIM, 2);
-    let determinant = {
-        let mut a = m[0][0];
-        a.mul_assign(&m[1][1]);
+    let mut a = m[0][0];
+    a.mul_assign(&m[1][1]);
 
-        let mut b = m[1][0];
-        b.mul_assign(&m[0][1]);
+    let mut b = m[1][0];
+    b.mul_assign(&m[0][1]);
 
-        a.sub_assign(&b);
+    a.sub_assign(&b);
 
-        a
-    };
+    a
+}
 
+// Computes inverse of 2x2 matrix from inverse of its determinant.
+fn inverse_dim_2<E: Engine, const DIM: usize>(
+    m: &[[E::Fr; DIM]; DIM],
+    det_inv: &E::Fr,
+) -> [[E::Fr; DIM]; DIM] {
+    assert_eq!(DIM, 2);
     let mut result = [[E::Fr::zero(); DIM]; DIM];
-    let det_inv = if let Some(inv) = determinant.inverse() {
-        inv
-    } else {
-        return None;
-    };
 
     // m22 / determinant;
     result[0][0] = {
         let mut tmp = m[1][1];
-        tmp.mul_assign(&det_inv);
+        tmp.mul_assign(det_inv);
         tmp
     };
     // -m12 / determinant;
     result[0][1] = {
         let mut tmp = m[0][1];
         tmp.negate();
-        tmp.mul_assign(&det_inv);
+        tmp.mul_assign(det_inv);
         tmp
     };
     // -m21 / determinant;
     result[1][0] = {
         let mut tmp = m[1][0];
         tmp.negate();
-        tmp.mul_assign(&det_inv);
+        tmp.mul_assign(det_inv);
         tmp
     };
     // m11 / determinant;
     result[1][1] = {
         let mut tmp = m[0][0];
-        tmp.mul_assign(&det_inv);
+        tmp.mul_assign(det_inv);
         tmp
     };
 
-    Some(result)
+    result
 }
 
-// Computes inverse of 3x3 matrix.
-fn try_inverse_dim_3<E: Engine, const DIM: usize>(
-    m: &[[E::Fr; DIM]; DIM],
-) -> Option<[[E::Fr; DIM]; DIM]> {
+// Computes minors of the first row of 3x3 matrix.
+fn minors_dim_3<E: Engine, const DIM: usize>(m: &[[E::Fr; DIM]; DIM]) -> (E::Fr, E::Fr, E::Fr) {
     assert_eq!(DIM, 3);
     // m22 * m33 - m32 * m23;
     let minor_m12_m23 = {
@@ -243,38 +451,41 @@ fn try_inverse_dim_3<E: Engine, const DIM: usize>(
         a
     };
 
+    (minor_m12_m23, minor_m11_m23, minor_m11_m22)
+}
+
+// Computes determinant of 3x3 matrix.
+fn determinant_dim_3<E: Engine, const DIM: usize>(m: &[[E::Fr; DIM]; DIM]) -> E::Fr {
+    let (minor_m12_m23, minor_m11_m23, minor_m11_m22) = minors_dim_3::<E, DIM>(m);
+
     // m11 * minor_m12_m23 - m12 * minor_m11_m23 + m13 * minor_m11_m22;
-    let determinant = {
-        let mut a = m[0][0];
-        a.mul_assign(&minor_m12_m23);
+    let mut a = m[0][0];
+    a.mul_assign(&minor_m12_m23);
 
-        let mut b = m[0][1];
-        b.mul_assign(&minor_m11_m23);
+    let mut b = m[0][1];
+    b.mul_assign(&minor_m11_m23);
 
-        let mut c = m[0][2];
-        c.mul_assign(&minor_m11_m22);
+    let mut c = m[0][2];
+    c.mul_assign(&minor_m11_m22);
 
-        a.sub_assign(&b);
-        a.add_assign(&c);
+    a.sub_assign(&b);
+    a.add_assign(&c);
 
-        a
-    };
+    a
+}
 
-    if determinant.is_zero() {
-        // matrix is not invertible
-        return None;
-    }
+// Computes inverse of 3x3 matrix from inverse of its determinant.
+fn inverse_dim_3<E: Engine, const DIM: usize>(
+    m: &[[E::Fr; DIM]; DIM],
+    det_inv: &E::Fr,
+) -> [[E::Fr; DIM]; DIM] {
+    let (minor_m12_m23, minor_m11_m23, minor_m11_m22) = minors_dim_3::<E, DIM>(m);
 
     let mut result = [[E::Fr::zero(); DIM]; DIM];
-    let det_inv = if let Some(inv) = determinant.inverse() {
-        inv
-    } else {
-        return None;
-    };
     //  minor_m12_m23 / determinant;
     result[0][0] = {
         let mut tmp = minor_m12_m23.clone();
-        tmp.mul_assign(&det_inv);
+        tmp.mul_assign(det_inv);
 
         tmp
     };
@@ -289,7 +500,7 @@ fn try_inverse_dim_3<E: Engine, const DIM: usize>(
 
         a.sub_assign(&b);
 
-        a.mul_assign(&det_inv);
+        a.mul_assign(det_inv);
 
         a
     };
@@ -304,7 +515,7 @@ fn try_inverse_dim_3<E: Engine, const DIM: usize>(
 
         a.sub_assign(&b);
 
-        a.mul_assign(&det_inv);
+        a.mul_assign(det_inv);
 
         a
     };
@@ -312,7 +523,7 @@ fn try_inverse_dim_3<E: Engine, const DIM: usize>(
     result[1][0] = {
         let mut tmp = minor_m11_m23;
         tmp.negate();
-        tmp.mul_assign(&det_inv);
+        tmp.mul_assign(det_inv);
 
         tmp
     };
@@ -326,7 +537,7 @@ fn try_inverse_dim_3<E: Engine, const DIM: usize>(
 
         a.sub_assign(&b);
 
-        a.mul_assign(&det_inv);
+        a.mul_assign(det_inv);
 
         a
     };
@@ -340,14 +551,14 @@ fn try_inverse_dim_3<E: Engine, const DIM: usize>(
 
         a.sub_assign(&b);
 
-        a.mul_assign(&det_inv);
+        a.mul_assign(det_inv);
 
         a
     };
     // minor_m11_m22 / determinant;
     result[2][0] = {
         let mut tmp = minor_m11_m22;
-        tmp.mul_assign(&det_inv);
+        tmp.mul_assign(det_inv);
 
         tmp
     };
@@ -361,7 +572,7 @@ fn try_inverse_dim_3<E: Engine, const DIM: usize>(
 
         a.sub_assign(&b);
 
-        a.mul_assign(&det_inv);
+        a.mul_assign(det_inv);
 
         a
     };
@@ -375,12 +586,12 @@ fn try_inverse_dim_3<E: Engine, const DIM: usize>(
 
         a.sub_assign(&b);
 
-        a.mul_assign(&det_inv);
+        a.mul_assign(det_inv);
 
         a
     };
 
-    Some(result)
+    result
 }
 
 // Computes identity of given dimension.
@@ -426,6 +637,85 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_matrix_inverse_by_elimination() {
+        fn check<const DIM: usize>() {
+            let rng = &mut init_rng();
+            let matrix = crate::common::utils::construct_mds_matrix::<Bn256, _, DIM>(rng);
+            let inverse = try_inverse::<Bn256, DIM>(&matrix).expect("inverse");
+            assert_eq!(multiply::<Bn256, DIM>(&inverse, &matrix), identity::<Bn256, DIM>());
+            assert_eq!(inverse_by_elimination::<Bn256, DIM>(&matrix), Some(inverse));
+
+            let mut singular = matrix;
+            singular[DIM - 1] = singular[0];
+            assert!(determinant::<Bn256, DIM>(&singular).is_zero());
+            assert!(try_inverse::<Bn256, DIM>(&singular).is_none());
+        }
+
+        // closed forms agree with elimination
+        check::<2>();
+        check::<3>();
+        check::<4>();
+        check::<8>();
+        check::<12>();
+
+        // pivot in the first column is zero
+        let (zero, one) = (Fr::zero(), Fr::one());
+        let permutation = [[zero, one, zero, zero], [one, zero, zero, zero], [zero, zero, zero, one], [zero, zero, one, zero]];
+        assert_eq!(determinant::<Bn256, 4>(&permutation), one);
+        assert_eq!(try_inverse::<Bn256, 4>(&permutation), Some(permutation));
+
+        // pivot is zero only after the first column is eliminated
+        let rng = &mut init_rng();
+        let mut matrix = [[Fr::zero(); 5]; 5];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            row[0] = Fr::from_str(&(i + 1).to_string()).unwrap();
+            for el in row[2..].iter_mut() {
+                *el = Fr::rand(rng);
+            }
+        }
+        // second column is twice the first one except the last row
+        for row in matrix.iter_mut().take(4) {
+            row[1] = row[0];
+            row[1].double();
+        }
+        matrix[4][1] = Fr::rand(rng);
+        let inverse = try_inverse::<Bn256, 5>(&matrix).expect("inverse");
+        assert_eq!(multiply::<Bn256, 5>(&matrix, &inverse), identity::<Bn256, 5>());
+    }
+
+    #[test]
+    fn test_matrix_batch_inverse() {
+        let rng = &mut init_rng();
+
+        const DIM: usize = 3;
+        let matrixes: Vec<_> = (0..4)
+            .map(|_| crate::common::utils::construct_mds_matrix::<Bn256, _, DIM>(rng))
+            .collect();
+
+        let inverses = try_batch_inverse::<Bn256, DIM>(&matrixes).expect("inverses");
+        for (m, inv) in matrixes.iter().zip(inverses.iter()) {
+            assert_eq!(try_inverse::<Bn256, DIM>(m).expect("inverse"), *inv);
+        }
+
+        let mut singular = matrixes.clone();
+        singular.push([[Fr::zero(); DIM]; DIM]);
+        assert!(try_batch_inverse::<Bn256, DIM>(&singular).is_none());
+    }
+
+    #[test]
+    fn test_scalar_product() {
+        let rng = &mut init_rng();
+
+        for len in 1..6 {
+            let a: Vec<_> = (0..len).map(|_| Fr::rand(rng)).collect();
+            let b: Vec<_> = (0..len).map(|_| Fr::rand(rng)).collect();
+
+            let expected = crate::common::utils::scalar_product_single_accumulator::<Bn256>(&a, &b);
+            assert_eq!(crate::common::utils::scalar_product::<Bn256>(&a, &b), expected);
+        }
+    }
+
     #[test]
     fn test_matrix_deconstruction() {
         let one = Fr::one();
@@ -498,6 +788,99 @@ mod test {
         let (_, _) = compute_optimized_matrixes::<Bn256, DIM, SUBDIM>(5, &original_mds);
     }
 
+    #[test]
+    fn test_optimized_matrixes_for_width() {
+        let rng = &mut init_rng();
+
+        const DIM: usize = 3;
+        const SUBDIM: usize = 2;
+
+        let original_mds = crate::common::utils::construct_mds_matrix::<Bn256, _, DIM>(rng);
+        assert_eq!(
+            compute_optimized_matrixes_for_width::<Bn256, DIM>(5, &original_mds),
+            compute_optimized_matrixes::<Bn256, DIM, SUBDIM>(5, &original_mds)
+        );
+
+        let original_mds = crate::common::utils::construct_mds_matrix::<Bn256, _, 12>(rng);
+        let (_, sparse_matrixes) = compute_optimized_matrixes_for_width::<Bn256, 12>(5, &original_mds);
+        assert_eq!(sparse_matrixes.len(), 5);
+    }
+
+    #[test]
+    fn test_optimized_matrixes_artifacts() {
+        let rng = &mut init_rng();
+
+        const DIM: usize = 3;
+        const SUBDIM: usize = 2;
+
+        let original_mds = crate::common::utils::construct_mds_matrix::<Bn256, _, DIM>(rng);
+        let (m_prime, sparse_matrixes) = compute_optimized_matrixes::<Bn256, DIM, SUBDIM>(5, &original_mds);
+        let result = compute_optimized_matrixes_with_artifacts::<Bn256, DIM, SUBDIM>(5, &original_mds);
+        assert_eq!(result.m_prime, m_prime);
+        assert_eq!(result.sparse_matrixes, sparse_matrixes);
+        assert_eq!(result.decompositions.len(), 5);
+
+        for decomposition in result.decompositions.iter() {
+            // m_hat x w_hat = w
+            let mut w_hat = [[Fr::zero(); SUBDIM]; SUBDIM];
+            for (row, el) in w_hat.iter_mut().zip(decomposition.w_hat.iter()) {
+                row[0] = *el;
+            }
+            let actual = multiply::<Bn256, SUBDIM>(&decomposition.m_hat, &w_hat);
+            for (row, el) in actual.iter().zip(decomposition.w.iter()) {
+                assert_eq!(row[0], *el);
+            }
+        }
+        // first processed round is the last partial round
+        let transposed = transpose::<Bn256, DIM>(&original_mds);
+        assert_eq!(result.decompositions[0].m_00, transposed[0][0]);
+        assert_eq!(result.decompositions[0].v, [transposed[0][1], transposed[0][2]]);
+        assert_eq!(result.decompositions[0].w, [transposed[1][0], transposed[2][0]]);
+    }
+
+    #[test]
+    fn test_mmul_assign_reads_rows() {
+        let rng = &mut init_rng();
+
+        const DIM: usize = 3;
+        let matrix: [[Fr; DIM]; DIM] = core::array::from_fn(|_| core::array::from_fn(|_| Fr::rand(rng)));
+        let vector: [Fr; DIM] = core::array::from_fn(|_| Fr::rand(rng));
+
+        let mut actual = vector;
+        mmul_assign::<Bn256, DIM>(&matrix, &mut actual);
+        for (row, el) in matrix.iter().zip(actual.iter()) {
+            let mut expected = Fr::zero();
+            for (m, v) in row.iter().zip(vector.iter()) {
+                let mut tmp = *m;
+                tmp.mul_assign(v);
+                expected.add_assign(&tmp);
+            }
+            assert_eq!(*el, expected);
+        }
+    }
+
+    #[test]
+    fn test_params_keep_transposed_mds() {
+        const DIM: usize = 3;
+        const SUBDIM: usize = 2;
+
+        let (mut params, _) = crate::poseidon::params::poseidon_params::<Bn256, 2, DIM>();
+        assert_eq!(params.mds_matrix_transposed(), &transpose::<Bn256, DIM>(params.mds_matrix()));
+
+        let expected = compute_optimized_matrixes_with_artifacts::<Bn256, DIM, SUBDIM>(
+            params.partial_rounds,
+            params.mds_matrix(),
+        );
+        let actual = compute_optimized_matrixes_of_transposed::<Bn256, DIM, SUBDIM>(
+            params.partial_rounds,
+            params.mds_matrix_transposed(),
+        );
+        assert_eq!(actual, expected);
+
+        params.set_circular_optimized_mds();
+        assert_eq!(params.mds_matrix_transposed(), &transpose::<Bn256, DIM>(params.mds_matrix()));
+    }
+
     fn int_to_fe<E: Engine>(elements: &[i8]) -> Vec<E::Fr> {
         elements
             .iter()
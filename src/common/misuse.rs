@@ -0,0 +1,164 @@
+//! Debug build checks of common ways to misuse the sponge. Misuse is logged
+//! as a warning instead of failing, since the sponge can't tell a protocol
+//! mistake from an intentional construction. Release builds skip all checks
+//! and the tracker is zero sized.
+use crate::traits::HashFamily;
+use franklin_crypto::bellman::Engine;
+
+/// Number of latest fixed length hash outputs remembered per thread.
+#[cfg(all(debug_assertions, feature = "std"))]
+const RECENT_OUTPUTS: usize = 16;
+
+#[cfg(all(debug_assertions, feature = "std"))]
+std::thread_local! {
+    static FIXED_LENGTH_OUTPUTS: core::cell::RefCell<alloc::collections::VecDeque<Vec<u64>>> =
+        core::cell::RefCell::new(alloc::collections::VecDeque::with_capacity(RECENT_OUTPUTS));
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Misuse {
+    // Output of a fixed length hash is the first element absorbed by a
+    // variable length sponge, so nothing separates the two domains.
+    UntaggedFixedLengthOutput,
+    // Sponge squeezed with params of one family is continued with another.
+    ReusedWithOtherParams { squeezed_with: HashFamily, used_with: HashFamily },
+}
+
+impl core::fmt::Display for Misuse {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UntaggedFixedLengthOutput => write!(
+                f,
+                "output of a fixed length hash is absorbed into a variable length sponge without a preceding tag"
+            ),
+            Self::ReusedWithOtherParams { squeezed_with, used_with } => write!(
+                f,
+                "sponge squeezed with {:?} params is reused with {:?} params",
+                squeezed_with, used_with
+            ),
+        }
+    }
+}
+
+/// Remembers output of a fixed length hash so that absorbing it untagged can
+/// be reported later.
+pub(crate) fn record_fixed_length_output<E: Engine>(_output: &[E::Fr]) {
+    #[cfg(all(debug_assertions, feature = "std"))]
+    FIXED_LENGTH_OUTPUTS.with(|outputs| {
+        let mut outputs = outputs.borrow_mut();
+        for el in _output.iter() {
+            if outputs.len() == RECENT_OUTPUTS {
+                outputs.pop_front();
+            }
+            outputs.push_back(limbs::<E>(el));
+        }
+    });
+}
+
+// Outputs are not remembered without `std`, there is no thread local storage.
+#[cfg(debug_assertions)]
+fn is_fixed_length_output<E: Engine>(_el: &E::Fr) -> bool {
+    #[cfg(feature = "std")]
+    return FIXED_LENGTH_OUTPUTS.with(|outputs| outputs.borrow().contains(&limbs::<E>(_el)));
+    #[cfg(not(feature = "std"))]
+    false
+}
+
+#[cfg(all(debug_assertions, feature = "std"))]
+fn limbs<E: Engine>(el: &E::Fr) -> Vec<u64> {
+    use franklin_crypto::bellman::{PrimeField, PrimeFieldRepr};
+    el.into_repr().as_ref().to_vec()
+}
+
+/// Per sponge state of the checks.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct MisuseTracker {
+    #[cfg(debug_assertions)]
+    absorbed: bool,
+    #[cfg(debug_assertions)]
+    squeezed_with: Option<HashFamily>,
+}
+
+impl MisuseTracker {
+    /// Called before `el` is absorbed by a variable length sponge.
+    pub(crate) fn on_absorb<E: Engine>(&mut self, _el: &E::Fr, _family: HashFamily) -> Option<Misuse> {
+        #[cfg(debug_assertions)]
+        {
+            let first = !core::mem::replace(&mut self.absorbed, true);
+
+            if let Some(squeezed_with) = self.squeezed_with {
+                if squeezed_with != _family {
+                    return report(Misuse::ReusedWithOtherParams { squeezed_with, used_with: _family });
+                }
+            }
+            if first && is_fixed_length_output::<E>(_el) {
+                return report(Misuse::UntaggedFixedLengthOutput);
+            }
+        }
+
+        None
+    }
+
+    /// Called before an element is squeezed.
+    pub(crate) fn on_squeeze(&mut self, _family: HashFamily) -> Option<Misuse> {
+        #[cfg(debug_assertions)]
+        match self.squeezed_with {
+            Some(squeezed_with) if squeezed_with != _family => {
+                return report(Misuse::ReusedWithOtherParams { squeezed_with, used_with: _family });
+            }
+            _ => self.squeezed_with = Some(_family),
+        }
+
+        None
+    }
+}
+
+#[cfg(debug_assertions)]
+fn report(misuse: Misuse) -> Option<Misuse> {
+    log::warn!("sponge misuse: {}", misuse);
+
+    Some(misuse)
+}
+
+#[cfg(all(test, debug_assertions))]
+mod tests {
+    use super::*;
+    use crate::tests::init_rng;
+    use crate::{GenericSponge, RescueParams};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_untagged_fixed_length_output() {
+        let rng = &mut init_rng();
+        let params = RescueParams::<Bn256, 2, 3>::default();
+        let digest = GenericSponge::<Bn256, 2, 3>::hash(&[Fr::rand(rng)], &params, None);
+
+        let mut tracker = MisuseTracker::default();
+        assert_eq!(
+            tracker.on_absorb::<Bn256>(&digest[0], HashFamily::Rescue),
+            Some(Misuse::UntaggedFixedLengthOutput)
+        );
+
+        // tagged output is fine
+        let mut tracker = MisuseTracker::default();
+        assert_eq!(tracker.on_absorb::<Bn256>(&Fr::rand(rng), HashFamily::Rescue), None);
+        assert_eq!(tracker.on_absorb::<Bn256>(&digest[0], HashFamily::Rescue), None);
+    }
+
+    #[test]
+    fn test_reused_with_other_params() {
+        let rng = &mut init_rng();
+        let mut tracker = MisuseTracker::default();
+        assert_eq!(tracker.on_absorb::<Bn256>(&Fr::rand(rng), HashFamily::Rescue), None);
+        assert_eq!(tracker.on_squeeze(HashFamily::Rescue), None);
+        assert_eq!(tracker.on_absorb::<Bn256>(&Fr::rand(rng), HashFamily::Rescue), None);
+
+        let misuse = Misuse::ReusedWithOtherParams {
+            squeezed_with: HashFamily::Rescue,
+            used_with: HashFamily::Poseidon,
+        };
+        assert_eq!(tracker.on_absorb::<Bn256>(&Fr::rand(rng), HashFamily::Poseidon), Some(misuse));
+        assert_eq!(tracker.on_squeeze(HashFamily::Poseidon), Some(misuse));
+    }
+}
@@ -4,4 +4,6 @@ pub(crate) mod utils;
 pub(crate) mod matrix;
 pub(crate) mod domain_strategy;
 pub(crate) mod params;
+pub(crate) mod fingerprint;
+pub(crate) mod misuse;
 pub(crate) const TEST_SEED: [u32; 4] = [0x5dbe6259, 0x8d313d76, 0x3237db17, 0xe5bc0654];
@@ -1,5 +1,5 @@
-use std::convert::TryInto;
 
+use alloc::{vec, vec::Vec};
 use byteorder::{BigEndian, ReadBytesExt, WriteBytesExt};
 use franklin_crypto::bellman::pairing::ff::{Field, PrimeField, PrimeFieldRepr};
 use franklin_crypto::bellman::pairing::Engine;
@@ -7,7 +7,10 @@ use franklin_crypto::constants;
 use franklin_crypto::group_hash::{BlakeHasher, GroupHasher};
 use rand::{chacha::ChaChaRng, Rng, SeedableRng};
 
-use crate::common::utils::construct_mds_matrix;
+use crate::common::matrix::transpose;
+use crate::common::utils::{
+    construct_mds_matrix, construct_mds_matrix_with_artifacts, try_slice_into_fixed, MdsArtifacts,
+};
 
 #[derive(Debug, Clone)]
 pub struct InnerHashParameters<E: Engine, const RATE: usize, const WIDTH: usize> {
@@ -15,12 +18,76 @@ pub struct InnerHashParameters<E: Engine, const RATE: usize, const WIDTH: usize>
     pub full_rounds: usize,
     pub partial_rounds: usize,
     pub round_constants: Vec<[E::Fr; WIDTH]>,
+    /// Stored row by row, i.e. `mds_matrix[row][col]`, which is what
+    /// `mmul_assign` reads when multiplying the state.
     pub mds_matrix: [[E::Fr; WIDTH]; WIDTH],
+    /// `mds_matrix[col][row]`, the orientation optimized matrixes are
+    /// derived in. Private and kept in sync with `mds_matrix` by
+    /// `set_mds_matrix`, which all writes within the crate go through.
+    mds_matrix_transposed: [[E::Fr; WIDTH]; WIDTH],
 }
 
-
 type H = BlakeHasher;
 
+/// Personalization of blake2s deriving round constants of Rescue, Poseidon
+/// and Poseidon2.
+pub(crate) const ROUND_CONSTANTS_TAG: &[u8] = b"Rescue_f";
+/// Personalization of blake2s seeding the rng of Cauchy MDS matrices.
+pub(crate) const MDS_TAG: &[u8] = b"ResM0003";
+/// Prefix of the prefixed blake2s input deriving seeded params, followed by
+/// the hash family, length of the seed and the seed itself.
+pub(crate) const SEEDED_PARAMS_TAG: &[u8] = b"RescuePoseidonSeeded";
+/// Key derivation context of the blake3 XOF deriving round constants. It is
+/// part of the parameter set, a change of the derivation gets a new version.
+#[cfg(feature = "blake3")]
+pub(crate) const BLAKE3_CONSTANTS_CONTEXT: &str = "rescue_poseidon round constants v1";
+
+/// Derivation of round constants from the tag of default params.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ConstantsSource {
+    /// Personalized blake2s of a counter per candidate constant, used by
+    /// default params.
+    #[default]
+    Blake2s,
+    /// Candidates are read from a single blake3 XOF stream keyed by
+    /// `rescue_poseidon round constants v1` and masked to the bit length of
+    /// the modulus, so most of them are accepted and no hasher is set up per
+    /// candidate. Params are a different set, they don't interoperate with
+    /// default ones.
+    #[cfg(feature = "blake3")]
+    Blake3Xof,
+}
+
+/// Errors of deriving params from an application seed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SeededParamsError {
+    /// Round numbers of the family don't reach the requested security.
+    UnsupportedSecurityLevel { requested: usize, supported: usize },
+    UnsupportedWidth(usize),
+    ZeroRoundConstant { round: usize, index: usize },
+    SingularMdsMatrix,
+}
+
+impl core::fmt::Display for SeededParamsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnsupportedSecurityLevel { requested, supported } => write!(
+                f,
+                "requested security of {} bits, params support at most {} bits",
+                requested, supported
+            ),
+            Self::UnsupportedWidth(width) => write!(f, "seeded params are not supported for width {}", width),
+            Self::ZeroRoundConstant { round, index } => {
+                write!(f, "round constant {} of round {} is zero", index, round)
+            }
+            Self::SingularMdsMatrix => write!(f, "MDS matrix is not invertible"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SeededParamsError {}
+
 impl<E: Engine, const RATE: usize, const WIDTH: usize> InnerHashParameters<E, RATE, WIDTH> {
     pub fn new(security_level: usize, full_rounds: usize, partial_rounds: usize) -> Self {
         assert_ne!(RATE, 0);
@@ -33,6 +100,7 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> InnerHashParameters<E, RA
             partial_rounds,
             round_constants: vec![[E::Fr::zero(); WIDTH]],
             mds_matrix: [[E::Fr::zero(); WIDTH]; WIDTH],
+            mds_matrix_transposed: [[E::Fr::zero(); WIDTH]; WIDTH],
         }
     }
 
@@ -47,6 +115,15 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> InnerHashParameters<E, RA
         &self.mds_matrix
     }
 
+    pub(crate) fn mds_matrix_transposed(&self) -> &[[E::Fr; WIDTH]; WIDTH] {
+        &self.mds_matrix_transposed
+    }
+
+    pub(crate) fn set_mds_matrix(&mut self, mds_matrix: [[E::Fr; WIDTH]; WIDTH]) {
+        self.mds_matrix = mds_matrix;
+        self.mds_matrix_transposed = transpose::<E, WIDTH>(&mds_matrix);
+    }
+
     pub(crate) fn compute_round_constants(&mut self, number_of_rounds: usize, tag: &[u8]) {
         let total_round_constants = WIDTH * number_of_rounds; 
 
@@ -84,7 +161,7 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> InnerHashParameters<E, RA
             .chunks_exact(WIDTH)
             .zip(self.round_constants.iter_mut())
             .for_each(|(values, constants)| {
-                *constants = values.try_into().expect("round constants in const")
+                *constants = try_slice_into_fixed(values).expect("round constants in const")
             });
     }
 
@@ -97,10 +174,57 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> InnerHashParameters<E, RA
             .chunks_exact(WIDTH)
             .zip(self.round_constants.iter_mut())
             .for_each(|(values, constants)| {
-                *constants = values.try_into().expect("round constants in const")
+                *constants = try_slice_into_fixed(values).expect("round constants in const")
             });
     }
 
+    pub(crate) fn compute_round_constants_from_source(
+        &mut self,
+        number_of_rounds: usize,
+        tag: &[u8],
+        source: ConstantsSource,
+    ) {
+        match source {
+            ConstantsSource::Blake2s => self.compute_round_constants(number_of_rounds, tag),
+            #[cfg(feature = "blake3")]
+            ConstantsSource::Blake3Xof => {
+                let total_round_constants = WIDTH * number_of_rounds;
+                let round_constants = get_field_elements_from_blake3_xof::<E>(total_round_constants, tag);
+
+                self.round_constants = vec![[E::Fr::zero(); WIDTH]; number_of_rounds];
+                round_constants
+                    .chunks_exact(WIDTH)
+                    .zip(self.round_constants.iter_mut())
+                    .for_each(|(values, constants)| {
+                        *constants = try_slice_into_fixed(values).expect("round constants in const")
+                    });
+            }
+        }
+    }
+
+    /// Round constants of params derived from `seed`, independent of the
+    /// default ones and of other families derived from the same seed.
+    pub(crate) fn compute_round_constants_from_seed(&mut self, number_of_rounds: usize, family: &[u8], seed: &[u8]) {
+        let tag = seeded_tag(family, b"constants", seed);
+        self.compute_round_constants_with_prefixed_blake2s(number_of_rounds, &tag);
+    }
+
+    pub(crate) fn compute_mds_matrix_from_seed(&mut self, family: &[u8], seed: &[u8]) {
+        let tag = seeded_tag(family, b"mds", seed);
+        let rng = &mut init_rng_from_tag(&tag);
+        self.compute_mds_matrix(rng)
+    }
+
+    /// Cross-checks params derived from a seed before they are used.
+    pub(crate) fn check_seeded(&self) -> Result<(), SeededParamsError> {
+        check_round_constants::<E, WIDTH>(&self.round_constants)?;
+        if crate::common::matrix::try_inverse::<E, WIDTH>(&self.mds_matrix).is_none() {
+            return Err(SeededParamsError::SingularMdsMatrix);
+        }
+
+        Ok(())
+    }
+
     pub(crate) fn compute_mds_matrix_for_poseidon(&mut self) {
         let rng = &mut init_rng_for_poseidon();
         self.compute_mds_matrix(rng)
@@ -112,30 +236,37 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> InnerHashParameters<E, RA
     }
 
     pub(crate) fn set_circular_optimized_mds(&mut self) {
-        assert_eq!(WIDTH, 3, "Circuilar (2, 1, 1) matrix is MDS only for state width = 3");
-        let one = E::Fr::one();
-        let mut two = one;
-        two.double();
-        let tmp = [
-            [two, one, one],
-            [one, two, one],
-            [one, one, two]
-        ];
-
-        for (dst_row, src_row) in self.mds_matrix.iter_mut().zip(tmp.iter()) {
-            for (dst, src) in dst_row.iter_mut().zip(src_row.iter()) {
-                *dst = *src;
-            }
-        }
+        self.set_mds_matrix(circular_mds_matrix::<E, WIDTH>());
     }
 
     fn compute_mds_matrix<R: Rng>(&mut self, rng: &mut R) {
-        self.mds_matrix = construct_mds_matrix::<E, _, WIDTH>(rng);
+        self.set_mds_matrix(construct_mds_matrix::<E, _, WIDTH>(rng));
+    }
+}
+
+/// circ(2, 1, 1) matrix, MDS for state width 3 and needs only additions.
+pub(crate) fn circular_mds_matrix<E: Engine, const WIDTH: usize>() -> [[E::Fr; WIDTH]; WIDTH] {
+    assert_eq!(WIDTH, 3, "Circular (2, 1, 1) matrix is MDS only for state width = 3");
+    let mut mds_matrix = [[E::Fr::one(); WIDTH]; WIDTH];
+    for (i, row) in mds_matrix.iter_mut().enumerate() {
+        row[i].double();
     }
+
+    mds_matrix
+}
+
+/// Regenerates Cauchy matrix artifacts of MDS matrix used by Rescue params.
+pub fn rescue_mds_artifacts<E: Engine, const WIDTH: usize>() -> MdsArtifacts<E, WIDTH> {
+    construct_mds_matrix_with_artifacts::<E, _, WIDTH>(&mut init_rng_for_rescue())
+}
+
+/// Regenerates Cauchy matrix artifacts of MDS matrix used by Poseidon params.
+pub fn poseidon_mds_artifacts<E: Engine, const WIDTH: usize>() -> MdsArtifacts<E, WIDTH> {
+    construct_mds_matrix_with_artifacts::<E, _, WIDTH>(&mut init_rng_for_poseidon())
 }
 
 fn init_rng_for_rescue() -> ChaChaRng {
-    let tag = b"ResM0003";
+    let tag = MDS_TAG;
     let mut h = H::new(&tag[..]);
     h.update(constants::GH_FIRST_BLOCK);
     let h = h.finalize();
@@ -151,7 +282,7 @@ fn init_rng_for_rescue() -> ChaChaRng {
 }
 
 fn init_rng_for_poseidon() -> ChaChaRng {
-    let tag = b"ResM0003"; // TODO: change tag?
+    let tag = MDS_TAG; // TODO: change tag?
     let mut h = H::new(&tag[..]);
     h.update(constants::GH_FIRST_BLOCK);
     let h = h.finalize();
@@ -167,6 +298,45 @@ fn init_rng_for_poseidon() -> ChaChaRng {
     ChaChaRng::from_seed(&seed)
 }
 
+// Seed is length prefixed so that no seed is a prefix of the input of
+// another one.
+pub(crate) fn seeded_tag(family: &[u8], purpose: &[u8], seed: &[u8]) -> Vec<u8> {
+    let mut tag = SEEDED_PARAMS_TAG.to_vec();
+    for part in [family, purpose, seed] {
+        tag.write_u64::<BigEndian>(part.len() as u64).unwrap();
+        tag.extend_from_slice(part);
+    }
+
+    tag
+}
+
+fn init_rng_from_tag(tag: &[u8]) -> ChaChaRng {
+    use blake2::Digest;
+    let mut h = blake2::Blake2s256::new();
+    h.update(tag);
+    h.update(constants::GH_FIRST_BLOCK);
+    let h = h.finalize();
+    let mut seed = [0u32; 8];
+    for (i, chunk) in h.chunks_exact(4).enumerate() {
+        seed[i] = (&chunk[..])
+            .read_u32::<BigEndian>()
+            .expect("digest is large enough for this to work");
+    }
+
+    ChaChaRng::from_seed(&seed)
+}
+
+pub(crate) fn check_round_constants<E: Engine, const WIDTH: usize>(
+    round_constants: &[[E::Fr; WIDTH]],
+) -> Result<(), SeededParamsError> {
+    for (round, constants) in round_constants.iter().enumerate() {
+        if let Some(index) = constants.iter().position(|c| c.is_zero()) {
+            return Err(SeededParamsError::ZeroRoundConstant { round, index });
+        }
+    }
+
+    Ok(())
+}
 
 pub(crate) fn get_random_field_elements_from_seed<E: Engine>(num_elements: usize, tag: &[u8]) -> Vec<E::Fr> {
     let mut round_constants = Vec::with_capacity(num_elements);
@@ -204,4 +374,198 @@ pub(crate) fn get_random_field_elements_from_seed<E: Engine>(num_elements: usize
     }
 
     round_constants
-}
\ No newline at end of file
+}
+
+// Candidates take the bit length of the modulus from the stream, so less
+// than a half of them is rejected instead of most of the 256 bit digests.
+#[cfg(feature = "blake3")]
+pub(crate) fn get_field_elements_from_blake3_xof<E: Engine>(num_elements: usize, tag: &[u8]) -> Vec<E::Fr> {
+    let mut hasher = blake3::Hasher::new_derive_key(BLAKE3_CONSTANTS_CONTEXT);
+    hasher.update(tag);
+    let mut reader = hasher.finalize_xof();
+
+    let mut constant_repr = <E::Fr as PrimeField>::Repr::default();
+    let num_bits = E::Fr::NUM_BITS as usize;
+    let num_bytes = (num_bits + 7) / 8;
+    let mut bytes = vec![0u8; constant_repr.as_ref().len() * 8];
+
+    let mut elements = Vec::with_capacity(num_elements);
+    while elements.len() < num_elements {
+        reader.fill(&mut bytes[..num_bytes]);
+        if num_bits % 8 != 0 {
+            bytes[num_bytes - 1] &= (1u8 << (num_bits % 8)) - 1;
+        }
+        constant_repr.read_le(&bytes[..]).unwrap();
+
+        if let Ok(constant) = E::Fr::from_repr(constant_repr) {
+            if !constant.is_zero() {
+                elements.push(constant);
+            }
+        }
+    }
+
+    elements
+}
+
+/// Numbers of full and partial rounds of Poseidon and Poseidon2 with sbox of
+/// degree `alpha` for `security_level` bits, as found by the round numbers
+/// script of the Poseidon authors: smallest number of sboxes satisfying the
+/// bounds of the Poseidon paper and of https://eprint.iacr.org/2023/537,
+/// plus a security margin of 2 full rounds and 7.5% of partial rounds.
+pub(crate) fn poseidon_number_of_rounds<E: Engine>(width: usize, alpha: u64, security_level: usize) -> (usize, usize) {
+    assert!(width > 1);
+    let log_p = modulus_log2::<E>();
+    let (t, m, a) = (width as f64, security_level as f64, alpha as f64);
+    let log_alpha_2 = 1.0 / a.log2();
+
+    let satisfied = |full_rounds: usize, partial_rounds: usize| {
+        let (rf, rp) = (full_rounds as f64, partial_rounds as f64);
+        let statistical = if m <= (log_p - (a - 1.0) / 2.0).floor() * (t + 1.0) { 6.0 } else { 10.0 };
+        let interpolation = 1.0 + (log_alpha_2 * m.min(log_p)).ceil() + (t.log2() / a.log2()).ceil() - rp;
+        let groebner_1 = log_alpha_2 * m.min(log_p) - rp;
+        let groebner_2 = t - 1.0 + log_alpha_2 * (m / (t + 1.0)).min(log_p / 2.0) - rp;
+        let groebner_3 = (t - 2.0 + m / (2.0 * a.log2()) - rp) / (t - 1.0);
+        let bound = [statistical, interpolation, groebner_1, groebner_2, groebner_3]
+            .iter()
+            .map(|b| b.ceil())
+            .fold(f64::MIN, f64::max);
+
+        let r = (t / 3.0).floor();
+        let over = (rf - 1.0) * t + rp + r + r * rf / 2.0 + rp + a;
+        let under = r * rf / 2.0 + rp + a;
+        let binomial_attack = (2.0 * log2_binomial(over as u64, under as u64)).ceil();
+
+        rf >= bound && binomial_attack >= m
+    };
+
+    // (number of sboxes, full rounds, partial rounds)
+    let mut best: Option<(usize, usize, usize)> = None;
+    for partial_rounds in 1..500 {
+        // more full rounds only cost more sboxes
+        let full_rounds = match (4..100).step_by(2).find(|full_rounds| satisfied(*full_rounds, partial_rounds)) {
+            Some(full_rounds) => full_rounds + 2,
+            None => continue,
+        };
+        let partial_rounds = (partial_rounds as f64 * 1.075).ceil() as usize;
+        let cost = full_rounds * width + partial_rounds;
+        if best.map_or(true, |(best_cost, best_full_rounds, _)| {
+            cost < best_cost || (cost == best_cost && full_rounds < best_full_rounds)
+        }) {
+            best = Some((cost, full_rounds, partial_rounds));
+        }
+    }
+    let (_, full_rounds, partial_rounds) = best.expect("round numbers for the security level");
+
+    (full_rounds, partial_rounds)
+}
+
+/// Number of rounds of Rescue, `2 * max(l, 5)` by the Rescue paper, where
+/// `l` is the bound of the Gröbner basis attack, see `groebner_basis_rounds`.
+pub(crate) fn rescue_number_of_rounds(width: usize, capacity: usize, security_level: usize, alpha: u64) -> usize {
+    2 * groebner_basis_rounds(width, capacity, security_level, alpha).max(5)
+}
+
+/// Number of rounds of Rescue Prime, `ceil(1.5 * max(l, 5))` by its
+/// specification, where `l` is the bound of the Gröbner basis attack, see
+/// `groebner_basis_rounds`.
+pub(crate) fn rescue_prime_number_of_rounds(width: usize, capacity: usize, security_level: usize, alpha: u64) -> usize {
+    (1.5 * groebner_basis_rounds(width, capacity, security_level, alpha).max(5) as f64).ceil() as usize
+}
+
+/// Number of rounds of Griffin, `ceil(1.2 * max(6, 1 + l))` by the Griffin
+/// paper, where `l` is the smallest number of rounds whose squared
+/// `binomial(l * (alpha + width) + 1, 1 + width * l)` reaches
+/// `2^security_level`.
+pub(crate) fn griffin_number_of_rounds(width: usize, alpha: u64, security_level: usize) -> usize {
+    use num_bigint::BigUint;
+
+    let target = BigUint::from(1u8) << security_level;
+    let groebner_basis_rounds = (1..)
+        .find(|l: &usize| {
+            let n = l * (alpha as usize + width) + 1;
+            let k = 1 + width * l;
+            let mut binomial = BigUint::from(1u8);
+            for i in 1..=k {
+                binomial = binomial * BigUint::from(n - k + i) / BigUint::from(i);
+            }
+
+            &binomial * &binomial >= target
+        })
+        .expect("unbounded range");
+
+    // ceil(1.2 * x) in integers
+    (12 * (groebner_basis_rounds + 1).max(6) + 9) / 10
+}
+
+/// Number of rounds of Anemoi with `columns` Flystels by its reference
+/// implementation: the smallest `r` whose squared
+/// `binomial(4 * columns * r + kappa, 2 * columns * r)` reaches
+/// `2^security_level`, plus 2 and a margin of `min(5, columns + 1)`, but no
+/// less than 8.
+pub(crate) fn anemoi_number_of_rounds(columns: usize, alpha: u64, security_level: usize) -> usize {
+    use num_bigint::BigUint;
+
+    let kappa = match alpha {
+        3 => 1,
+        5 => 2,
+        7 => 4,
+        9 => 7,
+        11 => 9,
+        _ => panic!("Anemoi is not specified for alpha {}", alpha),
+    };
+
+    let target = BigUint::from(1u8) << security_level;
+    let groebner_basis_rounds = (1..)
+        .find(|r: &usize| {
+            let n = 4 * columns * r + kappa;
+            let k = 2 * columns * r;
+            let mut binomial = BigUint::from(1u8);
+            for i in 1..=k {
+                binomial = binomial * BigUint::from(n - k + i) / BigUint::from(i);
+            }
+
+            &binomial * &binomial >= target
+        })
+        .expect("unbounded range");
+
+    (groebner_basis_rounds + 2 + (columns + 1).min(5)).max(8)
+}
+
+// Smallest `l` such that squared `binomial(v + d, v)` exceeds
+// `2^security_level`, with `d = floor((alpha - 1) * width * (l - 1) / 2) + 2`
+// and `v = width * (l - 1) + capacity`. Specification of Rescue Prime has rate
+// in place of capacity, default params have always been derived with the
+// latter so it is kept.
+fn groebner_basis_rounds(width: usize, capacity: usize, security_level: usize, alpha: u64) -> usize {
+    use num_bigint::BigUint;
+
+    let target = BigUint::from(1u8) << security_level;
+    (1..)
+        .find(|l| {
+            let d = ((alpha as usize - 1) * width * (l - 1)) / 2 + 2;
+            let v = width * (l - 1) + capacity;
+            let mut binomial = BigUint::from(1u8);
+            for i in 1..=v {
+                binomial = binomial * BigUint::from(d + i) / BigUint::from(i);
+            }
+
+            &binomial * &binomial > target
+        })
+        .expect("unbounded range")
+}
+
+fn log2_binomial(n: u64, k: u64) -> f64 {
+    (1..=k.min(n - k)).map(|i| ((n - k.min(n - k) + i) as f64 / i as f64).log2()).sum()
+}
+
+fn modulus_log2<E: Engine>() -> f64 {
+    let modulus = E::Fr::char();
+    let limbs = modulus.as_ref();
+    let top = limbs.iter().rposition(|limb| *limb != 0).expect("non-zero modulus");
+    let mut value = limbs[top] as f64;
+    if top > 0 {
+        value += limbs[top - 1] as f64 / 2f64.powi(64);
+    }
+
+    value.log2() + 64.0 * top as f64
+}
@@ -1,5 +1,5 @@
 use crate::traits::Sbox;
-use franklin_crypto::bellman::pairing::ff::Field;
+use franklin_crypto::bellman::pairing::ff::{BitIterator, Field};
 use franklin_crypto::bellman::pairing::Engine;
 
 // Substitution box is non-linear part of permutation function.
@@ -9,14 +9,14 @@ use franklin_crypto::bellman::pairing::Engine;
 #[inline]
 pub(crate) fn sbox<E: Engine>(power: &Sbox, state: &mut [E::Fr]) {
     match power {
-        Sbox::Alpha(alpha) => sbox_alpha::<E>(alpha, state),
-        Sbox::AlphaInverse(alpha_inv, _) => sbox_alpha_inv::<E>(alpha_inv, state),
+        Sbox::Alpha(alpha) => sbox_alpha(alpha, state),
+        Sbox::AlphaInverse(alpha_inv, _) => sbox_alpha_inv(alpha_inv, state),
         Sbox::AddChain(chain, _) => sbox_alpha_inv_via_add_chain::<E>(chain, state),
     }
 }
 
 #[inline]
-pub(crate) fn sbox_alpha<E: Engine>(alpha: &u64, state: &mut [E::Fr]) {
+pub(crate) fn sbox_alpha<F: Field>(alpha: &u64, state: &mut [F]) {
     match alpha {
         5 => {
             for el in state.iter_mut() {
@@ -41,19 +41,88 @@ pub(crate) fn sbox_alpha<E: Engine>(alpha: &u64, state: &mut [E::Fr]) {
     }
 }
 
+// Inverse power is not an inversion, so Montgomery's batch inversion doesn't
+// apply here. Instead all elements share the exponent and are raised in
+// lockstep: each squaring or multiplication is done for the whole state
+// before moving to the next bit, which keeps independent multiplications
+// next to each other and walks the exponent only once.
 #[inline]
-pub(crate) fn sbox_alpha_inv<E: Engine>(alpha_inv: &[u64], state: &mut [E::Fr]) {
-    for el in state.iter_mut() {
-        *el = el.pow(alpha_inv);
+pub(crate) fn sbox_alpha_inv<F: Field>(alpha_inv: &[u64], state: &mut [F]) {
+    let base: smallvec::SmallVec<[F; 8]> = state.iter().cloned().collect();
+    state.iter_mut().for_each(|el| *el = F::one());
+
+    let mut found_one = false;
+    for bit in BitIterator::new(alpha_inv) {
+        if found_one {
+            state.iter_mut().for_each(|el| el.square());
+        } else {
+            found_one = bit;
+        }
+        if bit {
+            for (el, b) in state.iter_mut().zip(base.iter()) {
+                el.mul_assign(b);
+            }
+        }
     }
 }
 
 #[cfg(all(not(feature = "rayon"), not(feature = "futures")))]
 #[inline]
 pub(crate) fn sbox_alpha_inv_via_add_chain<E: Engine>(chain: &[crate::traits::Step], state: &mut [E::Fr]) {
-    let mut scratch = smallvec::SmallVec::<[E::Fr; 512]>::new();
-    for el in state.iter_mut() {
-        *el = crate::add_chain_pow_smallvec(*el, chain, &mut scratch);
+    sbox_alpha_inv_via_add_chain_lockstep(chain, state)
+}
+
+// Same lockstep approach for addition chains: intermediate powers of all
+// elements are stored next to each other, `scratch[step * width + i]`.
+// Scratch space stays on the stack for state width 3 and chains of Bn256
+// params, which take a bit over 300 steps.
+#[inline]
+pub(crate) fn sbox_alpha_inv_via_add_chain_lockstep<F: Field>(chain: &[crate::traits::Step], state: &mut [F]) {
+    let width = state.len();
+    let mut scratch = smallvec::SmallVec::<[F; 1024]>::new();
+    scratch.extend_from_slice(state);
+
+    for step in chain.iter() {
+        let start = scratch.len();
+        match step {
+            crate::traits::Step::Double { index } => {
+                for i in 0..width {
+                    let mut el = scratch[index * width + i];
+                    el.square();
+                    scratch.push(el);
+                }
+            }
+            crate::traits::Step::Add { left, right } => {
+                for i in 0..width {
+                    let mut el = scratch[left * width + i];
+                    el.mul_assign(&scratch[right * width + i]);
+                    scratch.push(el);
+                }
+            }
+        }
+        debug_assert_eq!(scratch.len(), start + width);
+    }
+
+    state.copy_from_slice(&scratch[scratch.len() - width..]);
+}
+
+// Raises one element after another to the inverse power, as it was done
+// before lockstep paths. Kept as a reference for tests and benches.
+#[cfg(any(test, feature = "bench-internals"))]
+pub(crate) fn sbox_alpha_inv_per_element<E: Engine>(power: &Sbox, state: &mut [E::Fr]) {
+    match power {
+        Sbox::Alpha(alpha) => sbox_alpha(alpha, state),
+        Sbox::AlphaInverse(alpha_inv, _) => {
+            for el in state.iter_mut() {
+                *el = el.pow(alpha_inv);
+            }
+        }
+        Sbox::AddChain(chain, _) => {
+            let mut scratch = smallvec::SmallVec::<[E::Fr; 512]>::new();
+            for el in state.iter_mut() {
+                *el = crate::add_chain_pow_smallvec(*el, chain, &mut scratch);
+            }
+        }
     }
 }
 
@@ -76,7 +145,7 @@ lazy_static::lazy_static!{
 #[cfg(feature = "futures")]
 #[inline]
 pub(crate) fn sbox_alpha_inv_via_add_chain<E: Engine>(chain: &[crate::traits::Step], state: &mut [E::Fr]) {
-    let chain = unsafe {std::mem::transmute(chain)};
+    let chain = unsafe {core::mem::transmute(chain)};
     use futures::task::SpawnExt;
     let f0 = EXECUTOR.spawn_with_handle(sbox_alpha_inv_via_add_chain_fut::<E>(state[0], chain)).unwrap();
     let f1 = EXECUTOR.spawn_with_handle(sbox_alpha_inv_via_add_chain_fut::<E>(state[1], chain)).unwrap();
@@ -91,4 +160,37 @@ pub(crate) fn sbox_alpha_inv_via_add_chain<E: Engine>(chain: &[crate::traits::St
 pub(crate) async fn sbox_alpha_inv_via_add_chain_fut<E: Engine>(el: E::Fr, chain: &'static [crate::traits::Step]) -> E::Fr {
     let mut scratch = smallvec::SmallVec::<[E::Fr; 512]>::new();
     crate::add_chain_pow_smallvec(el, chain, &mut scratch)
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::init_rng;
+    use crate::traits::HashParams;
+    use crate::RescueParams;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_batched_inverse_sbox() {
+        let rng = &mut init_rng();
+        let state = [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)];
+
+        let params = RescueParams::<Bn256, 2, 3>::default();
+        let expected = match params.alpha_inv() {
+            Sbox::AlphaInverse(alpha_inv, _) => state.map(|el| el.pow(alpha_inv)),
+            _ => unreachable!("default params use plain exponent"),
+        };
+        let mut actual = state;
+        sbox::<Bn256>(params.alpha_inv(), &mut actual);
+        assert_eq!(actual, expected);
+
+        let params = RescueParams::<Bn256, 2, 3>::specialized_for_num_rounds(8, 120);
+        let mut actual = state;
+        sbox::<Bn256>(params.alpha_inv(), &mut actual);
+        assert_eq!(actual, expected);
+
+        let mut per_element = state;
+        sbox_alpha_inv_per_element::<Bn256>(params.alpha_inv(), &mut per_element);
+        assert_eq!(per_element, expected);
+    }
+}
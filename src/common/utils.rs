@@ -1,6 +1,8 @@
 use franklin_crypto::bellman::pairing::ff::{Field, PrimeField};
 use franklin_crypto::bellman::Engine;
 use rand::Rng;
+use alloc::{vec, vec::Vec};
+use derivative::*;
 extern crate num_bigint;
 extern crate num_integer;
 extern crate num_traits;
@@ -8,10 +10,11 @@ extern crate num_traits;
 use self::num_bigint::{BigInt, BigUint};
 use self::num_integer::{ExtendedGcd, Integer};
 use self::num_traits::{One, ToPrimitive, Zero};
-use std::convert::TryInto;
+use core::convert::TryInto;
 
-// Batch inverses vector of elements required for MDS matrix.
-pub(crate) fn batch_inversion<E: Engine>(v: &mut [E::Fr]) {
+/// Inverses all elements in place using a single field inversion.
+/// Zero elements have no inverse and are left untouched.
+pub fn batch_inversion<E: Engine>(v: &mut [E::Fr]) {
     // Montgomery’s Trick and Fast Implementation of Masked AES
     // Genelle, Prouff and Quisquater
     // Section 3.2
@@ -50,21 +53,122 @@ pub(crate) fn batch_inversion<E: Engine>(v: &mut [E::Fr]) {
     }
 }
 
+/// Compares digests in time which depends only on `N`. Canonical
+/// representations are compared limb by limb and differences are
+/// accumulated without early exit, so verifiers don't leak the position of
+/// the first mismatching limb of a secret digest.
+pub fn ct_eq_digest<E: Engine, const N: usize>(a: &[E::Fr; N], b: &[E::Fr; N]) -> bool {
+    let mut diff = 0u64;
+    for (a, b) in a.iter().zip(b.iter()) {
+        let (a, b) = (a.into_repr(), b.into_repr());
+        for (a_limb, b_limb) in a.as_ref().iter().zip(b.as_ref().iter()) {
+            diff |= a_limb ^ b_limb;
+        }
+    }
+
+    // highest bit of `diff | -diff` is set iff `diff` is non zero
+    ((diff | diff.wrapping_neg()) >> 63) == 0
+}
+
+/// Returned when a vector or slice is converted into an array of another length.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct LengthError {
+    pub expected: usize,
+    pub actual: usize,
+}
+
+impl core::fmt::Display for LengthError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "expected {} elements, got {}", self.expected, self.actual)
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for LengthError {}
+
+/// Converts a vector into an array of exactly `N` elements.
+pub fn try_into_fixed<T, const N: usize>(values: Vec<T>) -> Result<[T; N], LengthError> {
+    let actual = values.len();
+    values.try_into().map_err(|_| LengthError { expected: N, actual })
+}
+
+/// Same as `try_into_fixed` for slices, elements are cloned.
+pub fn try_slice_into_fixed<T: Clone, const N: usize>(values: &[T]) -> Result<[T; N], LengthError> {
+    if values.len() != N {
+        return Err(LengthError { expected: N, actual: values.len() });
+    }
+
+    Ok(core::array::from_fn(|i| values[i].clone()))
+}
+
+/// Field element with `value` as its canonical representation.
+pub(crate) fn fe_from_u64<E: Engine>(value: u64) -> E::Fr {
+    E::Fr::from_repr(<E::Fr as PrimeField>::Repr::from(value)).expect("a field element")
+}
+
 // Computes scalar product of two same length vector.
+// Even and odd products are accumulated independently so that consecutive
+// multiplications don't wait for the previous addition.
 pub(crate) fn scalar_product<E: Engine>(a: &[E::Fr], b: &[E::Fr]) -> E::Fr {
+    debug_assert_eq!(a.len(), b.len());
+    let mut acc0 = E::Fr::zero();
+    let mut acc1 = E::Fr::zero();
+    let mut a_pairs = a.chunks_exact(2);
+    let mut b_pairs = b.chunks_exact(2);
+    for (a, b) in (&mut a_pairs).zip(&mut b_pairs) {
+        let mut tmp0 = a[0];
+        tmp0.mul_assign(&b[0]);
+        let mut tmp1 = a[1];
+        tmp1.mul_assign(&b[1]);
+        acc0.add_assign(&tmp0);
+        acc1.add_assign(&tmp1);
+    }
+    for (a, b) in a_pairs.remainder().iter().zip(b_pairs.remainder().iter()) {
+        let mut tmp = *a;
+        tmp.mul_assign(b);
+        acc0.add_assign(&tmp);
+    }
+    acc0.add_assign(&acc1);
+
+    acc0
+}
+
+// Scalar product with a single accumulator, as it was done before
+// `scalar_product`. Kept as a reference for tests and benches.
+#[cfg(any(test, feature = "bench-internals"))]
+pub(crate) fn scalar_product_single_accumulator<E: Engine>(a: &[E::Fr], b: &[E::Fr]) -> E::Fr {
     let mut acc = E::Fr::zero();
     for (a, b) in a.iter().zip(b.iter()) {
-        let mut tmp = a.clone();
-        tmp.mul_assign(&b);
+        let mut tmp = *a;
+        tmp.mul_assign(b);
         acc.add_assign(&tmp);
     }
+
     acc
 }
 
+/// Cauchy matrix `M[i][j] = 1 / (x[i] - y[j])` together with the vectors it
+/// is generated from.
+#[derive(Derivative)]
+#[derivative(Clone, Debug, PartialEq(bound = ""), Eq(bound = ""))]
+pub struct MdsArtifacts<E: Engine, const S: usize> {
+    pub x: [E::Fr; S],
+    pub y: [E::Fr; S],
+    pub matrix: [[E::Fr; S]; S],
+}
+
 // Construct MDS matrix which required by lineary layer of permutation function.
-pub(crate) fn construct_mds_matrix<E: Engine, R: Rng, const S: usize>(
+pub fn construct_mds_matrix<E: Engine, R: Rng, const S: usize>(
     rng: &mut R,
 ) -> [[E::Fr; S]; S] {
+    construct_mds_matrix_with_artifacts::<E, R, S>(rng).matrix
+}
+
+/// Same as `construct_mds_matrix` but also returns `x` and `y` vectors of
+/// the Cauchy matrix.
+pub fn construct_mds_matrix_with_artifacts<E: Engine, R: Rng, const S: usize>(
+    rng: &mut R,
+) -> MdsArtifacts<E, S> {
     let width = S;
 
     loop {
@@ -129,7 +233,7 @@ pub(crate) fn construct_mds_matrix<E: Engine, R: Rng, const S: usize>(
 
         // by previous checks we can be sure in uniqueness and perform subtractions easily
         let mut mds_matrix = vec![E::Fr::zero(); width * width];
-        for (i, x) in x.into_iter().enumerate() {
+        for (i, x) in x.iter().cloned().enumerate() {
             for (j, y) in y.iter().enumerate() {
                 let place_into = i * (width) + j;
                 let mut element = x;
@@ -146,9 +250,13 @@ pub(crate) fn construct_mds_matrix<E: Engine, R: Rng, const S: usize>(
         mds_matrix
             .chunks_exact(S)
             .zip(result.iter_mut())
-            .for_each(|(values, row)| *row = values.try_into().expect("row in const"));
+            .for_each(|(values, row)| *row = try_slice_into_fixed(values).expect("row in const"));
 
-        return result;
+        return MdsArtifacts {
+            x: try_into_fixed(x).expect("x in const"),
+            y: try_into_fixed(y).expect("y in const"),
+            matrix: result,
+        };
     }
 }
 
@@ -156,7 +264,7 @@ pub(crate) fn compute_gcd<E: Engine, const N: usize>(n: u64) -> Option<[u64; N]>
     let y = compute_gcd_vec::<E>(n);
 
     match y {
-        Some(value) => return Some(value.try_into().unwrap()),
+        Some(value) => return Some(try_into_fixed(value).expect("gcd limbs")),
         _ => return None,
     }
 }
@@ -177,7 +285,9 @@ pub(crate) fn compute_gcd_biguint<E: Engine>(n: u64) -> Option<BigUint> {
     let p_minus_one_signed = BigInt::from(p_minus_one_biguint);
 
     let ExtendedGcd { gcd, x: _, mut y, .. } = p_minus_one_signed.extended_gcd(&alpha_signed);
-    assert!(gcd.is_one());
+    if !gcd.is_one() {
+        return None;
+    }
     if y < BigInt::zero() {
         y += p_minus_one_signed;
         
@@ -186,6 +296,12 @@ pub(crate) fn compute_gcd_biguint<E: Engine>(n: u64) -> Option<BigUint> {
     y.to_biguint()
 }
 
+// x^alpha is a permutation of the field iff alpha is invertible modulo p-1.
+// Default params use alpha = 5 which holds for both BN254 and BLS12-381.
+pub(crate) fn alpha_is_permutation<E: Engine>(alpha: u64) -> bool {
+    compute_gcd_biguint::<E>(alpha).is_some()
+}
+
 pub(crate) fn compute_gcd_vec<E: Engine>(n: u64) -> Option<Vec<u64>> {
     let y = compute_gcd_biguint::<E>(n);
 
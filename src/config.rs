@@ -0,0 +1,325 @@
+//! Hash settings which can be read from service configuration files.
+//!
+//! ```toml
+//! family = "poseidon2"
+//! width = 3
+//! rate = 2
+//! custom_gate = "QuinticWidth4"
+//! domain = "CustomFixedLength"
+//! ```
+use crate::common::domain_strategy::DomainStrategy;
+use crate::griffin::GriffinParams;
+use crate::poseidon2::Poseidon2Params;
+use crate::traits::{CustomGate, HashFamily, HashParams};
+use crate::{GenericSponge, PoseidonParams, RescueParams, RescuePrimeParams};
+use franklin_crypto::bellman::Engine;
+use serde::{Deserialize, Serialize};
+
+/// Rate of the only instantiation available at runtime.
+pub const CONFIG_RATE: usize = 2;
+/// Width of the only instantiation available at runtime.
+pub const CONFIG_WIDTH: usize = 3;
+
+impl HashFamily {
+    /// Name used by `Display` and accepted by `FromStr`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashFamily::Rescue => "rescue",
+            HashFamily::Poseidon => "poseidon",
+            HashFamily::RescuePrime => "rescue_prime",
+            HashFamily::Poseidon2 => "poseidon2",
+            HashFamily::Griffin => "griffin",
+            HashFamily::Monolith => "monolith",
+            HashFamily::Anemoi => "anemoi",
+        }
+    }
+}
+
+impl std::fmt::Display for HashFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Parses family name, case and separators (`-`, `_`) are ignored so both
+/// `rescue_prime` and `RescuePrime` are accepted.
+impl std::str::FromStr for HashFamily {
+    type Err = HashConfigError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized: String = s
+            .trim()
+            .chars()
+            .filter(|c| *c != '_' && *c != '-')
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+        match normalized.as_str() {
+            "rescue" => Ok(HashFamily::Rescue),
+            "poseidon" => Ok(HashFamily::Poseidon),
+            "rescueprime" => Ok(HashFamily::RescuePrime),
+            "poseidon2" => Ok(HashFamily::Poseidon2),
+            "griffin" => Ok(HashFamily::Griffin),
+            "monolith" => Ok(HashFamily::Monolith),
+            "anemoi" => Ok(HashFamily::Anemoi),
+            _ => Err(HashConfigError::UnknownFamily(s.to_string())),
+        }
+    }
+}
+
+/// Errors of parsing and instantiating a `HashConfig`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum HashConfigError {
+    /// Family name doesn't match any known hash family.
+    UnknownFamily(String),
+    /// Params are only available for `CONFIG_RATE` and `CONFIG_WIDTH`.
+    UnsupportedShape { rate: usize, width: usize },
+    /// Only fixed length domain strategies can be used for hashing.
+    UnsupportedDomain,
+    /// Family is only defined over small fields and has no big field params.
+    SmallFieldFamily(HashFamily),
+}
+
+impl std::fmt::Display for HashConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownFamily(name) => write!(f, "unknown hash family {:?}", name),
+            Self::UnsupportedShape { rate, width } => write!(
+                f,
+                "rate={} width={} is not supported, only rate={} width={} is available",
+                rate, width, CONFIG_RATE, CONFIG_WIDTH
+            ),
+            Self::UnsupportedDomain => write!(f, "only fixed length domain strategies are allowed"),
+            Self::SmallFieldFamily(family) => write!(f, "{} is only defined over small fields", family),
+        }
+    }
+}
+
+impl std::error::Error for HashConfigError {}
+
+/// Hash settings. Family is (de)serialized by its name, so `"poseidon2"` and
+/// `"Poseidon2"` are both accepted. Missing `custom_gate` keeps the default
+/// gate of the family and missing `domain` keeps the default strategy of
+/// the sponge.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct HashConfig {
+    #[serde(with = "family_by_name")]
+    pub family: HashFamily,
+    pub width: usize,
+    pub rate: usize,
+    #[serde(default)]
+    pub custom_gate: Option<CustomGate>,
+    #[serde(default)]
+    pub domain: Option<DomainStrategy>,
+}
+
+impl HashConfig {
+    pub fn new(family: HashFamily) -> Self {
+        Self {
+            family,
+            width: CONFIG_WIDTH,
+            rate: CONFIG_RATE,
+            custom_gate: None,
+            domain: None,
+        }
+    }
+
+    /// Builds params described by the config.
+    pub fn instantiate<E: Engine>(&self) -> Result<ConfiguredHash<E>, HashConfigError> {
+        if self.rate != CONFIG_RATE || self.width != CONFIG_WIDTH {
+            return Err(HashConfigError::UnsupportedShape {
+                rate: self.rate,
+                width: self.width,
+            });
+        }
+        match self.domain {
+            None | Some(DomainStrategy::FixedLength) | Some(DomainStrategy::CustomFixedLength) => (),
+            Some(_) => return Err(HashConfigError::UnsupportedDomain),
+        }
+
+        let mut params = match self.family {
+            HashFamily::Rescue => ConfiguredParams::Rescue(RescueParams::default()),
+            HashFamily::Poseidon => ConfiguredParams::Poseidon(PoseidonParams::default()),
+            HashFamily::RescuePrime => ConfiguredParams::RescuePrime(RescuePrimeParams::default()),
+            HashFamily::Poseidon2 => ConfiguredParams::Poseidon2(Poseidon2Params::default()),
+            HashFamily::Griffin => ConfiguredParams::Griffin(GriffinParams::default()),
+            HashFamily::Monolith => return Err(HashConfigError::SmallFieldFamily(self.family)),
+            // Anemoi needs an even width
+            HashFamily::Anemoi => {
+                return Err(HashConfigError::UnsupportedShape {
+                    rate: self.rate,
+                    width: self.width,
+                })
+            }
+        };
+        if let Some(custom_gate) = self.custom_gate {
+            params.use_custom_gate(custom_gate);
+        }
+
+        Ok(ConfiguredHash {
+            params,
+            domain: self.domain.clone(),
+        })
+    }
+}
+
+/// Params of any family at `CONFIG_RATE` and `CONFIG_WIDTH`.
+#[derive(Clone, Debug)]
+pub enum ConfiguredParams<E: Engine> {
+    Rescue(RescueParams<E, CONFIG_RATE, CONFIG_WIDTH>),
+    Poseidon(PoseidonParams<E, CONFIG_RATE, CONFIG_WIDTH>),
+    RescuePrime(RescuePrimeParams<E, CONFIG_RATE, CONFIG_WIDTH>),
+    Poseidon2(Poseidon2Params<E, CONFIG_RATE, CONFIG_WIDTH>),
+    Griffin(GriffinParams<E, CONFIG_RATE, CONFIG_WIDTH>),
+}
+
+impl<E: Engine> ConfiguredParams<E> {
+    pub fn hash_family(&self) -> HashFamily {
+        match self {
+            Self::Rescue(params) => params.hash_family(),
+            Self::Poseidon(params) => params.hash_family(),
+            Self::RescuePrime(params) => params.hash_family(),
+            Self::Poseidon2(params) => params.hash_family(),
+            Self::Griffin(params) => params.hash_family(),
+        }
+    }
+
+    pub fn custom_gate(&self) -> CustomGate {
+        match self {
+            Self::Rescue(params) => params.custom_gate(),
+            Self::Poseidon(params) => params.custom_gate(),
+            Self::RescuePrime(params) => params.custom_gate(),
+            Self::Poseidon2(params) => params.custom_gate(),
+            Self::Griffin(params) => params.custom_gate(),
+        }
+    }
+
+    pub fn use_custom_gate(&mut self, custom_gate: CustomGate) {
+        match self {
+            Self::Rescue(params) => params.use_custom_gate(custom_gate),
+            Self::Poseidon(params) => params.use_custom_gate(custom_gate),
+            Self::RescuePrime(params) => params.use_custom_gate(custom_gate),
+            Self::Poseidon2(params) => params.use_custom_gate(custom_gate),
+            Self::Griffin(params) => params.use_custom_gate(custom_gate),
+        }
+    }
+}
+
+/// Params together with the domain strategy from the config.
+#[derive(Clone)]
+pub struct ConfiguredHash<E: Engine> {
+    params: ConfiguredParams<E>,
+    domain: Option<DomainStrategy>,
+}
+
+impl<E: Engine> ConfiguredHash<E> {
+    pub fn params(&self) -> &ConfiguredParams<E> {
+        &self.params
+    }
+
+    pub fn domain(&self) -> Option<&DomainStrategy> {
+        self.domain.as_ref()
+    }
+
+    /// Fixed length hash of the input with configured params and domain strategy.
+    pub fn hash(&self, input: &[E::Fr]) -> [E::Fr; CONFIG_RATE] {
+        let domain = self.domain.clone();
+        match &self.params {
+            ConfiguredParams::Rescue(params) => GenericSponge::hash(input, params, domain),
+            ConfiguredParams::Poseidon(params) => GenericSponge::hash(input, params, domain),
+            ConfiguredParams::RescuePrime(params) => GenericSponge::hash(input, params, domain),
+            ConfiguredParams::Poseidon2(params) => GenericSponge::hash(input, params, domain),
+            ConfiguredParams::Griffin(params) => GenericSponge::hash(input, params, domain),
+        }
+    }
+}
+
+mod family_by_name {
+    use crate::traits::HashFamily;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(family: &HashFamily, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(family.name())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<HashFamily, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        name.parse().map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::init_rng;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_hash_family_names() {
+        for family in [
+            HashFamily::Rescue,
+            HashFamily::Poseidon,
+            HashFamily::RescuePrime,
+            HashFamily::Poseidon2,
+            HashFamily::Griffin,
+            HashFamily::Monolith,
+            HashFamily::Anemoi,
+        ] {
+            let name = family.to_string();
+            assert_eq!(name.parse::<HashFamily>().unwrap(), family);
+        }
+        assert_eq!("RescuePrime".parse::<HashFamily>().unwrap(), HashFamily::RescuePrime);
+        assert_eq!("rescue-prime".parse::<HashFamily>().unwrap(), HashFamily::RescuePrime);
+        assert!("sha256".parse::<HashFamily>().is_err());
+    }
+
+    #[test]
+    fn test_hash_config_from_json() {
+        let rng = &mut init_rng();
+        let input = [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)];
+
+        let config: HashConfig = serde_json::from_str(
+            r#"{"family": "poseidon2", "width": 3, "rate": 2, "domain": "FixedLength"}"#,
+        )
+        .unwrap();
+        let hasher = config.instantiate::<Bn256>().unwrap();
+        assert_eq!(hasher.params().hash_family(), HashFamily::Poseidon2);
+
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        let expected = GenericSponge::hash(&input, &params, Some(DomainStrategy::FixedLength));
+        assert_eq!(hasher.hash(&input), expected);
+
+        // serialized config is read back as is
+        let encoded = serde_json::to_string(&config).unwrap();
+        let decoded: HashConfig = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decoded.family, config.family);
+        assert_eq!(decoded.instantiate::<Bn256>().unwrap().hash(&input), expected);
+    }
+
+    #[test]
+    fn test_hash_config_rejects_unsupported_settings() {
+        let mut config = HashConfig::new(HashFamily::Rescue);
+        config.width = 4;
+        assert_eq!(
+            config.instantiate::<Bn256>().err(),
+            Some(HashConfigError::UnsupportedShape { rate: 2, width: 4 })
+        );
+
+        let mut config = HashConfig::new(HashFamily::Poseidon);
+        config.domain = Some(DomainStrategy::VariableLength);
+        assert_eq!(config.instantiate::<Bn256>().err(), Some(HashConfigError::UnsupportedDomain));
+
+        let config = HashConfig::new(HashFamily::Monolith);
+        assert_eq!(
+            config.instantiate::<Bn256>().err(),
+            Some(HashConfigError::SmallFieldFamily(HashFamily::Monolith))
+        );
+
+        let config = HashConfig::new(HashFamily::Anemoi);
+        assert_eq!(
+            config.instantiate::<Bn256>().err(),
+            Some(HashConfigError::UnsupportedShape { rate: 2, width: 3 })
+        );
+    }
+}
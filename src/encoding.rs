@@ -0,0 +1,423 @@
+//! Hashing of byte strings, e.g. transaction payloads.
+//!
+//! Bytes are packed into field elements `E::Fr::CAPACITY / 8` at a time, 31
+//! for Bn256, each chunk read as a little endian integer. Input is padded by
+//! a single `0x01` byte followed by zeros up to a multiple of the chunk size,
+//! so the packing is injective and `n` bytes always give `n / 31 + 1`
+//! elements. Packed elements are hashed by the fixed length sponge, so
+//! digests differ from the ones of `poseidon2_hash_bytes_fast`, which absorbs
+//! bytes straight into the Poseidon2 state.
+//!
+//! `RescuePoseidonDigest` hashes a stream of bytes with the same packing.
+//! Length of the stream isn't known upfront, so it uses the variable length
+//! sponge and its digests differ from the ones of `hash_bytes`. Packed
+//! elements are followed by zero elements up to a multiple of the rate
+//! instead of the padding of the sponge: the last packed element is never
+//! zero as it holds the marker byte, so trailing zeros can't be confused with
+//! input, while padding by ones could be, e.g. with a chunk packed into one. With the
+//! `digest` feature it implements traits of the `digest` crate.
+use crate::circuit::sponge::CircuitGenericSponge;
+use crate::sponge::GenericSponge;
+use crate::traits::HashParams;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Engine, Field, PrimeField, PrimeFieldRepr, SynthesisError};
+use franklin_crypto::plonk::circuit::allocated_num::Num;
+use franklin_crypto::plonk::circuit::byte::Byte;
+use franklin_crypto::plonk::circuit::linear_combination::LinearCombination;
+
+/// Byte appended to the input before zero padding.
+pub const BYTES_PADDING_MARKER: u8 = 0x01;
+
+/// Number of bytes packed into a single field element.
+pub fn bytes_per_element<E: Engine>() -> usize {
+    let bytes_per_element = E::Fr::CAPACITY as usize / 8;
+    assert!(bytes_per_element > 0, "field is too small to pack bytes");
+
+    bytes_per_element
+}
+
+/// Packs padded bytes into field elements, see module docs.
+pub fn bytes_to_field_elements<E: Engine>(input: &[u8]) -> Vec<E::Fr> {
+    let mut padded = input.to_vec();
+    padded.push(BYTES_PADDING_MARKER);
+    let chunk_len = bytes_per_element::<E>();
+    padded.resize((padded.len() + chunk_len - 1) / chunk_len * chunk_len, 0);
+
+    padded.chunks_exact(chunk_len).map(pack_chunk::<E>).collect()
+}
+
+/// Hashes bytes packed by `bytes_to_field_elements`.
+pub fn hash_bytes<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    input: &[u8],
+) -> [E::Fr; RATE] {
+    GenericSponge::<E, RATE, WIDTH>::hash(&bytes_to_field_elements::<E>(input), params, None)
+}
+
+/// Size of a digest of `RescuePoseidonDigest` in bytes.
+pub const DIGEST_BYTES: usize = 32;
+
+/// Incremental hasher of byte streams, see module docs. Digest is the first
+/// element squeezed after the padded last chunk is absorbed.
+#[derive(Clone)]
+pub struct RescuePoseidonDigest<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize> {
+    params: P,
+    sponge: GenericSponge<E, RATE, WIDTH>,
+    // incomplete chunk, always shorter than `bytes_per_element`
+    buffer: Vec<u8>,
+    // number of absorbed elements
+    absorbed: usize,
+}
+
+impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>
+    RescuePoseidonDigest<E, P, RATE, WIDTH>
+{
+    pub fn new(params: P) -> Self {
+        Self {
+            params,
+            sponge: GenericSponge::new(),
+            buffer: Vec::with_capacity(bytes_per_element::<E>()),
+            absorbed: 0,
+        }
+    }
+
+    /// Absorbs each complete chunk as soon as it is filled.
+    pub fn update(&mut self, mut input: &[u8]) {
+        let chunk_len = bytes_per_element::<E>();
+        while !input.is_empty() {
+            let taken = (chunk_len - self.buffer.len()).min(input.len());
+            self.buffer.extend_from_slice(&input[..taken]);
+            input = &input[taken..];
+
+            if self.buffer.len() == chunk_len {
+                self.sponge.absorb(pack_chunk::<E>(&self.buffer), &self.params);
+                self.absorbed += 1;
+                self.buffer.clear();
+            }
+        }
+    }
+
+    pub fn finalize(mut self) -> E::Fr {
+        let mut last_chunk = core::mem::take(&mut self.buffer);
+        last_chunk.push(BYTES_PADDING_MARKER);
+        self.sponge.absorb(pack_chunk::<E>(&last_chunk), &self.params);
+        self.absorbed += 1;
+        while self.absorbed % RATE != 0 {
+            self.sponge.absorb(E::Fr::zero(), &self.params);
+            self.absorbed += 1;
+        }
+
+        self.sponge.squeeze(&self.params).expect("a squeezed elem")
+    }
+
+    /// Digest as little endian bytes of its canonical representation.
+    pub fn finalize_bytes(self) -> [u8; DIGEST_BYTES] {
+        let mut bytes = Vec::with_capacity(DIGEST_BYTES);
+        self.finalize().into_repr().write_le(&mut bytes).expect("write into vec");
+
+        bytes.try_into().expect("representation of the field has 32 bytes")
+    }
+
+    /// Forgets everything absorbed so far, params are kept.
+    pub fn reset(&mut self) {
+        self.sponge = GenericSponge::new();
+        self.buffer.clear();
+        self.absorbed = 0;
+    }
+}
+
+impl<E: Engine, P: HashParams<E, RATE, WIDTH> + Default, const RATE: usize, const WIDTH: usize> Default
+    for RescuePoseidonDigest<E, P, RATE, WIDTH>
+{
+    fn default() -> Self {
+        Self::new(P::default())
+    }
+}
+
+#[cfg(feature = "digest")]
+mod digest_impls {
+    use super::*;
+    use digest::consts::U32;
+    use digest::{FixedOutput, HashMarker, Output, OutputSizeUser, Reset, Update};
+
+    impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize> OutputSizeUser
+        for RescuePoseidonDigest<E, P, RATE, WIDTH>
+    {
+        type OutputSize = U32;
+    }
+
+    impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize> Update
+        for RescuePoseidonDigest<E, P, RATE, WIDTH>
+    {
+        fn update(&mut self, data: &[u8]) {
+            RescuePoseidonDigest::update(self, data)
+        }
+    }
+
+    impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize> FixedOutput
+        for RescuePoseidonDigest<E, P, RATE, WIDTH>
+    {
+        fn finalize_into(self, out: &mut Output<Self>) {
+            out.copy_from_slice(&self.finalize_bytes());
+        }
+    }
+
+    impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize> Reset
+        for RescuePoseidonDigest<E, P, RATE, WIDTH>
+    {
+        fn reset(&mut self) {
+            RescuePoseidonDigest::reset(self)
+        }
+    }
+
+    impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize> HashMarker
+        for RescuePoseidonDigest<E, P, RATE, WIDTH>
+    {
+    }
+}
+
+/// Circuit counterpart of `bytes_to_field_elements`. Bytes are range checked
+/// on allocation, so packing needs no constraints besides linear combinations.
+pub fn circuit_bytes_to_field_elements<E: Engine, CS: ConstraintSystem<E>>(
+    cs: &mut CS,
+    input: &[Byte<E>],
+) -> Result<Vec<Num<E>>, SynthesisError> {
+    let mut padded: Vec<_> = input.iter().map(|byte| byte.inner).collect();
+    padded.push(Num::Constant(fe_from_u8::<E>(BYTES_PADDING_MARKER)));
+    let chunk_len = bytes_per_element::<E>();
+    padded.resize(
+        (padded.len() + chunk_len - 1) / chunk_len * chunk_len,
+        Num::Constant(E::Fr::zero()),
+    );
+
+    let mut shift = E::Fr::one();
+    for _ in 0..8 {
+        shift.double();
+    }
+
+    padded
+        .chunks_exact(chunk_len)
+        .map(|chunk| {
+            if chunk.iter().all(|byte| byte.is_constant()) {
+                let bytes: Vec<u8> = chunk
+                    .iter()
+                    .map(|byte| byte.get_value().expect("constant has a value").into_repr().as_ref()[0] as u8)
+                    .collect();
+                return Ok(Num::Constant(pack_chunk::<E>(&bytes)));
+            }
+
+            let mut lc = LinearCombination::zero();
+            let mut coeff = E::Fr::one();
+            for byte in chunk.iter() {
+                lc.add_assign_number_with_coeff(byte, coeff);
+                coeff.mul_assign(&shift);
+            }
+            lc.into_num(cs)
+        })
+        .collect()
+}
+
+/// Circuit counterpart of `hash_bytes`.
+pub fn circuit_hash_bytes<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &P,
+    input: &[Byte<E>],
+) -> Result<[Num<E>; RATE], SynthesisError> {
+    let input = circuit_bytes_to_field_elements(cs, input)?;
+
+    CircuitGenericSponge::<E, RATE, WIDTH>::hash_num(cs, &input, params, None)
+}
+
+/// Same as `circuit_hash_bytes` for bytes given as plain numbers, each of
+/// them is range checked to 8 bits first.
+pub fn circuit_hash_byte_limbs<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &P,
+    input: &[Num<E>],
+) -> Result<[Num<E>; RATE], SynthesisError> {
+    let mut bytes = Vec::with_capacity(input.len());
+    for limb in input.iter() {
+        match limb {
+            Num::Constant(constant) => {
+                let repr = constant.into_repr();
+                assert!(
+                    repr.as_ref()[1..].iter().all(|limb| *limb == 0) && repr.as_ref()[0] < 256,
+                    "constant limb should be a byte"
+                );
+            }
+            Num::Variable(limb) => {
+                let _ = limb.into_bits_le(cs, Some(8))?;
+            }
+        }
+        bytes.push(Byte { inner: *limb });
+    }
+
+    circuit_hash_bytes(cs, params, &bytes)
+}
+
+fn pack_chunk<E: Engine>(chunk: &[u8]) -> E::Fr {
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    for (limb, bytes) in repr.as_mut().iter_mut().zip(chunk.chunks(8)) {
+        let mut le_bytes = [0u8; 8];
+        le_bytes[..bytes.len()].copy_from_slice(bytes);
+        *limb = u64::from_le_bytes(le_bytes);
+    }
+
+    E::Fr::from_repr(repr).expect("chunk is shorter than capacity")
+}
+
+fn fe_from_u8<E: Engine>(value: u8) -> E::Fr {
+    E::Fr::from_repr(<E::Fr as PrimeField>::Repr::from(value as u64)).expect("a field element")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::init_cs;
+    use crate::{generic_hash, RescueParams};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+
+    #[test]
+    fn test_bytes_to_field_elements() {
+        assert_eq!(bytes_per_element::<Bn256>(), 31);
+
+        // padding keeps inputs differing by trailing zeros apart
+        assert_eq!(bytes_to_field_elements::<Bn256>(&[]), vec![fe_from_u8::<Bn256>(1)]);
+        assert_ne!(bytes_to_field_elements::<Bn256>(&[0]), bytes_to_field_elements::<Bn256>(&[0, 0]));
+
+        let mut expected = fe_from_u8::<Bn256>(1);
+        for _ in 0..8 {
+            expected.double();
+        }
+        expected.add_assign(&fe_from_u8::<Bn256>(7));
+        assert_eq!(bytes_to_field_elements::<Bn256>(&[7]), vec![expected]);
+
+        // full chunk is followed by a chunk of padding
+        assert_eq!(bytes_to_field_elements::<Bn256>(&[0xff; 30]).len(), 1);
+        assert_eq!(bytes_to_field_elements::<Bn256>(&[0xff; 31]).len(), 2);
+        assert_eq!(bytes_to_field_elements::<Bn256>(&[0xff; 62])[2], fe_from_u8::<Bn256>(1));
+
+        let params = RescueParams::<Bn256, 2, 3>::default();
+        let input: Vec<u8> = (0..40).collect();
+        let elements: [Fr; 2] = bytes_to_field_elements::<Bn256>(&input).try_into().unwrap();
+        assert_eq!(hash_bytes(&params, &input), generic_hash(&params, &elements, None));
+    }
+
+    #[test]
+    fn test_streaming_digest() {
+        let params = RescueParams::<Bn256, 2, 3>::default();
+        let input: Vec<u8> = (0..100u8).collect();
+
+        for len in [0, 1, 30, 31, 32, 62, 100] {
+            let input = &input[..len];
+            // packed elements are followed by zero elements up to a multiple of the rate
+            let mut elements = bytes_to_field_elements::<Bn256>(input);
+            elements.resize((elements.len() + 1) / 2 * 2, Fr::zero());
+            let mut sponge = GenericSponge::<Bn256, 2, 3>::new();
+            elements.iter().for_each(|el| sponge.absorb(*el, &params));
+            let expected = sponge.squeeze(&params).unwrap();
+
+            let mut hasher = RescuePoseidonDigest::<Bn256, _, 2, 3>::new(params.clone());
+            hasher.update(input);
+            assert_eq!(hasher.clone().finalize(), expected);
+
+            // split of the stream doesn't matter
+            let mut split = RescuePoseidonDigest::<Bn256, RescueParams<Bn256, 2, 3>, 2, 3>::default();
+            for part in input.chunks(7) {
+                split.update(part);
+            }
+            split.update(&[]);
+            assert_eq!(split.finalize_bytes(), hasher.finalize_bytes());
+        }
+
+        let mut hasher = RescuePoseidonDigest::<Bn256, _, 2, 3>::new(params.clone());
+        hasher.update(&input);
+        hasher.reset();
+        hasher.update(&[1, 2, 3]);
+        let mut expected = RescuePoseidonDigest::<Bn256, _, 2, 3>::new(params);
+        expected.update(&[1, 2, 3]);
+        assert_eq!(hasher.finalize(), expected.finalize());
+    }
+
+    #[test]
+    fn test_streaming_digest_padding() {
+        let params = RescueParams::<Bn256, 2, 3>::default();
+        let digest = |input: &[u8]| {
+            let mut hasher = RescuePoseidonDigest::<Bn256, _, 2, 3>::new(params.clone());
+            hasher.update(input);
+            hasher.finalize()
+        };
+
+        let chunk_len = bytes_per_element::<Bn256>();
+        for len in [0, 1, chunk_len - 1, chunk_len, 2 * chunk_len, 3 * chunk_len + 5] {
+            let input: Vec<_> = (0..len).map(|i| i as u8).collect();
+            let mut extended = input.clone();
+            extended.push(BYTES_PADDING_MARKER);
+            assert_ne!(digest(&input), digest(&extended));
+        }
+
+        // single chunk which packs into one is not a padding element
+        let mut one = vec![0u8; chunk_len];
+        one[0] = 1;
+        assert_ne!(digest(&[]), digest(&one));
+    }
+
+    #[cfg(feature = "digest")]
+    #[test]
+    fn test_digest_traits() {
+        use digest::Digest;
+        type Hasher = RescuePoseidonDigest<Bn256, RescueParams<Bn256, 2, 3>, 2, 3>;
+
+        let mut hasher = Hasher::default();
+        hasher.update(b"payload");
+        let expected = hasher.finalize_bytes();
+
+        assert_eq!(<Hasher as Digest>::digest(b"payload").as_slice(), &expected[..]);
+        let mut hasher = <Hasher as Digest>::new();
+        Digest::update(&mut hasher, b"pay");
+        Digest::update(&mut hasher, b"load");
+        assert_eq!(Digest::finalize(hasher).as_slice(), &expected[..]);
+    }
+
+    #[test]
+    fn test_circuit_hash_bytes() {
+        let params = RescueParams::<Bn256, 2, 3>::default();
+        for len in [0, 1, 30, 31, 45] {
+            let input: Vec<u8> = (0..len).map(|i| (i * 37 + 11) as u8).collect();
+            let expected = hash_bytes(&params, &input);
+
+            let cs = &mut init_cs::<Bn256>();
+            let bytes: Vec<_> = input
+                .iter()
+                .map(|byte| Byte::from_u8_witness(cs, Some(*byte)).unwrap())
+                .collect();
+            let actual = circuit_hash_bytes(cs, &params, &bytes).unwrap();
+            assert_eq!(actual.map(|el| el.get_value().unwrap()), expected);
+
+            let limbs: Vec<_> = input
+                .iter()
+                .map(|byte| Num::alloc(cs, Some(fe_from_u8::<Bn256>(*byte))).unwrap())
+                .collect();
+            let actual = circuit_hash_byte_limbs(cs, &params, &limbs).unwrap();
+            assert_eq!(actual.map(|el| el.get_value().unwrap()), expected);
+
+            let constants: Vec<_> = input.iter().map(|byte| Num::Constant(fe_from_u8::<Bn256>(*byte))).collect();
+            let actual = circuit_hash_byte_limbs(cs, &params, &constants).unwrap();
+            assert_eq!(actual.map(|el| el.get_value().unwrap()), expected);
+
+            assert!(cs.is_satisfied());
+        }
+    }
+}
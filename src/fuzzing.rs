@@ -0,0 +1,100 @@
+//! Entry points of fuzz targets in `fuzz/`. Not a stable API.
+use crate::circuit::sbox::sbox as circuit_sbox;
+use crate::common::sbox::sbox as native_sbox;
+use crate::traits::{CustomGate, HashParams, Sbox};
+use crate::RescueParams;
+use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+use franklin_crypto::bellman::plonk::better_better_cs::cs::{
+    ConstraintSystem, TrivialAssembly, Width4MainGateWithDNext,
+};
+use franklin_crypto::bellman::Field;
+use franklin_crypto::plonk::circuit::{
+    allocated_num::{AllocatedNum, Num},
+    linear_combination::LinearCombination,
+    Width4WithCustomGates,
+};
+use rand::{Rand, SeedableRng, XorShiftRng};
+
+const WIDTH: usize = 3;
+
+/// Applies native and circuit sbox to the same state and panics if outputs
+/// differ or constraint system is not satisfied. Input selects the power
+/// (alpha, inverse by exponentiation or by addition chain), the custom gate,
+/// partial application and, per element, whether it is a constant and
+/// whether it is an edge value (zero, one, minus one) or a random one.
+pub fn sbox_differential(data: &[u8]) {
+    if data.len() < 2 + WIDTH + 16 {
+        return;
+    }
+    let (selectors, rest) = data.split_at(2 + WIDTH);
+    let seed: [u32; 4] = core::array::from_fn(|i| u32::from_le_bytes(rest[4 * i..4 * (i + 1)].try_into().unwrap()));
+    // xorshift rng is stuck at zero seed
+    let rng = &mut XorShiftRng::from_seed(if seed == [0; 4] { crate::common::TEST_SEED } else { seed });
+
+    let power = match selectors[0] % 3 {
+        0 => RescueParams::<Bn256, 2, WIDTH>::default().alpha().clone(),
+        1 => RescueParams::<Bn256, 2, WIDTH>::default().alpha_inv().clone(),
+        _ => RescueParams::<Bn256, 2, WIDTH>::specialized_for_num_rounds(8, 120).alpha_inv().clone(),
+    };
+    let custom_gate = match (selectors[1] >> 1) % 3 {
+        0 => CustomGate::None,
+        1 => CustomGate::QuinticWidth3,
+        _ => CustomGate::QuinticWidth4,
+    };
+    // circuit inverse sbox is always applied to the whole state
+    let partial = selectors[1] & 1 == 1 && matches!(power, Sbox::Alpha(_));
+    let range = if partial { 0..1 } else { 0..WIDTH };
+
+    let mut state = [Fr::zero(); WIDTH];
+    for (el, selector) in state.iter_mut().zip(selectors[2..].iter()) {
+        *el = match (selector >> 1) % 4 {
+            0 => Fr::zero(),
+            1 => Fr::one(),
+            2 => {
+                let mut minus_one = Fr::one();
+                minus_one.negate();
+                minus_one
+            }
+            _ => Fr::rand(rng),
+        };
+    }
+
+    let cs = &mut TrivialAssembly::<Bn256, Width4WithCustomGates, Width4MainGateWithDNext>::new();
+    let mut state_as_lc: [LinearCombination<Bn256>; WIDTH] = core::array::from_fn(|_| LinearCombination::zero());
+    for ((lc, el), selector) in state_as_lc.iter_mut().zip(state.iter()).zip(selectors[2..].iter()) {
+        let num = if selector & 1 == 1 {
+            Num::Constant(*el)
+        } else {
+            Num::Variable(AllocatedNum::alloc(cs, || Ok(*el)).unwrap())
+        };
+        *lc = LinearCombination::from(num);
+    }
+
+    native_sbox::<Bn256>(&power, &mut state[range.clone()]);
+    circuit_sbox(cs, &power, &mut state_as_lc, Some(range), custom_gate).unwrap();
+
+    for (lc, expected) in state_as_lc.into_iter().zip(state.iter()) {
+        let actual = lc.into_num(cs).unwrap().get_value().unwrap();
+        assert_eq!(actual, *expected, "{:?} with {:?}", power, custom_gate);
+    }
+    assert!(cs.is_satisfied(), "{:?} with {:?} is not satisfied", power, custom_gate);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sbox_differential() {
+        // every power, gate, partial flag and element kind at least once
+        for power in 0..3u8 {
+            for gate_and_partial in 0..6u8 {
+                for element in 0..8u8 {
+                    let mut data = vec![power, gate_and_partial, element, element.wrapping_add(3), element.wrapping_add(6)];
+                    data.extend_from_slice(&[element; 16]);
+                    sbox_differential(&data);
+                }
+            }
+        }
+    }
+}
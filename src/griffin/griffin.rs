@@ -0,0 +1,176 @@
+use crate::common::matrix::mmul_assign;
+use crate::common::sbox::sbox;
+use crate::sponge::generic_hash;
+use crate::traits::{HashParams, NoopMiddleware, RoundFunctionMiddleware};
+use franklin_crypto::bellman::{Engine, Field};
+use super::params::GriffinParams;
+
+/// Receives inputs whose length `known` prior(fixed-length).
+/// Also uses custom domain strategy which basically sets value of capacity element to
+/// length of input and applies a padding rule which makes input size equals to multiple of
+/// rate parameter.
+/// Uses pre-defined state-width=3 and rate=2.
+pub fn griffin_hash<E: Engine, const L: usize>(input: &[E::Fr; L]) -> [E::Fr; 2] {
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+
+    let params = GriffinParams::<E, RATE, WIDTH>::default();
+    generic_hash(&params, input, None)
+}
+
+pub fn griffin_round_function<E: Engine, const RATE: usize, const WIDTH: usize>(
+    state: &mut [E::Fr; WIDTH],
+    params: &GriffinParams<E, RATE, WIDTH>,
+) {
+    griffin_round_function_with_middleware(state, params, &mut NoopMiddleware)
+}
+
+pub(crate) fn griffin_round_function_with_middleware<
+    E: Engine,
+    M: RoundFunctionMiddleware<E, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    state: &mut [E::Fr; WIDTH],
+    params: &GriffinParams<E, RATE, WIDTH>,
+    middleware: &mut M,
+) {
+    // linear layer at beginning
+    mmul_assign::<E, WIDTH>(params.mds_matrix(), state);
+
+    let rounds = params.number_of_full_rounds();
+    for round in 0..rounds {
+        // y0 = x0^(1/d)
+        sbox::<E>(params.alpha_inv(), &mut state[..1]);
+        middleware.after_inverse_sbox(round, &state[..1]);
+
+        nonlinear_layer(params, state);
+        mmul_assign::<E, WIDTH>(params.mds_matrix(), state);
+
+        if round + 1 < rounds {
+            state
+                .iter_mut()
+                .zip(params.constants_of_round(round).iter())
+                .for_each(|(s, c)| s.add_assign(c));
+        }
+
+        middleware.after_round(round, state);
+    }
+}
+
+// Rest of the non-linear layer after the inverse sbox: y1 = x1^d and
+// y2 = x2 * (L^2 + alpha * L + beta) where L = y0 + y1.
+fn nonlinear_layer<E: Engine, const RATE: usize, const WIDTH: usize>(
+    params: &GriffinParams<E, RATE, WIDTH>,
+    state: &mut [E::Fr; WIDTH],
+) {
+    debug_assert_eq!(WIDTH, 3);
+    sbox::<E>(params.alpha(), &mut state[1..2]);
+
+    let (alpha, beta) = params.alpha_beta();
+    let mut linear = state[0];
+    linear.add_assign(&state[1]);
+
+    let mut factor = linear;
+    factor.add_assign(alpha);
+    factor.mul_assign(&linear);
+    factor.add_assign(beta);
+
+    state[2].mul_assign(&factor);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::init_rng;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use franklin_crypto::bellman::pairing::ff::{LegendreSymbol, SqrtField};
+    use franklin_crypto::bellman::{PrimeField, PrimeFieldRepr};
+    use rand::Rand;
+
+    fn fr_from_hex(value: &str) -> Fr {
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.read_be(&hex::decode(value).unwrap()[..]).unwrap();
+        Fr::from_repr(repr).unwrap()
+    }
+
+    #[test]
+    fn test_griffin_number_of_rounds() {
+        use crate::common::params::griffin_number_of_rounds;
+
+        // round numbers of the reference instances for 128 bits of security
+        for (width, alpha, rounds) in [(3, 5, 12), (4, 5, 11), (8, 5, 9), (3, 3, 16), (4, 3, 14), (8, 3, 11)] {
+            assert_eq!(griffin_number_of_rounds(width, alpha, 128), rounds);
+        }
+    }
+
+    #[test]
+    fn test_griffin_params() {
+        let params = GriffinParams::<Bn256, 2, 3>::default();
+        assert_eq!(params.number_of_full_rounds(), 12);
+        assert_eq!(params.round_constants.len(), 11);
+
+        // constants of the reference implementation for BN254 with t=3, d=5
+        let first = [
+            "2fb30cafdb1f76156dfabf0cd0af4b895e764ac2a84386c9d0d7aed6a7f4eac9",
+            "282927892ce324572f19abb14871d2b539a80d8a5800cdb87a81e1697a94b6c9",
+            "03d0f3f2711dd59e3d97fc797261300cd3fee33b95cf710a32edf42aa2bc0905",
+        ];
+        assert_eq!(params.round_constants[0], first.map(fr_from_hex));
+        let last = [
+            "1b0a98be27b54ac9d5d72b94187c991c1872cb2c7777c0e880f439c133971e8d",
+            "1e10a35afda2e5a173d4f3edecf29dacf51d8fac33d6bfb4088cc787ec647605",
+            "1793cda85abe2782ea8e911ce92bab59a8c68e0dd561a57b064bb233f109cc57",
+        ];
+        assert_eq!(params.round_constants[10], last.map(fr_from_hex));
+        assert_eq!(
+            *params.alpha_beta(),
+            (
+                fr_from_hex("146ecffb34a66316fae66609f78d1310bc14ad7208082ca7943afebb1da4aa4a"),
+                fr_from_hex("2b568115d544c7e941eff6ccc935384619b0fb7d2c5ba6c078c34cf81697ee1c"),
+            )
+        );
+
+        let (alpha, beta) = params.alpha_beta();
+        let mut discriminant = *alpha;
+        discriminant.square();
+        let mut four_beta = *beta;
+        four_beta.double();
+        four_beta.double();
+        discriminant.sub_assign(&four_beta);
+        assert_eq!(discriminant.legendre(), LegendreSymbol::QuadraticNonResidue);
+
+        assert_eq!(params.fingerprint(), GriffinParams::<Bn256, 2, 3>::default().fingerprint());
+    }
+
+    #[test]
+    fn test_griffin_permutation_kat() {
+        let params = GriffinParams::<Bn256, 2, 3>::default();
+        let mut state = [0, 1, 2].map(|value| Fr::from_str(&value.to_string()).unwrap());
+        griffin_round_function(&mut state, &params);
+
+        let expected = [
+            "2311cdb3076c3a7ee37fd5a271e0f3a8a3cc38057d0cea37b78951f43b1b6ff6",
+            "1d3aaed9ea361e899e667abd18e5328555b97b5c3890d52b261f940d6ab4df58",
+            "22614a0ac719cb623a636adac3bac1b85b5a7a418fcf8ab3a3ae0787fb4bed9d",
+        ];
+        assert_eq!(state, expected.map(fr_from_hex));
+    }
+
+    #[test]
+    fn test_griffin_hash_matches_round_function() {
+        let rng = &mut init_rng();
+        let params = GriffinParams::<Bn256, 2, 3>::default();
+        let input = [Fr::rand(rng), Fr::rand(rng)];
+
+        let mut state = [input[0], input[1], Fr::zero()];
+        // capacity is specialized with input length
+        state[2] = Fr::from_str("2").unwrap();
+        griffin_round_function(&mut state, &params);
+        assert_eq!(griffin_hash::<Bn256, 2>(&input), [state[0], state[1]]);
+
+        let mut other_input = input;
+        other_input[1].add_assign(&Fr::one());
+        assert_ne!(griffin_hash::<Bn256, 2>(&other_input), griffin_hash::<Bn256, 2>(&input));
+    }
+}
@@ -0,0 +1,5 @@
+pub(crate) mod griffin;
+pub mod params;
+
+pub use self::griffin::*;
+pub use self::params::GriffinParams;
@@ -0,0 +1,215 @@
+use alloc::{vec, vec::Vec};
+use franklin_crypto::bellman::pairing::ff::{LegendreSymbol, SqrtField};
+use franklin_crypto::bellman::{Engine, Field, PrimeField, PrimeFieldRepr};
+use sha3::digest::{ExtendableOutput, Update, XofReader};
+use sha3::Shake128;
+
+use crate::common::fingerprint::Fingerprint;
+use crate::common::params::{griffin_number_of_rounds, InnerHashParameters};
+use crate::traits::{CustomGate, HashFamily, HashParams, Sbox};
+
+/// SHAKE128 deriving round constants and then alpha and beta is seeded with
+/// it followed by the modulus as little endian u64 limbs, as in the reference
+/// implementation.
+pub(crate) const CONSTANTS_SEED_PREFIX: &[u8] = b"Griffin";
+
+/// Params of the Griffin-π permutation https://eprint.iacr.org/2022/403.
+/// Only state width 3 is supported, its linear layer is circ(2, 1, 1).
+/// Constants are derived as by the reference implementation.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct GriffinParams<E: Engine, const RATE: usize, const WIDTH: usize> {
+    #[serde(serialize_with = "crate::serialize_array_of_arrays")]
+    #[serde(deserialize_with = "crate::deserialize_array_of_arrays")]
+    pub(crate) mds_matrix: [[E::Fr; WIDTH]; WIDTH],
+    #[serde(serialize_with = "crate::serialize_vec_of_arrays")]
+    #[serde(deserialize_with = "crate::deserialize_vec_of_arrays")]
+    pub(crate) round_constants: Vec<[E::Fr; WIDTH]>,
+    // coefficients of the quadratic factor of the third element, its
+    // discriminant alpha^2 - 4*beta is a quadratic non-residue
+    pub(crate) alpha_beta: (E::Fr, E::Fr),
+    pub(crate) alpha: Sbox,
+    pub(crate) alpha_inv: Sbox,
+    pub(crate) rounds: usize,
+    pub(crate) custom_gate: CustomGate,
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> PartialEq for GriffinParams<E, RATE, WIDTH> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash_family() == other.hash_family()
+    }
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> Default for GriffinParams<E, RATE, WIDTH> {
+    fn default() -> Self {
+        let (params, alpha, alpha_inv, alpha_beta) = griffin_params::<E, RATE, WIDTH>();
+
+        Self {
+            mds_matrix: *params.mds_matrix(),
+            round_constants: params.round_constants().to_vec(),
+            alpha_beta,
+            alpha: Sbox::Alpha(alpha),
+            alpha_inv: Sbox::AlphaInverse(alpha_inv, alpha),
+            rounds: params.full_rounds,
+            custom_gate: CustomGate::None,
+        }
+    }
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> GriffinParams<E, RATE, WIDTH> {
+    /// Coefficients of `L^2 + alpha * L + beta` the third element of the state
+    /// is multiplied by.
+    pub fn alpha_beta(&self) -> &(E::Fr, E::Fr) {
+        &self.alpha_beta
+    }
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> HashParams<E, RATE, WIDTH> for GriffinParams<E, RATE, WIDTH> {
+    fn hash_family(&self) -> HashFamily {
+        HashFamily::Griffin
+    }
+
+    /// Constants added after the linear layer of the round, the last round
+    /// doesn't have them.
+    fn constants_of_round(&self, round: usize) -> &[E::Fr; WIDTH] {
+        &self.round_constants[round]
+    }
+
+    fn mds_matrix(&self) -> &[[E::Fr; WIDTH]; WIDTH] {
+        &self.mds_matrix
+    }
+
+    fn number_of_full_rounds(&self) -> usize {
+        self.rounds
+    }
+
+    fn number_of_partial_rounds(&self) -> usize {
+        0
+    }
+
+    fn alpha(&self) -> &Sbox {
+        &self.alpha
+    }
+
+    fn alpha_inv(&self) -> &Sbox {
+        &self.alpha_inv
+    }
+
+    fn optimized_round_constants(&self) -> &[[E::Fr; WIDTH]] {
+        unimplemented!("Griffin doesn't use optimized constants")
+    }
+
+    fn optimized_mds_matrixes(&self) -> (&[[E::Fr; WIDTH]; WIDTH], &[[[E::Fr; WIDTH]; WIDTH]]) {
+        unimplemented!("Griffin doesn't use optimized matrixes")
+    }
+
+    fn custom_gate(&self) -> CustomGate {
+        self.custom_gate
+    }
+
+    fn use_custom_gate(&mut self, custom_gate: CustomGate) {
+        self.custom_gate = custom_gate;
+    }
+
+    fn fingerprint(&self) -> [u8; 32] {
+        let mut fingerprint = Fingerprint::new(self.hash_family(), RATE, WIDTH);
+        fingerprint.update_usize(self.rounds);
+        fingerprint.update_matrix::<E, WIDTH>(&self.mds_matrix);
+        for constants in self.round_constants.iter() {
+            fingerprint.update_frs::<E>(constants);
+        }
+        fingerprint.update_frs::<E>(&[self.alpha_beta.0, self.alpha_beta.1]);
+        fingerprint.update_sbox(&self.alpha);
+        fingerprint.update_sbox(&self.alpha_inv);
+
+        fingerprint.finalize()
+    }
+
+    fn try_to_griffin_params(&self) -> Option<&GriffinParams<E, RATE, WIDTH>> {
+        Some(self)
+    }
+}
+
+pub(crate) fn griffin_params<E: Engine, const RATE: usize, const WIDTH: usize>(
+) -> (InnerHashParameters<E, RATE, WIDTH>, u64, Vec<u64>, (E::Fr, E::Fr)) {
+    assert_eq!(WIDTH, 3, "Griffin is only supported for state width 3");
+    let security_level = 128;
+    let alpha = 5u64;
+    let rounds = griffin_number_of_rounds(WIDTH, alpha, security_level);
+
+    let alpha_inv = crate::common::utils::compute_gcd_vec::<E>(alpha).expect("inverse of alpha");
+
+    let mut shake = Shake128::default();
+    shake.update(CONSTANTS_SEED_PREFIX);
+    for limb in E::Fr::char().as_ref() {
+        shake.update(limb.to_le_bytes());
+    }
+    let mut reader = shake.finalize_xof();
+
+    let mut params = InnerHashParameters::new(security_level, rounds, 0);
+    params.round_constants = (0..rounds - 1)
+        .map(|_| {
+            let mut constants = [E::Fr::zero(); WIDTH];
+            constants.iter_mut().for_each(|c| *c = field_element_from_reader::<E>(&mut reader));
+            constants
+        })
+        .collect();
+    params.set_circular_optimized_mds();
+    let alpha_beta = compute_alpha_beta::<E>(&mut reader);
+
+    (params, alpha, alpha_inv, alpha_beta)
+}
+
+// Bytes covering the bit length of the modulus with the unused top bits
+// masked, read as a little endian integer and rejected if not less than the
+// modulus.
+fn field_element_from_reader<E: Engine>(reader: &mut impl XofReader) -> E::Fr {
+    let num_bits = E::Fr::NUM_BITS as usize;
+    let num_bytes = (num_bits + 7) / 8;
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    let mut bytes = vec![0u8; repr.as_ref().len() * 8];
+    loop {
+        reader.read(&mut bytes[..num_bytes]);
+        if num_bits % 8 != 0 {
+            bytes[num_bytes - 1] &= (1u8 << (num_bits % 8)) - 1;
+        }
+        repr.read_le(&bytes[..]).unwrap();
+
+        if let Ok(element) = E::Fr::from_repr(repr) {
+            return element;
+        }
+    }
+}
+
+fn nonzero_field_element_from_reader<E: Engine>(reader: &mut impl XofReader) -> E::Fr {
+    loop {
+        let element = field_element_from_reader::<E>(reader);
+        if !element.is_zero() {
+            return element;
+        }
+    }
+}
+
+// First pair of distinct nonzero elements whose discriminant is a
+// non-residue, so the quadratic factor never vanishes.
+fn compute_alpha_beta<E: Engine>(reader: &mut impl XofReader) -> (E::Fr, E::Fr) {
+    let mut four = E::Fr::one();
+    four.double();
+    four.double();
+
+    loop {
+        let alpha = nonzero_field_element_from_reader::<E>(reader);
+        let mut beta = nonzero_field_element_from_reader::<E>(reader);
+        while alpha == beta {
+            beta = nonzero_field_element_from_reader::<E>(reader);
+        }
+
+        let mut discriminant = alpha;
+        discriminant.square();
+        let mut four_beta = beta;
+        four_beta.mul_assign(&four);
+        discriminant.sub_assign(&four_beta);
+        if discriminant.legendre() == LegendreSymbol::QuadraticNonResidue {
+            return (alpha, beta);
+        }
+    }
+}
@@ -0,0 +1,132 @@
+//! Hashing into a prime field other than the one the hash is defined over,
+//! e.g. into the subgroup order of an embedded curve like BabyJubjub, which
+//! signature schemes over such curves need for challenges and nonces.
+use crate::common::utils::{biguint_to_u64_vec, fe_from_u64};
+use crate::sponge::GenericSponge;
+use crate::traits::HashParams;
+use alloc::vec::Vec;
+use franklin_crypto::bellman::{Engine, PrimeField, PrimeFieldRepr};
+use num_bigint::BigUint;
+
+/// Statistical distance of `hash_to_scalar` output from uniform is at most
+/// `2^-HASH_TO_SCALAR_SECURITY_BITS`.
+pub const HASH_TO_SCALAR_SECURITY_BITS: usize = 128;
+
+/// Hashes `input` prefixed by its length with the variable length sponge and
+/// maps the result into `F`. Length prefix makes the padding of the sponge
+/// unambiguous, so inputs ending with padding values don't collide with the
+/// shorter ones. The sponge squeezes `k` elements `e_i`, the smallest number with
+/// `k * E::Fr::CAPACITY >= F::NUM_BITS + HASH_TO_SCALAR_SECURITY_BITS`, and
+/// `x = sum(e_i * p^i)` is reduced modulo `q = F::char()`. Since `x` is
+/// uniform over `[0, p^k)` when the squeezed elements are, the bias of
+/// `x mod q` is at most `q / p^k <= 2^-HASH_TO_SCALAR_SECURITY_BITS`.
+///
+/// Outputs for different target fields are derived from the same squeezed
+/// elements, so callers mapping one input into several fields should
+/// domain separate the inputs themselves.
+pub fn hash_to_scalar<E: Engine, F: PrimeField, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    input: &[E::Fr],
+) -> F {
+    assert!(!input.is_empty(), "empty input");
+
+    let mut prefixed = Vec::with_capacity(input.len() + 1);
+    prefixed.push(fe_from_u64::<E>(input.len() as u64));
+    prefixed.extend_from_slice(input);
+
+    let mut sponge = GenericSponge::<E, RATE, WIDTH>::new();
+    sponge.absorb_multiple(&prefixed, params);
+    sponge.pad_if_necessary();
+
+    let p = field_char_to_biguint::<E::Fr>();
+    let mut combined = BigUint::from(0u64);
+    let mut power = BigUint::from(1u64);
+    for _ in 0..num_squeezed_elements::<E, F>() {
+        let el = sponge.squeeze_or_permute(params).expect("input is absorbed and padded");
+        combined += repr_to_biguint(el.into_repr().as_ref()) * &power;
+        power *= &p;
+    }
+
+    let reduced = combined % field_char_to_biguint::<F>();
+    let mut repr = F::Repr::default();
+    for (limb, value) in repr.as_mut().iter_mut().zip(biguint_to_u64_vec(reduced)) {
+        *limb = value;
+    }
+
+    F::from_repr(repr).expect("reduced value is in the field")
+}
+
+/// Number of elements `hash_to_scalar` squeezes for the target field `F`.
+pub fn num_squeezed_elements<E: Engine, F: PrimeField>() -> usize {
+    let required_bits = F::NUM_BITS as usize + HASH_TO_SCALAR_SECURITY_BITS;
+    let bits_per_element = E::Fr::CAPACITY as usize;
+
+    (required_bits + bits_per_element - 1) / bits_per_element
+}
+
+fn field_char_to_biguint<F: PrimeField>() -> BigUint {
+    repr_to_biguint(F::char().as_ref())
+}
+
+// Limbs are little endian
+fn repr_to_biguint(limbs: &[u64]) -> BigUint {
+    let mut result = BigUint::from(0u64);
+    for limb in limbs.iter().rev() {
+        result <<= 64;
+        result += BigUint::from(*limb);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::init_rng;
+    use crate::{AnemoiParams, RescueParams};
+    use franklin_crypto::alt_babyjubjub::fs::Fs;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use franklin_crypto::bellman::Field;
+    use rand::Rand;
+
+    #[test]
+    fn test_num_squeezed_elements() {
+        // 251 + 128 bits need two elements of 253 bits
+        assert_eq!(num_squeezed_elements::<Bn256, Fs>(), 2);
+        assert_eq!(num_squeezed_elements::<Bn256, Fr>(), 2);
+    }
+
+    #[test]
+    fn test_hash_to_scalar() {
+        let rng = &mut init_rng();
+        let params = RescueParams::<Bn256, 2, 3>::default();
+        let input = [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)];
+
+        // same as combining two squeezed elements by hand
+        let mut sponge = GenericSponge::<Bn256, 2, 3>::new();
+        sponge.absorb_multiple(&[fe_from_u64::<Bn256>(3), input[0], input[1], input[2]], &params);
+        sponge.pad_if_necessary();
+        let low = repr_to_biguint(sponge.squeeze(&params).unwrap().into_repr().as_ref());
+        let high = repr_to_biguint(sponge.squeeze(&params).unwrap().into_repr().as_ref());
+        let expected = (low + high * field_char_to_biguint::<Fr>()) % field_char_to_biguint::<Fs>();
+
+        let actual: Fs = hash_to_scalar(&params, &input);
+        assert_eq!(repr_to_biguint(actual.into_repr().as_ref()), expected);
+        assert_eq!(hash_to_scalar::<_, Fs, _, 2, 3>(&params, &input), actual);
+
+        let mut other_input = input;
+        other_input[2] = Fr::rand(rng);
+        assert_ne!(hash_to_scalar::<_, Fs, _, 2, 3>(&params, &other_input), actual);
+
+        // input ending with a padding value is not the shorter input padded
+        let padded_input = [input[0], Fr::one()];
+        assert_ne!(
+            hash_to_scalar::<_, Fs, _, 2, 3>(&params, &input[..1]),
+            hash_to_scalar::<_, Fs, _, 2, 3>(&params, &padded_input)
+        );
+
+        // squeezing more elements than the rate permutes again
+        let params = AnemoiParams::<Bn256, 1, 2>::default();
+        assert_ne!(hash_to_scalar::<_, Fs, _, 1, 2>(&params, &input), actual);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::circuit::sponge::CircuitGenericSponge;
+use crate::common::utils::fe_from_u64;
+use crate::sponge::GenericSponge;
+use crate::traits::HashParams;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Engine, PrimeField, SynthesisError};
+use franklin_crypto::plonk::circuit::allocated_num::Num;
+
+/// Structured data which can be hashed deterministically.
+///
+/// Domain tag of the type is absorbed first, then implementor absorbs its
+/// fields in declaration order. Nested structs should be absorbed through
+/// their own `absorb_into` so that their tags are included as well. Variable
+/// length fields should be prefixed by their length.
+pub trait HashableStruct<E: Engine> {
+    /// Tag which is unique per type.
+    const DOMAIN_TAG: u64;
+
+    /// Absorbs fields in canonical order, without the domain tag.
+    fn absorb_fields_into<P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+        &self,
+        sponge: &mut GenericSponge<E, RATE, WIDTH>,
+        params: &P,
+    );
+
+    fn absorb_into<P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+        &self,
+        sponge: &mut GenericSponge<E, RATE, WIDTH>,
+        params: &P,
+    ) {
+        sponge.absorb(fe_from_u64::<E>(Self::DOMAIN_TAG), params);
+        self.absorb_fields_into(sponge, params);
+    }
+}
+
+/// Circuit counterpart of `HashableStruct`, should use same domain tag and
+/// field order as the native implementation.
+pub trait CircuitHashableStruct<E: Engine> {
+    const DOMAIN_TAG: u64;
+
+    fn absorb_fields_into<
+        CS: ConstraintSystem<E>,
+        P: HashParams<E, RATE, WIDTH>,
+        const RATE: usize,
+        const WIDTH: usize,
+    >(
+        &self,
+        cs: &mut CS,
+        sponge: &mut CircuitGenericSponge<E, RATE, WIDTH>,
+        params: &P,
+    ) -> Result<(), SynthesisError>;
+
+    fn absorb_into<
+        CS: ConstraintSystem<E>,
+        P: HashParams<E, RATE, WIDTH>,
+        const RATE: usize,
+        const WIDTH: usize,
+    >(
+        &self,
+        cs: &mut CS,
+        sponge: &mut CircuitGenericSponge<E, RATE, WIDTH>,
+        params: &P,
+    ) -> Result<(), SynthesisError> {
+        sponge.absorb(cs, Num::Constant(fe_from_u64::<E>(Self::DOMAIN_TAG)), params)?;
+        self.absorb_fields_into(cs, sponge, params)
+    }
+}
+
+/// Hashes a struct into a single field element using variable length sponge.
+pub fn hash_struct<
+    E: Engine,
+    S: HashableStruct<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    value: &S,
+    params: &P,
+) -> E::Fr {
+    let mut sponge = GenericSponge::<E, RATE, WIDTH>::new();
+    value.absorb_into(&mut sponge, params);
+    sponge.pad_if_necessary();
+
+    sponge.squeeze(params).expect("a squeezed elem")
+}
+
+/// Circuit counterpart of `hash_struct`.
+pub fn circuit_hash_struct<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    S: CircuitHashableStruct<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    value: &S,
+    params: &P,
+) -> Result<Num<E>, SynthesisError> {
+    let mut sponge = CircuitGenericSponge::<E, RATE, WIDTH>::new();
+    value.absorb_into(cs, &mut sponge, params)?;
+    sponge.pad_if_necessary();
+
+    Ok(sponge.squeeze_num(cs, params)?.expect("a squeezed elem"))
+}
+
+/// Hashes a sequence of tagged values into a single field element.
+///
+/// Tags are packed as 64-bit words, `E::Fr::CAPACITY / 64` of them per field
+/// element. Each packed element is followed by the values it tags, i.e. items
+/// are absorbed in groups of `[tags, value_0, .., value_k]`. Sequence is hashed
+/// by fixed length sponge whose capacity element is set to total length, so
+/// sequences with different number of items never collide.
+pub fn hash_tagged_items<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    items: impl IntoIterator<Item = (u64, E::Fr)>,
+) -> E::Fr {
+    let items: Vec<_> = items.into_iter().collect();
+    assert!(!items.is_empty(), "no items");
+
+    let mut input = Vec::with_capacity(items.len() + items.len() / tags_per_element::<E>() + 1);
+    for group in items.chunks(tags_per_element::<E>()) {
+        input.push(pack_tags::<E>(group.iter().map(|(tag, _)| *tag)));
+        input.extend(group.iter().map(|(_, value)| *value));
+    }
+
+    GenericSponge::<E, RATE, WIDTH>::hash(&input, params, None)[0]
+}
+
+/// Circuit counterpart of `hash_tagged_items`. Tags are constants.
+pub fn circuit_hash_tagged_items<
+    E: Engine,
+    CS: ConstraintSystem<E>,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    cs: &mut CS,
+    params: &P,
+    items: impl IntoIterator<Item = (u64, Num<E>)>,
+) -> Result<Num<E>, SynthesisError> {
+    let items: Vec<_> = items.into_iter().collect();
+    assert!(!items.is_empty(), "no items");
+
+    let mut input = Vec::with_capacity(items.len() + items.len() / tags_per_element::<E>() + 1);
+    for group in items.chunks(tags_per_element::<E>()) {
+        input.push(Num::Constant(pack_tags::<E>(group.iter().map(|(tag, _)| *tag))));
+        input.extend(group.iter().map(|(_, value)| *value));
+    }
+
+    Ok(CircuitGenericSponge::<E, RATE, WIDTH>::hash_num(cs, &input, params, None)?[0])
+}
+
+fn tags_per_element<E: Engine>() -> usize {
+    let tags_per_element = E::Fr::CAPACITY as usize / 64;
+    assert!(tags_per_element > 0, "field is too small for 64-bit tags");
+
+    tags_per_element
+}
+
+// Packs tags as little endian 64-bit limbs.
+fn pack_tags<E: Engine>(tags: impl Iterator<Item = u64>) -> E::Fr {
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    for (limb, tag) in repr.as_mut().iter_mut().zip(tags) {
+        *limb = tag;
+    }
+
+    E::Fr::from_repr(repr).expect("a field element")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rescue_prime::params::RescuePrimeParams;
+    use crate::tests::{init_cs, init_rng};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    struct Point {
+        x: Fr,
+        y: Fr,
+    }
+
+    impl HashableStruct<Bn256> for Point {
+        const DOMAIN_TAG: u64 = 1;
+
+        fn absorb_fields_into<P: HashParams<Bn256, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+            &self,
+            sponge: &mut GenericSponge<Bn256, RATE, WIDTH>,
+            params: &P,
+        ) {
+            sponge.absorb(self.x, params);
+            sponge.absorb(self.y, params);
+        }
+    }
+
+    struct CircuitPoint {
+        x: Num<Bn256>,
+        y: Num<Bn256>,
+    }
+
+    impl CircuitHashableStruct<Bn256> for CircuitPoint {
+        const DOMAIN_TAG: u64 = 1;
+
+        fn absorb_fields_into<
+            CS: ConstraintSystem<Bn256>,
+            P: HashParams<Bn256, RATE, WIDTH>,
+            const RATE: usize,
+            const WIDTH: usize,
+        >(
+            &self,
+            cs: &mut CS,
+            sponge: &mut CircuitGenericSponge<Bn256, RATE, WIDTH>,
+            params: &P,
+        ) -> Result<(), SynthesisError> {
+            sponge.absorb(cs, self.x, params)?;
+            sponge.absorb(cs, self.y, params)
+        }
+    }
+
+    #[test]
+    fn test_hash_struct() {
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let params = RescuePrimeParams::<Bn256, 2, 3>::default();
+
+        let point = Point {
+            x: Fr::rand(rng),
+            y: Fr::rand(rng),
+        };
+        let circuit_point = CircuitPoint {
+            x: Num::alloc(cs, Some(point.x)).unwrap(),
+            y: Num::alloc(cs, Some(point.y)).unwrap(),
+        };
+
+        let expected = hash_struct(&point, &params);
+        let actual = circuit_hash_struct(cs, &circuit_point, &params).unwrap();
+        assert_eq!(actual.get_value().unwrap(), expected);
+
+        let swapped = Point { x: point.y, y: point.x };
+        assert_ne!(hash_struct(&swapped, &params), expected);
+    }
+
+    #[test]
+    fn test_hash_tagged_items() {
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let params = RescuePrimeParams::<Bn256, 2, 3>::default();
+
+        let items: Vec<_> = (0..5u64).map(|tag| (tag + 1, Fr::rand(rng))).collect();
+        let expected = hash_tagged_items(&params, items.iter().cloned());
+
+        let circuit_items: Vec<_> = items
+            .iter()
+            .map(|(tag, value)| (*tag, Num::alloc(cs, Some(*value)).unwrap()))
+            .collect();
+        let actual = circuit_hash_tagged_items(cs, &params, circuit_items).unwrap();
+        assert_eq!(actual.get_value().unwrap(), expected);
+        assert!(cs.is_satisfied());
+
+        // tag is bound to value
+        let mut retagged = items.clone();
+        retagged[4].0 = 42;
+        assert_ne!(hash_tagged_items(&params, retagged), expected);
+
+        // dropping an item changes the length
+        assert_ne!(hash_tagged_items(&params, items[..4].iter().cloned()), expected);
+    }
+}
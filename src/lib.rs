@@ -1,32 +1,150 @@
-#![feature(allocator_api)]
-
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+// `Derivative` expands to `::std` paths
+#[cfg(not(feature = "std"))]
+extern crate core as std;
+
+pub mod anemoi;
+#[cfg(all(feature = "asm", target_arch = "x86_64"))]
+mod asm;
+pub mod beacon;
+#[cfg(feature = "bench-internals")]
+#[doc(hidden)]
+pub mod bench_internals;
+pub mod chunked_commitment;
+#[cfg(feature = "std")]
 pub mod circuit;
+#[cfg(feature = "std")]
+pub mod circuits;
+#[cfg(feature = "std")]
+pub mod config;
+#[cfg(feature = "std")]
+pub mod encoding;
+#[cfg(any(test, feature = "fuzzing"))]
+#[doc(hidden)]
+pub mod fuzzing;
+pub mod griffin;
 #[allow(dead_code)]
 mod common;
+pub mod hash_to_scalar;
+#[cfg(feature = "std")]
+pub mod hashable;
+pub mod merkle;
+pub mod monolith;
+#[cfg(feature = "std")]
+pub mod params_blob;
+#[cfg(feature = "std")]
+pub mod point_encoding;
 mod sponge;
 pub mod poseidon;
 pub mod poseidon2;
+pub mod public_inputs;
+pub mod randomness;
 pub mod rescue;
 pub mod rescue_prime;
+#[cfg(feature = "std")]
+pub mod round_function;
+pub mod safe;
+#[cfg(feature = "std")]
+pub mod self_test;
+#[cfg(feature = "std")]
+pub mod spec;
+mod state;
 #[cfg(test)]
 mod tests;
 mod traits;
+pub mod transcript_protocol;
+pub mod typed_params;
+pub mod vector_commitment;
+pub mod witness;
+pub mod wots;
 
-use std::convert::TryInto;
-
+#[cfg(feature = "std")]
 pub use circuit::sponge::{
-    circuit_generic_hash, circuit_generic_round_function, CircuitGenericSponge, circuit_generic_round_function_conditional
+    circuit_generic_hash, circuit_generic_hash_with_gate, circuit_generic_hash_with_witness,
+    circuit_generic_round_function, circuit_generic_round_function_many, CircuitGenericSponge,
+    circuit_generic_round_function_conditional, try_circuit_generic_round_function,
+    try_circuit_generic_round_function_with_gate,
 };
+use alloc::{format, vec::Vec};
 use serde::{ser::{SerializeTuple}, Serialize};
 use smallvec::SmallVec;
-pub use traits::{HashParams, CustomGate, HashFamily};
-pub use sponge::{generic_hash, generic_round_function, GenericSponge};
-pub use poseidon::{params::PoseidonParams, poseidon_hash};
+pub use traits::{HashParams, CustomGate, HashFamily, RoundFunctionMiddleware, NoopMiddleware};
+pub use sponge::{
+    generic_hash, generic_hash_batch, generic_hash_with_output_convention, generic_round_function,
+    generic_round_function_with_middleware, GenericSponge, OutputConvention, SpongeMode,
+    SpongeStateError, SqueezeError,
+};
+pub use anemoi::{anemoi_hash, anemoi_jive_compress, AnemoiParams};
+pub use griffin::{griffin_hash, GriffinParams};
+pub use monolith::{monolith_hash, MonolithParams, MonolithSponge, MonolithTranscript};
+pub use poseidon::{legacy_poseidon_hash, params::PoseidonParams, poseidon_hash, poseidon_round_function_reference};
 pub use rescue::{params::RescueParams, rescue_hash};
-pub use rescue_prime::{params::RescuePrimeParams, rescue_prime_hash};
-pub use common::domain_strategy::DomainStrategy;
+pub use rescue_prime::{params::RescuePrimeParams, rescue_prime_hash, rescue_prime_hash_fixed_spec};
+pub use common::domain_strategy::{DomainStrategy, DomainStrategyError};
+#[cfg(feature = "std")]
+pub use circuit::error::{Gadget, GadgetError};
+#[cfg(feature = "std")]
+pub use circuits::PreimageCircuit;
+#[cfg(feature = "std")]
+pub use config::{ConfiguredHash, ConfiguredParams, HashConfig, HashConfigError};
+pub use common::utils::{
+    batch_inversion, construct_mds_matrix, construct_mds_matrix_with_artifacts, ct_eq_digest, try_into_fixed,
+    try_slice_into_fixed, LengthError, MdsArtifacts,
+};
+pub use common::matrix::{
+    compute_optimized_matrixes, compute_optimized_matrixes_for_width, compute_optimized_matrixes_with_artifacts,
+    try_inverse, OptimizedMatrixes, SparseDecomposition,
+};
+pub use common::params::{poseidon_mds_artifacts, rescue_mds_artifacts, ConstantsSource, SeededParamsError};
+pub use vector_commitment::{VectorCommitment, VectorOpening};
+pub use merkle::{MerkleProof, MerkleTree};
+pub use witness::{precompute_witnesses, HashWitness};
+pub use chunked_commitment::{ChunkedCommitment, ChunkOpening};
+#[cfg(feature = "std")]
+pub use circuit::chunked_commitment::circuit_verify_chunk_opening;
+#[cfg(feature = "std")]
+pub use circuit::merkle::verify_merkle_path;
+#[cfg(feature = "std")]
+pub use circuit::anemoi::circuit_anemoi_jive_compress;
+#[cfg(feature = "std")]
+pub use round_function::AlgebraicRoundFunction;
+pub use state::{State, STATE_ALIGNMENT};
+pub use safe::{IOPattern, SafeSponge, SafeSpongeError, SpongeOp};
+#[cfg(feature = "std")]
+pub use circuit::safe::CircuitSafeSponge;
+pub use transcript_protocol::{PoseidonTranscript, RescueTranscript, SpongeTranscript, TranscriptProtocol};
+#[cfg(feature = "std")]
+pub use circuit::transcript_protocol::{CircuitPoseidonTranscript, CircuitRescueTranscript, CircuitSpongeTranscript};
+#[cfg(feature = "std")]
+pub use point_encoding::{commit_point, decode_compressed_point, encode_compressed_point, PointEncodingError};
+#[cfg(feature = "std")]
+pub use circuit::point_encoding::{circuit_commit_g1, circuit_commit_g2};
+pub use typed_params::{ParamsFor, TypedParams, load_params, store_params};
+#[cfg(feature = "std")]
+pub use params_blob::{generate_params_blob, load_params_blob, store_params_blob, BlobParams, ParamsBlobError};
+#[cfg(feature = "mmap")]
+pub use params_blob::map_params_blob;
+pub use public_inputs::hash_public_inputs;
+pub use hash_to_scalar::hash_to_scalar;
+#[cfg(feature = "std")]
+pub use encoding::{circuit_hash_bytes, hash_bytes, RescuePoseidonDigest};
+pub use randomness::derive_prover_randomness;
+#[cfg(feature = "std")]
+pub use self_test::{self_test, SelfTestReport};
+#[cfg(feature = "std")]
+pub use hashable::{
+    HashableStruct, CircuitHashableStruct, hash_struct, circuit_hash_struct, hash_tagged_items,
+    circuit_hash_tagged_items,
+};
+#[cfg(feature = "std")]
+pub use circuit::public_inputs::circuit_hash_public_inputs;
 
 pub extern crate franklin_crypto;
+// `PrimeFieldAsm` expands to `crate::ff` paths
+#[cfg(all(feature = "asm", target_arch = "x86_64"))]
+use franklin_crypto::bellman::pairing::ff;
 
 pub trait BigArraySerde<'de>: Sized {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -47,7 +165,7 @@ impl<'de, B: BigArraySerde<'de>> serde::Serialize for BigArrayRefWrapper<'de, B>
     }
 }
 
-pub struct BigArrayWrapper<'de, B: BigArraySerde<'de>>(B, std::marker::PhantomData<& 'de ()>);
+pub struct BigArrayWrapper<'de, B: BigArraySerde<'de>>(B, core::marker::PhantomData<& 'de ()>);
 
 impl<'de, B: BigArraySerde<'de>> serde::Serialize for BigArrayWrapper<'de, B> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
@@ -63,12 +181,12 @@ fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
         D: serde::Deserializer<'de> {
         let new = B::deserialize(deserializer)?;
 
-        Ok(Self(new, std::marker::PhantomData))
+        Ok(Self(new, core::marker::PhantomData))
     }
 }
 
 struct ArrayVisitor<T, const M: usize> {
-    element: std::marker::PhantomData<T>,
+    element: core::marker::PhantomData<T>,
 }
 
 impl<'de, T, const M: usize> serde::de::Visitor<'de> for ArrayVisitor<T, M>
@@ -76,7 +194,7 @@ impl<'de, T, const M: usize> serde::de::Visitor<'de> for ArrayVisitor<T, M>
 {
     type Value = [T; M];
 
-    fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+    fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
         formatter.write_str(&format!("an array of length {}", M))
     }
 
@@ -89,7 +207,7 @@ impl<'de, T, const M: usize> serde::de::Visitor<'de> for ArrayVisitor<T, M>
                 .ok_or_else(|| serde::de::Error::invalid_length(i, &self))?;
             arr.push(el);
         }
-        let arr: [T; M] = arr.try_into().map_err(|_| serde::de::Error::invalid_length(M, &self))?;
+        let arr: [T; M] = try_into_fixed(arr).map_err(|err| serde::de::Error::invalid_length(err.actual, &self))?;
 
         Ok(arr)
     }
@@ -111,7 +229,7 @@ impl<'de, T, const N: usize> BigArraySerde<'de> for [T; N]
     fn deserialize<D>(deserializer: D) -> Result<[T; N], D::Error>
         where D: serde::Deserializer<'de>
     {
-        let visitor = ArrayVisitor::<_, N> { element: std::marker::PhantomData };
+        let visitor = ArrayVisitor::<_, N> { element: core::marker::PhantomData };
         deserializer.deserialize_tuple(N, visitor)
     }
 }
@@ -141,7 +259,7 @@ impl<'de, T, const N: usize> BigArraySerde<'de> for [T; N]
 //         where D: serde::Deserializer<'de>
 //     {
 //         struct ArrayVisitor<T, const M: usize> {
-//             element: std::marker::PhantomData<T>,
+//             element: core::marker::PhantomData<T>,
 //         }
 
 //         impl<'de, T, const M: usize> serde::de::Visitor<'de> for ArrayVisitor<T, M>
@@ -149,7 +267,7 @@ impl<'de, T, const N: usize> BigArraySerde<'de> for [T; N]
 //         {
 //             type Value = [T; M];
 
-//             fn expecting(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+//             fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
 //                 formatter.write_str(&format!("an array of length {}", M))
 //             }
 
@@ -162,14 +280,14 @@ impl<'de, T, const N: usize> BigArraySerde<'de> for [T; N]
 //                         .ok_or_else(|| serde::de::Error::invalid_length(i, &self))?;
 //                     arr.push(el);
 //                 }
-//                 use std::convert::TryInto;
+//                 use core::convert::TryInto;
 //                 let arr: [T; M] = arr.try_into().map_err(|_| serde::de::Error::invalid_length(M, &self))?;
 
 //                 Ok(arr)
 //             }
 //         }
 
-//         let visitor = ArrayVisitor::<_, N> { element: std::marker::PhantomData };
+//         let visitor = ArrayVisitor::<_, N> { element: core::marker::PhantomData };
 //         deserializer.deserialize_tuple(N, visitor)
 //     }
 // }
@@ -210,10 +328,8 @@ fn deserialize_vec_of_arrays_of_arrays<'de, D, T: serde::Serialize + serde::de::
 
     let mut result = Vec::with_capacity(flat_result.len() / M);
     for _ in 0..num_elements {
-        let subarray: [[T; N]; M] = match flat_result.drain(..M).collect::<Vec<_>>().try_into() {
-            Ok(a) => a,
-            Err(..) => panic!("length must patch")
-        };
+        let subarray: [[T; N]; M] = try_into_fixed(flat_result.drain(..M).collect())
+            .map_err(<D::Error as serde::de::Error>::custom)?;
         result.push(subarray);
     }
 
@@ -231,7 +347,7 @@ fn serialize_array_of_arrays<T: serde::Serialize + serde::de::DeserializeOwned,
 }
 
 fn deserialize_array_of_arrays<'de, D, T: serde::Serialize + serde::de::DeserializeOwned, const N: usize, const M: usize>(deserializer: D) -> Result<[[T; N]; M], D::Error> where D: serde::Deserializer<'de> {
-    let visitor = ArrayVisitor::<BigArrayWrapper<'de, [T; N]>, M> { element: std::marker::PhantomData };
+    let visitor = ArrayVisitor::<BigArrayWrapper<'de, [T; N]>, M> { element: core::marker::PhantomData };
     let result = deserializer.deserialize_tuple(M, visitor)?;
 
     let subarray = result.map(|el| el.0);
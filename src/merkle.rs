@@ -0,0 +1,300 @@
+use crate::common::utils::{ct_eq_digest, fe_from_u64};
+use crate::sponge::GenericSponge;
+use crate::state::State;
+use crate::traits::HashParams;
+use alloc::{vec, vec::Vec};
+use franklin_crypto::bellman::{Engine, Field};
+
+/// Domain tag leaf inputs start with.
+pub const MERKLE_LEAF_DOMAIN_TAG: u64 = u64::from_be_bytes(*b"MrklLeaf");
+
+/// Domain tag node inputs start with.
+pub const MERKLE_NODE_DOMAIN_TAG: u64 = u64::from_be_bytes(*b"MrklNode");
+
+/// Binary Merkle tree over field element leaves built with 2-to-1
+/// compression of any of the sponges. Leaf and node inputs are prefixed by
+/// distinct domain tags, so a node can't be passed off as a leaf whatever
+/// the length of leaf inputs is. Number of leaves must be a power of two.
+#[derive(Clone, Debug)]
+pub struct MerkleTree<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize> {
+    params: P,
+    layers: MerkleLayers<E>,
+}
+
+/// Authentication path of a single leaf, siblings are ordered from the
+/// leaf layer up to the root.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MerkleProof<E: Engine> {
+    pub index: usize,
+    pub path: Vec<E::Fr>,
+}
+
+impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize> MerkleTree<E, P, RATE, WIDTH> {
+    pub fn new(leaves: &[E::Fr], params: &P) -> Self {
+        assert!(leaves.len().is_power_of_two(), "number of leaves should be a power of two");
+
+        // same buffers are reused for every leaf of the tree
+        let tag = fe_from_u64::<E>(MERKLE_LEAF_DOMAIN_TAG);
+        let mut state = State::<E, WIDTH>::new();
+        let mut output = [E::Fr::zero(); RATE];
+        let leaf_hashes = leaves
+            .iter()
+            .map(|leaf| {
+                GenericSponge::<E, RATE, WIDTH>::hash_into(&[tag, *leaf], params, None, &mut state, &mut output);
+                output[0]
+            })
+            .collect();
+
+        Self {
+            params: params.clone(),
+            layers: MerkleLayers::new(leaf_hashes, params),
+        }
+    }
+
+    pub fn root(&self) -> E::Fr {
+        self.layers.root()
+    }
+
+    pub fn num_leaves(&self) -> usize {
+        self.layers.num_leaves()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.layers.depth()
+    }
+
+    pub fn get_proof(&self, index: usize) -> MerkleProof<E> {
+        MerkleProof {
+            index,
+            path: self.layers.path(index),
+        }
+    }
+
+    /// Verifies proof against the root of this tree.
+    pub fn verify_proof(&self, leaf: &E::Fr, proof: &MerkleProof<E>) -> bool {
+        verify_proof::<E, P, RATE, WIDTH>(&self.root(), leaf, proof, &self.params)
+    }
+}
+
+/// Verifies that `leaf` is at `proof.index` of the tree with the given root.
+pub fn verify_proof<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    root: &E::Fr,
+    leaf: &E::Fr,
+    proof: &MerkleProof<E>,
+    params: &P,
+) -> bool {
+    let leaf_hash = hash_leaf::<E, P, RATE, WIDTH>(&[*leaf], params);
+
+    verify_path::<E, P, RATE, WIDTH>(root, leaf_hash, proof.index, &proof.path, params)
+}
+
+// Layers of a tree over already hashed leaves, shared by all the commitment
+// schemes of the crate which differ only in what goes into a leaf.
+#[derive(Clone, Debug)]
+pub(crate) struct MerkleLayers<E: Engine> {
+    // layers[0] holds leaf hashes, last layer holds the root
+    layers: Vec<Vec<E::Fr>>,
+}
+
+impl<E: Engine> MerkleLayers<E> {
+    pub(crate) fn new<P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+        leaf_hashes: Vec<E::Fr>,
+        params: &P,
+    ) -> Self {
+        assert!(leaf_hashes.len().is_power_of_two(), "number of leaves should be a power of two");
+
+        // same buffers are reused for every node of the tree
+        let tag = fe_from_u64::<E>(MERKLE_NODE_DOMAIN_TAG);
+        let mut state = State::<E, WIDTH>::new();
+        let mut output = [E::Fr::zero(); RATE];
+
+        let mut layers = vec![leaf_hashes];
+        while layers.last().expect("a layer").len() > 1 {
+            let next: Vec<_> = layers
+                .last()
+                .expect("a layer")
+                .chunks_exact(2)
+                .map(|pair| {
+                    GenericSponge::<E, RATE, WIDTH>::hash_into(&[tag, pair[0], pair[1]], params, None, &mut state, &mut output);
+                    output[0]
+                })
+                .collect();
+            layers.push(next);
+        }
+
+        Self { layers }
+    }
+
+    pub(crate) fn root(&self) -> E::Fr {
+        self.layers.last().expect("a layer")[0]
+    }
+
+    pub(crate) fn num_leaves(&self) -> usize {
+        self.layers[0].len()
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.layers[0].is_empty()
+    }
+
+    pub(crate) fn depth(&self) -> usize {
+        self.layers.len() - 1
+    }
+
+    pub(crate) fn path(&self, index: usize) -> Vec<E::Fr> {
+        assert!(index < self.num_leaves(), "index is out of range");
+
+        let mut path = Vec::with_capacity(self.depth());
+        let mut idx = index;
+        for layer in self.layers[..self.depth()].iter() {
+            path.push(layer[idx ^ 1]);
+            idx >>= 1;
+        }
+
+        path
+    }
+
+    /// Replaces a leaf hash and recomputes only the nodes on its path to the root.
+    pub(crate) fn update_leaf<P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+        &mut self,
+        index: usize,
+        leaf_hash: E::Fr,
+        params: &P,
+    ) {
+        assert!(index < self.num_leaves(), "index is out of range");
+
+        self.layers[0][index] = leaf_hash;
+        let mut idx = index;
+        for layer_idx in 1..self.layers.len() {
+            let left = self.layers[layer_idx - 1][idx & !1];
+            let right = self.layers[layer_idx - 1][idx | 1];
+            idx >>= 1;
+            self.layers[layer_idx][idx] = hash_node::<E, P, RATE, WIDTH>(&left, &right, params);
+        }
+    }
+}
+
+/// Hashes leaf `input` prefixed by the leaf domain tag.
+pub(crate) fn hash_leaf<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    input: &[E::Fr],
+    params: &P,
+) -> E::Fr {
+    let mut tagged = Vec::with_capacity(input.len() + 1);
+    tagged.push(fe_from_u64::<E>(MERKLE_LEAF_DOMAIN_TAG));
+    tagged.extend_from_slice(input);
+
+    GenericSponge::<E, RATE, WIDTH>::hash(&tagged, params, None)[0]
+}
+
+pub(crate) fn hash_node<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    left: &E::Fr,
+    right: &E::Fr,
+    params: &P,
+) -> E::Fr {
+    let tag = fe_from_u64::<E>(MERKLE_NODE_DOMAIN_TAG);
+
+    GenericSponge::<E, RATE, WIDTH>::hash(&[tag, *left, *right], params, None)[0]
+}
+
+/// Recomputes the root from a leaf hash and its path and compares it with
+/// `root` in constant time. Index should fit into the length of the path.
+pub(crate) fn verify_path<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    root: &E::Fr,
+    leaf_hash: E::Fr,
+    index: usize,
+    path: &[E::Fr],
+    params: &P,
+) -> bool {
+    if path.len() < usize::BITS as usize && index >> path.len() != 0 {
+        return false;
+    }
+
+    let mut current = leaf_hash;
+    let mut idx = index;
+    for sibling in path.iter() {
+        current = if idx & 1 == 0 {
+            hash_node::<E, P, RATE, WIDTH>(&current, sibling, params)
+        } else {
+            hash_node::<E, P, RATE, WIDTH>(sibling, &current, params)
+        };
+        idx >>= 1;
+    }
+
+    ct_eq_digest::<E, 1>(&[current], &[*root])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::tests::init_rng;
+    use crate::{PoseidonParams, RescueParams};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use franklin_crypto::bellman::Field;
+    use rand::Rand;
+
+    fn check_proofs<P: HashParams<Bn256, 2, 3>>(params: &P) {
+        let rng = &mut init_rng();
+        let leaves: Vec<_> = (0..8).map(|_| Fr::rand(rng)).collect();
+
+        let tree = MerkleTree::new(&leaves, params);
+        assert_eq!(tree.depth(), 3);
+        for (index, leaf) in leaves.iter().enumerate() {
+            let proof = tree.get_proof(index);
+            assert!(tree.verify_proof(leaf, &proof));
+            assert!(verify_proof(&tree.root(), leaf, &proof, params));
+        }
+
+        // proof is bound to the leaf and to its position
+        let proof = tree.get_proof(3);
+        let mut other_leaf = leaves[3];
+        other_leaf.add_assign(&Fr::one());
+        assert!(!tree.verify_proof(&other_leaf, &proof));
+        let moved = MerkleProof { index: 2, ..proof.clone() };
+        assert!(!tree.verify_proof(&leaves[3], &moved));
+        let out_of_range = MerkleProof { index: 8 + 3, ..proof };
+        assert!(!tree.verify_proof(&leaves[3], &out_of_range));
+    }
+
+    #[test]
+    fn test_merkle_tree_proofs() {
+        check_proofs(&Poseidon2Params::<Bn256, 2, 3>::default());
+        check_proofs(&PoseidonParams::<Bn256, 2, 3>::default());
+        check_proofs(&RescueParams::<Bn256, 2, 3>::default());
+    }
+
+    #[test]
+    fn test_merkle_tree_node_is_not_a_leaf() {
+        let rng = &mut init_rng();
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        let leaves: Vec<_> = (0..4).map(|_| Fr::rand(rng)).collect();
+        let tree = MerkleTree::new(&leaves, &params);
+
+        // inner node of the tree is not accepted as a leaf of the smaller tree
+        let node = hash_node::<Bn256, _, 2, 3>(
+            &hash_leaf::<Bn256, _, 2, 3>(&leaves[..1], &params),
+            &hash_leaf::<Bn256, _, 2, 3>(&leaves[1..2], &params),
+            &params,
+        );
+        let proof = MerkleProof {
+            index: 0,
+            path: tree.get_proof(0).path[1..].to_vec(),
+        };
+        assert!(!tree.verify_proof(&node, &proof));
+
+        // nor is a leaf of two elements hashed like a node of them
+        assert_ne!(
+            hash_leaf::<Bn256, _, 2, 3>(&leaves[..2], &params),
+            hash_node::<Bn256, _, 2, 3>(&leaves[0], &leaves[1], &params)
+        );
+
+        let single = MerkleTree::new(&leaves[..1], &params);
+        assert_eq!(single.depth(), 0);
+        assert!(!single.is_empty());
+        assert!(single.verify_proof(&leaves[0], &single.get_proof(0)));
+    }
+}
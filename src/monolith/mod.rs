@@ -0,0 +1,13 @@
+pub mod monolith;
+pub mod params;
+pub mod sponge;
+pub mod transcript;
+#[cfg(test)]
+mod tests;
+
+pub use self::monolith::*;
+pub use self::params::{
+    MonolithParams, MonolithParamsError, MONOLITH_NUM_BARS, MONOLITH_ROUNDS, MONOLITH_SUPPORTED_WIDTHS,
+};
+pub use self::sponge::MonolithSponge;
+pub use self::transcript::MonolithTranscript;
@@ -0,0 +1,125 @@
+use super::params::{MonolithParams, MONOLITH_NUM_BARS};
+use alloc::vec::Vec;
+use franklin_crypto::boojum::field::goldilocks::GoldilocksField;
+use franklin_crypto::boojum::field::{SmallField, U64Representable};
+
+/// Width of the instantiation used by `monolith_hash`.
+pub const MONOLITH_HASH_WIDTH: usize = 12;
+/// Rate of the instantiation used by `monolith_hash`.
+pub const MONOLITH_HASH_RATE: usize = 8;
+/// Number of field elements of a Monolith digest, i.e. the capacity.
+pub const MONOLITH_DIGEST_SIZE: usize = 4;
+
+/// Hashes an input of known length with Monolith of state-width=12 and
+/// rate=8. Same domain strategy as the big field hashes: the last capacity
+/// element is set to the input length and the input is padded with ones up
+/// to a multiple of the rate.
+pub fn monolith_hash(input: &[GoldilocksField]) -> [GoldilocksField; MONOLITH_DIGEST_SIZE] {
+    let params = MonolithParams::<MONOLITH_HASH_WIDTH>::default();
+
+    let mut state = [0u64; MONOLITH_HASH_WIDTH];
+    state[MONOLITH_HASH_WIDTH - 1] = input.len() as u64;
+
+    let mut padded: Vec<u64> = input.iter().map(|el| el.as_u64_reduced()).collect();
+    while padded.is_empty() || padded.len() % MONOLITH_HASH_RATE != 0 {
+        padded.push(1);
+    }
+
+    for chunk in padded.chunks_exact(MONOLITH_HASH_RATE) {
+        for (s, value) in state.iter_mut().zip(chunk.iter()) {
+            *s = add(*s, *value);
+        }
+        monolith_permutation_raw(&mut state, &params);
+    }
+
+    digest_of_state(&state)
+}
+
+pub(crate) fn digest_of_state<const WIDTH: usize>(state: &[u64; WIDTH]) -> [GoldilocksField; MONOLITH_DIGEST_SIZE] {
+    let mut digest = [GoldilocksField::from_u64_unchecked(0); MONOLITH_DIGEST_SIZE];
+    for (d, s) in digest.iter_mut().zip(state.iter()) {
+        *d = GoldilocksField::from_u64_unchecked(*s);
+    }
+    digest
+}
+
+/// Monolith permutation: the linear layer is applied to the state first, then
+/// each round applies bars to the first `MONOLITH_NUM_BARS` elements, bricks,
+/// the linear layer and adds round constants, the last round doesn't have
+/// them.
+pub fn monolith_permutation<const WIDTH: usize>(state: &mut [GoldilocksField; WIDTH], params: &MonolithParams<WIDTH>) {
+    let mut raw = [0u64; WIDTH];
+    for (r, el) in raw.iter_mut().zip(state.iter()) {
+        *r = el.as_u64_reduced();
+    }
+    monolith_permutation_raw(&mut raw, params);
+    for (el, r) in state.iter_mut().zip(raw.iter()) {
+        *el = GoldilocksField::from_u64_unchecked(*r);
+    }
+}
+
+pub(crate) fn monolith_permutation_raw<const WIDTH: usize>(state: &mut [u64; WIDTH], params: &MonolithParams<WIDTH>) {
+    concrete(state, &params.mds_row);
+    for round in 0..=params.round_constants.len() {
+        bars(state);
+        bricks(state);
+        concrete(state, &params.mds_row);
+        if let Some(constants) = params.round_constants.get(round) {
+            for (s, c) in state.iter_mut().zip(constants.iter()) {
+                *s = add(*s, *c);
+            }
+        }
+    }
+}
+
+// chi-like map of each byte, `y ^ (!(y <<< 1) & (y <<< 2) & (y <<< 3))`
+// rotated left by one, rotations are within the byte. Maps canonical
+// Goldilocks elements to canonical ones.
+fn bar(value: u64) -> u64 {
+    #[inline(always)]
+    fn rotl_bytes(value: u64, by: u32) -> u64 {
+        let high_mask = u64::from_ne_bytes([0xffu8 << (8 - by); 8]);
+        ((value & high_mask) >> (8 - by)) | ((value & !high_mask) << by)
+    }
+
+    let mixed = value ^ (!rotl_bytes(value, 1) & rotl_bytes(value, 2) & rotl_bytes(value, 3));
+    let result = rotl_bytes(mixed, 1);
+    debug_assert!(result < GoldilocksField::CHAR);
+
+    result
+}
+
+fn bars<const WIDTH: usize>(state: &mut [u64; WIDTH]) {
+    for el in state[..MONOLITH_NUM_BARS].iter_mut() {
+        *el = bar(*el);
+    }
+}
+
+// x_i += x_{i-1}^2 with squares of the previous values
+fn bricks<const WIDTH: usize>(state: &mut [u64; WIDTH]) {
+    for i in (1..WIDTH).rev() {
+        state[i] = add(state[i], mul(state[i - 1], state[i - 1]));
+    }
+}
+
+fn concrete<const WIDTH: usize>(state: &mut [u64; WIDTH], mds_row: &[u64; WIDTH]) {
+    let mut result = [0u64; WIDTH];
+    for (row, r) in result.iter_mut().enumerate() {
+        let mut acc = 0u128;
+        for (col, s) in state.iter().enumerate() {
+            acc += mds_row[(WIDTH + col - row) % WIDTH] as u128 * *s as u128;
+        }
+        *r = (acc % GoldilocksField::CHAR as u128) as u64;
+    }
+    *state = result;
+}
+
+#[inline(always)]
+pub(crate) fn add(a: u64, b: u64) -> u64 {
+    ((a as u128 + b as u128) % GoldilocksField::CHAR as u128) as u64
+}
+
+#[inline(always)]
+fn mul(a: u64, b: u64) -> u64 {
+    ((a as u128 * b as u128) % GoldilocksField::CHAR as u128) as u64
+}
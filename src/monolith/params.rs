@@ -0,0 +1,116 @@
+use alloc::vec::Vec;
+use franklin_crypto::boojum::field::goldilocks::GoldilocksField;
+use franklin_crypto::boojum::field::SmallField;
+
+/// Seed of the SHAKE128 stream deriving round constants.
+pub(crate) const ROUND_CONSTANTS_SEED: &[u8] = b"Monolith";
+
+/// Number of rounds of Monolith-64, the last one doesn't add constants.
+pub const MONOLITH_ROUNDS: usize = 6;
+
+/// Number of leading state elements the bars layer is applied to.
+pub const MONOLITH_NUM_BARS: usize = 4;
+
+/// State widths Monolith params can be constructed for, only these ones have
+/// an MDS circulant matrix defined.
+pub const MONOLITH_SUPPORTED_WIDTHS: &[usize] = &[8, 12];
+
+const MDS_ROW_WIDTH_8: [u64; 8] = [23, 8, 13, 10, 7, 6, 21, 8];
+const MDS_ROW_WIDTH_12: [u64; 12] = [7, 23, 8, 26, 13, 10, 9, 7, 6, 22, 21, 8];
+
+/// Params of the Monolith-64 permutation https://eprint.iacr.org/2023/1025
+/// over the Goldilocks field. Values are kept as canonical `u64`
+/// representatives since the bars layer works on their bytes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MonolithParams<const WIDTH: usize> {
+    pub(crate) mds_row: [u64; WIDTH],
+    pub(crate) round_constants: Vec<[u64; WIDTH]>,
+}
+
+/// Errors of constructing Monolith params.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum MonolithParamsError {
+    UnsupportedWidth(usize),
+}
+
+impl core::fmt::Display for MonolithParamsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnsupportedWidth(width) => write!(
+                f,
+                "Monolith is not supported for width {}, supported widths are {:?}",
+                width, MONOLITH_SUPPORTED_WIDTHS
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for MonolithParamsError {}
+
+impl<const WIDTH: usize> Default for MonolithParams<WIDTH> {
+    /// Panics for widths out of `MONOLITH_SUPPORTED_WIDTHS`, see `try_default`.
+    fn default() -> Self {
+        Self::try_default().unwrap_or_else(|err| panic!("{}", err))
+    }
+}
+
+impl<const WIDTH: usize> MonolithParams<WIDTH> {
+    /// Default params, unsupported width is reported here instead of in the
+    /// middle of the first hash.
+    pub fn try_default() -> Result<Self, MonolithParamsError> {
+        let mds_row: &[u64] = match WIDTH {
+            8 => &MDS_ROW_WIDTH_8,
+            12 => &MDS_ROW_WIDTH_12,
+            _ => return Err(MonolithParamsError::UnsupportedWidth(WIDTH)),
+        };
+
+        Ok(Self {
+            mds_row: mds_row.try_into().expect("width is checked above"),
+            round_constants: compute_round_constants::<WIDTH>(),
+        })
+    }
+
+    /// First row of the circulant linear layer, row `i` is this one rotated
+    /// right by `i`.
+    pub fn mds_row(&self) -> &[u64; WIDTH] {
+        &self.mds_row
+    }
+
+    /// Constants added at the end of each round except the last one.
+    pub fn round_constants(&self) -> &[[u64; WIDTH]] {
+        &self.round_constants
+    }
+}
+
+// SHAKE128 over `seed || width || rounds || p as 8 le bytes || 8 limb sizes of
+// the bars`, constants are read as 8 byte little endian words and rejected if
+// not less than the modulus.
+fn compute_round_constants<const WIDTH: usize>() -> Vec<[u64; WIDTH]> {
+    use sha3::digest::{ExtendableOutput, Update, XofReader};
+    use sha3::Shake128;
+
+    let mut shake = Shake128::default();
+    shake.update(ROUND_CONSTANTS_SEED);
+    shake.update([WIDTH as u8, MONOLITH_ROUNDS as u8]);
+    shake.update(GoldilocksField::CHAR.to_le_bytes());
+    shake.update([8u8; 8]);
+    let mut reader = shake.finalize_xof();
+
+    let mut next_constant = || loop {
+        let mut bytes = [0u8; 8];
+        reader.read(&mut bytes);
+        let value = u64::from_le_bytes(bytes);
+        if value < GoldilocksField::CHAR {
+            return value;
+        }
+    };
+
+    (0..MONOLITH_ROUNDS - 1)
+        .map(|_| {
+            let mut constants = [0u64; WIDTH];
+            constants.iter_mut().for_each(|c| *c = next_constant());
+            constants
+        })
+        .collect()
+}
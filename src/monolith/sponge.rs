@@ -0,0 +1,185 @@
+use super::monolith::{add, digest_of_state, monolith_permutation_raw, MONOLITH_DIGEST_SIZE};
+use super::params::MonolithParams;
+
+use derivative::*;
+use franklin_crypto::boojum::cs::oracle::TreeHasher;
+use franklin_crypto::boojum::field::goldilocks::GoldilocksField;
+use franklin_crypto::boojum::field::U64Representable;
+
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use std::sync::RwLock;
+#[cfg(feature = "std")]
+use typemap_rev::{TypeMap, TypeMapKey};
+
+#[cfg(feature = "std")]
+impl<const WIDTH: usize> TypeMapKey for MonolithParams<WIDTH> {
+    type Value = Arc<MonolithParams<WIDTH>>;
+}
+
+#[cfg(feature = "std")]
+fn cached_params<const WIDTH: usize>() -> Arc<MonolithParams<WIDTH>> {
+    lazy_static::lazy_static! {
+        static ref MONOLITH_PARAMS: RwLock<TypeMap> = RwLock::new(TypeMap::new());
+    };
+
+    let static_params = MONOLITH_PARAMS.read().unwrap();
+    let params = static_params.get::<MonolithParams<WIDTH>>().cloned();
+    drop(static_params);
+
+    if let Some(params) = params {
+        params
+    } else {
+        let params = Arc::new(MonolithParams::<WIDTH>::default());
+        let mut static_params = MONOLITH_PARAMS.write().unwrap();
+        static_params.insert::<MonolithParams<WIDTH>>(params.clone());
+        params
+    }
+}
+
+#[cfg(not(feature = "std"))]
+fn cached_params<const WIDTH: usize>() -> Arc<MonolithParams<WIDTH>> {
+    Arc::new(MonolithParams::<WIDTH>::default())
+}
+
+/// Goldilocks sponge over the Monolith permutation, elements are added to
+/// the rate one at a time and the digest is the first `MONOLITH_DIGEST_SIZE`
+/// elements of the state. Usable as a boojum `TreeHasher`.
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub struct MonolithSponge<const RATE: usize, const WIDTH: usize> {
+    pub(crate) state: [u64; WIDTH],
+    pub(crate) filled: usize,
+    #[derivative(Debug = "ignore")]
+    pub(crate) params: Arc<MonolithParams<WIDTH>>,
+}
+
+impl<const RATE: usize, const WIDTH: usize> MonolithSponge<RATE, WIDTH> {
+    pub fn new() -> Self {
+        assert!(RATE + MONOLITH_DIGEST_SIZE <= WIDTH, "capacity must fit the digest");
+
+        Self {
+            state: [0u64; WIDTH],
+            filled: 0,
+            params: cached_params::<WIDTH>(),
+        }
+    }
+
+    pub fn rate(&self) -> usize {
+        RATE
+    }
+
+    pub fn capacity(&self) -> usize {
+        WIDTH - RATE
+    }
+
+    pub fn run_round_function(&mut self) {
+        monolith_permutation_raw(&mut self.state, &self.params);
+    }
+
+    pub fn absorb_single(&mut self, value: &GoldilocksField) {
+        self.state[self.filled] = add(self.state[self.filled], value.as_u64_reduced());
+        self.filled += 1;
+        if self.filled == RATE {
+            self.run_round_function();
+            self.filled = 0;
+        }
+    }
+
+    pub fn absorb(&mut self, values: &[GoldilocksField]) {
+        for value in values.iter() {
+            self.absorb_single(value);
+        }
+    }
+
+    /// Pads pending elements with a single `ONE` and permutes, so every
+    /// finalization costs a permutation even with nothing pending.
+    pub(crate) fn pad_and_permute(&mut self) {
+        self.absorb_single(&GoldilocksField::from_u64_unchecked(1));
+        if self.filled != 0 {
+            self.run_round_function();
+            self.filled = 0;
+        }
+    }
+
+    pub(crate) fn rate_elements(&self) -> [GoldilocksField; RATE] {
+        let mut result = [GoldilocksField::from_u64_unchecked(0); RATE];
+        for (r, s) in result.iter_mut().zip(self.state.iter()) {
+            *r = GoldilocksField::from_u64_unchecked(*s);
+        }
+        result
+    }
+
+    pub fn finalize(&mut self) -> [GoldilocksField; MONOLITH_DIGEST_SIZE] {
+        self.pad_and_permute();
+        digest_of_state(&self.state)
+    }
+
+    pub fn finalize_reset(&mut self) -> [GoldilocksField; MONOLITH_DIGEST_SIZE] {
+        let digest = self.finalize();
+        self.state = [0u64; WIDTH];
+        digest
+    }
+}
+
+impl<const RATE: usize, const WIDTH: usize> TreeHasher<GoldilocksField> for MonolithSponge<RATE, WIDTH> {
+    type Output = [GoldilocksField; MONOLITH_DIGEST_SIZE];
+
+    #[inline]
+    fn new() -> Self {
+        Self::new()
+    }
+
+    #[inline]
+    fn placeholder_output() -> Self::Output {
+        [GoldilocksField::from_u64_unchecked(0); MONOLITH_DIGEST_SIZE]
+    }
+
+    #[inline]
+    fn accumulate_into_leaf(&mut self, value: &GoldilocksField) {
+        self.absorb_single(value);
+    }
+
+    #[inline]
+    fn finalize_into_leaf_hash_and_reset(&mut self) -> Self::Output {
+        self.finalize_reset()
+    }
+
+    #[inline]
+    fn hash_into_leaf<'a, S: IntoIterator<Item = &'a GoldilocksField>>(source: S) -> Self::Output
+    where
+        GoldilocksField: 'a,
+    {
+        let mut hasher = Self::new();
+
+        for el in source.into_iter() {
+            hasher.absorb_single(el);
+        }
+        hasher.finalize()
+    }
+
+    #[inline]
+    fn hash_into_leaf_owned<S: IntoIterator<Item = GoldilocksField>>(source: S) -> Self::Output {
+        let mut hasher = Self::new();
+
+        for el in source.into_iter() {
+            hasher.absorb_single(&el);
+        }
+        hasher.finalize()
+    }
+
+    /// Both children are placed into the rate of a zero state and permuted
+    /// once, without padding.
+    #[inline]
+    fn hash_into_node(left: &Self::Output, right: &Self::Output, _depth: usize) -> Self::Output {
+        assert!(2 * MONOLITH_DIGEST_SIZE <= RATE, "rate must fit both children");
+
+        let mut state = [0u64; WIDTH];
+        for (s, el) in state.iter_mut().zip(left.iter().chain(right.iter())) {
+            *s = el.as_u64_reduced();
+        }
+        monolith_permutation_raw(&mut state, &cached_params::<WIDTH>());
+
+        digest_of_state(&state)
+    }
+}
@@ -0,0 +1,142 @@
+use super::*;
+use franklin_crypto::boojum::cs::implementations::transcript::Transcript;
+use franklin_crypto::boojum::cs::oracle::TreeHasher;
+use franklin_crypto::boojum::field::goldilocks::GoldilocksField;
+use franklin_crypto::boojum::field::{SmallField, U64Representable};
+use rand::Rng;
+
+fn random_elements(n: usize) -> Vec<GoldilocksField> {
+    let rng = &mut crate::tests::init_rng();
+    (0..n)
+        .map(|_| GoldilocksField::from_u64_unchecked(rng.gen_range(0, GoldilocksField::CHAR)))
+        .collect()
+}
+
+#[test]
+fn test_monolith_params() {
+    let params = MonolithParams::<12>::default();
+    assert_eq!(params.round_constants().len(), MONOLITH_ROUNDS - 1);
+    assert!(params
+        .round_constants()
+        .iter()
+        .flatten()
+        .all(|c| *c < GoldilocksField::CHAR));
+    assert_eq!(params, MonolithParams::<12>::default());
+    assert_ne!(MonolithParams::<8>::default().round_constants()[0][..], params.round_constants()[0][..8]);
+
+    assert_eq!(
+        MonolithParams::<3>::try_default(),
+        Err(MonolithParamsError::UnsupportedWidth(3))
+    );
+}
+
+#[test]
+fn test_monolith_permutation() {
+    fn check<const WIDTH: usize>() {
+        let params = MonolithParams::<WIDTH>::default();
+        let input: [GoldilocksField; WIDTH] = random_elements(WIDTH).try_into().unwrap();
+
+        let mut state = input;
+        monolith_permutation(&mut state, &params);
+        let mut again = input;
+        monolith_permutation(&mut again, &params);
+        assert_eq!(state, again);
+        assert_ne!(state, input);
+
+        let mut edge = [GoldilocksField::from_u64_unchecked(GoldilocksField::CHAR - 1); WIDTH];
+        monolith_permutation(&mut edge, &params);
+        assert!(edge.iter().all(|el| el.as_u64_reduced() < GoldilocksField::CHAR));
+    }
+
+    check::<8>();
+    check::<12>();
+}
+
+#[test]
+fn test_monolith_permutation_kat() {
+    fn check<const WIDTH: usize>(expected: [u64; WIDTH]) {
+        let params = MonolithParams::<WIDTH>::default();
+        let mut state: [_; WIDTH] = core::array::from_fn(|i| GoldilocksField::from_u64_unchecked(i as u64));
+        monolith_permutation(&mut state, &params);
+        assert_eq!(state.map(|el| el.as_u64_reduced()), expected);
+    }
+
+    // outputs of the reference implementation for 0, 1, .., WIDTH - 1
+    check::<8>([
+        0x32be4af2d3128873,
+        0x0f1a0f8342e9cc5f,
+        0x005180db40168b13,
+        0xc85083fc2122a614,
+        0x60e4e895c111c4b7,
+        0xe4e1ea35d94ba42a,
+        0xf99bc1dc57d18ee5,
+        0x7f23d5656dda898f,
+    ]);
+    check::<12>([
+        0x516dd661e959f541,
+        0x082c137169707901,
+        0x53dff3fd9f0a5beb,
+        0x0b2ebaa261590650,
+        0x89aadb57e2969cb6,
+        0x5d3d6905970259bd,
+        0x6e5ac1a4c0cfa0fe,
+        0xd674b7736abfc5ce,
+        0x0d8697e1cd9a235f,
+        0x85fc4017c247136e,
+        0x572bafd76e511424,
+        0xbec1638e28eae57f,
+    ]);
+}
+
+#[test]
+fn test_monolith_hash_domain() {
+    let input = random_elements(3);
+    let digest = monolith_hash(&input);
+    assert_eq!(digest, monolith_hash(&input));
+
+    let mut padded = input.clone();
+    padded.push(GoldilocksField::from_u64_unchecked(1));
+    assert_ne!(digest, monolith_hash(&padded));
+    assert_ne!(monolith_hash(&[]), monolith_hash(&[GoldilocksField::from_u64_unchecked(1)]));
+}
+
+#[test]
+fn test_monolith_tree_hasher() {
+    type Hasher = MonolithSponge<MONOLITH_HASH_RATE, MONOLITH_HASH_WIDTH>;
+
+    let leaf = random_elements(11);
+    let expected = Hasher::hash_into_leaf(&leaf);
+    assert_eq!(expected, Hasher::hash_into_leaf_owned(leaf.clone()));
+
+    let mut hasher = Hasher::new();
+    for _ in 0..2 {
+        for el in leaf.iter() {
+            hasher.accumulate_into_leaf(el);
+        }
+        assert_eq!(hasher.finalize_into_leaf_hash_and_reset(), expected);
+    }
+
+    let other = Hasher::hash_into_leaf(&leaf[1..]);
+    assert_ne!(
+        Hasher::hash_into_node(&expected, &other, 0),
+        Hasher::hash_into_node(&other, &expected, 0)
+    );
+}
+
+#[test]
+fn test_monolith_transcript() {
+    type T = MonolithTranscript<MONOLITH_HASH_RATE, MONOLITH_HASH_WIDTH>;
+
+    let elements = random_elements(5);
+    let challenges = |elements: &[GoldilocksField]| {
+        let mut transcript = T::new();
+        transcript.witness_field_elements(elements);
+        // more than the rate to run out of buffered challenges
+        (0..MONOLITH_HASH_RATE + 2).map(|_| transcript.get_challenge()).collect::<Vec<_>>()
+    };
+
+    let first = challenges(&elements);
+    assert_eq!(first, challenges(&elements));
+    assert_ne!(first, challenges(&elements[1..]));
+    assert_ne!(first[0], first[MONOLITH_HASH_RATE]);
+}
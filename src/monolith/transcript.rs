@@ -0,0 +1,74 @@
+use super::monolith::MONOLITH_DIGEST_SIZE;
+use super::sponge::MonolithSponge;
+
+use alloc::vec::Vec;
+use derivative::*;
+use franklin_crypto::boojum::cs::implementations::transcript::Transcript;
+use franklin_crypto::boojum::field::goldilocks::GoldilocksField;
+use alloc::collections::VecDeque;
+
+/// Goldilocks transcript over `MonolithSponge`. Interaction model is the same
+/// as of `Poseidon2Transcript`: witnessed elements are buffered and absorbed
+/// when the first challenge after them is requested, challenges are the rate
+/// elements of the state after padding and permuting.
+#[derive(Derivative)]
+#[derivative(Clone, Debug)]
+pub struct MonolithTranscript<const RATE: usize, const WIDTH: usize> {
+    buffer: Vec<GoldilocksField>,
+    available_challenges: VecDeque<GoldilocksField>,
+    #[derivative(Debug = "ignore")]
+    sponge: MonolithSponge<RATE, WIDTH>,
+}
+
+impl<const RATE: usize, const WIDTH: usize> MonolithTranscript<RATE, WIDTH> {
+    pub fn new() -> Self {
+        Self {
+            buffer: Vec::new(),
+            available_challenges: VecDeque::new(),
+            sponge: MonolithSponge::<RATE, WIDTH>::new(),
+        }
+    }
+}
+
+impl<const RATE: usize, const WIDTH: usize> Transcript<GoldilocksField> for MonolithTranscript<RATE, WIDTH> {
+    type CompatibleCap = [GoldilocksField; MONOLITH_DIGEST_SIZE];
+    type TransciptParameters = ();
+
+    const IS_ALGEBRAIC: bool = true;
+
+    fn new(_params: Self::TransciptParameters) -> Self {
+        Self::new()
+    }
+
+    fn witness_field_elements(&mut self, field_els: &[GoldilocksField]) {
+        self.buffer.extend_from_slice(field_els);
+        self.available_challenges = VecDeque::new();
+    }
+
+    fn witness_merkle_tree_cap(&mut self, cap: &[Self::CompatibleCap]) {
+        for el in cap.iter() {
+            self.buffer.extend_from_slice(el);
+        }
+        self.available_challenges = VecDeque::new();
+    }
+
+    fn get_challenge(&mut self) -> GoldilocksField {
+        assert_eq!(self.sponge.filled, 0);
+
+        if let Some(challenge) = self.available_challenges.pop_front() {
+            return challenge;
+        }
+
+        if self.buffer.is_empty() {
+            self.sponge.run_round_function();
+        } else {
+            let to_absorb = core::mem::take(&mut self.buffer);
+            self.sponge.absorb(&to_absorb);
+            self.sponge.pad_and_permute();
+        }
+        self.available_challenges.extend(self.sponge.rate_elements());
+
+        // to avoid duplication
+        self.get_challenge()
+    }
+}
@@ -0,0 +1,636 @@
+//! Precomputed params stored as a flat binary blob.
+//!
+//! Generating params (round constants, MDS matrixes, optimized schedules)
+//! is expensive, so a process can generate them once with
+//! `generate_params_blob` and every other process loads the blob instead.
+//! With `mmap` feature the blob can be mapped directly from a file.
+//!
+//! Layout is a sequence of little endian `u64` words:
+//! magic, version, family, rate, width, 4 words of fingerprint, number of
+//! payload words, payload. Field elements are stored as limbs of their
+//! canonical representation. When blob is 8-byte aligned (which is always
+//! the case for a mapped file) words are read in place, otherwise they are
+//! copied into an aligned buffer first. Each element is checked to be
+//! canonical and fingerprint of the decoded params must match the header.
+use crate::poseidon2::Poseidon2Params;
+use crate::traits::{CustomGate, HashFamily, HashParams, Sbox, Step};
+use crate::{PoseidonParams, RescueParams, RescuePrimeParams};
+use franklin_crypto::bellman::{Engine, Field, PrimeField};
+use std::borrow::Cow;
+use std::convert::TryInto;
+
+const MAGIC: u64 = u64::from_le_bytes(*b"RPPARAMS");
+const VERSION: u64 = 1;
+const HEADER_WORDS: usize = 10;
+
+/// Errors of loading a params blob.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamsBlobError {
+    /// Blob doesn't start with the expected magic value.
+    BadMagic,
+    /// Blob was generated by an unknown version of the format.
+    UnsupportedVersion(u64),
+    /// Blob holds params of another family, rate or width.
+    ParamsMismatch,
+    /// Blob ends before all values are read or has trailing words.
+    InvalidLength,
+    /// Encoded field element is not in canonical form.
+    InvalidFieldElement,
+    /// Enum tag doesn't match any variant.
+    InvalidTag,
+    /// Fingerprint of the decoded params differs from the one in header.
+    FingerprintMismatch,
+}
+
+impl std::fmt::Display for ParamsBlobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::BadMagic => write!(f, "not a params blob"),
+            Self::UnsupportedVersion(version) => write!(f, "unsupported params blob version {}", version),
+            Self::ParamsMismatch => write!(f, "params blob is for another family, rate or width"),
+            Self::InvalidLength => write!(f, "params blob has invalid length"),
+            Self::InvalidFieldElement => write!(f, "params blob contains non canonical field element"),
+            Self::InvalidTag => write!(f, "params blob contains unknown tag"),
+            Self::FingerprintMismatch => write!(f, "params blob fingerprint mismatch"),
+        }
+    }
+}
+
+impl std::error::Error for ParamsBlobError {}
+
+/// Params which can be stored in a blob.
+pub trait BlobParams<E: Engine, const RATE: usize, const WIDTH: usize>:
+    HashParams<E, RATE, WIDTH> + Sized
+{
+    const FAMILY: HashFamily;
+
+    fn write_payload(&self, writer: &mut BlobWriter<E>);
+    fn read_payload(reader: &mut BlobReader<'_, E>) -> Result<Self, ParamsBlobError>;
+}
+
+/// Encodes params into a blob.
+pub fn generate_params_blob<E, P, const RATE: usize, const WIDTH: usize>(params: &P) -> Vec<u8>
+where
+    E: Engine,
+    P: BlobParams<E, RATE, WIDTH>,
+{
+    let mut writer = BlobWriter::<E>::new();
+    params.write_payload(&mut writer);
+    let payload = writer.words;
+
+    let mut words = Vec::with_capacity(HEADER_WORDS + payload.len());
+    words.push(MAGIC);
+    words.push(VERSION);
+    words.push(params.hash_family() as u64);
+    words.push(RATE as u64);
+    words.push(WIDTH as u64);
+    for chunk in params.fingerprint().chunks(8) {
+        words.push(u64::from_le_bytes(chunk.try_into().expect("8 bytes")));
+    }
+    words.push(payload.len() as u64);
+    words.extend(payload);
+
+    words.iter().flat_map(|w| w.to_le_bytes()).collect()
+}
+
+/// Decodes params from a blob produced by `generate_params_blob`.
+pub fn load_params_blob<E, P, const RATE: usize, const WIDTH: usize>(
+    blob: &[u8],
+) -> Result<P, ParamsBlobError>
+where
+    E: Engine,
+    P: BlobParams<E, RATE, WIDTH>,
+{
+    let words = blob_words(blob)?;
+    if words.len() < HEADER_WORDS {
+        return Err(ParamsBlobError::InvalidLength);
+    }
+    if words[0] != MAGIC {
+        return Err(ParamsBlobError::BadMagic);
+    }
+    if words[1] != VERSION {
+        return Err(ParamsBlobError::UnsupportedVersion(words[1]));
+    }
+    if words[2] != P::FAMILY as u64 || words[3] != RATE as u64 || words[4] != WIDTH as u64 {
+        return Err(ParamsBlobError::ParamsMismatch);
+    }
+    if words[9] != (words.len() - HEADER_WORDS) as u64 {
+        return Err(ParamsBlobError::InvalidLength);
+    }
+
+    let mut reader = BlobReader::<E>::new(&words[HEADER_WORDS..]);
+    let params = P::read_payload(&mut reader)?;
+    if !reader.is_empty() {
+        return Err(ParamsBlobError::InvalidLength);
+    }
+    let fingerprint: Vec<u8> = words[5..9].iter().flat_map(|w| w.to_le_bytes()).collect();
+    if params.fingerprint()[..] != fingerprint[..] {
+        return Err(ParamsBlobError::FingerprintMismatch);
+    }
+
+    Ok(params)
+}
+
+/// Writes blob of the params into a file.
+pub fn store_params_blob<E, P, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    path: impl AsRef<std::path::Path>,
+) -> std::io::Result<()>
+where
+    E: Engine,
+    P: BlobParams<E, RATE, WIDTH>,
+{
+    std::fs::write(path, generate_params_blob(params))
+}
+
+/// Maps a file written by `store_params_blob` and decodes params from it.
+#[cfg(feature = "mmap")]
+pub fn map_params_blob<E, P, const RATE: usize, const WIDTH: usize>(
+    path: impl AsRef<std::path::Path>,
+) -> std::io::Result<P>
+where
+    E: Engine,
+    P: BlobParams<E, RATE, WIDTH>,
+{
+    let file = std::fs::File::open(path)?;
+    // Safety: mapping is only read during this call, the file is expected
+    // not to be modified concurrently and any content is validated.
+    let mmap = unsafe { memmap2::Mmap::map(&file)? };
+    load_params_blob(&mmap[..]).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+}
+
+// Reinterprets aligned blob as words in place, otherwise copies it.
+fn blob_words(blob: &[u8]) -> Result<Cow<'_, [u64]>, ParamsBlobError> {
+    if blob.len() % 8 != 0 {
+        return Err(ParamsBlobError::InvalidLength);
+    }
+    if cfg!(target_endian = "little") {
+        // Safety: any bit pattern is a valid u64
+        let (prefix, words, suffix) = unsafe { blob.align_to::<u64>() };
+        if prefix.is_empty() && suffix.is_empty() {
+            return Ok(Cow::Borrowed(words));
+        }
+    }
+
+    Ok(Cow::Owned(
+        blob.chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().expect("8 bytes")))
+            .collect(),
+    ))
+}
+
+pub struct BlobWriter<E: Engine> {
+    words: Vec<u64>,
+    _marker: std::marker::PhantomData<E>,
+}
+
+impl<E: Engine> BlobWriter<E> {
+    fn new() -> Self {
+        Self {
+            words: vec![],
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    pub fn write_u64(&mut self, value: u64) {
+        self.words.push(value);
+    }
+
+    pub fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    pub fn write_bool(&mut self, value: bool) {
+        self.write_u64(value as u64);
+    }
+
+    pub fn write_fr(&mut self, el: &E::Fr) {
+        self.words.extend_from_slice(el.into_repr().as_ref());
+    }
+
+    pub fn write_frs(&mut self, elements: &[E::Fr]) {
+        for el in elements.iter() {
+            self.write_fr(el);
+        }
+    }
+
+    pub fn write_rows<const WIDTH: usize>(&mut self, rows: &[[E::Fr; WIDTH]]) {
+        self.write_usize(rows.len());
+        for row in rows.iter() {
+            self.write_frs(row);
+        }
+    }
+
+    pub fn write_matrixes<const WIDTH: usize>(&mut self, matrixes: &[[[E::Fr; WIDTH]; WIDTH]]) {
+        self.write_usize(matrixes.len());
+        for matrix in matrixes.iter() {
+            for row in matrix.iter() {
+                self.write_frs(row);
+            }
+        }
+    }
+
+    pub fn write_sbox(&mut self, sbox: &Sbox) {
+        match sbox {
+            Sbox::Alpha(alpha) => {
+                self.write_u64(0);
+                self.write_u64(*alpha);
+            }
+            Sbox::AlphaInverse(alpha_inv, alpha) => {
+                self.write_u64(1);
+                self.write_u64(*alpha);
+                self.write_usize(alpha_inv.len());
+                self.words.extend_from_slice(alpha_inv);
+            }
+            Sbox::AddChain(chain, alpha) => {
+                self.write_u64(2);
+                self.write_u64(*alpha);
+                self.write_usize(chain.len());
+                for step in chain.iter() {
+                    match step {
+                        Step::Double { index } => {
+                            self.write_u64(0);
+                            self.write_usize(*index);
+                        }
+                        Step::Add { left, right } => {
+                            self.write_u64(1);
+                            self.write_usize(*left);
+                            self.write_usize(*right);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn write_custom_gate(&mut self, gate: CustomGate) {
+        self.write_u64(match gate {
+            CustomGate::QuinticWidth4 => 0,
+            CustomGate::QuinticWidth3 => 1,
+            CustomGate::None => 2,
+        });
+    }
+}
+
+pub struct BlobReader<'a, E: Engine> {
+    words: &'a [u64],
+    _marker: std::marker::PhantomData<E>,
+}
+
+impl<'a, E: Engine> BlobReader<'a, E> {
+    fn new(words: &'a [u64]) -> Self {
+        Self {
+            words,
+            _marker: std::marker::PhantomData,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.words.is_empty()
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u64], ParamsBlobError> {
+        if n > self.words.len() {
+            return Err(ParamsBlobError::InvalidLength);
+        }
+        let (head, tail) = self.words.split_at(n);
+        self.words = tail;
+
+        Ok(head)
+    }
+
+    pub fn read_u64(&mut self) -> Result<u64, ParamsBlobError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub fn read_usize(&mut self) -> Result<usize, ParamsBlobError> {
+        self.read_u64()?
+            .try_into()
+            .map_err(|_| ParamsBlobError::InvalidLength)
+    }
+
+    // Lengths are bounded by the number of remaining words so corrupted
+    // blob can't request a huge allocation.
+    fn read_len(&mut self, words_per_item: usize) -> Result<usize, ParamsBlobError> {
+        let len = self.read_usize()?;
+        if len.saturating_mul(words_per_item) > self.words.len() {
+            return Err(ParamsBlobError::InvalidLength);
+        }
+
+        Ok(len)
+    }
+
+    pub fn read_bool(&mut self) -> Result<bool, ParamsBlobError> {
+        match self.read_u64()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(ParamsBlobError::InvalidTag),
+        }
+    }
+
+    pub fn read_fr(&mut self) -> Result<E::Fr, ParamsBlobError> {
+        let mut repr = <E::Fr as PrimeField>::Repr::default();
+        let num_limbs = repr.as_ref().len();
+        repr.as_mut().copy_from_slice(self.take(num_limbs)?);
+
+        E::Fr::from_repr(repr).map_err(|_| ParamsBlobError::InvalidFieldElement)
+    }
+
+    pub fn read_frs<const N: usize>(&mut self) -> Result<[E::Fr; N], ParamsBlobError> {
+        let mut elements = [E::Fr::zero(); N];
+        for el in elements.iter_mut() {
+            *el = self.read_fr()?;
+        }
+
+        Ok(elements)
+    }
+
+    pub fn read_matrix<const WIDTH: usize>(&mut self) -> Result<[[E::Fr; WIDTH]; WIDTH], ParamsBlobError> {
+        let mut matrix = [[E::Fr::zero(); WIDTH]; WIDTH];
+        for row in matrix.iter_mut() {
+            *row = self.read_frs()?;
+        }
+
+        Ok(matrix)
+    }
+
+    pub fn read_rows<const WIDTH: usize>(&mut self) -> Result<Vec<[E::Fr; WIDTH]>, ParamsBlobError> {
+        let len = self.read_len(WIDTH)?;
+        (0..len).map(|_| self.read_frs()).collect()
+    }
+
+    pub fn read_matrixes<const WIDTH: usize>(
+        &mut self,
+    ) -> Result<Vec<[[E::Fr; WIDTH]; WIDTH]>, ParamsBlobError> {
+        let len = self.read_len(WIDTH * WIDTH)?;
+        (0..len).map(|_| self.read_matrix()).collect()
+    }
+
+    pub fn read_sbox(&mut self) -> Result<Sbox, ParamsBlobError> {
+        let tag = self.read_u64()?;
+        let alpha = self.read_u64()?;
+        match tag {
+            0 => Ok(Sbox::Alpha(alpha)),
+            1 => {
+                let len = self.read_len(1)?;
+                Ok(Sbox::AlphaInverse(self.take(len)?.to_vec(), alpha))
+            }
+            2 => {
+                let len = self.read_len(2)?;
+                let mut chain = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let step = match self.read_u64()? {
+                        0 => Step::Double {
+                            index: self.read_usize()?,
+                        },
+                        1 => Step::Add {
+                            left: self.read_usize()?,
+                            right: self.read_usize()?,
+                        },
+                        _ => return Err(ParamsBlobError::InvalidTag),
+                    };
+                    chain.push(step);
+                }
+                Ok(Sbox::AddChain(chain, alpha))
+            }
+            _ => Err(ParamsBlobError::InvalidTag),
+        }
+    }
+
+    pub fn read_custom_gate(&mut self) -> Result<CustomGate, ParamsBlobError> {
+        match self.read_u64()? {
+            0 => Ok(CustomGate::QuinticWidth4),
+            1 => Ok(CustomGate::QuinticWidth3),
+            2 => Ok(CustomGate::None),
+            _ => Err(ParamsBlobError::InvalidTag),
+        }
+    }
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> BlobParams<E, RATE, WIDTH>
+    for PoseidonParams<E, RATE, WIDTH>
+{
+    const FAMILY: HashFamily = HashFamily::Poseidon;
+
+    fn write_payload(&self, writer: &mut BlobWriter<E>) {
+        writer.write_frs(&self.state);
+        writer.write_matrixes(std::slice::from_ref(&self.mds_matrix));
+        writer.write_rows(&self.round_constants);
+        writer.write_rows(&self.optimized_round_constants);
+        writer.write_matrixes(std::slice::from_ref(&self.optimized_mds_matrixes_0));
+        writer.write_matrixes(&self.optimized_mds_matrixes_1);
+        writer.write_sbox(&self.alpha);
+        writer.write_usize(self.full_rounds);
+        writer.write_usize(self.partial_rounds);
+        writer.write_custom_gate(self.custom_gate);
+        writer.write_bool(self.circular_full_rounds_mds);
+    }
+
+    fn read_payload(reader: &mut BlobReader<'_, E>) -> Result<Self, ParamsBlobError> {
+        let state = reader.read_frs()?;
+        let mds_matrix = read_single_matrix(reader)?;
+        let round_constants = reader.read_rows()?;
+        let optimized_round_constants = reader.read_rows()?;
+        let optimized_mds_matrixes_0 = read_single_matrix(reader)?;
+        let optimized_mds_matrixes_1 = reader.read_matrixes()?;
+        let alpha = reader.read_sbox()?;
+        let full_rounds = reader.read_usize()?;
+        let partial_rounds = reader.read_usize()?;
+        let custom_gate = reader.read_custom_gate()?;
+        let circular_full_rounds_mds = reader.read_bool()?;
+        // circ(2, 1, 1) is MDS only for state width 3
+        if circular_full_rounds_mds && WIDTH != 3 {
+            return Err(ParamsBlobError::ParamsMismatch);
+        }
+
+        Ok(Self {
+            state,
+            mds_matrix,
+            round_constants,
+            optimized_round_constants,
+            optimized_mds_matrixes_0,
+            optimized_mds_matrixes_1,
+            alpha,
+            full_rounds,
+            partial_rounds,
+            custom_gate,
+            circular_full_rounds_mds,
+        })
+    }
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> BlobParams<E, RATE, WIDTH>
+    for Poseidon2Params<E, RATE, WIDTH>
+{
+    const FAMILY: HashFamily = HashFamily::Poseidon2;
+
+    fn write_payload(&self, writer: &mut BlobWriter<E>) {
+        writer.write_matrixes(std::slice::from_ref(&self.mds_external_matrix));
+        writer.write_frs(&self.diag_internal_matrix);
+        writer.write_rows(&self.round_constants);
+        writer.write_sbox(&self.alpha);
+        writer.write_usize(self.full_rounds);
+        writer.write_usize(self.partial_rounds);
+        writer.write_custom_gate(self.custom_gate);
+    }
+
+    fn read_payload(reader: &mut BlobReader<'_, E>) -> Result<Self, ParamsBlobError> {
+        Ok(Self {
+            mds_external_matrix: read_single_matrix(reader)?,
+            diag_internal_matrix: reader.read_frs()?,
+            round_constants: reader.read_rows()?,
+            alpha: reader.read_sbox()?,
+            full_rounds: reader.read_usize()?,
+            partial_rounds: reader.read_usize()?,
+            custom_gate: reader.read_custom_gate()?,
+        })
+    }
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> BlobParams<E, RATE, WIDTH>
+    for RescueParams<E, RATE, WIDTH>
+{
+    const FAMILY: HashFamily = HashFamily::Rescue;
+
+    fn write_payload(&self, writer: &mut BlobWriter<E>) {
+        writer.write_bool(self.allows_specialization);
+        writer.write_usize(self.full_rounds);
+        writer.write_rows(&self.round_constants);
+        writer.write_matrixes(std::slice::from_ref(&self.mds_matrix));
+        writer.write_sbox(&self.alpha);
+        writer.write_sbox(&self.alpha_inv);
+        writer.write_custom_gate(self.custom_gate);
+    }
+
+    fn read_payload(reader: &mut BlobReader<'_, E>) -> Result<Self, ParamsBlobError> {
+        Ok(Self {
+            allows_specialization: reader.read_bool()?,
+            full_rounds: reader.read_usize()?,
+            round_constants: reader.read_rows()?,
+            mds_matrix: read_single_matrix(reader)?,
+            alpha: reader.read_sbox()?,
+            alpha_inv: reader.read_sbox()?,
+            custom_gate: reader.read_custom_gate()?,
+        })
+    }
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> BlobParams<E, RATE, WIDTH>
+    for RescuePrimeParams<E, RATE, WIDTH>
+{
+    const FAMILY: HashFamily = HashFamily::RescuePrime;
+
+    fn write_payload(&self, writer: &mut BlobWriter<E>) {
+        writer.write_bool(self.allows_specialization);
+        writer.write_usize(self.full_rounds);
+        writer.write_rows(&self.round_constants);
+        writer.write_matrixes(std::slice::from_ref(&self.mds_matrix));
+        writer.write_sbox(&self.alpha);
+        writer.write_sbox(&self.alpha_inv);
+        writer.write_custom_gate(self.custom_gate);
+    }
+
+    fn read_payload(reader: &mut BlobReader<'_, E>) -> Result<Self, ParamsBlobError> {
+        Ok(Self {
+            allows_specialization: reader.read_bool()?,
+            full_rounds: reader.read_usize()?,
+            round_constants: reader.read_rows()?,
+            mds_matrix: read_single_matrix(reader)?,
+            alpha: reader.read_sbox()?,
+            alpha_inv: reader.read_sbox()?,
+            custom_gate: reader.read_custom_gate()?,
+        })
+    }
+}
+
+// Single matrixes are written as a list of one so all matrixes share encoding.
+fn read_single_matrix<E: Engine, const WIDTH: usize>(
+    reader: &mut BlobReader<'_, E>,
+) -> Result<[[E::Fr; WIDTH]; WIDTH], ParamsBlobError> {
+    match reader.read_u64()? {
+        1 => reader.read_matrix(),
+        _ => Err(ParamsBlobError::InvalidLength),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::init_rng;
+    use crate::GenericSponge;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    fn roundtrip<P: BlobParams<Bn256, 2, 3>>(params: P) {
+        let rng = &mut init_rng();
+        let input = [Fr::rand(rng), Fr::rand(rng), Fr::rand(rng)];
+        let blob = generate_params_blob(&params);
+
+        // aligned blob is read in place
+        let words: Vec<u64> = blob
+            .chunks_exact(8)
+            .map(|chunk| u64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        let aligned = unsafe { std::slice::from_raw_parts(words.as_ptr() as *const u8, blob.len()) };
+        let loaded: P = load_params_blob(aligned).unwrap();
+        assert_eq!(loaded.fingerprint(), params.fingerprint());
+        assert_eq!(
+            GenericSponge::hash(&input, &loaded, None),
+            GenericSponge::hash(&input, &params, None)
+        );
+
+        // unaligned blob is copied
+        let mut shifted = vec![0u8; blob.len() + 1];
+        shifted[1..].copy_from_slice(&blob);
+        let loaded: P = load_params_blob(&shifted[1..]).unwrap();
+        assert_eq!(loaded.fingerprint(), params.fingerprint());
+    }
+
+    #[test]
+    fn test_params_blob_roundtrip() {
+        roundtrip(RescueParams::<Bn256, 2, 3>::default());
+        roundtrip(PoseidonParams::<Bn256, 2, 3>::default());
+        roundtrip(PoseidonParams::<Bn256, 2, 3>::with_circular_mds());
+        roundtrip(RescuePrimeParams::<Bn256, 2, 3>::default());
+        roundtrip(Poseidon2Params::<Bn256, 2, 3>::default());
+    }
+
+    #[test]
+    fn test_params_blob_rejects_invalid_blobs() {
+        let blob = generate_params_blob(&PoseidonParams::<Bn256, 2, 3>::default());
+
+        assert_eq!(
+            load_params_blob::<Bn256, RescueParams<Bn256, 2, 3>, 2, 3>(&blob).err(),
+            Some(ParamsBlobError::ParamsMismatch)
+        );
+        assert_eq!(
+            load_params_blob::<Bn256, PoseidonParams<Bn256, 2, 3>, 2, 3>(&blob[..blob.len() - 8]).err(),
+            Some(ParamsBlobError::InvalidLength)
+        );
+
+        // flip a bit in the first element of MDS matrix, which follows the
+        // state and the number of matrixes
+        let mut corrupted = blob.clone();
+        let num_limbs = <Fr as PrimeField>::Repr::default().as_ref().len();
+        corrupted[(HEADER_WORDS + 3 * num_limbs + 1) * 8] ^= 1;
+        assert_eq!(
+            load_params_blob::<Bn256, PoseidonParams<Bn256, 2, 3>, 2, 3>(&corrupted).err(),
+            Some(ParamsBlobError::FingerprintMismatch)
+        );
+
+        let mut corrupted = blob;
+        corrupted[0] ^= 1;
+        assert_eq!(
+            load_params_blob::<Bn256, PoseidonParams<Bn256, 2, 3>, 2, 3>(&corrupted).err(),
+            Some(ParamsBlobError::BadMagic)
+        );
+
+        // circ(2, 1, 1) full rounds are only valid for width 3
+        let mut params = PoseidonParams::<Bn256, 4, 5>::default();
+        params.circular_full_rounds_mds = true;
+        assert_eq!(
+            load_params_blob::<Bn256, PoseidonParams<Bn256, 4, 5>, 4, 5>(&generate_params_blob(&params)).err(),
+            Some(ParamsBlobError::ParamsMismatch)
+        );
+    }
+}
@@ -0,0 +1,213 @@
+//! Field element encoding of compressed curve points, e.g. commitments of a
+//! proof system, shared by all transcripts of the crate.
+//!
+//! Point is compressed by `CurveAffine::into_compressed`, i.e. big-endian
+//! x-coordinate with point at infinity and sign of y flags in the top bits,
+//! and compressed bytes are split into 128-bit big-endian chunks starting
+//! from the end, each chunk being one field element. BN254 G1 points are
+//! encoded by 2 elements and G2 points by 4 elements.
+use crate::transcript_protocol::TranscriptProtocol;
+use franklin_crypto::bellman::pairing::{CurveAffine, EncodedPoint};
+use franklin_crypto::bellman::{Engine, PrimeField};
+
+/// Number of compressed point bytes in a single field element.
+pub const CHUNK_BYTES: usize = 16;
+
+/// Errors of decoding a point from field elements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointEncodingError {
+    /// Number of field elements doesn't match the point type.
+    WrongLength { expected: usize, actual: usize },
+    /// Field element at the index doesn't fit into its chunk.
+    NonCanonicalChunk(usize),
+    /// Bytes are not a compressed point of the group.
+    InvalidPoint,
+    /// Bytes decode into a point whose compressed form is different, e.g.
+    /// point at infinity with the sign flag set.
+    NonCanonicalPoint,
+}
+
+impl std::fmt::Display for PointEncodingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongLength { expected, actual } => {
+                write!(f, "point is encoded by {} field elements, got {}", expected, actual)
+            }
+            Self::NonCanonicalChunk(index) => write!(f, "field element {} is not a canonical chunk", index),
+            Self::InvalidPoint => write!(f, "encoding is not a valid compressed point"),
+            Self::NonCanonicalPoint => write!(f, "encoding is not the canonical compressed form of the point"),
+        }
+    }
+}
+
+impl std::error::Error for PointEncodingError {}
+
+/// Number of field elements encoding a point of `G`.
+pub fn encoded_point_len<G: CurveAffine>() -> usize {
+    (G::Compressed::size() + CHUNK_BYTES - 1) / CHUNK_BYTES
+}
+
+pub fn encode_compressed_point<E: Engine, G: CurveAffine>(point: &G) -> Vec<E::Fr> {
+    assert!(E::Fr::CAPACITY as usize >= CHUNK_BYTES * 8, "field is too small for a chunk");
+    let compressed = point.into_compressed();
+
+    let mut encoding: Vec<_> = compressed.as_ref().rchunks(CHUNK_BYTES).map(chunk_as_fe::<E>).collect();
+    encoding.reverse();
+
+    encoding
+}
+
+/// Decodes a point and checks that the encoding is exactly the one produced
+/// by `encode_compressed_point`, so every point has a single encoding.
+pub fn decode_compressed_point<E: Engine, G: CurveAffine>(encoding: &[E::Fr]) -> Result<G, PointEncodingError> {
+    let expected = encoded_point_len::<G>();
+    if encoding.len() != expected {
+        return Err(PointEncodingError::WrongLength {
+            expected,
+            actual: encoding.len(),
+        });
+    }
+
+    let mut compressed = G::Compressed::empty();
+    let bytes = compressed.as_mut();
+    let first_chunk_len = bytes.len() - (expected - 1) * CHUNK_BYTES;
+    let mut offset = 0;
+    for (index, el) in encoding.iter().enumerate() {
+        let chunk_len = if index == 0 { first_chunk_len } else { CHUNK_BYTES };
+        let chunk = fe_as_chunk::<E>(el).ok_or(PointEncodingError::NonCanonicalChunk(index))?;
+        let (high, low) = chunk.split_at(CHUNK_BYTES - chunk_len);
+        if high.iter().any(|byte| *byte != 0) {
+            return Err(PointEncodingError::NonCanonicalChunk(index));
+        }
+        bytes[offset..offset + chunk_len].copy_from_slice(low);
+        offset += chunk_len;
+    }
+
+    let point = compressed.into_affine().map_err(|_| PointEncodingError::InvalidPoint)?;
+    if point.into_compressed().as_ref() != compressed.as_ref() {
+        return Err(PointEncodingError::NonCanonicalPoint);
+    }
+
+    Ok(point)
+}
+
+/// Commits a point as a proof element of the transcript.
+pub fn commit_point<E: Engine, G: CurveAffine, T: TranscriptProtocol<E> + ?Sized>(transcript: &mut T, point: &G) {
+    transcript.commit_proof_element(&encode_compressed_point::<E, G>(point));
+}
+
+// Big-endian chunk into the lower 128 bits of a field element.
+fn chunk_as_fe<E: Engine>(chunk: &[u8]) -> E::Fr {
+    let mut padded = [0u8; CHUNK_BYTES];
+    padded[CHUNK_BYTES - chunk.len()..].copy_from_slice(chunk);
+
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    repr.as_mut()[0] = u64::from_be_bytes(padded[8..].try_into().expect("8 bytes"));
+    repr.as_mut()[1] = u64::from_be_bytes(padded[..8].try_into().expect("8 bytes"));
+
+    E::Fr::from_repr(repr).expect("a field element")
+}
+
+fn fe_as_chunk<E: Engine>(el: &E::Fr) -> Option<[u8; CHUNK_BYTES]> {
+    let repr = el.into_repr();
+    if repr.as_ref()[2..].iter().any(|limb| *limb != 0) {
+        return None;
+    }
+
+    let mut chunk = [0u8; CHUNK_BYTES];
+    chunk[..8].copy_from_slice(&repr.as_ref()[1].to_be_bytes());
+    chunk[8..].copy_from_slice(&repr.as_ref()[0].to_be_bytes());
+
+    Some(chunk)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::tests::init_rng;
+    use crate::transcript_protocol::SpongeTranscript;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr, G1Affine, G2Affine, G1, G2};
+    use franklin_crypto::bellman::pairing::CurveProjective;
+    use franklin_crypto::bellman::Field;
+    use rand::Rand;
+
+    #[test]
+    fn test_compressed_point_roundtrip() {
+        let rng = &mut init_rng();
+        assert_eq!(encoded_point_len::<G1Affine>(), 2);
+        assert_eq!(encoded_point_len::<G2Affine>(), 4);
+
+        for _ in 0..4 {
+            let point = G1::rand(rng).into_affine();
+            let encoding = encode_compressed_point::<Bn256, _>(&point);
+            assert_eq!(decode_compressed_point::<Bn256, G1Affine>(&encoding), Ok(point));
+
+            let point = G2::rand(rng).into_affine();
+            let encoding = encode_compressed_point::<Bn256, _>(&point);
+            assert_eq!(decode_compressed_point::<Bn256, G2Affine>(&encoding), Ok(point));
+        }
+
+        let encoding = encode_compressed_point::<Bn256, _>(&G1Affine::zero());
+        assert_eq!(decode_compressed_point::<Bn256, G1Affine>(&encoding), Ok(G1Affine::zero()));
+    }
+
+    #[test]
+    fn test_decode_rejects_non_canonical_encodings() {
+        let rng = &mut init_rng();
+        let point = G1::rand(rng).into_affine();
+        let encoding = encode_compressed_point::<Bn256, _>(&point);
+
+        assert_eq!(
+            decode_compressed_point::<Bn256, G1Affine>(&encoding[..1]),
+            Err(PointEncodingError::WrongLength { expected: 2, actual: 1 })
+        );
+
+        // chunk shifted by 2^128 still has the same lower bits
+        let mut two_pow_128 = Fr::one();
+        for _ in 0..128 {
+            two_pow_128.double();
+        }
+        let mut shifted = encoding.clone();
+        shifted[1].add_assign(&two_pow_128);
+        assert_eq!(
+            decode_compressed_point::<Bn256, G1Affine>(&shifted),
+            Err(PointEncodingError::NonCanonicalChunk(1))
+        );
+
+        // x-coordinate with all bits set is not less than the modulus
+        let mut high_chunk = [0xff; CHUNK_BYTES];
+        high_chunk[0] = 0x3f;
+        let mut overflowing = encoding.clone();
+        overflowing[0] = chunk_as_fe::<Bn256>(&high_chunk);
+        overflowing[1] = chunk_as_fe::<Bn256>(&[0xff; CHUNK_BYTES]);
+        assert_eq!(
+            decode_compressed_point::<Bn256, G1Affine>(&overflowing),
+            Err(PointEncodingError::InvalidPoint)
+        );
+
+        // both flags set on the point at infinity
+        let mut infinity = encode_compressed_point::<Bn256, _>(&G1Affine::zero());
+        let mut high_chunk = [0x00; CHUNK_BYTES];
+        high_chunk[0] = 0xc0;
+        infinity[0] = chunk_as_fe::<Bn256>(&high_chunk);
+        assert!(matches!(
+            decode_compressed_point::<Bn256, G1Affine>(&infinity),
+            Err(PointEncodingError::InvalidPoint) | Err(PointEncodingError::NonCanonicalPoint)
+        ));
+    }
+
+    #[test]
+    fn test_commit_point() {
+        let rng = &mut init_rng();
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        let point = G2::rand(rng).into_affine();
+
+        let mut transcript = SpongeTranscript::<Bn256, _, 2, 3>::new(params.clone());
+        let mut expected_transcript = transcript.clone();
+        commit_point(&mut transcript, &point);
+        expected_transcript.commit_proof_element(&encode_compressed_point::<Bn256, _>(&point));
+
+        assert_eq!(transcript.get_challenge(), expected_transcript.get_challenge());
+    }
+}
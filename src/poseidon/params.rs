@@ -1,9 +1,16 @@
+use alloc::{vec, vec::Vec};
 use franklin_crypto::bellman::{Engine, Field};
 
-use crate::common::matrix::{compute_optimized_matrixes, mmul_assign, try_inverse};
-use crate::common::params::InnerHashParameters;
+use crate::common::matrix::{compute_optimized_matrixes_of_transposed_for_width, mmul_assign, multiply, try_inverse};
+use crate::common::fingerprint::Fingerprint;
+use crate::common::params::{circular_mds_matrix, poseidon_number_of_rounds, ConstantsSource, InnerHashParameters, SeededParamsError, ROUND_CONSTANTS_TAG};
 use crate::traits::{CustomGate, HashFamily, HashParams, Sbox};
 
+/// Serde representation is not versioned. Fields added after the first
+/// release are `#[serde(default)]`, which lets self-describing formats like
+/// JSON load params serialized before, but not bincode or other formats
+/// relying on field order: such old params fail to load or are misread. Use
+/// `params_blob`, whose format is versioned, to store params in binary form.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct PoseidonParams<E: Engine, const RATE: usize, const WIDTH: usize> {
     #[serde(with = "crate::BigArraySerde")]
@@ -11,6 +18,12 @@ pub struct PoseidonParams<E: Engine, const RATE: usize, const WIDTH: usize> {
     #[serde(serialize_with = "crate::serialize_array_of_arrays")]
     #[serde(deserialize_with = "crate::deserialize_array_of_arrays")]
     pub(crate) mds_matrix: [[E::Fr; WIDTH]; WIDTH],
+    // original schedule, params serialized by JSON before it was stored
+    // don't have it, see struct docs for binary formats
+    #[serde(default)]
+    #[serde(serialize_with = "crate::serialize_vec_of_arrays")]
+    #[serde(deserialize_with = "crate::deserialize_vec_of_arrays")]
+    pub(crate) round_constants: Vec<[E::Fr; WIDTH]>,
     #[serde(serialize_with = "crate::serialize_vec_of_arrays")]
     #[serde(deserialize_with = "crate::deserialize_vec_of_arrays")]
     pub(crate) optimized_round_constants: Vec<[E::Fr; WIDTH]>,
@@ -24,6 +37,11 @@ pub struct PoseidonParams<E: Engine, const RATE: usize, const WIDTH: usize> {
     pub(crate) full_rounds: usize,
     pub(crate) partial_rounds: usize,
     pub(crate) custom_gate: CustomGate,
+    // full rounds use circ(2, 1, 1), partial rounds keep `mds_matrix`,
+    // defaults to false only for JSON, see struct docs for binary formats
+    #[serde(default)]
+    #[serde(deserialize_with = "deserialize_circular_full_rounds_mds::<_, WIDTH>")]
+    pub(crate) circular_full_rounds_mds: bool,
 }
 
 impl<E: Engine, const RATE: usize, const WIDTH: usize> PartialEq
@@ -36,15 +54,84 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> PartialEq
 
 impl<E: Engine, const RATE: usize, const WIDTH: usize> Default for PoseidonParams<E, RATE, WIDTH> {
     fn default() -> Self {
-        let (params, 
-            alpha, 
-            optimized_round_constants, 
-            (optimized_mds_matrixes_0, optimized_mds_matrixes_1)
-        ) =
-            super::params::poseidon_light_params::<E, RATE, WIDTH>();
+        Self::from_light_params(super::params::poseidon_light_params::<E, RATE, WIDTH>())
+    }
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> PoseidonParams<E, RATE, WIDTH> {
+    /// Params of `Bn256PoseidonParams::new_checked_2_into_1` from the legacy
+    /// `poseidon_hash` crate: same constants and MDS matrix derivation but
+    /// 83 partial rounds instead of 33. Legacy sponge doesn't specialize the
+    /// capacity for input length and pads input with ones, so it matches
+    /// `GenericSponge` with `DomainStrategy::CustomVariableLength`, see
+    /// `legacy_poseidon_hash`.
+    pub fn legacy_2_into_1() -> Self {
+        Self::from_light_params(optimize_poseidon_params(poseidon_params_for_rounds(
+            LEGACY_SECURITY_LEVEL,
+            LEGACY_FULL_ROUNDS,
+            LEGACY_PARTIAL_ROUNDS,
+            ConstantsSource::default(),
+        )))
+    }
+
+    /// Params derived the same way as default ones but with numbers of rounds
+    /// computed for `security_bits` by the round numbers script of the
+    /// Poseidon authors. Default params keep their historical 33 partial
+    /// rounds, while the formula gives 34 for 80 bits.
+    pub fn with_security_level(security_bits: usize) -> Self {
+        let alpha = compute_alpha::<E>();
+        let (full_rounds, partial_rounds) = poseidon_number_of_rounds::<E>(WIDTH, alpha, security_bits);
+
+        Self::from_light_params(optimize_poseidon_params(poseidon_params_for_rounds(
+            security_bits,
+            full_rounds,
+            partial_rounds,
+            ConstantsSource::default(),
+        )))
+    }
+
+    /// Params with the rounds and MDS matrix of default ones but round
+    /// constants derived by `source`.
+    pub fn with_constants_source(source: ConstantsSource) -> Self {
+        Self::from_light_params(optimize_poseidon_params(poseidon_params_for_rounds(
+            SECURITY_LEVEL,
+            FULL_ROUNDS,
+            PARTIAL_ROUNDS,
+            source,
+        )))
+    }
+
+    /// Params with round constants and MDS matrix derived from `seed` instead
+    /// of the tags shared by default params. Up to `SECURITY_LEVEL` bits the
+    /// default 33 partial rounds are used and up to 126 bits the 83 partial
+    /// rounds of `legacy_2_into_1`.
+    pub fn generate_from_seed(seed: &[u8], security_bits: usize) -> Result<Self, SeededParamsError> {
+        let partial_rounds = if security_bits <= SECURITY_LEVEL {
+            PARTIAL_ROUNDS
+        } else if security_bits <= LEGACY_SECURITY_LEVEL {
+            LEGACY_PARTIAL_ROUNDS
+        } else {
+            return Err(SeededParamsError::UnsupportedSecurityLevel {
+                requested: security_bits,
+                supported: LEGACY_SECURITY_LEVEL,
+            });
+        };
+
+        let mut params = InnerHashParameters::new(security_bits, FULL_ROUNDS, partial_rounds);
+        params.compute_round_constants_from_seed(FULL_ROUNDS + partial_rounds, b"Poseidon", seed);
+        params.compute_mds_matrix_from_seed(b"Poseidon", seed);
+        params.check_seeded()?;
+
+        Ok(Self::from_light_params(optimize_poseidon_params((params, compute_alpha::<E>()))))
+    }
+
+    fn from_light_params(light_params: LightParams<E, RATE, WIDTH>) -> Self {
+        let (params, alpha, optimized_round_constants, (optimized_mds_matrixes_0, optimized_mds_matrixes_1)) =
+            light_params;
         Self {
             state: [E::Fr::zero(); WIDTH],
-            mds_matrix: params.mds_matrix,
+            mds_matrix: *params.mds_matrix(),
+            round_constants: params.round_constants().to_vec(),
             alpha: Sbox::Alpha(alpha),
             optimized_round_constants,
             optimized_mds_matrixes_0,
@@ -52,8 +139,24 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> Default for PoseidonParam
             full_rounds: params.full_rounds,
             partial_rounds: params.partial_rounds,
             custom_gate: CustomGate::None,
+            circular_full_rounds_mds: false,
         }
     }
+
+    /// Params whose full rounds multiply by circ(2, 1, 1) matrix, which is MDS
+    /// for state width 3 and needs only additions. Partial rounds keep the
+    /// Cauchy matrix and its optimized form: circ(2, 1, 1) maps `(0, a, -a)`
+    /// to itself and such vectors are not touched by the partial S-box, which
+    /// would give an infinitely long subspace trail.
+    ///
+    /// Multiplication of the last of first full rounds is folded into the
+    /// first matrix of optimized partial rounds, see `fold_circular_mds`.
+    pub fn with_circular_mds() -> Self {
+        let mut params = Self::from_light_params(fold_circular_mds(poseidon_light_params()));
+        params.circular_full_rounds_mds = true;
+
+        params
+    }
 }
 
 impl<E: Engine, const RATE: usize, const WIDTH: usize> HashParams<E, RATE, WIDTH>
@@ -63,8 +166,14 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> HashParams<E, RATE, WIDTH
         HashFamily::Poseidon
     }
 
-    fn constants_of_round(&self, _round: usize) -> &[E::Fr; WIDTH] {
-        unimplemented!("Poseidon uses optimized constants")
+    /// Returns original, non-optimized, constants of the round. Round
+    /// function itself uses optimized constants.
+    fn constants_of_round(&self, round: usize) -> &[E::Fr; WIDTH] {
+        assert!(
+            !self.round_constants.is_empty(),
+            "params don't have original round constants"
+        );
+        &self.round_constants[round]
     }
 
     fn mds_matrix(&self) -> &[[E::Fr; WIDTH]; WIDTH] {
@@ -105,49 +214,132 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> HashParams<E, RATE, WIDTH
     fn use_custom_gate(&mut self, custom_gate: CustomGate) {
         self.custom_gate = custom_gate;
     }
+
+    fn uses_circular_full_rounds_mds(&self) -> bool {
+        self.circular_full_rounds_mds
+    }
+
+    fn fingerprint(&self) -> [u8; 32] {
+        let mut fingerprint = Fingerprint::new(self.hash_family(), RATE, WIDTH);
+        fingerprint.update_usize(self.full_rounds);
+        fingerprint.update_usize(self.partial_rounds);
+        fingerprint.update_matrix::<E, WIDTH>(&self.mds_matrix);
+        for constants in self.optimized_round_constants.iter() {
+            fingerprint.update_frs::<E>(constants);
+        }
+        fingerprint.update_matrix::<E, WIDTH>(&self.optimized_mds_matrixes_0);
+        for matrix in self.optimized_mds_matrixes_1.iter() {
+            fingerprint.update_matrix::<E, WIDTH>(matrix);
+        }
+        fingerprint.update_sbox(&self.alpha);
+        if self.circular_full_rounds_mds {
+            fingerprint.update_u64(u64::from_be_bytes(*b"circ_211"));
+        }
+
+        fingerprint.finalize()
+    }
 }
 
+/// Security level default params are claimed for.
+pub const SECURITY_LEVEL: usize = 80;
+const FULL_ROUNDS: usize = 8;
+const PARTIAL_ROUNDS: usize = 33;
+
+const LEGACY_SECURITY_LEVEL: usize = 126;
+const LEGACY_FULL_ROUNDS: usize = 8;
+const LEGACY_PARTIAL_ROUNDS: usize = 83;
+
+type LightParams<E, const RATE: usize, const WIDTH: usize> = (
+    InnerHashParameters<E, RATE, WIDTH>,
+    u64,
+    Vec<[<E as franklin_crypto::bellman::ScalarEngine>::Fr; WIDTH]>,
+    (
+        [[<E as franklin_crypto::bellman::ScalarEngine>::Fr; WIDTH]; WIDTH],
+        Vec<[[<E as franklin_crypto::bellman::ScalarEngine>::Fr; WIDTH]; WIDTH]>,
+    ),
+);
+
 pub fn poseidon_params<E: Engine, const RATE: usize, const WIDTH: usize>(
 ) -> (InnerHashParameters<E, RATE, WIDTH>, u64) {
-    let security_level = 80;
-    let full_rounds = 8;
-    // let partial_rounds = 83;
-    let partial_rounds = 33;
+    let security_level = SECURITY_LEVEL;
+    let full_rounds = FULL_ROUNDS;
+    let partial_rounds = PARTIAL_ROUNDS;
 
+    poseidon_params_for_rounds(security_level, full_rounds, partial_rounds, ConstantsSource::default())
+}
+
+fn poseidon_params_for_rounds<E: Engine, const RATE: usize, const WIDTH: usize>(
+    security_level: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    source: ConstantsSource,
+) -> (InnerHashParameters<E, RATE, WIDTH>, u64) {
     let mut params = InnerHashParameters::new(security_level, full_rounds, partial_rounds);
 
     let number_of_rounds = full_rounds + partial_rounds;
-    let rounds_tag = b"Rescue_f";
-    params.compute_round_constants(number_of_rounds, rounds_tag);
+    let rounds_tag = ROUND_CONSTANTS_TAG;
+    params.compute_round_constants_from_source(number_of_rounds, rounds_tag, source);
     params.compute_mds_matrix_for_poseidon();
 
+    (params, compute_alpha::<E>())
+}
+
+fn compute_alpha<E: Engine>() -> u64 {
     let alpha = 5u64;
+    assert!(
+        crate::common::utils::alpha_is_permutation::<E>(alpha),
+        "sbox of degree {} is not a permutation of the field",
+        alpha
+    );
 
-    (params, alpha)
+    alpha
 }
 
-pub(crate) fn poseidon_light_params<E: Engine, const RATE: usize, const WIDTH: usize>() -> (
-    InnerHashParameters<E, RATE, WIDTH>,
-    u64,
-    Vec<[E::Fr; WIDTH]>,
-    ([[E::Fr; WIDTH]; WIDTH], Vec<[[E::Fr; WIDTH]; WIDTH]>),
-) {
-    let (params, alpha) = poseidon_params();
+pub(crate) fn poseidon_light_params<E: Engine, const RATE: usize, const WIDTH: usize>() -> LightParams<E, RATE, WIDTH> {
+    optimize_poseidon_params(poseidon_params())
+}
+
+// Last of first full rounds skips its circ(2, 1, 1) multiplication C, which
+// moves into M' together with the optimized constants c added right after:
+// M' * (C * x + c) = (M' * C) * (x + C^-1 * c)
+fn fold_circular_mds<E: Engine, const RATE: usize, const WIDTH: usize>(
+    (params, alpha, mut optimized_constants, (m_prime, sparse_matrixes)): LightParams<E, RATE, WIDTH>,
+) -> LightParams<E, RATE, WIDTH> {
+    let circular = circular_mds_matrix::<E, WIDTH>();
+    let circular_inverse = try_inverse::<E, WIDTH>(&circular).expect("has inverse");
+    mmul_assign::<E, WIDTH>(&circular_inverse, &mut optimized_constants[params.full_rounds / 2]);
+    let m_prime = multiply::<E, WIDTH>(&m_prime, &circular);
 
+    (params, alpha, optimized_constants, (m_prime, sparse_matrixes))
+}
+
+// circ(2, 1, 1) is MDS only for width 3, params of other widths are rejected
+fn deserialize_circular_full_rounds_mds<'de, D, const WIDTH: usize>(deserializer: D) -> Result<bool, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let circular = <bool as serde::Deserialize>::deserialize(deserializer)?;
+    if circular && WIDTH != 3 {
+        return Err(serde::de::Error::custom("circular full rounds matrix is MDS only for state width = 3"));
+    }
+
+    Ok(circular)
+}
+
+fn optimize_poseidon_params<E: Engine, const RATE: usize, const WIDTH: usize>(
+    (params, alpha): (InnerHashParameters<E, RATE, WIDTH>, u64),
+) -> LightParams<E, RATE, WIDTH> {
     let optimized_constants = compute_optimized_round_constants::<E, WIDTH>(
         params.round_constants(),
-        &params.mds_matrix,
+        params.mds_matrix(),
         params.partial_rounds,
         params.full_rounds,
     );
 
-    const SUBDIM: usize = 2; // TODO:
-    assert!(
-        WIDTH - SUBDIM == 1,
-        "only dim 2 and dim 3 matrixes are allowed for now."
+    let optimized_matrixes = compute_optimized_matrixes_of_transposed_for_width::<E, WIDTH>(
+        params.partial_rounds,
+        params.mds_matrix_transposed(),
     );
-    let optimized_matrixes =
-        compute_optimized_matrixes::<E, WIDTH, SUBDIM>(params.partial_rounds, &params.mds_matrix);
     (params, alpha, optimized_constants, optimized_matrixes)
 }
 
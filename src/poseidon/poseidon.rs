@@ -1,6 +1,7 @@
 use crate::common::{matrix::mmul_assign, sbox::sbox};
-use crate::sponge::{generic_hash};
-use crate::traits::{HashFamily, HashParams};
+use crate::common::domain_strategy::DomainStrategy;
+use crate::sponge::{generic_hash, GenericSponge};
+use crate::traits::{HashFamily, HashParams, NoopMiddleware, RoundFunctionMiddleware};
 use franklin_crypto::bellman::{Engine, Field};
 use super::params::PoseidonParams;
 
@@ -16,6 +17,22 @@ pub fn poseidon_hash<E: Engine, const L: usize>(input: &[E::Fr; L]) -> [E::Fr; 2
     generic_hash(&params, input, None)
 }
 
+/// Same digest as `poseidon_hash` of the legacy `poseidon_hash` crate with
+/// `Bn256PoseidonParams::new_checked_2_into_1`. Capacity is not specialized
+/// for input length and input is padded with ones to a multiple of rate.
+/// Uses state-width=3 and rate=2.
+pub fn legacy_poseidon_hash<E: Engine>(input: &[E::Fr]) -> [E::Fr; 2] {
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+    assert!(!input.is_empty(), "empty input");
+
+    let params = PoseidonParams::<E, RATE, WIDTH>::legacy_2_into_1();
+    let mut sponge = GenericSponge::<E, RATE, WIDTH>::new_from_domain_strategy(DomainStrategy::CustomVariableLength);
+    sponge.absorb_multiple(input, &params);
+
+    [(); RATE].map(|_| sponge.squeeze(&params).expect("a squeezed elem"))
+}
+
 pub(crate) fn poseidon_round_function<
     E: Engine,
     P: HashParams<E, RATE, WIDTH>,
@@ -24,6 +41,65 @@ pub(crate) fn poseidon_round_function<
 >(
     params: &P,
     state: &mut [E::Fr; WIDTH]
+) {
+    poseidon_round_function_with_middleware(params, state, &mut NoopMiddleware)
+}
+
+/// Textbook Poseidon permutation over the original round constants, with
+/// the full MDS matrix in every round. Gives the same result as the
+/// optimized round function, so it is only meant for differential testing
+/// and for verifying the optimized constants and matrixes externally.
+pub fn poseidon_round_function_reference<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    params: &P,
+    state: &mut [E::Fr; WIDTH],
+) {
+    assert_eq!(params.hash_family(), HashFamily::Poseidon, "Incorrect hash family!");
+    let half_of_full_rounds = params.number_of_full_rounds() / 2;
+    let total_rounds = params.number_of_full_rounds() + params.number_of_partial_rounds();
+
+    for round in 0..total_rounds {
+        for (s, c) in state.iter_mut().zip(params.constants_of_round(round).iter()) {
+            s.add_assign(c);
+        }
+        if round < half_of_full_rounds || round >= total_rounds - half_of_full_rounds {
+            sbox::<E>(params.alpha(), state);
+            full_round_matmul::<E, P, RATE, WIDTH>(params, state);
+        } else {
+            sbox::<E>(params.alpha(), &mut state[..1]);
+            mmul_assign::<E, WIDTH>(params.mds_matrix(), state);
+        }
+    }
+}
+
+// Full rounds multiply either by circ(2, 1, 1) or by the MDS matrix of params.
+fn full_round_matmul<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    state: &mut [E::Fr; WIDTH],
+) {
+    if params.uses_circular_full_rounds_mds() {
+        let mut sum = E::Fr::zero();
+        state.iter().for_each(|el| sum.add_assign(el));
+        state.iter_mut().for_each(|el| el.add_assign(&sum));
+    } else {
+        mmul_assign::<E, WIDTH>(&params.mds_matrix(), state);
+    }
+}
+
+pub(crate) fn poseidon_round_function_with_middleware<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    M: RoundFunctionMiddleware<E, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    params: &P,
+    state: &mut [E::Fr; WIDTH],
+    middleware: &mut M,
 ) {
     assert_eq!(params.hash_family(), HashFamily::Poseidon, "Incorrect hash family!");
     debug_assert!(params.number_of_full_rounds() & 1 == 0);
@@ -41,8 +117,12 @@ pub(crate) fn poseidon_round_function<
         }
         // apply sbox
         sbox::<E>(params.alpha(), state);
-        // mul state by mds
-        mmul_assign::<E, WIDTH>(&params.mds_matrix(), state);
+        // mul state by mds, circular matrix of the last round is folded into M'
+        if !params.uses_circular_full_rounds_mds() || round + 1 < half_of_full_rounds {
+            full_round_matmul::<E, P, RATE, WIDTH>(params, state);
+        }
+
+        middleware.after_round(round, state);
     }
 
     // partial rounds
@@ -57,11 +137,12 @@ pub(crate) fn poseidon_round_function<
     mmul_assign::<E, WIDTH>(&sparse_matrixes.0, state);
 
     // this is an unrolled version of partial rounds
-    for (round_constants, sparse_matrix) in optimized_round_constants
+    for (partial_round, (round_constants, sparse_matrix)) in optimized_round_constants
         [half_of_full_rounds + 1..half_of_full_rounds + params.number_of_partial_rounds()]
         .iter()
         .chain(&[[E::Fr::zero(); WIDTH]])
         .zip(sparse_matrixes.1.iter())
+        .enumerate()
     {
         let mut quad = state[0];
         quad.square();
@@ -77,17 +158,17 @@ pub(crate) fn poseidon_round_function<
             mds_result[0].add_assign(&tmp);
         }
 
-        let mut tmp = sparse_matrix[1][0];
-        tmp.mul_assign(&state[0]);
-        tmp.add_assign(&state[1]);
-        mds_result[1] = tmp;
-
-        let mut tmp = sparse_matrix[2][0];
-        tmp.mul_assign(&state[0]);
-        tmp.add_assign(&state[2]);
-        mds_result[2] = tmp;
+        for i in 1..WIDTH {
+            let mut tmp = sparse_matrix[i][0];
+            tmp.mul_assign(&state[0]);
+            tmp.add_assign(&state[i]);
+            mds_result[i] = tmp;
+        }
 
         state.copy_from_slice(&mds_result[..]);
+
+        // state is in optimized (transformed) basis during partial rounds
+        middleware.after_round(half_of_full_rounds + partial_round, state);
     }
 
     // full rounds
@@ -102,6 +183,8 @@ pub(crate) fn poseidon_round_function<
         sbox::<E>(params.alpha(), state);
 
         // mul state by mds
-        mmul_assign::<E, WIDTH>(&params.mds_matrix(), state);
+        full_round_matmul::<E, P, RATE, WIDTH>(params, state);
+
+        middleware.after_round(round, state);
     }
 }
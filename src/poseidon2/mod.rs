@@ -1,11 +1,19 @@
+pub mod oracle;
 pub mod params;
 pub mod poseidon2;
 pub mod sponge;
 pub mod transcript;
+#[cfg(feature = "std")]
 pub mod pow_runner;
 #[cfg(test)]
 mod tests;
 
 pub use self::sponge::*;
-pub use self::params::Poseidon2Params;
+pub use self::params::{Poseidon2Params, Poseidon2ParamsError, POSEIDON2_SUPPORTED_WIDTHS};
+pub use self::oracle::{MerkleOracle, OracleQuery, Poseidon2MerkleOracle};
 pub use self::poseidon2::*;
+pub use self::transcript::{
+    fr_into_u64_limbs, get_challenges_from_fr, num_challenges_per_fr, TranscriptMode, TranscriptRoundError,
+    CHALLENGE_LIMBS,
+    TRANSCRIPT_AGGREGATION_DOMAIN_TAG, TRANSCRIPT_DIGEST_DOMAIN_TAG,
+};
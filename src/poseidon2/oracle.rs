@@ -0,0 +1,96 @@
+use super::sponge::Poseidon2Sponge;
+use crate::common::utils::ct_eq_digest;
+use alloc::vec::Vec;
+use franklin_crypto::bellman::Engine;
+use franklin_crypto::boojum::cs::oracle::merkle_tree::MerkleTreeWithCap;
+use franklin_crypto::boojum::cs::oracle::TreeHasher;
+use franklin_crypto::boojum::field::SmallField;
+use franklin_crypto::boojum::worker::Worker;
+
+/// 2-ary Merkle tree oracle with cap over any boojum `TreeHasher`. Tree is
+/// boojum's own `MerkleTreeWithCap`, so commitments and queries are exactly
+/// the ones produced and checked by boojum provers and verifiers with the
+/// same hasher. Number of leaves and cap size must be powers of two.
+pub struct MerkleOracle<F: SmallField, H: TreeHasher<F>> {
+    leaves: Vec<Vec<F>>,
+    tree: MerkleTreeWithCap<F, H>,
+    cap: Vec<H::Output>,
+}
+
+/// Bn256 Poseidon2 Merkle oracle over small field elements.
+pub type Poseidon2MerkleOracle<E, F, M> = MerkleOracle<F, Poseidon2Sponge<E, F, M, 2, 3>>;
+
+/// Answer to a query: opened leaf and authentication path to the cap.
+#[derive(Clone, Debug)]
+pub struct OracleQuery<F: SmallField, T> {
+    pub index: usize,
+    pub leaf: Vec<F>,
+    pub path: Vec<T>,
+}
+
+impl<F: SmallField, H: TreeHasher<F>> MerkleOracle<F, H> {
+    /// Commits to the leaves, tree is built up to `cap_size` nodes.
+    pub fn commit(leaves: Vec<Vec<F>>, cap_size: usize, worker: &Worker) -> Self {
+        assert!(leaves.len().is_power_of_two(), "number of leaves should be a power of two");
+        assert!(cap_size.is_power_of_two(), "cap size should be a power of two");
+        assert!(cap_size <= leaves.len(), "cap is larger than the number of leaves");
+
+        let tree = MerkleTreeWithCap::<F, H>::construct(leaves.clone(), cap_size, worker);
+        let cap = tree.get_cap();
+
+        Self { leaves, tree, cap }
+    }
+
+    pub fn cap(&self) -> &[H::Output] {
+        &self.cap
+    }
+
+    pub fn num_leaves(&self) -> usize {
+        self.leaves.len()
+    }
+
+    /// Underlying boojum tree.
+    pub fn tree(&self) -> &MerkleTreeWithCap<F, H> {
+        &self.tree
+    }
+
+    pub fn query(&self, index: usize) -> OracleQuery<F, H::Output> {
+        assert!(index < self.leaves.len(), "index is out of range");
+
+        let (_leaf_hash, path): (_, Vec<_>) = self.tree.get_proof(index);
+
+        OracleQuery {
+            index,
+            leaf: self.leaves[index].clone(),
+            path,
+        }
+    }
+}
+
+impl<F: SmallField, T: Copy> OracleQuery<F, T> {
+    /// Checks the query against the cap of a committed oracle. Path is
+    /// walked the same way as by boojum verifiers, and the resulting node is
+    /// compared with the cap in constant time, so only hashers with field
+    /// element outputs are supported.
+    pub fn verify<E: Engine<Fr = T>, H: TreeHasher<F, Output = T>>(&self, cap: &[T]) -> bool {
+        if !cap.len().is_power_of_two() {
+            return false;
+        }
+        if self.path.len() >= usize::BITS as usize || self.index >> self.path.len() >= cap.len() {
+            return false;
+        }
+
+        let mut current = H::hash_into_leaf(self.leaf.iter());
+        let mut idx = self.index;
+        for (depth, sibling) in self.path.iter().enumerate() {
+            current = if idx & 1 == 0 {
+                H::hash_into_node(&current, sibling, depth)
+            } else {
+                H::hash_into_node(sibling, &current, depth)
+            };
+            idx >>= 1;
+        }
+
+        ct_eq_digest::<E, 1>(&[current], &[cap[idx]])
+    }
+}
@@ -1,9 +1,14 @@
+use alloc::{borrow::ToOwned, string::ToString, vec::Vec};
 use franklin_crypto::bellman::{Engine, Field};
 
-use crate::common::params::InnerHashParameters;
+use crate::common::fingerprint::Fingerprint;
+use crate::common::params::{check_round_constants, poseidon_number_of_rounds, ConstantsSource, InnerHashParameters, SeededParamsError, ROUND_CONSTANTS_TAG};
 use crate::traits::{CustomGate, HashFamily, HashParams, Sbox};
 use franklin_crypto::bellman::PrimeField;
 
+/// Params of the Poseidon2 permutation https://eprint.iacr.org/2023/323, see
+/// `POSEIDON2_SUPPORTED_WIDTHS` for the widths which are interoperable with
+/// the HorizenLabs implementation and the experimental ones.
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct Poseidon2Params<E: Engine, const RATE: usize, const WIDTH: usize> {
     #[serde(serialize_with = "crate::serialize_array_of_arrays")]
@@ -28,22 +33,131 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> PartialEq
     }
 }
 
+/// State widths Poseidon2 params can be constructed for. Linear layers are
+/// implemented for more widths, but the internal matrix is only defined for
+/// these ones.
+///
+/// Width 3 matches the HorizenLabs implementation
+/// https://github.com/HorizenLabs/poseidon2, the only width it has params
+/// for over the BN254 scalar field. Widths 4, 8 and 12 are experimental and
+/// enabled by the `poseidon2-experimental-widths` feature: their internal
+/// matrix diagonals are chosen by this crate and aren't tested against any
+/// reference, so digests may change once a reference appears.
+#[cfg(not(feature = "poseidon2-experimental-widths"))]
+pub const POSEIDON2_SUPPORTED_WIDTHS: &[usize] = &[3];
+#[cfg(feature = "poseidon2-experimental-widths")]
+pub const POSEIDON2_SUPPORTED_WIDTHS: &[usize] = &[3, 4, 8, 12];
+
+/// Security level the round numbers are chosen for, by table 1 of the
+/// Poseidon2 paper https://eprint.iacr.org/2023/323 for sbox of degree 5 over
+/// 254 bit fields, same as the original implementation
+/// https://github.com/HorizenLabs/poseidon2 uses for state width 3.
+pub const SECURITY_LEVEL: usize = 128;
+const FULL_ROUNDS: usize = 8;
+
+const fn partial_rounds(width: usize) -> usize {
+    match width {
+        3 | 4 => 56,
+        _ => 57,
+    }
+}
+
+/// Errors of constructing Poseidon2 params.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Poseidon2ParamsError {
+    UnsupportedWidth(usize),
+}
+
+impl core::fmt::Display for Poseidon2ParamsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnsupportedWidth(width) => write!(
+                f,
+                "Poseidon2 is not supported for width {}, supported widths are {:?}",
+                width, POSEIDON2_SUPPORTED_WIDTHS
+            ),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Poseidon2ParamsError {}
+
 impl<E: Engine, const RATE: usize, const WIDTH: usize> Default for Poseidon2Params<E, RATE, WIDTH> {
+    /// Panics for widths out of `POSEIDON2_SUPPORTED_WIDTHS`, see `try_default`.
     fn default() -> Self {
-        let security_level = 80; // TODO: check, but we actually don't use it anywhere
+        Self::try_default().unwrap_or_else(|err| panic!("{}", err))
+    }
+}
 
-        // Number of rounds from the original Poseidon2 implementation
-        // https://github.com/HorizenLabs/poseidon2
-        let full_rounds = 8;
-        let partial_rounds = 56;
+impl<E: Engine, const RATE: usize, const WIDTH: usize> Poseidon2Params<E, RATE, WIDTH> {
+    /// Default params, unsupported width is reported here instead of in the
+    /// middle of the first hash.
+    pub fn try_default() -> Result<Self, Poseidon2ParamsError> {
+        Self::with_constants_source(ConstantsSource::default())
+    }
+
+    /// Params with the rounds of default ones but round constants derived
+    /// by `source`.
+    pub fn with_constants_source(source: ConstantsSource) -> Result<Self, Poseidon2ParamsError> {
+        if !POSEIDON2_SUPPORTED_WIDTHS.contains(&WIDTH) {
+            return Err(Poseidon2ParamsError::UnsupportedWidth(WIDTH));
+        }
 
-        let mut params = InnerHashParameters::<E, RATE, WIDTH>::new(security_level, full_rounds, partial_rounds);
+        let partial_rounds = partial_rounds(WIDTH);
+        let mut params = InnerHashParameters::<E, RATE, WIDTH>::new(SECURITY_LEVEL, FULL_ROUNDS, partial_rounds);
 
         // Same constants as in the Poseidon
-        let number_of_rounds = full_rounds + partial_rounds;
-        let rounds_tag = b"Rescue_f";
-        params.compute_round_constants(number_of_rounds, rounds_tag);
+        let number_of_rounds = FULL_ROUNDS + partial_rounds;
+        let rounds_tag = ROUND_CONSTANTS_TAG;
+        params.compute_round_constants_from_source(number_of_rounds, rounds_tag, source);
+
+        Ok(Self::from_inner(params))
+    }
+
+    /// Params with default constants derivation but numbers of rounds
+    /// computed for `security_bits` by the round numbers script of the
+    /// Poseidon authors. For 128 bits these are the numbers of the table used
+    /// by default params.
+    pub fn with_security_level(security_bits: usize) -> Result<Self, Poseidon2ParamsError> {
+        if !POSEIDON2_SUPPORTED_WIDTHS.contains(&WIDTH) {
+            return Err(Poseidon2ParamsError::UnsupportedWidth(WIDTH));
+        }
+
+        let (full_rounds, partial_rounds) = poseidon_number_of_rounds::<E>(WIDTH, 5, security_bits);
+        let mut params = InnerHashParameters::<E, RATE, WIDTH>::new(security_bits, full_rounds, partial_rounds);
+        params.compute_round_constants(full_rounds + partial_rounds, ROUND_CONSTANTS_TAG);
 
+        Ok(Self::from_inner(params))
+    }
+
+    /// Params with round constants derived from `seed` instead of the tag
+    /// shared by default params. Linear layers are fixed by the design, so
+    /// only the constants depend on the seed.
+    pub fn generate_from_seed(seed: &[u8], security_bits: usize) -> Result<Self, SeededParamsError> {
+        if !POSEIDON2_SUPPORTED_WIDTHS.contains(&WIDTH) {
+            return Err(SeededParamsError::UnsupportedWidth(WIDTH));
+        }
+        if security_bits > SECURITY_LEVEL {
+            return Err(SeededParamsError::UnsupportedSecurityLevel {
+                requested: security_bits,
+                supported: SECURITY_LEVEL,
+            });
+        }
+
+        let partial_rounds = partial_rounds(WIDTH);
+        let mut params = InnerHashParameters::<E, RATE, WIDTH>::new(security_bits, FULL_ROUNDS, partial_rounds);
+        params.compute_round_constants_from_seed(FULL_ROUNDS + partial_rounds, b"Poseidon2", seed);
+        // checked before partial rounds keep only their first constant
+        check_round_constants::<E, WIDTH>(params.round_constants())?;
+        if crate::common::matrix::try_inverse::<E, WIDTH>(&poseidon2_external_matrix::<E, WIDTH>()).is_none() {
+            return Err(SeededParamsError::SingularMdsMatrix);
+        }
+
+        Ok(Self::from_inner(params))
+    }
+
+    fn from_inner(params: InnerHashParameters<E, RATE, WIDTH>) -> Self {
         let mds_external_matrix = poseidon2_external_matrix::<E, WIDTH>();
         let diag_internal_matrix = poseidon2_internal_matrix::<E, WIDTH>();
 
@@ -55,6 +169,11 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> Default for Poseidon2Para
         }
 
         let alpha = 5u64;
+        assert!(
+            crate::common::utils::alpha_is_permutation::<E>(alpha),
+            "sbox of degree {} is not a permutation of the field",
+            alpha
+        );
 
         Self {
             alpha: Sbox::Alpha(alpha),
@@ -116,6 +235,20 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> HashParams<E, RATE, WIDTH
         self.custom_gate = custom_gate;
     }
 
+    fn fingerprint(&self) -> [u8; 32] {
+        let mut fingerprint = Fingerprint::new(self.hash_family(), RATE, WIDTH);
+        fingerprint.update_usize(self.full_rounds);
+        fingerprint.update_usize(self.partial_rounds);
+        fingerprint.update_matrix::<E, WIDTH>(&self.mds_external_matrix);
+        fingerprint.update_frs::<E>(&self.diag_internal_matrix);
+        for constants in self.round_constants.iter() {
+            fingerprint.update_frs::<E>(constants);
+        }
+        fingerprint.update_sbox(&self.alpha);
+
+        fingerprint.finalize()
+    }
+
     fn try_to_poseidon2_params(&self) -> Option<&crate::poseidon2::Poseidon2Params<E, RATE, WIDTH>> {
         Some(self)
     }
@@ -166,11 +299,11 @@ fn poseidon2_external_matrix<E: Engine, const WIDTH: usize>() -> [[E::Fr; WIDTH]
                 [one, one, four, six],
             ];
 
-            // circ(2*M4, M4, ..., M4)
+            // M4 itself for width 4, circ(2*M4, M4, ..., M4) otherwise
             for i in 0..WIDTH {
                 for j in 0..WIDTH {
                     result[i][j] = m_4_mds_matrix[i % 4][j % 4];
-                    if i/4 == j/4 {
+                    if WIDTH > 4 && i/4 == j/4 {
                         result[i][j].mul_assign(&two);
                     }
                 }
@@ -181,19 +314,26 @@ fn poseidon2_external_matrix<E: Engine, const WIDTH: usize>() -> [[E::Fr; WIDTH]
     result
 }
 
-fn poseidon2_internal_matrix<E: Engine, const WIDTH: usize>() -> [E::Fr; WIDTH] {
-    let two = E::Fr::from_str("2").unwrap();
-    let three = E::Fr::from_str("3").unwrap();
+// Diagonal of the internal matrix, which has ones off the diagonal. Width 3
+// uses the matrix of the original implementation. There are no reference
+// matrices over 254 bit fields for other widths, so as the paper requires
+// for them, diagonal minus one is the lexicographically first set of distinct
+// positive integers such that minimal polynomials of the matrix powers up to
+// `2 * WIDTH` are irreducible of degree `WIDTH` over the BN254 scalar field,
+// see `test_internal_matrix_conditions`.
+pub(crate) fn poseidon2_internal_matrix<E: Engine, const WIDTH: usize>() -> [E::Fr; WIDTH] {
+    let diagonal: &[u64] = match WIDTH {
+        3 => &[2, 2, 3],
+        4 => &[2, 3, 4, 11],
+        8 => &[2, 3, 4, 5, 6, 7, 8, 20],
+        12 => &[2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 20],
+        _ => unreachable!("width {} is rejected by params construction", WIDTH),
+    };
 
     let mut result = [E::Fr::zero(); WIDTH];
-    match WIDTH {
-        3 => {
-            result[0] = two;
-            result[1] = two;
-            result[2] = three;
-        },
-        _ => todo!("poseidon_2_internal_matrix for WIDTH == {}", WIDTH),
-    };
+    for (el, value) in result.iter_mut().zip(diagonal.iter()) {
+        *el = E::Fr::from_str(&value.to_string()).unwrap();
+    }
 
     result
 }
@@ -1,8 +1,9 @@
 use crate::traits::HashParams;
-use franklin_crypto::bellman::{Engine, Field, PrimeField};
+use alloc::vec::Vec;
+use franklin_crypto::bellman::{Engine, Field, PrimeField, PrimeFieldRepr};
 use crate::common::domain_strategy::DomainStrategy;
 use super::params::Poseidon2Params;
-use crate::traits::Sbox;
+use crate::traits::{NoopMiddleware, RoundFunctionMiddleware, Sbox};
 
 /// Receives inputs whose length `known` prior(fixed-length).
 /// Also uses custom domain strategy which basically sets value of capacity element to
@@ -19,6 +20,142 @@ pub fn poseidon2_hash<
     crate::generic_hash(&params, input, None)
 }
 
+/// Same as `poseidon2_hash` for many inputs of any length, see
+/// `generic_hash_batch`.
+pub fn poseidon2_hash_batch<E: Engine>(inputs: &[&[E::Fr]]) -> Vec<[E::Fr; 2]> {
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+
+    let params = Poseidon2Params::<E, RATE, WIDTH>::default();
+    crate::generic_hash_batch(&params, inputs)
+}
+
+/// Number of bytes packed into a single field element by `poseidon2_hash_bytes_fast`.
+pub const POSEIDON2_BYTES_PER_ELEMENT: usize = 31;
+
+/// Hashes a byte buffer with Poseidon2 of state-width=3 and rate=2.
+/// Bytes are packed into field elements `POSEIDON2_BYTES_PER_ELEMENT` at a
+/// time in little endian order and absorbed straight from the input, without
+/// intermediate buffers. Capacity element is set to `2^64 + bytes.len()`, so
+/// digests never collide with ones of field element hashing, and the last
+/// chunk is padded with ones.
+pub fn poseidon2_hash_bytes_fast<E: Engine>(bytes: &[u8]) -> [E::Fr; 2] {
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+    const CHUNK_LEN: usize = RATE * POSEIDON2_BYTES_PER_ELEMENT;
+    assert!(E::Fr::CAPACITY as usize >= 8 * POSEIDON2_BYTES_PER_ELEMENT);
+
+    let params = Poseidon2Params::<E, RATE, WIDTH>::default();
+    let mut capacity = <E::Fr as PrimeField>::Repr::default();
+    capacity.as_mut()[0] = bytes.len() as u64;
+    capacity.as_mut()[1] = 1;
+    let mut state = [E::Fr::zero(), E::Fr::zero(), E::Fr::from_repr(capacity).expect("a field element")];
+
+    let mut chunks = bytes.chunks_exact(CHUNK_LEN);
+    for chunk in &mut chunks {
+        for (s, value) in state.iter_mut().zip(chunk.chunks_exact(POSEIDON2_BYTES_PER_ELEMENT)) {
+            s.add_assign(&fe_from_le_bytes::<E>(value));
+        }
+        poseidon2_round_function(&mut state, &params);
+    }
+
+    // last chunk is absorbed whenever input is not rate-aligned or empty
+    let remainder = chunks.remainder();
+    if !remainder.is_empty() || bytes.is_empty() {
+        let mut values = remainder.chunks(POSEIDON2_BYTES_PER_ELEMENT);
+        for s in state[..RATE].iter_mut() {
+            match values.next() {
+                Some(value) => s.add_assign(&fe_from_le_bytes::<E>(value)),
+                None => s.add_assign(&E::Fr::one()),
+            }
+        }
+        poseidon2_round_function(&mut state, &params);
+    }
+
+    [state[0], state[1]]
+}
+
+/// Number of rate-sized chunks `hash_reader` keeps in memory at once.
+#[cfg(feature = "std")]
+pub const HASH_READER_CHUNKS_PER_BLOCK: usize = 1024;
+
+/// Hashes bytes of a reader, e.g. a multi-GB witness file, with memory
+/// bounded by `HASH_READER_CHUNKS_PER_BLOCK` chunks. Bytes are packed as in
+/// `poseidon2_hash_bytes_fast`, but length is not known in advance, so
+/// capacity element starts at `2^65` and the length is absorbed after the
+/// padded last chunk instead. Digests therefore differ from the ones of
+/// `poseidon2_hash_bytes_fast` for the same bytes.
+#[cfg(feature = "std")]
+pub fn hash_reader<E: Engine, P: HashParams<E, RATE, WIDTH>, R: std::io::Read, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    mut reader: R,
+) -> std::io::Result<[E::Fr; RATE]> {
+    assert!(E::Fr::CAPACITY as usize >= 8 * POSEIDON2_BYTES_PER_ELEMENT);
+    let chunk_len = RATE * POSEIDON2_BYTES_PER_ELEMENT;
+
+    let mut capacity = <E::Fr as PrimeField>::Repr::default();
+    capacity.as_mut()[1] = 2;
+    let mut state = [E::Fr::zero(); WIDTH];
+    state[WIDTH - 1] = E::Fr::from_repr(capacity).expect("a field element");
+
+    let mut buffer = vec![0u8; chunk_len * HASH_READER_CHUNKS_PER_BLOCK];
+    let mut filled = 0;
+    let mut total_len = 0u64;
+    loop {
+        let read = match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        filled += read;
+        total_len += read as u64;
+
+        // incomplete chunk is kept for the next read
+        let processed = filled - filled % chunk_len;
+        for chunk in buffer[..processed].chunks_exact(chunk_len) {
+            for (s, value) in state.iter_mut().zip(chunk.chunks_exact(POSEIDON2_BYTES_PER_ELEMENT)) {
+                s.add_assign(&fe_from_le_bytes::<E>(value));
+            }
+            crate::generic_round_function(params, &mut state);
+        }
+        buffer.copy_within(processed..filled, 0);
+        filled -= processed;
+    }
+
+    // last chunk is always absorbed and padded with ones
+    let mut values = buffer[..filled].chunks(POSEIDON2_BYTES_PER_ELEMENT);
+    for s in state[..RATE].iter_mut() {
+        match values.next() {
+            Some(value) => s.add_assign(&fe_from_le_bytes::<E>(value)),
+            None => s.add_assign(&E::Fr::one()),
+        }
+    }
+    crate::generic_round_function(params, &mut state);
+
+    let mut len_repr = <E::Fr as PrimeField>::Repr::default();
+    len_repr.as_mut()[0] = total_len;
+    state[0].add_assign(&E::Fr::from_repr(len_repr).expect("a field element"));
+    crate::generic_round_function(params, &mut state);
+
+    let mut output = [E::Fr::zero(); RATE];
+    output.copy_from_slice(&state[..RATE]);
+
+    Ok(output)
+}
+
+// Bytes are copied into a zeroed buffer of the representation size at once.
+fn fe_from_le_bytes<E: Engine>(bytes: &[u8]) -> E::Fr {
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    let mut buffer = [0u8; 64];
+    let repr_len = repr.as_ref().len() * 8;
+    assert!(bytes.len() < repr_len && repr_len <= buffer.len());
+    buffer[..bytes.len()].copy_from_slice(bytes);
+    repr.read_le(&buffer[..repr_len]).expect("consume all bytes");
+
+    E::Fr::from_repr(repr).expect("a field element")
+}
+
 pub(crate) fn poseidon2_round_function<
     E: Engine,
     const RATE: usize,
@@ -26,6 +163,36 @@ pub(crate) fn poseidon2_round_function<
 >(
     state: &mut [E::Fr; WIDTH],
     params: &Poseidon2Params<E, RATE, WIDTH>,
+) {
+    poseidon2_round_function_with_middleware(state, params, &mut NoopMiddleware)
+}
+
+pub(crate) fn poseidon2_round_function_with_middleware<
+    E: Engine,
+    M: RoundFunctionMiddleware<E, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    state: &mut [E::Fr; WIDTH],
+    params: &Poseidon2Params<E, RATE, WIDTH>,
+    middleware: &mut M,
+) {
+    if WIDTH == 3 {
+        poseidon2_round_function_width_3(state, params, middleware)
+    } else {
+        poseidon2_round_function_generic(state, params, middleware)
+    }
+}
+
+pub(crate) fn poseidon2_round_function_generic<
+    E: Engine,
+    M: RoundFunctionMiddleware<E, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    state: &mut [E::Fr; WIDTH],
+    params: &Poseidon2Params<E, RATE, WIDTH>,
+    middleware: &mut M,
 ) {
     debug_assert!(params.full_rounds & 1 == 0);
     let half_of_full_rounds = params.number_of_full_rounds() / 2;
@@ -37,21 +204,90 @@ pub(crate) fn poseidon2_round_function<
         add_rc::<E, WIDTH>(state, &params.round_constants[r]);
         apply_sbox::<E>(state, &params.alpha);
         poseidon2_matmul_external::<E, WIDTH>(state);
+        middleware.after_round(r, state);
     }
 
     for r in half_of_full_rounds..(half_of_full_rounds + params.partial_rounds) {
         state[0].add_assign(&params.round_constants[r][0]);
         apply_sbox::<E>(&mut state[..1], &params.alpha);
         poseidon2_matmul_internal::<E, WIDTH>(state, &params.diag_internal_matrix);
+        middleware.after_round(r, state);
     }
     
     for r in (half_of_full_rounds + params.partial_rounds)..(2*half_of_full_rounds + params.partial_rounds) {
         add_rc::<E, WIDTH>(state, &params.round_constants[r]);
         apply_sbox::<E>(state, &params.alpha);
         poseidon2_matmul_external::<E, WIDTH>(state);
+        middleware.after_round(r, state);
     }
 }
 
+// Hot path for state width 3. Each round adds constants, applies x^5 and
+// accumulates the sum for the matrix in a single pass over the state.
+pub(crate) fn poseidon2_round_function_width_3<
+    E: Engine,
+    M: RoundFunctionMiddleware<E, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    state: &mut [E::Fr; WIDTH],
+    params: &Poseidon2Params<E, RATE, WIDTH>,
+    middleware: &mut M,
+) {
+    debug_assert_eq!(WIDTH, 3);
+    debug_assert!(params.full_rounds & 1 == 0);
+    debug_assert!(params.alpha == Sbox::Alpha(5));
+    debug_assert_eq!(params.diag_internal_matrix[0], E::Fr::from_str("2").unwrap());
+    debug_assert_eq!(params.diag_internal_matrix[1], E::Fr::from_str("2").unwrap());
+    debug_assert_eq!(params.diag_internal_matrix[2], E::Fr::from_str("3").unwrap());
+    let half_of_full_rounds = params.full_rounds / 2;
+    let partial_rounds_end = half_of_full_rounds + params.partial_rounds;
+
+    // Linear layer at beginning, circ(2, 1, 1)
+    let mut sum = state[0];
+    sum.add_assign(&state[1]);
+    sum.add_assign(&state[2]);
+    state[0].add_assign(&sum);
+    state[1].add_assign(&sum);
+    state[2].add_assign(&sum);
+
+    for r in 0..params.full_rounds + params.partial_rounds {
+        let constants = &params.round_constants[r];
+        if r < half_of_full_rounds || r >= partial_rounds_end {
+            let mut sum = E::Fr::zero();
+            for (element, constant) in state.iter_mut().zip(constants.iter()) {
+                element.add_assign(constant);
+                quintic::<E>(element);
+                sum.add_assign(element);
+            }
+            for element in state.iter_mut() {
+                element.add_assign(&sum);
+            }
+        } else {
+            state[0].add_assign(&constants[0]);
+            quintic::<E>(&mut state[0]);
+            // diag(2, 2, 3) + 1
+            let mut sum = state[0];
+            sum.add_assign(&state[1]);
+            sum.add_assign(&state[2]);
+            state[0].add_assign(&sum);
+            state[1].add_assign(&sum);
+            state[2].double();
+            state[2].add_assign(&sum);
+        }
+        middleware.after_round(r, state);
+    }
+}
+
+#[inline(always)]
+fn quintic<E: Engine>(element: &mut E::Fr) {
+    let mut res = *element;
+    res.square();
+    res.square();
+    res.mul_assign(element);
+    *element = res;
+}
+
 pub(crate) fn poseidon2_matmul_external<
     E: Engine,
     const WIDTH: usize,
@@ -97,7 +333,7 @@ pub(crate) fn poseidon2_matmul_external<
             }
         }
         _ => {
-            panic!()
+            unreachable!("width {} is rejected by params construction", WIDTH)
         }
     }
 }
@@ -200,7 +436,7 @@ pub(crate) fn poseidon2_matmul_internal<
             }
         }
         _ => {
-            panic!()
+            unreachable!("width {} is rejected by params construction", WIDTH)
         }
     }
 }
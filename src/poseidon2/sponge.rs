@@ -6,13 +6,51 @@ use franklin_crypto::boojum::cs::oracle::TreeHasher;
 use franklin_crypto::bellman::{Engine, Field, PrimeField, PrimeFieldRepr};
 use franklin_crypto::boojum::algebraic_props::round_function::AbsorptionModeTrait;
 
+#[cfg(feature = "std")]
 use typemap_rev::{TypeMap, TypeMapKey};
-use std::sync::{Arc, RwLock};
-
+#[cfg(feature = "std")]
+use std::sync::RwLock;
+use alloc::sync::Arc;
+use crate::sponge::absorb_into_rate;
+use crate::common::utils::try_slice_into_fixed;
+use crate::state::State;
+
+#[cfg(feature = "std")]
 impl<E: Engine, const RATE: usize, const WIDTH: usize> TypeMapKey for Poseidon2Params::<E, RATE, WIDTH> {
     type Value = Arc<Poseidon2Params::<E, RATE, WIDTH>>;
 }
 
+impl<E: Engine, const RATE: usize, const WIDTH: usize> Poseidon2Params<E, RATE, WIDTH> {
+    /// Default params shared by sponges and circuit hashes of the same
+    /// (E, RATE, WIDTH), so constants are derived once per process. Without
+    /// `std` there is no lock to guard the cache and every call derives its
+    /// own copy.
+    pub fn shared() -> Arc<Self> {
+        #[cfg(feature = "std")]
+        {
+            lazy_static::lazy_static!{
+                static ref POSEIDON_PARAMS: RwLock<TypeMap> = RwLock::new(TypeMap::new());
+            };
+
+            let static_params = POSEIDON_PARAMS.read().unwrap();
+            let params = static_params.get::<Self>().cloned();
+            drop(static_params);
+
+            if let Some(params) = params {
+                params
+            } else {
+                let params = Arc::new(Self::default());
+                let mut static_params = POSEIDON_PARAMS.write().unwrap();
+                static_params.insert::<Self>(params.clone());
+                params
+            }
+        }
+
+        #[cfg(not(feature = "std"))]
+        Arc::new(Self::default())
+    }
+}
+
 #[derive(Derivative)]
 #[derivative(Clone, Debug)]
 pub struct Poseidon2Sponge<
@@ -22,12 +60,12 @@ pub struct Poseidon2Sponge<
     const RATE: usize,
     const WIDTH: usize
 >{
-    pub(crate) state: [E::Fr; WIDTH],
+    pub(crate) state: State<E, WIDTH>,
     pub(crate) buffer: [E::Fr; RATE],
     pub(crate) filled: usize,
     #[derivative(Debug = "ignore")]
     pub(crate) params: Arc<Poseidon2Params<E, RATE, WIDTH>>,
-    _marker: std::marker::PhantomData<(F, M)>,
+    _marker: core::marker::PhantomData<(F, M)>,
 }
 
 impl<
@@ -40,38 +78,85 @@ impl<
     pub fn new() -> Self {
         assert!(Self::capasity_per_element() > 0);
 
-        lazy_static::lazy_static!{
-            static ref POSEIDON_PARAMS: RwLock<TypeMap> = RwLock::new(TypeMap::new());
-        };
-
-        let static_params = POSEIDON_PARAMS.read().unwrap();
-        let params = static_params.get::<Poseidon2Params<E, RATE, WIDTH>>().map(|p| p.clone());
-        drop(static_params);
-
-        let params = if let Some(params) = params {
-            params
-        } else {
-            let params = Arc::new(Poseidon2Params::<E, RATE, WIDTH>::default());
-            let mut static_params = POSEIDON_PARAMS.write().unwrap();
-            static_params.insert::<Poseidon2Params<E, RATE, WIDTH>>(params.clone());
-            params
-        };
+        let params = Poseidon2Params::<E, RATE, WIDTH>::shared();
 
         Self {
             params,
-            state: [E::Fr::zero(); WIDTH],
+            state: State::new(),
             buffer: [E::Fr::zero(); RATE],
             filled: 0,
-            _marker: std::marker::PhantomData,
+            _marker: core::marker::PhantomData,
         }
     }
 
+    /// Restores sponge from raw state, buffer and number of small field
+    /// elements already packed into buffer. Returns `None` if buffer
+    /// content can not be reached by the sponge itself.
+    pub fn from_raw_state(
+        state: [E::Fr; WIDTH],
+        buffer: [E::Fr; RATE],
+        filled: usize,
+    ) -> Option<Self> {
+        let capasity_per_element = Self::capasity_per_element();
+        if filled >= RATE * capasity_per_element {
+            // full buffer is always absorbed into state immediately
+            return None;
+        }
+
+        let used = (filled + capasity_per_element - 1) / capasity_per_element;
+        if buffer[used..].iter().any(|el| !el.is_zero()) {
+            return None;
+        }
+
+        let mut sponge = Self::new();
+        sponge.state = State::from(state);
+        sponge.buffer = buffer;
+        sponge.filled = filled;
+
+        Some(sponge)
+    }
+
+    /// Exports raw state, buffer and number of packed small field elements.
+    pub fn into_raw_state(self) -> ([E::Fr; WIDTH], [E::Fr; RATE], usize) {
+        (self.state.into_array(), self.buffer, self.filled)
+    }
+
+    pub fn rate(&self) -> usize {
+        RATE
+    }
+
+    pub fn capacity(&self) -> usize {
+        WIDTH - RATE
+    }
+
     pub fn capasity_per_element() -> usize {
         (E::Fr::CAPACITY as usize) / (F::CHAR_BITS as usize)
     }
 
     pub fn run_round_function(&mut self) {
-        poseidon2_round_function(&mut self.state, &self.params);
+        poseidon2_round_function(&mut *self.state, &self.params);
+    }
+
+    /// Absorbs pending elements of the buffer into the state. Buffer is padded
+    /// the same way as in `finalize`, i.e. a single small field `ONE` is packed
+    /// right after the last pending element and the rest is zero. Does nothing
+    /// if there are no pending elements.
+    pub fn flush(&mut self) {
+        if self.filled == 0 {
+            return;
+        }
+        self.absorb_single_small_field(&F::ONE);
+        if self.filled > 0 {
+            self.absorb_buffer_to_state();
+        }
+    }
+
+    /// Returns commitment to everything absorbed so far, flushing pending
+    /// elements first. Unlike `try_get_committment` it is always defined.
+    pub fn committed_state(&mut self) -> [E::Fr; RATE] {
+        self.flush();
+
+        try_slice_into_fixed(&self.state[..RATE]).expect("rate part")
     }
 
     pub fn try_get_committment(&mut self) -> Option<[E::Fr; RATE]> {
@@ -79,16 +164,12 @@ impl<
             return None;
         }
 
-        Some(self.state[..RATE].try_into().unwrap())
+        Some(try_slice_into_fixed(&self.state[..RATE]).expect("rate part"))
     }
 
     pub fn absorb_buffer_to_state(&mut self) {
-        for (dst, src) in self.state.iter_mut()
-            .zip(self.buffer.iter_mut())
-        {
-            M::absorb(dst, src);
-            *src = E::Fr::zero();
-        }
+        absorb_into_rate::<_, _, RATE, WIDTH>(&mut *self.state, &self.buffer, M::absorb);
+        self.buffer = [E::Fr::zero(); RATE];
 
         self.run_round_function();
         self.filled = 0;
@@ -171,9 +252,7 @@ impl<
         self.absorb_buffer_to_state();
 
         for chunk in values[chunks_start..chunk_finish].chunks_exact(RATE) {
-            for (j, value) in chunk.iter().enumerate() {
-                M::absorb(&mut self.state[j], value);
-            }
+            absorb_into_rate::<_, _, RATE, WIDTH>(&mut *self.state, chunk, M::absorb);
             self.run_round_function();
         }
 
@@ -190,7 +269,7 @@ impl<
             self.absorb_buffer_to_state();
         }
 
-        self.state[..RATE].try_into().unwrap()
+        try_slice_into_fixed(&self.state[..RATE]).expect("rate part")
     }
 
     pub fn finalize_reset(&mut self) -> [E::Fr; RATE] {
@@ -198,21 +277,19 @@ impl<
         self.absorb_single_small_field(&F::ONE);
 
         // reset
-        let mut state = std::mem::replace(&mut self.state, [E::Fr::zero(); WIDTH]);
+        let mut state = core::mem::replace(&mut self.state, State::new());
         let filled = self.filled;
         self.filled = 0;
 
         // run round function if necessary
         if filled > 0 {
-            for (dst, src) in state.iter_mut().zip(self.buffer.iter_mut()) {
-                M::absorb(dst, src);
-                *src = E::Fr::zero();
-            }
+            absorb_into_rate::<_, _, RATE, WIDTH>(&mut *state, &self.buffer, M::absorb);
+            self.buffer = [E::Fr::zero(); RATE];
 
-            poseidon2_round_function(&mut state, &self.params);
+            poseidon2_round_function(&mut *state, &self.params);
         }
 
-        self.state[..RATE].try_into().unwrap()
+        try_slice_into_fixed(&self.state[..RATE]).expect("rate part")
     }
 }
 
@@ -270,28 +347,13 @@ impl<
 
     #[inline]
     fn hash_into_node(left: &Self::Output, right: &Self::Output, _depth: usize) -> Self::Output {
-        lazy_static::lazy_static!{
-            static ref POSEIDON_PARAMS: RwLock<TypeMap> = RwLock::new(TypeMap::new());
-        };
-
-        let static_params = POSEIDON_PARAMS.read().unwrap();
-        let params = static_params.get::<Poseidon2Params<E, RATE, WIDTH>>().map(|p| p.clone());
-        drop(static_params);
-
-        let params = if let Some(params) = params {
-            params
-        } else {
-            let params = Arc::new(Poseidon2Params::<E, RATE, WIDTH>::default());
-            let mut static_params = POSEIDON_PARAMS.write().unwrap();
-            static_params.insert::<Poseidon2Params<E, RATE, WIDTH>>(params.clone());
-            params
-        };
-
-        let mut state = [E::Fr::zero(); WIDTH];
+        let params = Poseidon2Params::<E, RATE, WIDTH>::shared();
+
+        let mut state = State::<E, WIDTH>::new();
         M::absorb(&mut state[0], left);
         M::absorb(&mut state[1], right);
 
-        poseidon2_round_function(&mut state, params.as_ref());
+        poseidon2_round_function(&mut *state, params.as_ref());
 
         state[0]
     }
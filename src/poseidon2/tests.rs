@@ -146,6 +146,244 @@ fn test_circuit_round_function() {
     assert_eq!(state, circuit_state.map(|x| x.get_value().unwrap()));
 }
 
+#[test]
+#[cfg(feature = "poseidon2-experimental-widths")]
+fn test_wide_round_functions() {
+    fn check<const RATE: usize, const WIDTH: usize>() {
+        let params = crate::poseidon2::Poseidon2Params::<Bn256, RATE, WIDTH>::default();
+        let cs = &mut init_cs::<Bn256>();
+
+        let mut rng = rand::thread_rng();
+        let mut state = [0; WIDTH].map(|_| Fr::rand(&mut rng));
+        let mut circuit_state = state.map(|x| Num::alloc(cs, Some(x)).unwrap().into());
+
+        // circuit uses dense matrices while native uses fast linear layers
+        poseidon2_round_function::<Bn256, RATE, WIDTH>(&mut state, &params);
+        circuit_poseidon2_round_function(cs, &params, &mut circuit_state).unwrap();
+
+        assert!(cs.is_satisfied());
+        assert_eq!(state, circuit_state.map(|x: LinearCombination<Bn256>| x.get_value().unwrap()));
+    }
+
+    check::<3, 4>();
+    check::<4, 8>();
+    check::<8, 12>();
+}
+
+#[test]
+fn test_wide_linear_layers() {
+    use crate::poseidon2::poseidon2::{poseidon2_matmul_external, poseidon2_matmul_internal};
+    use franklin_crypto::bellman::Field;
+
+    fn dense_product<const WIDTH: usize>(matrix: &[[Fr; WIDTH]; WIDTH], state: &[Fr; WIDTH]) -> [Fr; WIDTH] {
+        core::array::from_fn(|i| {
+            let mut acc = Fr::zero();
+            for (m, s) in matrix[i].iter().zip(state.iter()) {
+                let mut tmp = *m;
+                tmp.mul_assign(s);
+                acc.add_assign(&tmp);
+            }
+            acc
+        })
+    }
+
+    fn check<const RATE: usize, const WIDTH: usize>() {
+        let params = crate::poseidon2::Poseidon2Params::<Bn256, RATE, WIDTH>::default();
+        let mut internal = [[Fr::one(); WIDTH]; WIDTH];
+        for (i, row) in internal.iter_mut().enumerate() {
+            row[i] = params.diag_internal_matrix[i];
+        }
+
+        let mut rng = rand::thread_rng();
+        let state = [0; WIDTH].map(|_| Fr::rand(&mut rng));
+
+        let mut actual = state;
+        poseidon2_matmul_external::<Bn256, WIDTH>(&mut actual);
+        assert_eq!(actual, dense_product(&params.mds_external_matrix, &state));
+
+        let mut actual = state;
+        poseidon2_matmul_internal::<Bn256, WIDTH>(&mut actual, &params.diag_internal_matrix);
+        assert_eq!(actual, dense_product(&internal, &state));
+    }
+
+    check::<2, 3>();
+    #[cfg(feature = "poseidon2-experimental-widths")]
+    {
+        check::<3, 4>();
+        check::<4, 8>();
+        check::<8, 12>();
+    }
+}
+
+// Internal matrices of widths other than 3 are chosen by this condition, see
+// `poseidon2_internal_matrix`. Characteristic polynomial of a power of the
+// matrix which is irreducible is also its minimal polynomial.
+#[test]
+fn test_internal_matrix_conditions() {
+    use franklin_crypto::bellman::{Field, PrimeField};
+
+    fn fe(value: usize) -> Fr {
+        Fr::from_str(&value.to_string()).unwrap()
+    }
+
+    fn trim(mut a: Vec<Fr>) -> Vec<Fr> {
+        while a.last().map_or(false, |c| c.is_zero()) {
+            a.pop();
+        }
+        a
+    }
+
+    // remainder of `a` divided by non-zero `b`
+    fn rem(mut a: Vec<Fr>, b: &[Fr]) -> Vec<Fr> {
+        let b = trim(b.to_vec());
+        let lead_inv = b.last().unwrap().inverse().unwrap();
+        while a.len() >= b.len() {
+            let mut factor = *a.last().unwrap();
+            factor.mul_assign(&lead_inv);
+            let shift = a.len() - b.len();
+            for (i, c) in b.iter().enumerate() {
+                let mut tmp = *c;
+                tmp.mul_assign(&factor);
+                a[shift + i].sub_assign(&tmp);
+            }
+            a.pop();
+        }
+        trim(a)
+    }
+
+    fn mul_mod(a: &[Fr], b: &[Fr], f: &[Fr]) -> Vec<Fr> {
+        if a.is_empty() || b.is_empty() {
+            return vec![];
+        }
+        let mut result = vec![Fr::zero(); a.len() + b.len() - 1];
+        for (i, x) in a.iter().enumerate() {
+            for (j, y) in b.iter().enumerate() {
+                let mut tmp = *x;
+                tmp.mul_assign(y);
+                result[i + j].add_assign(&tmp);
+            }
+        }
+        rem(result, f)
+    }
+
+    // g(h) mod f
+    fn compose(g: &[Fr], h: &[Fr], f: &[Fr]) -> Vec<Fr> {
+        let mut result = vec![];
+        for c in g.iter().rev() {
+            result = mul_mod(&result, h, f);
+            if result.is_empty() {
+                result.push(Fr::zero());
+            }
+            result[0].add_assign(c);
+            result = trim(result);
+        }
+        result
+    }
+
+    fn gcd(mut a: Vec<Fr>, mut b: Vec<Fr>) -> Vec<Fr> {
+        while !b.is_empty() {
+            let r = rem(a, &b);
+            a = b;
+            b = r;
+        }
+        a
+    }
+
+    fn minus_x(mut a: Vec<Fr>) -> Vec<Fr> {
+        a.resize(a.len().max(2), Fr::zero());
+        a[1].sub_assign(&Fr::one());
+        trim(a)
+    }
+
+    // Rabin's test: f of degree n divides x^(p^n) - x and is coprime with
+    // x^(p^(n/q)) - x for each prime q dividing n
+    fn is_irreducible(f: &[Fr]) -> bool {
+        let n = f.len() - 1;
+        let x = vec![Fr::zero(), Fr::one()];
+        let mut x_to_p = vec![Fr::one()];
+        for limb in Fr::char().as_ref().iter().rev() {
+            for bit in (0..64).rev() {
+                x_to_p = mul_mod(&x_to_p, &x_to_p, f);
+                if (limb >> bit) & 1 == 1 {
+                    x_to_p = mul_mod(&x_to_p, &x, f);
+                }
+            }
+        }
+        // frobenius[i] = x^(p^i) mod f
+        let mut frobenius = vec![x.clone(), x_to_p.clone()];
+        for i in 2..=n {
+            let next = compose(&frobenius[i - 1], &x_to_p, f);
+            frobenius.push(next);
+        }
+
+        let prime_divisors = (2..=n).filter(|q| n % q == 0 && (2..*q).all(|d| q % d != 0));
+        minus_x(frobenius[n].clone()).is_empty()
+            && prime_divisors.into_iter().all(|q| gcd(f.to_vec(), minus_x(frobenius[n / q].clone())).len() == 1)
+    }
+
+    // Faddeev-LeVerrier
+    fn characteristic_polynomial<const WIDTH: usize>(matrix: &[[Fr; WIDTH]; WIDTH]) -> Vec<Fr> {
+        let mut coeffs = vec![Fr::zero(); WIDTH + 1];
+        coeffs[WIDTH] = Fr::one();
+        let mut m = [[Fr::zero(); WIDTH]; WIDTH];
+        for k in 1..=WIDTH {
+            m = mat_mul(matrix, &m);
+            for i in 0..WIDTH {
+                m[i][i].add_assign(&coeffs[WIDTH + 1 - k]);
+            }
+            let am = mat_mul(matrix, &m);
+            let mut trace = Fr::zero();
+            for i in 0..WIDTH {
+                trace.add_assign(&am[i][i]);
+            }
+            trace.mul_assign(&fe(k).inverse().unwrap());
+            trace.negate();
+            coeffs[WIDTH - k] = trace;
+        }
+        coeffs
+    }
+
+    fn mat_mul<const WIDTH: usize>(a: &[[Fr; WIDTH]; WIDTH], b: &[[Fr; WIDTH]; WIDTH]) -> [[Fr; WIDTH]; WIDTH] {
+        let mut result = [[Fr::zero(); WIDTH]; WIDTH];
+        for i in 0..WIDTH {
+            for j in 0..WIDTH {
+                for k in 0..WIDTH {
+                    let mut tmp = a[i][k];
+                    tmp.mul_assign(&b[k][j]);
+                    result[i][j].add_assign(&tmp);
+                }
+            }
+        }
+        result
+    }
+
+    fn check<const WIDTH: usize>() {
+        let diagonal = super::params::poseidon2_internal_matrix::<Bn256, WIDTH>();
+        let mut matrix = [[Fr::one(); WIDTH]; WIDTH];
+        for (i, row) in matrix.iter_mut().enumerate() {
+            row[i] = diagonal[i];
+        }
+
+        let mut power = matrix;
+        for k in 1..=2 * WIDTH {
+            assert!(is_irreducible(&characteristic_polynomial(&power)), "width {}, power {}", WIDTH, k);
+            power = mat_mul(&matrix, &power);
+        }
+    }
+
+    // sanity check: x^2 - 5 is irreducible as 5 is not a square modulo p
+    let mut minus_five = fe(5);
+    minus_five.negate();
+    assert!(is_irreducible(&[minus_five, Fr::zero(), Fr::one()]));
+    // while diagonal of width 3 has repeated entries
+    let repeated = characteristic_polynomial(&[[fe(2), fe(1), fe(1)], [fe(1), fe(2), fe(1)], [fe(1), fe(1), fe(3)]]);
+    assert!(!is_irreducible(&repeated));
+
+    check::<4>();
+    check::<8>();
+    check::<12>();
+}
+
 #[test]
 fn test_circuit_hash() {
     let cs = &mut init_cs::<Bn256>();
@@ -164,6 +402,26 @@ fn test_circuit_hash() {
     assert_eq!(hash1, hash2.map(|x| x.get_value().unwrap()));
 }
 
+#[test]
+fn test_circuit_hash_with_shared_params() {
+    use crate::circuit::poseidon2::circuit_poseidon2_hash_with_params;
+    use std::sync::Arc;
+
+    let cs = &mut init_cs::<Bn256>();
+    let mut rng = rand::thread_rng();
+    let buffer = [0; 3].map(|_| Fr::rand(&mut rng));
+    let num_buffer = buffer.map(|x| Num::alloc(cs, Some(x)).unwrap());
+
+    // same params instance is handed out to every user
+    let params = crate::poseidon2::Poseidon2Params::<Bn256, 2, 3>::shared();
+    assert!(Arc::ptr_eq(&params, &crate::poseidon2::Poseidon2Params::<Bn256, 2, 3>::shared()));
+
+    let expected = circuit_poseidon2_hash(cs, &num_buffer, None).unwrap();
+    let actual = circuit_poseidon2_hash_with_params(cs, &num_buffer, &params, None).unwrap();
+    assert_eq!(actual.map(|x| x.get_value().unwrap()), expected.map(|x| x.get_value().unwrap()));
+    assert!(cs.is_satisfied());
+}
+
 #[test]
 fn test_pow_runner() {
     let worker = Worker::new();
@@ -178,3 +436,653 @@ fn test_pow_runner() {
 
     dbg!(challenge);
 }
+
+#[test]
+fn test_params_fingerprint() {
+    use crate::traits::HashParams;
+    use crate::poseidon2::transcript::Poseidon2Transcript;
+    use franklin_crypto::boojum::cs::implementations::transcript::Transcript;
+
+    let poseidon2 = crate::poseidon2::Poseidon2Params::<Bn256, 2, 3>::default();
+    let poseidon = crate::PoseidonParams::<Bn256, 2, 3>::default();
+    assert_eq!(poseidon2.fingerprint(), poseidon2.clone().fingerprint());
+    assert_ne!(poseidon2.fingerprint(), poseidon.fingerprint());
+
+    let mut bound = Poseidon2Transcript::<Bn256, GoldilocksField, TestingAbsorption, 2, 3>::new_bound_to_params();
+    let mut unbound = Poseidon2Transcript::<Bn256, GoldilocksField, TestingAbsorption, 2, 3>::new();
+    bound.witness_field_elements(&[GoldilocksField::ONE]);
+    unbound.witness_field_elements(&[GoldilocksField::ONE]);
+    assert_ne!(bound.get_challenge(), unbound.get_challenge());
+}
+
+#[test]
+fn test_extension_challenge() {
+    use crate::poseidon2::transcript::Poseidon2Transcript;
+    use franklin_crypto::boojum::cs::implementations::transcript::Transcript;
+
+    let mut rng = rand::thread_rng();
+    let elements: Vec<_> = (0..5).map(|_| GoldilocksField::from_u64_unchecked(rng.gen_range(0, GoldilocksField::CHAR))).collect();
+
+    let mut first = Poseidon2Transcript::<Bn256, GoldilocksField, TestingAbsorption, 2, 3>::new();
+    let mut second = first.clone();
+    first.witness_field_elements(&elements);
+    second.witness_field_elements(&elements);
+
+    let challenge = first.get_extension_challenge::<2>();
+    let expected = [second.get_challenge(), second.get_challenge()];
+    assert_eq!(challenge, expected);
+    assert_ne!(challenge[0], challenge[1]);
+}
+
+#[test]
+fn test_circuit_poseidon2_hash_small_field() {
+    use crate::circuit::poseidon2::{circuit_poseidon2_absorb_small_field, circuit_poseidon2_hash_small_field};
+    use franklin_crypto::bellman::{PrimeField, PrimeFieldRepr};
+
+    let mut rng = rand::thread_rng();
+    let cs = &mut init_cs::<Bn256>();
+
+    for num_limbs in [5, 6, 7] {
+        let limbs: Vec<_> = (0..num_limbs).map(|_|
+            GoldilocksField::from_u64_unchecked(rng.gen_range(0, GoldilocksField::CHAR))
+        ).collect();
+        let limbs_as_num: Vec<_> = limbs.iter().map(|limb| {
+            let value = Fr::from_repr(<Fr as PrimeField>::Repr::from(limb.as_u64_reduced())).unwrap();
+            Num::alloc(cs, Some(value)).unwrap()
+        }).collect();
+
+        let mut hash = Poseidon2Sponge::<Bn256, GoldilocksField, TestingAbsorption, 2, 3>::new();
+        for limb in limbs.iter() {
+            hash.absorb_single_small_field(limb);
+        }
+        let expected = hash.finalize();
+
+        let actual = circuit_poseidon2_absorb_small_field(cs, &limbs_as_num).unwrap();
+        for (a, e) in actual.iter().zip(expected.iter()) {
+            assert_eq!(a.get_value().unwrap(), *e);
+        }
+
+        let expected_challenges: Vec<_> = expected.iter().flat_map(|el| {
+            el.into_repr().as_ref()[..3].to_vec().into_iter()
+                .map(|word| GoldilocksField::from_u64_with_reduction(word).as_u64_reduced())
+        }).collect();
+        let actual_challenges = circuit_poseidon2_hash_small_field(cs, &limbs_as_num).unwrap();
+        assert_eq!(actual_challenges.len(), expected_challenges.len());
+        for (a, e) in actual_challenges.iter().zip(expected_challenges.iter()) {
+            assert_eq!(a.get_value().unwrap().into_repr().as_ref()[0], *e);
+        }
+    }
+
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_circuit_poseidon2_hash_small_field_rejects_non_canonical_limbs() {
+    use crate::circuit::poseidon2::circuit_poseidon2_hash_small_field;
+    use franklin_crypto::bellman::{Field, PrimeField, PrimeFieldRepr};
+
+    let cs = &mut init_cs::<Bn256>();
+    // modulus is zero as a Goldilocks element, native transcript would absorb
+    // it reduced
+    let value = Fr::from_repr(<Fr as PrimeField>::Repr::from(GoldilocksField::CHAR)).unwrap();
+    let limbs = [Num::alloc(cs, Some(value)).unwrap(), Num::alloc(cs, Some(Fr::one())).unwrap()];
+
+    circuit_poseidon2_hash_small_field(cs, &limbs).unwrap();
+    assert!(!cs.is_satisfied());
+}
+
+impl crate::circuit::poseidon2_sponge::CircuitAbsorptionMode<Bn256> for TestingAbsorption {
+    fn absorb_lc(dst: &mut LinearCombination<Bn256>, src: &LinearCombination<Bn256>) {
+        *dst = src.clone();
+    }
+}
+
+#[test]
+fn test_circuit_poseidon2_sponge() {
+    use crate::circuit::poseidon2_sponge::CircuitPoseidon2Sponge;
+    use franklin_crypto::bellman::{PrimeField, PrimeFieldRepr};
+
+    let mut rng = rand::thread_rng();
+    let cs = &mut init_cs::<Bn256>();
+
+    let mut native = Poseidon2Sponge::<Bn256, GoldilocksField, TestingAbsorption, 2, 3>::new();
+    let mut circuit = CircuitPoseidon2Sponge::<Bn256, TestingAbsorption, 2, 3>::new();
+
+    // small field elements interleaved with full field elements
+    for round in 0..4 {
+        for _ in 0..(round + 2) {
+            let limb = GoldilocksField::from_u64_unchecked(rng.gen_range(0, GoldilocksField::CHAR));
+            let value = Fr::from_repr(<Fr as PrimeField>::Repr::from(limb.as_u64_reduced())).unwrap();
+            native.absorb_single_small_field(&limb);
+            circuit.absorb_single_small_field(cs, &Num::alloc(cs, Some(value)).unwrap()).unwrap();
+        }
+        let value = Fr::rand(&mut rng);
+        native.absorb_single(&value);
+        circuit.absorb_single(cs, &Num::alloc(cs, Some(value)).unwrap()).unwrap();
+
+        assert_eq!(native.try_get_committment().is_some(), circuit.try_get_committment(cs).unwrap().is_some());
+    }
+
+    let expected = native.committed_state();
+    let actual = circuit.committed_state(cs).unwrap();
+    for (a, e) in actual.iter().zip(expected.iter()) {
+        assert_eq!(a.get_value().unwrap(), *e);
+    }
+
+    let values: Vec<_> = (0..5).map(|_| Fr::rand(&mut rng)).collect();
+    let values_as_num: Vec<_> = values.iter().map(|value| Num::alloc(cs, Some(*value)).unwrap()).collect();
+    native.absorb(&values);
+    circuit.absorb(cs, &values_as_num).unwrap();
+
+    let expected = native.finalize();
+    let actual = circuit.finalize(cs).unwrap();
+    for (a, e) in actual.iter().zip(expected.iter()) {
+        assert_eq!(a.get_value().unwrap(), *e);
+    }
+    assert!(cs.is_satisfied());
+
+    // non canonical limb is rejected
+    let cs = &mut init_cs::<Bn256>();
+    let mut circuit = CircuitPoseidon2Sponge::<Bn256, TestingAbsorption, 2, 3>::new();
+    let value = Fr::from_repr(<Fr as PrimeField>::Repr::from(GoldilocksField::CHAR)).unwrap();
+    circuit.absorb_single_small_field(cs, &Num::alloc(cs, Some(value)).unwrap()).unwrap();
+    circuit.finalize(cs).unwrap();
+    assert!(!cs.is_satisfied());
+}
+
+#[test]
+fn test_circuit_pack_goldilocks_limbs() {
+    use crate::circuit::poseidon2::{enforce_goldilocks_range, pack_goldilocks_limbs};
+    use franklin_crypto::bellman::{Field, PrimeField};
+
+    let mut rng = rand::thread_rng();
+    let fe_from_u64 = |value: u64| Fr::from_repr(<Fr as PrimeField>::Repr::from(value)).unwrap();
+
+    let cs = &mut init_cs::<Bn256>();
+    let limbs: [u64; 3] = [0, 1, 2].map(|_| rng.gen_range(0, GoldilocksField::CHAR));
+    let limbs_as_num = limbs.map(|limb| Num::alloc(cs, Some(fe_from_u64(limb))).unwrap());
+    let packed = pack_goldilocks_limbs(cs, &limbs_as_num).unwrap();
+
+    let mut shift = Fr::one();
+    for _ in 0..64 {
+        shift.double();
+    }
+    let mut expected = Fr::zero();
+    for limb in limbs.iter().rev() {
+        expected.mul_assign(&shift);
+        expected.add_assign(&fe_from_u64(*limb));
+    }
+    assert_eq!(packed.get_value().unwrap(), expected);
+    assert!(cs.is_satisfied());
+
+    // largest canonical element passes, modulus itself doesn't
+    let cs = &mut init_cs::<Bn256>();
+    let value = Num::alloc(cs, Some(fe_from_u64(GoldilocksField::CHAR - 1))).unwrap();
+    enforce_goldilocks_range(cs, &value).unwrap();
+    assert!(cs.is_satisfied());
+
+    let cs = &mut init_cs::<Bn256>();
+    let value = Num::alloc(cs, Some(fe_from_u64(GoldilocksField::CHAR))).unwrap();
+    enforce_goldilocks_range(cs, &value).unwrap();
+    assert!(!cs.is_satisfied());
+}
+
+#[test]
+fn test_transcript_modes() {
+    use crate::poseidon2::transcript::{Poseidon2Transcript, TranscriptMode};
+    use franklin_crypto::boojum::cs::implementations::transcript::Transcript;
+
+    let mut rng = rand::thread_rng();
+    let caps: Vec<_> = (0..8).map(|_| Fr::rand(&mut rng)).collect();
+    let elements: Vec<_> = (0..5).map(|_| GoldilocksField::from_u64_unchecked(rng.gen_range(0, GoldilocksField::CHAR))).collect();
+
+    let mut challenges = vec![];
+    for mode in [TranscriptMode::Compatible, TranscriptMode::Lazy] {
+        let mut sealed = Poseidon2Transcript::<Bn256, GoldilocksField, TestingAbsorption, 2, 3>::new_with_mode(mode);
+        let mut unsealed = sealed.clone();
+        for transcript in [&mut sealed, &mut unsealed] {
+            transcript.witness_merkle_tree_cap(&caps);
+        }
+        sealed.seal();
+        for transcript in [&mut sealed, &mut unsealed] {
+            transcript.witness_field_elements(&elements);
+        }
+        sealed.seal();
+
+        let challenge = sealed.get_challenge();
+        assert_eq!(challenge, unsealed.get_challenge());
+
+        // full last chunk
+        for transcript in [&mut sealed, &mut unsealed] {
+            transcript.witness_merkle_tree_cap(&caps);
+        }
+        sealed.seal();
+        let challenge = sealed.get_challenge();
+        assert_eq!(challenge, unsealed.get_challenge());
+        challenges.push(challenge);
+    }
+    assert_ne!(challenges[0], challenges[1]);
+}
+
+#[test]
+fn test_transcript_rounds() {
+    use crate::poseidon2::transcript::{Poseidon2Transcript, TranscriptRoundError};
+    use franklin_crypto::boojum::cs::implementations::transcript::Transcript;
+
+    let mut rng = rand::thread_rng();
+    let caps: Vec<_> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+    let integers = [u64::MAX, 1 << 40, 7];
+
+    let mut explicit = Poseidon2Transcript::<Bn256, GoldilocksField, TestingAbsorption, 2, 3>::new();
+    let mut implicit = explicit.clone();
+    assert_eq!(explicit.try_witness_fr(&caps), Err(TranscriptRoundError::RoundNotStarted));
+
+    explicit.begin_round().unwrap();
+    assert_eq!(explicit.begin_round(), Err(TranscriptRoundError::RoundNotEnded));
+    explicit.try_witness_fr(&caps).unwrap();
+    explicit.try_witness_u64s(&integers).unwrap();
+    explicit.try_witness_u32s(&[5]).unwrap();
+    assert_eq!(explicit.try_get_challenge(), Err(TranscriptRoundError::RoundNotEnded));
+    explicit.end_round().unwrap();
+    assert_eq!(explicit.end_round(), Err(TranscriptRoundError::RoundNotStarted));
+    let challenge = explicit.try_get_challenge().unwrap();
+    assert_eq!(explicit.try_witness_u32s(&[1]), Err(TranscriptRoundError::WitnessAfterChallenge));
+
+    // rounds don't affect challenges, u64 is witnessed as lower and upper halves
+    implicit.witness_merkle_tree_cap(&caps);
+    let halves: Vec<_> = integers
+        .iter()
+        .flat_map(|v| [*v & 0xffff_ffff, *v >> 32])
+        .chain([5])
+        .map(GoldilocksField::from_u64_unchecked)
+        .collect();
+    implicit.witness_field_elements(&halves);
+    assert_eq!(challenge, implicit.get_challenge());
+
+    explicit.begin_round().unwrap();
+    explicit.try_witness_u32s(&[1]).unwrap();
+    explicit.end_round().unwrap();
+    implicit.witness_field_elements(&[GoldilocksField::from_u64_unchecked(1)]);
+    assert_eq!(explicit.try_get_challenge().unwrap(), implicit.get_challenge());
+
+    // methods of `Transcript` are checked once rounds are used
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(move || explicit.witness_merkle_tree_cap(&caps)));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_circuit_transcript_matches_native() {
+    use crate::circuit::poseidon2_transcript::CircuitPoseidon2Transcript;
+    use crate::poseidon2::transcript::{Poseidon2Transcript, TranscriptMode};
+    use franklin_crypto::bellman::{PrimeField, PrimeFieldRepr};
+    use franklin_crypto::boojum::cs::implementations::transcript::Transcript;
+
+    let mut rng = rand::thread_rng();
+    let fe_from_u64 = |value: u64| Fr::from_repr(<Fr as PrimeField>::Repr::from(value)).unwrap();
+
+    for mode in [TranscriptMode::Compatible, TranscriptMode::Lazy] {
+        let cs = &mut init_cs::<Bn256>();
+        let mut native = Poseidon2Transcript::<Bn256, GoldilocksField, TestingAbsorption, 2, 3>::new_with_mode(mode);
+        let mut circuit = CircuitPoseidon2Transcript::<Bn256, TestingAbsorption, 2, 3>::new_with_mode(mode);
+
+        let mut native_challenges = vec![];
+        let mut circuit_challenges = vec![];
+        // odd and even number of caps, elements crossing packed boundaries,
+        // more challenges than a single squeeze gives
+        for (num_caps, num_elements, num_challenges) in [(3, 5, 2), (4, 0, 1), (0, 2, 1), (0, 4, 8), (2, 7, 3)] {
+            let caps: Vec<_> = (0..num_caps).map(|_| Fr::rand(&mut rng)).collect();
+            let caps_as_num: Vec<_> = caps.iter().map(|el| Num::alloc(cs, Some(*el)).unwrap()).collect();
+            native.witness_merkle_tree_cap(&caps);
+            circuit.witness_merkle_tree_cap(&caps_as_num);
+
+            // witness elements in two calls to test packing into last element
+            let elements: Vec<_> = (0..num_elements)
+                .map(|_| GoldilocksField::from_u64_unchecked(rng.gen_range(0, GoldilocksField::CHAR)))
+                .collect();
+            let elements_as_num: Vec<_> = elements
+                .iter()
+                .map(|el| Num::alloc(cs, Some(fe_from_u64(el.as_u64_reduced()))).unwrap())
+                .collect();
+            let split = num_elements / 2;
+            native.witness_field_elements(&elements[..split]);
+            native.witness_field_elements(&elements[split..]);
+            circuit.witness_field_elements(cs, &elements_as_num[..split]).unwrap();
+            circuit.witness_field_elements(cs, &elements_as_num[split..]).unwrap();
+
+            for _ in 0..num_challenges {
+                native_challenges.push(Transcript::get_challenge(&mut native).as_u64_reduced());
+                circuit_challenges.push(circuit.get_challenge(cs).unwrap());
+            }
+        }
+
+        assert_eq!(native_challenges.len(), circuit_challenges.len());
+        for (n, c) in native_challenges.iter().zip(circuit_challenges.iter()) {
+            assert_eq!(fe_from_u64(*n), c.get_value().unwrap());
+        }
+        assert!(cs.is_satisfied());
+    }
+
+    // bound to params
+    let cs = &mut init_cs::<Bn256>();
+    let mut native = Poseidon2Transcript::<Bn256, GoldilocksField, TestingAbsorption, 2, 3>::new_bound_to_params();
+    let mut circuit = CircuitPoseidon2Transcript::<Bn256, TestingAbsorption, 2, 3>::new_bound_to_params(cs).unwrap();
+    let expected = native.get_extension_challenge::<2>().map(|el| fe_from_u64(el.as_u64_reduced()));
+    let actual = circuit.get_extension_challenge::<_, 2>(cs).unwrap();
+    for (a, e) in actual.iter().zip(expected.iter()) {
+        assert_eq!(a.get_value().unwrap(), *e);
+    }
+    assert!(cs.is_satisfied());
+}
+
+#[test]
+fn test_fused_width_3_round_function() {
+    use crate::poseidon2::poseidon2::{poseidon2_round_function_generic, poseidon2_round_function_width_3};
+    use crate::traits::NoopMiddleware;
+
+    let params = crate::poseidon2::Poseidon2Params::<Bn256, 2, 3>::default();
+
+    let mut rng = rand::thread_rng();
+    for _ in 0..16 {
+        let mut expected = [0; 3].map(|_| Fr::rand(&mut rng));
+        let mut actual = expected;
+
+        poseidon2_round_function_generic(&mut expected, &params, &mut NoopMiddleware);
+        poseidon2_round_function_width_3(&mut actual, &params, &mut NoopMiddleware);
+
+        assert_eq!(actual, expected);
+    }
+}
+
+#[test]
+fn test_transcript_aggregation() {
+    use crate::poseidon2::transcript::Poseidon2Transcript;
+    use franklin_crypto::boojum::cs::implementations::transcript::Transcript;
+    type TestTranscript = Poseidon2Transcript<Bn256, GoldilocksField, TestingAbsorption, 2, 3>;
+
+    let mut rng = rand::thread_rng();
+    let sub_transcripts: Vec<_> = (0..3)
+        .map(|_| {
+            let mut transcript = TestTranscript::new();
+            let caps: Vec<_> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+            transcript.witness_merkle_tree_cap(&caps);
+            transcript
+        })
+        .collect();
+
+    // digest doesn't depend on consumed challenges
+    let mut consumed = sub_transcripts[0].clone();
+    let _ = consumed.get_challenge();
+    assert_eq!(consumed.digest(), sub_transcripts[0].digest());
+
+    let challenge = TestTranscript::aggregate_challenge(&sub_transcripts);
+    assert_eq!(challenge, TestTranscript::aggregate_challenge(&sub_transcripts));
+
+    let mut reordered = sub_transcripts.clone();
+    reordered.swap(0, 1);
+    assert_ne!(challenge, TestTranscript::aggregate_challenge(&reordered));
+    assert_ne!(challenge, TestTranscript::aggregate_challenge(&sub_transcripts[..2]));
+}
+
+#[test]
+fn test_poseidon2_hash_bytes_fast() {
+    use crate::poseidon2::{poseidon2_hash_bytes_fast, POSEIDON2_BYTES_PER_ELEMENT};
+    use franklin_crypto::bellman::{Field, PrimeField, PrimeFieldRepr};
+
+    let params = crate::poseidon2::Poseidon2Params::<Bn256, 2, 3>::default();
+    let mut rng = rand::thread_rng();
+    let bytes: Vec<u8> = (0..200).map(|_| rng.gen()).collect();
+
+    for len in [0, 1, 31, 32, 62, 63, 124, 200] {
+        let bytes = &bytes[..len];
+
+        // reference packs all elements up front
+        let mut elements: Vec<Fr> = bytes
+            .chunks(POSEIDON2_BYTES_PER_ELEMENT)
+            .map(|chunk| {
+                let mut buffer = [0u8; 32];
+                buffer[..chunk.len()].copy_from_slice(chunk);
+                let mut repr = <Fr as PrimeField>::Repr::default();
+                repr.read_le(&buffer[..]).unwrap();
+                Fr::from_repr(repr).unwrap()
+            })
+            .collect();
+        if elements.is_empty() || elements.len() % 2 != 0 {
+            elements.resize(elements.len() + 2 - elements.len() % 2, Fr::one());
+        }
+        let mut capacity = <Fr as PrimeField>::Repr::default();
+        capacity.as_mut()[0] = len as u64;
+        capacity.as_mut()[1] = 1;
+        let mut state = [Fr::zero(), Fr::zero(), Fr::from_repr(capacity).unwrap()];
+        for chunk in elements.chunks(2) {
+            state[0].add_assign(&chunk[0]);
+            state[1].add_assign(&chunk[1]);
+            poseidon2_round_function::<Bn256, 2, 3>(&mut state, &params);
+        }
+
+        assert_eq!(poseidon2_hash_bytes_fast::<Bn256>(bytes), [state[0], state[1]], "length {}", len);
+    }
+
+    // trailing zero byte changes the digest
+    assert_ne!(
+        poseidon2_hash_bytes_fast::<Bn256>(&[1, 2, 3]),
+        poseidon2_hash_bytes_fast::<Bn256>(&[1, 2, 3, 0]),
+    );
+}
+
+#[test]
+fn test_sponge_flush() {
+    let mut rng = rand::thread_rng();
+    let values: Vec<_> = (0..3).map(|_| Fr::rand(&mut rng)).collect();
+    let limbs: Vec<_> = (0..5).map(|_| GoldilocksField::from_u64_unchecked(rng.gen_range(0, GoldilocksField::CHAR))).collect();
+
+    // nothing pending
+    let mut sponge = Poseidon2Sponge::<Bn256, GoldilocksField, TestingAbsorption, 2, 3>::new();
+    sponge.absorb(&values[..2]);
+    let expected = sponge.try_get_committment().unwrap();
+    assert_eq!(sponge.committed_state(), expected);
+
+    // pending field elements and limbs are padded as in finalize
+    for num_values in [1, 3] {
+        for num_limbs in [0, 1, 5] {
+            let mut sponge = Poseidon2Sponge::<Bn256, GoldilocksField, TestingAbsorption, 2, 3>::new();
+            sponge.absorb(&values[..num_values]);
+            for limb in limbs[..num_limbs].iter() {
+                sponge.absorb_single_small_field(limb);
+            }
+            let mut expected = sponge.clone();
+            let expected = expected.finalize();
+
+            assert!(sponge.try_get_committment().is_none());
+            assert_eq!(sponge.committed_state(), expected);
+            assert_eq!(sponge.try_get_committment(), Some(expected));
+        }
+    }
+}
+
+#[test]
+fn test_poseidon2_merkle_oracle() {
+    use super::oracle::Poseidon2MerkleOracle;
+    use franklin_crypto::bellman::Field;
+    use franklin_crypto::boojum::cs::oracle::merkle_tree::MerkleTreeWithCap;
+    use franklin_crypto::boojum::cs::oracle::TreeHasher;
+
+    type Oracle = Poseidon2MerkleOracle<Bn256, GoldilocksField, TestingAbsorption>;
+    type Hasher = Poseidon2Sponge<Bn256, GoldilocksField, TestingAbsorption, 2, 3>;
+
+    let worker = Worker::new();
+    let mut rng = rand::thread_rng();
+    let leaves: Vec<Vec<_>> = (0..16)
+        .map(|_| {
+            (0..5)
+                .map(|_| GoldilocksField::from_u64_unchecked(rng.gen_range(0, GoldilocksField::CHAR)))
+                .collect()
+        })
+        .collect();
+
+    for cap_size in [1, 4] {
+        let oracle = Oracle::commit(leaves.clone(), cap_size, &worker);
+        assert_eq!(oracle.cap().len(), cap_size);
+
+        // commitment and proofs are interchangeable with boojum's own tree
+        let boojum_tree = MerkleTreeWithCap::<GoldilocksField, Hasher>::construct(leaves.clone(), cap_size, &worker);
+        assert_eq!(oracle.cap(), &boojum_tree.get_cap()[..]);
+
+        for index in 0..oracle.num_leaves() {
+            let query = oracle.query(index);
+            assert_eq!(query.path.len(), 4 - cap_size.trailing_zeros() as usize);
+            assert!(query.verify::<Bn256, Hasher>(oracle.cap()));
+
+            let (leaf_hash, boojum_path): (_, Vec<_>) = boojum_tree.get_proof(index);
+            assert_eq!(leaf_hash, Hasher::hash_into_leaf(query.leaf.iter()));
+            assert_eq!(boojum_path, query.path);
+            assert!(MerkleTreeWithCap::<GoldilocksField, Hasher>::verify_proof_over_cap(
+                &query.path,
+                oracle.cap(),
+                leaf_hash,
+                index
+            ));
+
+            let mut wrong_index = query.clone();
+            wrong_index.index ^= 1;
+            assert!(!wrong_index.verify::<Bn256, Hasher>(oracle.cap()));
+
+            let mut wrong_leaf = query.clone();
+            wrong_leaf.leaf[0] = GoldilocksField::from_u64_unchecked(
+                (wrong_leaf.leaf[0].as_u64_reduced() + 1) % GoldilocksField::CHAR,
+            );
+            assert!(!wrong_leaf.verify::<Bn256, Hasher>(oracle.cap()));
+        }
+    }
+
+    // root is hashed the same way as by the tree hasher itself
+    let leaf_hashes: Vec<_> = leaves.iter().map(|leaf| Hasher::hash_into_leaf(leaf.iter())).collect();
+    let mut layer = leaf_hashes;
+    let mut depth = 0;
+    while layer.len() > 1 {
+        layer = layer.chunks(2).map(|pair| Hasher::hash_into_node(&pair[0], &pair[1], depth)).collect();
+        depth += 1;
+    }
+    let root = Oracle::commit(leaves, 1, &worker).cap()[0];
+    assert_eq!(root, layer[0]);
+    assert_ne!(root, Fr::zero());
+}
+
+#[test]
+fn test_unsupported_width_is_rejected_by_params() {
+    use super::{Poseidon2Params, Poseidon2ParamsError, POSEIDON2_SUPPORTED_WIDTHS};
+
+    assert!(POSEIDON2_SUPPORTED_WIDTHS.contains(&3));
+    assert!(Poseidon2Params::<Bn256, 2, 3>::try_default().is_ok());
+
+    let err = Poseidon2Params::<Bn256, 4, 5>::try_default().unwrap_err();
+    assert_eq!(err, Poseidon2ParamsError::UnsupportedWidth(5));
+    assert!(err.to_string().contains(&format!("{:?}", POSEIDON2_SUPPORTED_WIDTHS)), "{}", err);
+    // widths without reference params are opt-in
+    #[cfg(not(feature = "poseidon2-experimental-widths"))]
+    assert_eq!(
+        Poseidon2Params::<Bn256, 3, 4>::try_default().unwrap_err(),
+        Poseidon2ParamsError::UnsupportedWidth(4)
+    );
+
+    let result = std::panic::catch_unwind(|| Poseidon2Params::<Bn256, 4, 5>::default());
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_hash_reader() {
+    use crate::poseidon2::{hash_reader, poseidon2_hash_bytes_fast, HASH_READER_CHUNKS_PER_BLOCK, POSEIDON2_BYTES_PER_ELEMENT};
+    use franklin_crypto::bellman::{Field, PrimeField, PrimeFieldRepr};
+
+    // hands out at most `step` bytes per read
+    struct SlowReader<'a> {
+        bytes: &'a [u8],
+        step: usize,
+    }
+    impl std::io::Read for SlowReader<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let len = self.step.min(buf.len()).min(self.bytes.len());
+            buf[..len].copy_from_slice(&self.bytes[..len]);
+            self.bytes = &self.bytes[len..];
+            Ok(len)
+        }
+    }
+
+    let params = crate::poseidon2::Poseidon2Params::<Bn256, 2, 3>::default();
+    let mut rng = rand::thread_rng();
+    let block_len = 2 * POSEIDON2_BYTES_PER_ELEMENT * HASH_READER_CHUNKS_PER_BLOCK;
+    let bytes: Vec<u8> = (0..block_len + 100).map(|_| rng.gen()).collect();
+
+    for len in [0, 1, 62, 63, 200, block_len, block_len + 100] {
+        let bytes = &bytes[..len];
+        let expected = hash_reader(&params, bytes).unwrap();
+        for step in [1, 7, 62, 1000] {
+            let reader = SlowReader { bytes, step };
+            assert_eq!(hash_reader(&params, reader).unwrap(), expected, "length {} step {}", len, step);
+        }
+        if len > 0 {
+            assert_ne!(expected, poseidon2_hash_bytes_fast::<Bn256>(bytes));
+        }
+    }
+
+    // reference for a single partial chunk followed by the length block
+    let bytes = &bytes[..40];
+    let mut elements = [Fr::zero(); 2];
+    for (el, chunk) in elements.iter_mut().zip(bytes.chunks(POSEIDON2_BYTES_PER_ELEMENT)) {
+        let mut buffer = [0u8; 32];
+        buffer[..chunk.len()].copy_from_slice(chunk);
+        let mut repr = <Fr as PrimeField>::Repr::default();
+        repr.read_le(&buffer[..]).unwrap();
+        *el = Fr::from_repr(repr).unwrap();
+    }
+    let mut capacity = <Fr as PrimeField>::Repr::default();
+    capacity.as_mut()[1] = 2;
+    let mut state = [elements[0], elements[1], Fr::from_repr(capacity).unwrap()];
+    poseidon2_round_function::<Bn256, 2, 3>(&mut state, &params);
+    state[0].add_assign(&Fr::from_str("40").unwrap());
+    poseidon2_round_function::<Bn256, 2, 3>(&mut state, &params);
+    assert_eq!(hash_reader(&params, bytes).unwrap(), [state[0], state[1]]);
+
+    // trailing zero byte changes the digest
+    assert_ne!(
+        hash_reader(&params, &[1u8, 2, 3][..]).unwrap(),
+        hash_reader(&params, &[1u8, 2, 3, 0][..]).unwrap(),
+    );
+}
+
+#[test]
+fn test_circuit_challenges_from_fr() {
+    use crate::circuit::poseidon2::{circuit_fr_into_u64_limbs, circuit_get_challenges_from_fr};
+    use crate::poseidon2::{fr_into_u64_limbs, get_challenges_from_fr};
+    use franklin_crypto::bellman::{Field, PrimeField};
+
+    let mut rng = rand::thread_rng();
+    let mut minus_one = Fr::one();
+    minus_one.negate();
+    // zero and the largest element bound the canonical decomposition
+    let values = [Fr::zero(), minus_one, Fr::rand(&mut rng), Fr::rand(&mut rng)];
+
+    let cs = &mut init_cs::<Bn256>();
+    for value in values.iter() {
+        let expected_limbs = fr_into_u64_limbs::<Bn256>(value);
+        let expected_challenges: Vec<u64> = get_challenges_from_fr::<Bn256, GoldilocksField>(*value)
+            .iter()
+            .map(|challenge| challenge.as_u64_reduced())
+            .collect();
+        assert_eq!(expected_challenges.len(), 3);
+
+        for num in [Num::alloc(cs, Some(*value)).unwrap(), Num::Constant(*value)] {
+            let limbs = circuit_fr_into_u64_limbs(cs, &num).unwrap();
+            let limbs = limbs.map(|limb| limb.get_value().unwrap().into_repr().as_ref()[0]);
+            assert_eq!(limbs, expected_limbs);
+
+            let challenges: Vec<u64> = circuit_get_challenges_from_fr(cs, &num)
+                .unwrap()
+                .iter()
+                .map(|challenge| challenge.get_value().unwrap().into_repr().as_ref()[0])
+                .collect();
+            assert_eq!(challenges, expected_challenges);
+        }
+    }
+
+    assert!(cs.is_satisfied());
+}
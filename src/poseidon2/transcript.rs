@@ -3,13 +3,71 @@ use super::*;
 use derivative::*;
 
 use franklin_crypto::boojum::field::SmallField;
-use franklin_crypto::boojum::cs::traits::GoodAllocator;
+use alloc::{vec, vec::Vec};
 use franklin_crypto::boojum::algebraic_props::round_function::AbsorptionModeTrait;
 use franklin_crypto::boojum::cs::implementations::transcript::Transcript;
-use std::collections::VecDeque;
+use alloc::collections::VecDeque;
+use crate::common::utils::fe_from_u64;
+use crate::sponge::absorb_into_rate;
+use crate::traits::HashParams;
 
 use franklin_crypto::bellman::{Engine, Field, PrimeField, PrimeFieldRepr};
 
+/// Interaction model of the transcript is witness* -> get_challenge*.
+/// Witnessed elements are only buffered and absorbed into the sponge when
+/// the first challenge after them is requested.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TranscriptMode {
+    /// Compatible with boojum transcript, last chunk of witnessed elements is
+    /// always padded, so a full last chunk costs an extra permutation.
+    Compatible,
+    /// Full last chunk is absorbed without padding and separated from padded
+    /// chunks by incrementing last capacity element, saving a permutation.
+    /// Challenges differ from `Compatible` mode.
+    Lazy,
+}
+
+/// Errors of the explicit round API of `Poseidon2Transcript`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TranscriptRoundError {
+    /// Round API is used outside of a round, e.g. before the first
+    /// `begin_round` or `end_round` is repeated.
+    RoundNotStarted,
+    /// `begin_round` or a challenge is requested before `end_round`.
+    RoundNotEnded,
+    /// Elements are witnessed after `end_round`, i.e. after challenges of the
+    /// round could have been drawn, without `begin_round`.
+    WitnessAfterChallenge,
+}
+
+impl core::fmt::Display for TranscriptRoundError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::RoundNotStarted => write!(f, "no round of the transcript is started"),
+            Self::RoundNotEnded => write!(f, "current round of the transcript is not ended"),
+            Self::WitnessAfterChallenge => write!(f, "elements are witnessed after the round ended, begin a new round"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for TranscriptRoundError {}
+
+// Phase of the explicit round API, transcripts not using it are never checked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RoundPhase {
+    Untracked,
+    Witness,
+    Challenge,
+}
+
+/// Domain tag witnessed before a digest of another transcript.
+pub const TRANSCRIPT_DIGEST_DOMAIN_TAG: u64 = u64::from_be_bytes(*b"TrDigest");
+
+/// Domain tag witnessed by a master transcript before number of aggregated
+/// sub-transcripts.
+pub const TRANSCRIPT_AGGREGATION_DOMAIN_TAG: u64 = u64::from_be_bytes(*b"TrAggreg");
+
 #[derive(Derivative)]
 #[derivative(Clone, Debug)]
 pub struct Poseidon2Transcript<
@@ -22,6 +80,8 @@ pub struct Poseidon2Transcript<
     buffer: Vec<E::Fr>,
     last_filled: usize,
     available_challenges: VecDeque<F>,
+    mode: TranscriptMode,
+    phase: RoundPhase,
     #[derivative(Debug = "ignore")]
     sponge: Poseidon2Sponge<E, F, M, RATE, WIDTH>,
 }
@@ -34,13 +94,196 @@ impl<
     const WIDTH: usize
 > Poseidon2Transcript<E, F, M, RATE, WIDTH> {
     pub fn new() -> Self {
+        Self::new_with_mode(TranscriptMode::Compatible)
+    }
+
+    pub fn new_with_mode(mode: TranscriptMode) -> Self {
         Self {
             buffer: Vec::new(),
             last_filled: 0,
             available_challenges: VecDeque::new(),
+            mode,
+            phase: RoundPhase::Untracked,
             sponge: Poseidon2Sponge::<E, F, M, RATE, WIDTH>::new(),
         }
     }
+
+    /// Starts a round of the explicit round API: elements of the round are
+    /// witnessed by `try_witness_*` methods, then `end_round` closes the
+    /// round and its challenges are drawn by `try_get_challenge`. Witnessing
+    /// after `end_round` fails until the next `begin_round`, so elements are
+    /// never silently mixed into a round whose challenges are already used.
+    /// Once rounds are used, methods of `Transcript` are checked the same way
+    /// and panic on misuse. Rounds don't affect challenges.
+    pub fn begin_round(&mut self) -> Result<(), TranscriptRoundError> {
+        if self.phase == RoundPhase::Witness {
+            return Err(TranscriptRoundError::RoundNotEnded);
+        }
+        self.phase = RoundPhase::Witness;
+
+        Ok(())
+    }
+
+    /// Ends witnessing of the current round, challenges can be drawn after it.
+    pub fn end_round(&mut self) -> Result<(), TranscriptRoundError> {
+        match self.phase {
+            RoundPhase::Witness => {
+                self.phase = RoundPhase::Challenge;
+                Ok(())
+            }
+            RoundPhase::Untracked | RoundPhase::Challenge => Err(TranscriptRoundError::RoundNotStarted),
+        }
+    }
+
+    fn check_witness(&self) -> Result<(), TranscriptRoundError> {
+        match self.phase {
+            RoundPhase::Untracked | RoundPhase::Witness => Ok(()),
+            RoundPhase::Challenge => Err(TranscriptRoundError::WitnessAfterChallenge),
+        }
+    }
+
+    fn check_challenge(&self) -> Result<(), TranscriptRoundError> {
+        match self.phase {
+            RoundPhase::Untracked | RoundPhase::Challenge => Ok(()),
+            RoundPhase::Witness => Err(TranscriptRoundError::RoundNotEnded),
+        }
+    }
+
+    fn check_round_started(&self) -> Result<(), TranscriptRoundError> {
+        match self.phase {
+            RoundPhase::Untracked => Err(TranscriptRoundError::RoundNotStarted),
+            _ => Ok(()),
+        }
+    }
+
+    /// Witnesses small field elements within the current round.
+    pub fn try_witness_field_elements(&mut self, field_els: &[F]) -> Result<(), TranscriptRoundError> {
+        self.check_round_started()?;
+        self.check_witness()?;
+        Transcript::witness_field_elements(self, field_els);
+
+        Ok(())
+    }
+
+    /// Witnesses integers within the current round, each as a single field
+    /// element.
+    pub fn try_witness_u32s(&mut self, values: &[u32]) -> Result<(), TranscriptRoundError> {
+        let field_els: Vec<F> = values.iter().map(|v| F::from_u64_unchecked(*v as u64)).collect();
+
+        self.try_witness_field_elements(&field_els)
+    }
+
+    /// Witnesses integers within the current round, each as two field
+    /// elements holding its lower and upper 32 bits, so values above the
+    /// field modulus aren't reduced.
+    pub fn try_witness_u64s(&mut self, values: &[u64]) -> Result<(), TranscriptRoundError> {
+        let field_els: Vec<F> = values
+            .iter()
+            .flat_map(|v| [*v as u32, (*v >> 32) as u32])
+            .map(|limb| F::from_u64_unchecked(limb as u64))
+            .collect();
+
+        self.try_witness_field_elements(&field_els)
+    }
+
+    /// Witnesses elements of the sponge field within the current round, same
+    /// as `Transcript::witness_merkle_tree_cap`.
+    pub fn try_witness_fr(&mut self, elements: &[E::Fr]) -> Result<(), TranscriptRoundError> {
+        self.check_round_started()?;
+        self.check_witness()?;
+        Transcript::witness_merkle_tree_cap(self, elements);
+
+        Ok(())
+    }
+
+    /// Draws a challenge of the current round.
+    pub fn try_get_challenge(&mut self) -> Result<F, TranscriptRoundError> {
+        self.check_round_started()?;
+        self.check_challenge()?;
+
+        Ok(Transcript::get_challenge(self))
+    }
+
+    /// Absorbs all complete chunks of buffered elements except the last one
+    /// right away, e.g. at the end of a prover message, so that next challenge
+    /// only pays for the last chunk. Doesn't affect challenges.
+    pub fn seal(&mut self) {
+        let num_to_absorb = self.buffer.len() - Self::last_chunk_len(self.buffer.len());
+        if num_to_absorb == 0 {
+            return;
+        }
+        let to_absorb: Vec<_> = self.buffer.drain(..num_to_absorb).collect();
+        self.sponge.absorb(&to_absorb);
+    }
+
+    // Last chunk is never empty if there is anything buffered.
+    fn last_chunk_len(len: usize) -> usize {
+        match len % RATE {
+            0 => len.min(RATE),
+            rem => rem,
+        }
+    }
+
+    /// Creates transcript with the fingerprint of sponge params witnessed up front,
+    /// so all challenges are bound to the exact parameter set in use.
+    pub fn new_bound_to_params() -> Self {
+        let mut transcript = Self::new();
+        let fingerprint = transcript.sponge.params.fingerprint();
+        let elements: Vec<F> = fingerprint
+            .chunks_exact(4)
+            .map(|chunk| F::from_u64_unchecked(u32::from_le_bytes(chunk.try_into().unwrap()) as u64))
+            .collect();
+        transcript.witness_field_elements(&elements);
+
+        transcript
+    }
+
+    /// Returns coefficients of a challenge from degree `N` extension of `F`.
+    /// Each coefficient is taken from a distinct limb of squeezed elements, so
+    /// coefficients are independent from each other.
+    pub fn get_extension_challenge<const N: usize>(&mut self) -> [F; N] {
+        core::array::from_fn(|_| Transcript::get_challenge(self))
+    }
+
+    /// Returns a digest of everything witnessed so far, which is the first
+    /// state element once buffered elements are absorbed. Consuming already
+    /// squeezed challenges doesn't change the digest. Transcript itself is
+    /// not modified.
+    pub fn digest(&self) -> E::Fr {
+        if self.buffer.is_empty() {
+            return self.sponge.state[0];
+        }
+        let mut transcript = self.clone();
+        transcript.phase = RoundPhase::Untracked;
+        let _ = Transcript::get_challenge(&mut transcript);
+
+        transcript.sponge.state[0]
+    }
+
+    /// Binds a digest of another transcript to this one. The digest is
+    /// witnessed as a pair of `TRANSCRIPT_DIGEST_DOMAIN_TAG` and digest itself.
+    pub fn absorb_transcript_digest(&mut self, other_digest: E::Fr) {
+        let tag = fe_from_u64::<E>(TRANSCRIPT_DIGEST_DOMAIN_TAG);
+        self.witness_merkle_tree_cap(&[tag, other_digest]);
+    }
+
+    /// Aggregates independent sub-transcripts into a single master challenge.
+    /// Master transcript witnesses `TRANSCRIPT_AGGREGATION_DOMAIN_TAG` and the
+    /// number of sub-transcripts, then absorbs their digests in the given
+    /// order. Order of sub-transcripts matters.
+    pub fn aggregate_challenge(sub_transcripts: &[Self]) -> F {
+        let mut master = Self::new_with_mode(
+            sub_transcripts.first().map(|t| t.mode).unwrap_or(TranscriptMode::Compatible),
+        );
+        let tag = fe_from_u64::<E>(TRANSCRIPT_AGGREGATION_DOMAIN_TAG);
+        let count = fe_from_u64::<E>(sub_transcripts.len() as u64);
+        master.witness_merkle_tree_cap(&[tag, count]);
+        for transcript in sub_transcripts.iter() {
+            master.absorb_transcript_digest(transcript.digest());
+        }
+
+        Transcript::get_challenge(&mut master)
+    }
 }
 
 impl<
@@ -56,15 +299,13 @@ impl<
     const IS_ALGEBRAIC: bool = true;
 
     fn new(_params: Self::TransciptParameters) -> Self {
-        Self {
-            buffer: Vec::new(),
-            last_filled: 0,
-            available_challenges: VecDeque::new(),
-            sponge: Poseidon2Sponge::<E, F, M, RATE, WIDTH>::new(),
-        }
+        Self::new_with_mode(TranscriptMode::Compatible)
     }
 
     fn witness_field_elements(&mut self, field_els: &[F]) {
+        if let Err(e) = self.check_witness() {
+            panic!("{}", e);
+        }
         let capasity_per_element = Poseidon2Sponge::<E, F, M, RATE, WIDTH>::capasity_per_element();
         debug_assert!(self.last_filled < capasity_per_element);
         
@@ -99,6 +340,9 @@ impl<
     }
 
     fn witness_merkle_tree_cap(&mut self, cap: &[Self::CompatibleCap]) {
+        if let Err(e) = self.check_witness() {
+            panic!("{}", e);
+        }
         self.last_filled = 0;
         self.buffer.extend_from_slice(cap);
 
@@ -106,6 +350,9 @@ impl<
     }
 
     fn get_challenge(&mut self) -> F {
+        if let Err(e) = self.check_challenge() {
+            panic!("{}", e);
+        }
         assert_eq!(self.sponge.filled, 0);
 
         if self.buffer.is_empty() {
@@ -128,12 +375,29 @@ impl<
             }
         }
 
-        let to_absorb = std::mem::replace(&mut self.buffer, vec![]);
-        self.sponge.absorb(&to_absorb);
+        let to_absorb = core::mem::replace(&mut self.buffer, vec![]);
         self.last_filled = 0;
-
         self.available_challenges = VecDeque::new();
-        let commitment = self.sponge.finalize();
+
+        let commitment = match self.mode {
+            TranscriptMode::Compatible => {
+                self.sponge.absorb(&to_absorb);
+                self.sponge.finalize()
+            }
+            TranscriptMode::Lazy if to_absorb.len() % RATE == 0 => {
+                let (head, last_chunk) = to_absorb.split_at(to_absorb.len() - RATE);
+                self.sponge.absorb(head);
+                absorb_into_rate::<_, _, RATE, WIDTH>(&mut *self.sponge.state, last_chunk, M::absorb);
+                self.sponge.state[WIDTH - 1].add_assign(&E::Fr::one());
+                self.sponge.run_round_function();
+
+                self.sponge.try_get_committment().expect("must have no pending elements in the buffer")
+            }
+            TranscriptMode::Lazy => {
+                self.sponge.absorb(&to_absorb);
+                self.sponge.finalize()
+            }
+        };
         for &el in commitment.iter() {
             self.available_challenges.extend(get_challenges_from_fr::<E, F>(el));
         }
@@ -143,14 +407,34 @@ impl<
     }
 }
 
-fn get_challenges_from_fr<E: Engine, F: SmallField>(
+/// Number of 64-bit limbs squeezed elements are split into.
+pub const CHALLENGE_LIMBS: usize = 4;
+
+/// Little endian 64-bit limbs of the canonical representation of `el`.
+/// Circuit counterpart is `circuit_fr_into_u64_limbs`.
+pub fn fr_into_u64_limbs<E: Engine>(el: &E::Fr) -> [u64; CHALLENGE_LIMBS] {
+    let repr = el.into_repr();
+
+    repr.as_ref().try_into().expect("representation of the scalar field has four limbs")
+}
+
+/// Number of challenges derived from a single squeezed element. Only limbs
+/// below `CAPACITY` bits are used, so each of them is uniform.
+pub fn num_challenges_per_fr<E: Engine, F: SmallField>() -> usize {
+    assert!(F::CHAR_BITS <= 64, "Goldilocks has less than 64 bits per element");
+
+    (E::Fr::CAPACITY as usize) / (F::CHAR_BITS as usize)
+}
+
+/// Challenges of `Poseidon2Transcript` derived from a squeezed element: lower
+/// `num_challenges_per_fr` limbs of `fr_into_u64_limbs`, each reduced into
+/// `F`. Circuit counterpart is `circuit_get_challenges_from_fr`.
+pub fn get_challenges_from_fr<E: Engine, F: SmallField>(
     scalar_element: E::Fr,
 ) -> Vec<F> {
-    assert!(F::CHAR_BITS <= 64, "Goldilocks has less than 64 bits per element");
-    let num_challenges = (E::Fr::CAPACITY as usize) / (F::CHAR_BITS as usize);
+    let num_challenges = num_challenges_per_fr::<E, F>();
 
-    scalar_element.into_repr()
-        .as_ref()[..num_challenges]
+    fr_into_u64_limbs::<E>(&scalar_element)[..num_challenges]
         .iter()
         .map(|x|
             F::from_u64_with_reduction(*x)
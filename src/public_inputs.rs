@@ -0,0 +1,45 @@
+use crate::common::utils::fe_from_u64;
+use crate::poseidon2::Poseidon2Params;
+use crate::sponge::GenericSponge;
+use alloc::vec::Vec;
+use franklin_crypto::bellman::{Engine, PrimeField};
+
+/// Version of public inputs hashing convention. Any change of the hash
+/// function, params or truncation rule requires bumping the version.
+pub const PUBLIC_INPUTS_HASH_VERSION: u64 = 1;
+
+/// Domain tag absorbed before public inputs, encodes the convention version.
+pub const PUBLIC_INPUTS_DOMAIN_TAG: u64 =
+    u64::from_be_bytes(*b"PubInp\0\0") | PUBLIC_INPUTS_HASH_VERSION;
+
+/// Hashes public inputs into a single field element.
+/// Domain tag is prepended to the inputs, then the sequence is hashed by
+/// fixed length Poseidon2 with state-width=3 and rate=2. First output element
+/// is truncated to `E::Fr::CAPACITY` bits which is what verifier contracts
+/// mask public input with.
+pub fn hash_public_inputs<E: Engine>(inputs: &[E::Fr]) -> E::Fr {
+    let params = Poseidon2Params::<E, 2, 3>::default();
+
+    let mut input_with_tag = Vec::with_capacity(inputs.len() + 1);
+    input_with_tag.push(public_inputs_domain_tag::<E>());
+    input_with_tag.extend_from_slice(inputs);
+
+    let output = GenericSponge::<E, 2, 3>::hash(&input_with_tag, &params, None);
+
+    truncate_to_capacity::<E>(output[0])
+}
+
+pub(crate) fn public_inputs_domain_tag<E: Engine>() -> E::Fr {
+    fe_from_u64::<E>(PUBLIC_INPUTS_DOMAIN_TAG)
+}
+
+// Clears all bits starting from CAPACITY.
+pub(crate) fn truncate_to_capacity<E: Engine>(el: E::Fr) -> E::Fr {
+    let mut repr = el.into_repr();
+    let num_bits = repr.as_ref().len() * 64;
+    for bit in (E::Fr::CAPACITY as usize)..num_bits {
+        repr.as_mut()[bit / 64] &= !(1u64 << (bit % 64));
+    }
+
+    E::Fr::from_repr(repr).expect("truncated value is a field element")
+}
@@ -0,0 +1,69 @@
+use crate::common::utils::fe_from_u64;
+use crate::poseidon2::Poseidon2Params;
+use crate::sponge::GenericSponge;
+use alloc::vec::Vec;
+use franklin_crypto::bellman::Engine;
+
+/// Domain tag absorbed before seed and label of prover randomness derivation.
+pub const PROVER_RANDOMNESS_DOMAIN_TAG: u64 = u64::from_be_bytes(*b"PrvRnd\0\0") | 1;
+
+/// Derives an independent field element for each label from a secret seed.
+/// Each element is produced by a separate Poseidon2 sponge(state-width=3,
+/// rate=2) absorbing domain tag, length prefixed seed and length prefixed
+/// label, so it depends only on the seed and its own label and is the same
+/// across runs, platforms and positions of the label in `labels`.
+///
+/// # Panics
+///
+/// Panics if a label occurs in `labels` more than once, since equal labels
+/// would produce equal elements.
+pub fn derive_prover_randomness<E: Engine>(seed: &[u8], labels: &[&str]) -> Vec<E::Fr> {
+    for (i, label) in labels.iter().enumerate() {
+        assert!(!labels[..i].contains(label), "label {} is used twice", label);
+    }
+    let params = Poseidon2Params::<E, 2, 3>::default();
+
+    labels
+        .iter()
+        .map(|label| {
+            let mut sponge = GenericSponge::<E, 2, 3>::new();
+            sponge.absorb(fe_from_u64::<E>(PROVER_RANDOMNESS_DOMAIN_TAG), &params);
+            for bytes in [seed, label.as_bytes()] {
+                sponge.absorb(fe_from_u64::<E>(bytes.len() as u64), &params);
+                sponge.absorb_bits(&bytes_to_bits_le(bytes), &params);
+            }
+            sponge.pad_if_necessary();
+
+            sponge.squeeze(&params).expect("a squeezed elem")
+        })
+        .collect()
+}
+
+fn bytes_to_bits_le(bytes: &[u8]) -> Vec<bool> {
+    bytes
+        .iter()
+        .flat_map(|byte| (0..8).map(move |i| (byte >> i) & 1 == 1))
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use franklin_crypto::bellman::pairing::bn256::Bn256;
+
+    #[test]
+    fn test_derive_prover_randomness() {
+        let seed = [42u8; 32];
+        let values = derive_prover_randomness::<Bn256>(&seed, &["blinding", "mask"]);
+        assert_ne!(values[0], values[1]);
+
+        // element of a label doesn't depend on other labels
+        let reordered = derive_prover_randomness::<Bn256>(&seed, &["mask", "blinding"]);
+        assert_eq!(values[0], reordered[1]);
+        assert_eq!(values[1], reordered[0]);
+
+        let other_seed = derive_prover_randomness::<Bn256>(&seed[..31], &["blinding"]);
+        assert_ne!(values[0], other_seed[0]);
+    }
+}
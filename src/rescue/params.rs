@@ -1,8 +1,9 @@
+use alloc::vec::Vec;
 use franklin_crypto::bellman::{Engine};
 
-use crate::common::params::InnerHashParameters;
+use crate::common::fingerprint::Fingerprint;
+use crate::common::params::{rescue_number_of_rounds, ConstantsSource, InnerHashParameters, SeededParamsError, ROUND_CONSTANTS_TAG};
 use crate::traits::{HashParams, HashFamily, Sbox, CustomGate};
-use std::convert::TryInto;
 
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -30,14 +31,59 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> Default
     for RescueParams<E, RATE, WIDTH>
 {
     fn default() -> Self {
-        let (params, alpha, alpha_inv) = compute_params::<E, RATE, WIDTH>();
+        Self::from_inner(compute_params::<E, RATE, WIDTH>())
+    }
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> RescueParams<E, RATE, WIDTH> {
+    /// Params with round constants and MDS matrix derived from `seed` instead
+    /// of the tags shared by default params, so applications get a domain
+    /// specific instance. Number of rounds is the default one, so at most
+    /// `SECURITY_LEVEL` bits can be requested.
+    pub fn generate_from_seed(seed: &[u8], security_bits: usize) -> Result<Self, SeededParamsError> {
+        if security_bits > SECURITY_LEVEL {
+            return Err(SeededParamsError::UnsupportedSecurityLevel {
+                requested: security_bits,
+                supported: SECURITY_LEVEL,
+            });
+        }
+
+        let mut params = InnerHashParameters::new(security_bits, FULL_ROUNDS, 0);
+        params.compute_round_constants_from_seed(2 * FULL_ROUNDS + 1, b"Rescue", seed);
+        params.compute_mds_matrix_from_seed(b"Rescue", seed);
+        params.check_seeded()?;
+
+        let (alpha, alpha_inv) = compute_alpha::<E>();
+
+        Ok(Self::from_inner((params, alpha, alpha_inv)))
+    }
+
+    /// Params derived the same way as default ones but with the number of
+    /// rounds given by the formula of the Rescue paper for `security_bits`.
+    /// Default params use fewer rounds than the formula gives for their
+    /// claimed `SECURITY_LEVEL`.
+    pub fn with_security_level(security_bits: usize) -> Self {
+        let (alpha, _) = compute_alpha::<E>();
+        let full_rounds = rescue_number_of_rounds(WIDTH, WIDTH - RATE, security_bits, alpha);
+
+        Self::from_inner(compute_params_for_rounds::<E, RATE, WIDTH>(
+            security_bits,
+            full_rounds,
+            ConstantsSource::default(),
+        ))
+    }
+
+    /// Params with the rounds and MDS matrix of default ones but round
+    /// constants derived by `source`.
+    pub fn with_constants_source(source: ConstantsSource) -> Self {
+        Self::from_inner(compute_params_for_rounds::<E, RATE, WIDTH>(SECURITY_LEVEL, FULL_ROUNDS, source))
+    }
+
+    fn from_inner((params, alpha, alpha_inv): (InnerHashParameters<E, RATE, WIDTH>, u64, Vec<u64>)) -> Self {
         Self {
             allows_specialization: false,
             full_rounds: params.full_rounds,
-            round_constants: params
-                .round_constants()
-                .try_into()
-                .expect("round constants"),
+            round_constants: params.round_constants().to_vec(),
             mds_matrix: *params.mds_matrix(),
             alpha: Sbox::Alpha(alpha),
             alpha_inv: Sbox::AlphaInverse(alpha_inv, alpha),
@@ -98,6 +144,22 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> HashParams<E, RATE, WIDTH
         self.custom_gate = custom_gate;    
     }
 
+    fn fingerprint(&self) -> [u8; 32] {
+        let mut fingerprint = Fingerprint::new(self.hash_family(), RATE, WIDTH);
+        fingerprint.update_usize(self.full_rounds);
+        for constants in self.round_constants.iter() {
+            fingerprint.update_frs::<E>(constants);
+        }
+        fingerprint.update_matrix::<E, WIDTH>(&self.mds_matrix);
+        fingerprint.update_sbox(&self.alpha);
+        fingerprint.update_sbox(&self.alpha_inv);
+        if self.allows_specialization {
+            fingerprint.update_u64(u64::from_be_bytes(*b"spec_211"));
+        }
+
+        fingerprint.finalize()
+    }
+
     fn specialized_affine_transformation_for_round(&self, state: &mut [E::Fr; WIDTH], round_constants: &[E::Fr; WIDTH]) {
         debug_assert_eq!(WIDTH, 3);
         debug_assert!(self.allows_specialization);
@@ -134,10 +196,7 @@ impl<E: Engine> RescueParams<E, 2, 3> {
         Self {
             allows_specialization: true,
             full_rounds: params.full_rounds,
-            round_constants: params
-                .round_constants()
-                .try_into()
-                .expect("round constants"),
+            round_constants: params.round_constants().to_vec(),
             mds_matrix: *params.mds_matrix(),
             alpha: Sbox::Alpha(alpha),
             alpha_inv: Sbox::AddChain(addition_chain, alpha),
@@ -146,28 +205,42 @@ impl<E: Engine> RescueParams<E, 2, 3> {
     }
 }
 
+/// Security level default params are claimed for.
+pub const SECURITY_LEVEL: usize = 126;
+const FULL_ROUNDS: usize = 8;
+
 pub(crate) fn compute_params<E: Engine, const RATE: usize, const WIDTH: usize>() -> (InnerHashParameters<E, RATE, WIDTH>, u64, Vec<u64>) {
     // let full_rounds = 22;
-    let full_rounds = 8;
-    let security_level = 126;
+    compute_params_for_rounds(SECURITY_LEVEL, FULL_ROUNDS, ConstantsSource::default())
+}
 
+fn compute_params_for_rounds<E: Engine, const RATE: usize, const WIDTH: usize>(
+    security_level: usize,
+    full_rounds: usize,
+    source: ConstantsSource,
+) -> (InnerHashParameters<E, RATE, WIDTH>, u64, Vec<u64>) {
     let mut params = InnerHashParameters::new(        
         security_level,
         full_rounds,
         0,
     );
 
-    let rounds_tag = b"Rescue_f";
-    let _mds_tag = b"ResM0003";
+    let rounds_tag = ROUND_CONSTANTS_TAG;
     let total_number_of_rounds = 2*full_rounds + 1;
     
-    params.compute_round_constants(total_number_of_rounds, rounds_tag);
+    params.compute_round_constants_from_source(total_number_of_rounds, rounds_tag, source);
     params.compute_mds_matrix_for_rescue();
 
+    let (alpha, alpha_inv) = compute_alpha::<E>();
+
+    (params, alpha, alpha_inv)
+}
+
+fn compute_alpha<E: Engine>() -> (u64, Vec<u64>) {
     let alpha = 5u64;
     let alpha_inv = crate::common::utils::compute_gcd_vec::<E>(alpha).expect("inverse of alpha");
 
-    (params, alpha, alpha_inv)
+    (alpha, alpha_inv)
 }
 
 pub(crate) fn mds_optimized_params_alpha_5<E: Engine>(
@@ -180,7 +253,7 @@ pub(crate) fn mds_optimized_params_alpha_5<E: Engine>(
         0,
     );
 
-    let rounds_tag = b"Rescue_f";
+    let rounds_tag = ROUND_CONSTANTS_TAG;
     let total_number_of_rounds = 2*full_rounds + 1;
     params.compute_round_constants_with_prefixed_blake2s(total_number_of_rounds, rounds_tag);
     params.set_circular_optimized_mds();
@@ -202,6 +275,15 @@ mod tests {
     use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
     use franklin_crypto::bellman::{PrimeField, ScalarEngine};
     use num_bigint::{BigInt, Sign};
+
+    #[test]
+    fn test_fingerprint_covers_specialization() {
+        let specialized = RescueParams::<Bn256, 2, 3>::specialized_for_num_rounds(8, 120);
+        let mut generic = specialized.clone();
+        generic.allows_specialization = false;
+        assert_ne!(specialized.fingerprint(), generic.fingerprint());
+    }
+
     #[test]
     fn test_addition_chains() {
         let mut rng = rand::thread_rng();
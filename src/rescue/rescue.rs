@@ -1,6 +1,6 @@
 use crate::common::{matrix::mmul_assign, sbox::{sbox}};
 use crate::sponge::{generic_hash};
-use crate::traits::{HashFamily, HashParams};
+use crate::traits::{HashFamily, HashParams, NoopMiddleware, RoundFunctionMiddleware};
 use franklin_crypto::bellman::{Engine, Field};
 use super::params::RescueParams;
 
@@ -24,6 +24,20 @@ pub(crate) fn rescue_round_function<
 >(
     params: &P,
     state: &mut [E::Fr; WIDTH]
+) {
+    rescue_round_function_with_middleware(params, state, &mut NoopMiddleware)
+}
+
+pub(crate) fn rescue_round_function_with_middleware<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    M: RoundFunctionMiddleware<E, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    params: &P,
+    state: &mut [E::Fr; WIDTH],
+    middleware: &mut M,
 ) {
     assert_eq!(params.hash_family(), HashFamily::Rescue, "Incorrect hash family!");
 
@@ -37,6 +51,7 @@ pub(crate) fn rescue_round_function<
         // sbox
         if round & 1 == 0 {
             sbox::<E>(params.alpha_inv(), state);
+            middleware.after_inverse_sbox(round, state);
         } else {
             sbox::<E>(params.alpha(), state);
         }
@@ -55,5 +70,7 @@ pub(crate) fn rescue_round_function<
                 .zip(params.constants_of_round(round + 1).iter())
                 .for_each(|(s, c)| s.add_assign(c));
         }
+
+        middleware.after_round(round, state);
     }
 }
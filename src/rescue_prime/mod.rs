@@ -3,3 +3,4 @@ pub(crate) mod rescue_prime;
 pub(crate) mod params;
 
 pub use rescue_prime::*;
+pub use params::MAX_SECURITY_LEVEL;
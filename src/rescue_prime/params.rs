@@ -1,18 +1,24 @@
-use crate::common::params::InnerHashParameters;
+use crate::common::fingerprint::Fingerprint;
+use crate::common::params::{rescue_prime_number_of_rounds, seeded_tag, InnerHashParameters, SeededParamsError};
+use alloc::{borrow::ToOwned, format, vec, vec::Vec};
 use franklin_crypto::bellman::pairing::ff::{PrimeFieldRepr, ScalarEngine};
 use franklin_crypto::bellman::pairing::Engine;
 extern crate num_bigint;
 extern crate num_integer;
 extern crate num_traits;
-use crate::common::utils::biguint_to_u64_vec;
+use crate::common::utils::{biguint_to_u64_vec, try_slice_into_fixed};
 use crate::traits::{CustomGate, HashFamily, HashParams, Sbox};
-use franklin_crypto::bellman::pairing::bn256::Bn256;
 use franklin_crypto::bellman::{Field, PrimeField};
 use num_bigint::{BigInt, BigUint, Sign};
 use num_integer::{ExtendedGcd, Integer};
 use num_traits::{One, ToPrimitive, Zero};
-use std::convert::TryInto;
-use std::ops::{Mul, Sub};
+use core::ops::Sub;
+#[cfg(feature = "std")]
+use alloc::sync::Arc;
+#[cfg(feature = "std")]
+use std::sync::RwLock;
+#[cfg(feature = "std")]
+use typemap_rev::{TypeMap, TypeMapKey};
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct RescuePrimeParams<E: Engine, const RATE: usize, const WIDTH: usize> {
@@ -36,23 +42,101 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> PartialEq
     }
 }
 
+#[cfg(feature = "std")]
+impl<E: Engine, const RATE: usize, const WIDTH: usize> TypeMapKey
+    for RescuePrimeParams<E, RATE, WIDTH>
+{
+    type Value = Arc<RescuePrimeParams<E, RATE, WIDTH>>;
+}
+
 impl<E: Engine, const RATE: usize, const WIDTH: usize> Default
     for RescuePrimeParams<E, RATE, WIDTH>
 {
     fn default() -> Self {
-        let (params, alpha, alpha_inv) = super::params::rescue_prime_params::<E, RATE, WIDTH>();
+        #[cfg(feature = "std")]
+        {
+            Self::shared().as_ref().clone()
+        }
+
+        #[cfg(not(feature = "std"))]
+        Self::compute()
+    }
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> RescuePrimeParams<E, RATE, WIDTH> {
+    /// Default params shared by all callers, generation is expensive so
+    /// params are computed once per (E, RATE, WIDTH).
+    #[cfg(feature = "std")]
+    pub fn shared() -> Arc<Self> {
+        lazy_static::lazy_static! {
+            static ref RESCUE_PRIME_PARAMS: RwLock<TypeMap> = RwLock::new(TypeMap::new());
+        };
+
+        let static_params = RESCUE_PRIME_PARAMS.read().unwrap();
+        let params = static_params
+            .get::<RescuePrimeParams<E, RATE, WIDTH>>()
+            .cloned();
+        drop(static_params);
+
+        if let Some(params) = params {
+            return params;
+        }
+
+        let params = Arc::new(Self::compute());
+        let mut static_params = RESCUE_PRIME_PARAMS.write().unwrap();
+        // another thread might have computed params in the meantime, all
+        // callers get the same instance
+        static_params
+            .entry::<RescuePrimeParams<E, RATE, WIDTH>>()
+            .or_insert(params)
+            .clone()
+    }
+
+    fn compute() -> Self {
+        Self::from_inner(super::params::rescue_prime_params::<E, RATE, WIDTH>())
+    }
+
+    /// Params with round constants and MDS matrix derived from `seed` on top
+    /// of the inputs of default params. Number of rounds is computed for
+    /// `security_bits`, which can be at most `MAX_SECURITY_LEVEL`.
+    pub fn generate_from_seed(seed: &[u8], security_bits: usize) -> Result<Self, SeededParamsError> {
+        if security_bits > MAX_SECURITY_LEVEL {
+            return Err(SeededParamsError::UnsupportedSecurityLevel {
+                requested: security_bits,
+                supported: MAX_SECURITY_LEVEL,
+            });
+        }
+
+        let (mut params, alpha, alpha_inv) = rescue_prime_params_for::<E, RATE, WIDTH>(security_bits, Some(seed));
+        params.compute_mds_matrix_from_seed(b"RescuePrime", seed);
+        params.check_seeded()?;
+
+        Ok(Self::from_inner((params, alpha, alpha_inv)))
+    }
+
+    fn from_inner((params, alpha, alpha_inv): (InnerHashParameters<E, RATE, WIDTH>, u64, Vec<u64>)) -> Self {
         Self {
             allows_specialization: false,
             full_rounds: params.full_rounds,
-            round_constants: params.round_constants().try_into().expect("constant array"),
+            round_constants: params.round_constants().to_vec(),
             mds_matrix: *params.mds_matrix(),
             alpha: Sbox::Alpha(alpha),
             alpha_inv: Sbox::AlphaInverse(alpha_inv, alpha),
             custom_gate: CustomGate::None,
         }
     }
-}
-impl<E: Engine, const RATE: usize, const WIDTH: usize> RescuePrimeParams<E, RATE, WIDTH> {
+
+    /// Params derived the same way as default ones but for `security_bits`
+    /// instead of `SECURITY_LEVEL`, number of rounds is computed by the
+    /// formula of the specification. Round constants depend on the security
+    /// level, so they differ from the default ones.
+    pub fn with_security_level(security_bits: usize) -> Self {
+        let (mut params, alpha, alpha_inv) = rescue_prime_params_for::<E, RATE, WIDTH>(security_bits, None);
+        params.compute_mds_matrix_for_rescue();
+
+        Self::from_inner((params, alpha, alpha_inv))
+    }
+
     pub fn new_with_width3_custom_gate() -> Self {
         Self::new_with_custom_gate(CustomGate::QuinticWidth3)
     }
@@ -60,16 +144,10 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> RescuePrimeParams<E, RATE
         Self::new_with_custom_gate(CustomGate::QuinticWidth4)
     }
     fn new_with_custom_gate(custom_gate: CustomGate) -> Self {
-        let (params, alpha, alpha_inv) = super::params::rescue_prime_params::<E, RATE, WIDTH>();
-        Self {
-            allows_specialization: false,
-            full_rounds: params.full_rounds,
-            round_constants: params.round_constants().try_into().expect("constant array"),
-            mds_matrix: *params.mds_matrix(),
-            alpha: Sbox::Alpha(alpha),
-            alpha_inv: Sbox::AlphaInverse(alpha_inv, alpha),
-            custom_gate,
-        }
+        let mut params = Self::default();
+        params.custom_gate = custom_gate;
+
+        params
     }
 }
 
@@ -123,46 +201,19 @@ impl<E: Engine, const RATE: usize, const WIDTH: usize> HashParams<E, RATE, WIDTH
     fn use_custom_gate(&mut self, gate: CustomGate) {
         self.custom_gate = gate;
     }
-}
 
-fn get_number_of_rounds(m: usize, r: usize, security_level: usize, alpha: usize) -> usize {
-    let capacity = m - r;
-    fn factorial(n: &BigUint) -> BigUint {
-        if n.is_zero() {
-            return BigUint::one();
-        } else {
-            return n * factorial(&(n - &BigUint::one()));
+    fn fingerprint(&self) -> [u8; 32] {
+        let mut fingerprint = Fingerprint::new(self.hash_family(), RATE, WIDTH);
+        fingerprint.update_usize(self.full_rounds);
+        for constants in self.round_constants.iter() {
+            fingerprint.update_frs::<E>(constants);
         }
-    }
-
-    fn binomial(n: &BigUint, k: &BigUint) -> BigUint {
-        factorial(n) / (factorial(&(n - k)) * factorial(k))
-    }
-
-    let rate = m - capacity;
-    let dcon = |n: usize| -> BigUint {
-        let tmp = ((alpha - 1) * m * (n - 1)) as f64;
-        let result = BigUint::from((0.5 * tmp).floor() as u64) + BigUint::from(2u8);
-        BigUint::from(result)
-    };
-
-    let v = |n| -> BigUint {
-        let result = m * (n - 1) + rate;
-        BigUint::from(result)
-    };
+        fingerprint.update_matrix::<E, WIDTH>(&self.mds_matrix);
+        fingerprint.update_sbox(&self.alpha);
+        fingerprint.update_sbox(&self.alpha_inv);
 
-    let target = BigUint::from(2u128.pow(security_level as u32));
-
-    let mut actual_l1 = 0;
-    for l1 in 1..25 {
-        if (binomial(&(v(l1) + dcon(l1)), &v(l1)).pow(2u32)) > target {
-            actual_l1 = l1;
-            break;
-        }
+        fingerprint.finalize()
     }
-    assert!(actual_l1 > 0, "l1 must be greater than zero");
-
-    (1.5 * actual_l1.max(5) as f64).ceil() as usize
 }
 
 fn compute_alpha(p: &[u8]) -> (BigUint, BigUint) {
@@ -196,78 +247,112 @@ fn compute_round_constants<E: Engine, const RATE: usize, const WIDTH: usize>(
     p_big: BigInt,
     security_level: usize,
     n: usize,
+    seed: Option<&[u8]>,
 ) -> Vec<[E::Fr; WIDTH]> {
-    fn shake256(input: &[u8], num_bytes: usize) -> Box<[u8]> {
-        use sha3::digest::ExtendableOutput;
-        use sha3::digest::Update;
+    fn shake256(input: &[u8], num_bytes: usize) -> Vec<u8> {
+        use sha3::digest::{ExtendableOutput, Update, XofReader};
         use sha3::Shake256;
 
         let mut shake = Shake256::default();
         shake.update(input);
-        shake.finalize_boxed(num_bytes)
+        let mut output = vec![0u8; num_bytes];
+        shake.finalize_xof().read(&mut output);
+
+        output
     }
 
     let m = WIDTH;
     let capacity = WIDTH - RATE;
 
-    let modulus_bit_len = (modulus_bytes.len() * 8 - 2) as f32;
+    let p_biguint = p_big.to_biguint().expect("valid modulus");
+    let modulus_bit_len = p_biguint.bits() as f32;
+    assert!(modulus_bytes.len() * 8 >= p_biguint.bits() as usize);
 
     let bytes_per_int = ((modulus_bit_len / 8f32) + 1f32).ceil() as usize;
     let num_bytes = bytes_per_int * 2 * m * n;
     let seed_string = format!(
-        "Rescue-XLIX({},{},{},{})",
-        p_big, m, capacity, security_level
+        "{}({},{},{},{})",
+        ROUND_CONSTANTS_SEED_PREFIX, p_big, m, capacity, security_level
     );
-    let seed_bytes = seed_string.as_bytes();
-    let byte_string = shake256(seed_bytes, num_bytes);
-    let mut round_constants = vec![];
-    for i in 0..2 * m * n {
-        let chunk = byte_string[bytes_per_int * i..bytes_per_int * (i + 1)].to_vec();
-        let constant = chunk
-            .iter()
-            .enumerate()
-            .map(|(i, j)| {
-                // (256^j) * ZZ(chunk[j])
-                let pow = BigUint::from(256u16).pow(i as u32);
-                let zz = BigUint::from_bytes_le(&[*j]);
-                let result = pow.clone().mul(zz.clone());
-
-                result
-            })
-            .fold(BigUint::zero(), |acc, next| acc + next);
-        let remainder = constant.mod_floor(&p_big.to_biguint().expect("valid modulus"));
-        let mut bytes_le = remainder.to_bytes_le();
-        if bytes_le.len() < 64 {
-            bytes_le.resize(64, 0u8);
-        }
-
-        let mut repr = <E::Fr as PrimeField>::Repr::default();
-        repr.read_le(&bytes_le[..]).unwrap();
-        let constant_fe = E::Fr::from_repr(repr).unwrap();
-        round_constants.push(constant_fe);
+    let mut seed_bytes = seed_string.into_bytes();
+    if let Some(seed) = seed {
+        seed_bytes.extend_from_slice(&seeded_tag(b"RescuePrime", b"constants", seed));
     }
+    let byte_string = shake256(&seed_bytes, num_bytes);
+
+    // each chunk is independent so order of the output doesn't depend on scheduling
+    #[cfg(feature = "rayon")]
+    let round_constants: Vec<E::Fr> = {
+        use rayon::prelude::*;
+        byte_string
+            .par_chunks_exact(bytes_per_int)
+            .map(|chunk| constant_from_chunk::<E>(chunk, &p_biguint))
+            .collect()
+    };
+    #[cfg(not(feature = "rayon"))]
+    let round_constants: Vec<E::Fr> = byte_string
+        .chunks_exact(bytes_per_int)
+        .map(|chunk| constant_from_chunk::<E>(chunk, &p_biguint))
+        .collect();
+    assert_eq!(round_constants.len(), 2 * m * n);
+
     let mut final_constants = vec![[E::Fr::zero(); WIDTH]; n];
 
     round_constants
         .chunks_exact(WIDTH)
         .zip(final_constants.iter_mut())
-        .for_each(|(src, dst)| *dst = src.try_into().expect("constants in const"));
+        .for_each(|(src, dst)| *dst = try_slice_into_fixed(src).expect("constants in const"));
 
     final_constants
 }
 
+// sum of (256^j) * ZZ(chunk[j]) reduced by modulus. Chunk may be of any
+// length, remainder is always encoded into the exact size of the field repr.
+fn constant_from_chunk<E: Engine>(chunk: &[u8], p_biguint: &BigUint) -> E::Fr {
+    let constant = BigUint::from_bytes_le(chunk);
+    let remainder = constant.mod_floor(p_biguint);
+
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    let repr_byte_len = repr.as_ref().len() * 8;
+    let mut bytes_le = remainder.to_bytes_le();
+    assert!(bytes_le.len() <= repr_byte_len, "modulus doesn't fit into field repr");
+    bytes_le.resize(repr_byte_len, 0u8);
+
+    repr.read_le(&bytes_le[..]).expect("read repr");
+    E::Fr::from_repr(repr).expect("reduced value is a field element")
+}
+
+/// SHAKE256 input deriving round constants is this prefix followed by
+/// `(p,m,c,s)`: modulus, width, capacity and security level in decimal.
+/// Params derived from a seed append the seed to it.
+pub(crate) const ROUND_CONSTANTS_SEED_PREFIX: &str = "Rescue-XLIX";
+
+/// Security level default params are derived for.
+pub(crate) const SECURITY_LEVEL: usize = 80;
+
+/// Largest security level seeded params can be derived for.
+pub const MAX_SECURITY_LEVEL: usize = 127;
+
 pub fn rescue_prime_params<E: Engine, const RATE: usize, const WIDTH: usize>(
 ) -> (InnerHashParameters<E, RATE, WIDTH>, u64, Vec<u64>) {
-    let security_level = 80;
+    let (mut params, alpha, alpha_inv) = rescue_prime_params_for(SECURITY_LEVEL, None);
+    params.compute_mds_matrix_for_rescue();
 
+    (params, alpha, alpha_inv)
+}
+
+// MDS matrix is left to the caller
+fn rescue_prime_params_for<E: Engine, const RATE: usize, const WIDTH: usize>(
+    security_level: usize,
+    seed: Option<&[u8]>,
+) -> (InnerHashParameters<E, RATE, WIDTH>, u64, Vec<u64>) {
     let mut modulus_bytes = vec![];
-    let p_fe = <Bn256 as ScalarEngine>::Fr::char();
+    let p_fe = <E as ScalarEngine>::Fr::char();
     p_fe.write_le(&mut modulus_bytes).unwrap();
     let p_big = BigInt::from_bytes_le(Sign::Plus, &modulus_bytes);
     let (alpha, alpha_inv) = compute_alpha(&modulus_bytes);
     let alpha = alpha.to_u64().expect("u64");
-    let number_of_rounds =
-        get_number_of_rounds(WIDTH, WIDTH - RATE, security_level, alpha as usize);
+    let number_of_rounds = rescue_prime_number_of_rounds(WIDTH, WIDTH - RATE, security_level, alpha);
 
     let mut params = InnerHashParameters::new(security_level, number_of_rounds, 0);
     params.round_constants = compute_round_constants::<E, RATE, WIDTH>(
@@ -275,10 +360,9 @@ pub fn rescue_prime_params<E: Engine, const RATE: usize, const WIDTH: usize>(
         p_big,
         security_level,
         number_of_rounds,
+        seed,
     );
 
-    params.compute_mds_matrix_for_rescue();
-
     let alpha_inv = biguint_to_u64_vec(alpha_inv);
 
     (params, alpha, alpha_inv)
@@ -301,7 +385,7 @@ mod tests {
         let p_big = BigInt::from_bytes_le(Sign::Plus, &modulus_bytes);
         let (alpha, alpha_inv) = compute_alpha(&modulus_bytes);
         let alpha = alpha.to_u32_digits()[0] as usize;
-        let n = get_number_of_rounds(m, capacity, security_level, alpha);
+        let n = rescue_prime_number_of_rounds(m, capacity, security_level, alpha as u64);
 
         println!(
             "alpha {} alpha inv {:x} number of rounds {}",
@@ -309,7 +393,7 @@ mod tests {
         );
 
         let round_constants =
-            compute_round_constants::<Bn256, 2, 3>(&modulus_bytes, p_big, security_level, n);
+            compute_round_constants::<Bn256, 2, 3>(&modulus_bytes, p_big, security_level, n, None);
 
         println!("number of rounds {}", n);
         println!("number of round constants {}", round_constants.len());
@@ -351,6 +435,54 @@ mod tests {
             .for_each(|(actual, expected)| assert_eq!(actual, expected));
     }
 
+    #[test]
+    fn test_rescue_prime_round_constants_for_other_field() {
+        use franklin_crypto::bellman::pairing::bls12_381::{Bls12, Fr};
+
+        // first round constants of the reference script for the BLS12-381
+        // scalar field with m=3, capacity=1 and 80 bits of security, and the
+        // last one of the N * m constants params keep
+        const EXPECTED_FIRST: [&str; 6] = [
+            "310d470625aa48705c95cb8ee977b841a50bf24b74b05e6f7431160192023951",
+            "6746eb62b7b84198c1c61296a555a9ebb22f0248f46373e69e2342b47b9d71a5",
+            "37c68f30853718ef659a1d421baab9670dfc224864f8281d27d5f1b90835eae4",
+            "26bc475af280b2a6fe5bc540296d29dca2f2eabe77e2cc8a2037362d9fba4322",
+            "659c716c3111a5e4b9017d5482d97496c3a1c0572bc50eec2c3fb31dd45e8832",
+            "42b8e9c8bd396bb2ca79b5d9e3596bf393bf2fea7fdf5e4ab4fbe14f0df2988f",
+        ];
+        const EXPECTED_LAST: &str = "5ca1deffd4c2d9558711c2f3281ca5e36ae9dbe01835a824508390930971cda0";
+        let decode = |constant: &str| {
+            let mut repr = <Fr as PrimeField>::Repr::default();
+            repr.read_be(&hex::decode(constant).unwrap()[..]).unwrap();
+            Fr::from_repr(repr).unwrap()
+        };
+
+        let (params, _, _) = rescue_prime_params::<Bls12, 2, 3>();
+        let actual: Vec<Fr> = params.round_constants().iter().flatten().cloned().collect();
+        assert_eq!(actual.len(), 9 * 3);
+        for (actual, expected) in actual.iter().zip(EXPECTED_FIRST.iter()) {
+            assert_eq!(*actual, decode(expected));
+        }
+        assert_eq!(*actual.last().unwrap(), decode(EXPECTED_LAST));
+    }
+
+    #[test]
+    fn test_rescue_prime_params_are_memoized() {
+        let shared = RescuePrimeParams::<Bn256, 2, 3>::shared();
+        assert!(Arc::ptr_eq(&shared, &RescuePrimeParams::<Bn256, 2, 3>::shared()));
+        // params of other width are cached separately
+        let other = RescuePrimeParams::<Bn256, 3, 4>::shared();
+        assert!(Arc::ptr_eq(&other, &RescuePrimeParams::<Bn256, 3, 4>::shared()));
+        assert_ne!(other.fingerprint(), shared.fingerprint());
+
+        let first = RescuePrimeParams::<Bn256, 2, 3>::default();
+        assert_eq!(first.fingerprint(), shared.fingerprint());
+
+        let with_gate = RescuePrimeParams::<Bn256, 2, 3>::new_with_width4_custom_gate();
+        assert!(matches!(with_gate.custom_gate, CustomGate::QuinticWidth4));
+        assert_eq!(with_gate.round_constants, first.round_constants);
+    }
+
     fn expected_round_constants<'a, F: PrimeField>() -> Vec<&'a str> {
         vec![
             "25fa60d3d93901eabe9b6cc8682b1c141261bf7e9355e4565a7d6a79efaa1272",
@@ -1,7 +1,8 @@
 use crate::common::matrix::mmul_assign;
 use crate::common::sbox::sbox;
+use crate::common::domain_strategy::DomainStrategy;
 use crate::sponge::{generic_hash};
-use crate::traits::{HashFamily, HashParams};
+use crate::traits::{HashFamily, HashParams, NoopMiddleware, RoundFunctionMiddleware};
 use franklin_crypto::bellman::pairing::ff::Field;
 use franklin_crypto::bellman::pairing::Engine;
 use super::params::RescuePrimeParams;
@@ -19,6 +20,17 @@ pub fn rescue_prime_hash<E: Engine, const L: usize>(input: &[E::Fr; L]) -> [E::F
     generic_hash(&params, input, None)
 }
 
+/// Fixed length hash as the Rescue Prime specification defines it: capacity
+/// is not specialized and input is not padded, so `L` should be a multiple
+/// of rate. Uses pre-defined state-width=3 and rate=2.
+pub fn rescue_prime_hash_fixed_spec<E: Engine, const L: usize>(input: &[E::Fr; L]) -> [E::Fr; 2] {
+    const WIDTH: usize = 3;
+    const RATE: usize = 2;
+    assert!(L % RATE == 0, "input length should be a multiple of rate without padding");
+
+    let params = RescuePrimeParams::<E, RATE, WIDTH>::default();
+    generic_hash(&params, input, Some(DomainStrategy::NoPadding))
+}
 
 pub(crate) fn rescue_prime_round_function<
     E: Engine,
@@ -28,6 +40,20 @@ pub(crate) fn rescue_prime_round_function<
 >(
     params: &P,
     state: &mut [E::Fr; WIDTH],
+) {
+    rescue_prime_round_function_with_middleware(params, state, &mut NoopMiddleware)
+}
+
+pub(crate) fn rescue_prime_round_function_with_middleware<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    M: RoundFunctionMiddleware<E, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    params: &P,
+    state: &mut [E::Fr; WIDTH],
+    middleware: &mut M,
 ) {
     assert_eq!(
         params.hash_family(),
@@ -47,6 +73,7 @@ pub(crate) fn rescue_prime_round_function<
             .for_each(|(s, c)| s.add_assign(c));
         // sbox alpha inv
         sbox::<E>(params.alpha_inv(), state);
+        middleware.after_inverse_sbox(round, state);
 
         // mds
         mmul_assign::<E, WIDTH>(&params.mds_matrix(), state);
@@ -56,5 +83,7 @@ pub(crate) fn rescue_prime_round_function<
             .iter_mut()
             .zip(params.constants_of_round(round + 1).iter())
             .for_each(|(s, c)| s.add_assign(c));
+
+        middleware.after_round(round, state);
     }
 }
@@ -0,0 +1,130 @@
+use crate::circuit::sponge::circuit_generic_round_function;
+use crate::common::utils::try_slice_into_fixed;
+use crate::sponge::generic_round_function;
+use crate::traits::HashParams;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::ConstraintSystem;
+use franklin_crypto::bellman::{Engine, Field, SynthesisError};
+use franklin_crypto::plonk::circuit::{allocated_num::Num, linear_combination::LinearCombination};
+
+/// Round function interface in the shape circuits built on top of it consume
+/// it: explicit state which is split into `RATE` and capacity parts,
+/// absorption with replacement of the rate part and length specialization
+/// through the last capacity element. Implemented for all params of this
+/// crate, so `compress` over `RATE=2` params gives 2→1 compression and over
+/// `RATE=4` params gives 4→1 compression.
+pub trait AlgebraicRoundFunction<E: Engine, const RATE: usize, const WIDTH: usize>:
+    Clone + Send + Sync
+{
+    fn empty_state(&self) -> [E::Fr; WIDTH] {
+        [E::Fr::zero(); WIDTH]
+    }
+
+    fn round_function(&self, state: &mut [E::Fr; WIDTH]);
+
+    fn circuit_round_function<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        state: &mut [LinearCombination<E>; WIDTH],
+    ) -> Result<(), SynthesisError>;
+
+    /// Sets last capacity element to the length of the input.
+    fn apply_length_specialization(&self, state: &mut [E::Fr; WIDTH], length: usize) {
+        assert!(WIDTH > RATE, "state should have capacity");
+        state[WIDTH - 1] = fe_from_u64::<E>(length as u64);
+    }
+
+    /// Replaces rate part of the state by the input and permutes.
+    fn absorb_with_replacement(&self, state: &mut [E::Fr; WIDTH], input: &[E::Fr; RATE]) {
+        state[..RATE].copy_from_slice(&input[..]);
+        self.round_function(state);
+    }
+
+    fn circuit_absorb_with_replacement<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        state: &mut [Num<E>; WIDTH],
+        input: &[Num<E>; RATE],
+    ) -> Result<(), SynthesisError> {
+        state[..RATE].copy_from_slice(&input[..]);
+
+        let mut lc_state: [LinearCombination<E>; WIDTH] =
+            std::array::from_fn(|i| LinearCombination::from(state[i]));
+        self.circuit_round_function(cs, &mut lc_state)?;
+        for (dst, src) in state.iter_mut().zip(lc_state.into_iter()) {
+            *dst = src.into_num(cs)?;
+        }
+
+        Ok(())
+    }
+
+    fn state_into_commitment(state: &[E::Fr; WIDTH]) -> [E::Fr; RATE] {
+        try_slice_into_fixed(&state[..RATE]).expect("rate part")
+    }
+
+    fn capacity(state: &[E::Fr; WIDTH]) -> &[E::Fr] {
+        &state[RATE..]
+    }
+
+    /// Compresses `RATE` elements into a single one.
+    fn compress(&self, input: &[E::Fr; RATE]) -> E::Fr {
+        let mut state = self.empty_state();
+        self.apply_length_specialization(&mut state, RATE);
+        self.absorb_with_replacement(&mut state, input);
+
+        state[0]
+    }
+}
+
+impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>
+    AlgebraicRoundFunction<E, RATE, WIDTH> for P
+{
+    fn round_function(&self, state: &mut [E::Fr; WIDTH]) {
+        generic_round_function(self, state);
+    }
+
+    fn circuit_round_function<CS: ConstraintSystem<E>>(
+        &self,
+        cs: &mut CS,
+        state: &mut [LinearCombination<E>; WIDTH],
+    ) -> Result<(), SynthesisError> {
+        circuit_generic_round_function(cs, state, self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::rescue::params::RescueParams;
+    use crate::tests::{init_cs, init_rng};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    fn check_round_function<R: AlgebraicRoundFunction<Bn256, 2, 3>>(round_function: &R) {
+        let rng = &mut init_rng();
+        let cs = &mut init_cs::<Bn256>();
+        let input = [Fr::rand(rng), Fr::rand(rng)];
+
+        let mut state = round_function.empty_state();
+        round_function.apply_length_specialization(&mut state, 2);
+        let mut circuit_state = state.map(Num::Constant);
+        round_function.absorb_with_replacement(&mut state, &input);
+        assert_eq!(R::state_into_commitment(&state)[0], round_function.compress(&input));
+
+        let input_as_num = input.map(|el| Num::alloc(cs, Some(el)).unwrap());
+        round_function
+            .circuit_absorb_with_replacement(cs, &mut circuit_state, &input_as_num)
+            .unwrap();
+        for (a, e) in circuit_state.iter().zip(state.iter()) {
+            assert_eq!(a.get_value().unwrap(), *e);
+        }
+
+        assert!(cs.is_satisfied());
+    }
+
+    #[test]
+    fn test_algebraic_round_function() {
+        check_round_function(&RescueParams::<Bn256, 2, 3>::default());
+        check_round_function(&Poseidon2Params::<Bn256, 2, 3>::default());
+    }
+}
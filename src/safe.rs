@@ -0,0 +1,254 @@
+//! SAFE (Sponge API for Field Elements) calling convention, see
+//! <https://hackmd.io/bHgsH6mMStCVibM_wYvb2w>.
+//!
+//! The whole sequence of calls is declared upfront as an `IOPattern`. The
+//! pattern and a domain separator are hashed into a tag which initializes
+//! the capacity, so sponges with different patterns or domains are
+//! independent. Calls are checked against the pattern and `finish` fails if
+//! it is not exhausted.
+//!
+//! Tag is computed as in the reference implementation of the spec: each
+//! operation is encoded as a big endian 32-bit word, absorption with the
+//! most significant bit set, then SHA3-256 of the words followed by the
+//! domain separator is truncated to its first 16 bytes which are read as a
+//! little endian integer. The tag is placed to the first capacity element,
+//! `state[RATE]`.
+use crate::sponge::generic_round_function;
+use crate::state::State;
+use crate::traits::HashParams;
+use alloc::vec::Vec;
+use franklin_crypto::bellman::{Engine, Field, PrimeField};
+
+const ABSORB_FLAG: u32 = 1 << 31;
+
+/// Operation of an IO pattern with the number of elements.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpongeOp {
+    Absorb(u32),
+    Squeeze(u32),
+}
+
+impl SpongeOp {
+    fn len(&self) -> u32 {
+        match self {
+            Self::Absorb(len) | Self::Squeeze(len) => *len,
+        }
+    }
+
+    fn with_len(&self, len: u32) -> Self {
+        match self {
+            Self::Absorb(_) => Self::Absorb(len),
+            Self::Squeeze(_) => Self::Squeeze(len),
+        }
+    }
+
+    fn same_kind(&self, other: &Self) -> bool {
+        matches!((self, other), (Self::Absorb(_), Self::Absorb(_)) | (Self::Squeeze(_), Self::Squeeze(_)))
+    }
+
+    fn encode(&self) -> u32 {
+        match self {
+            Self::Absorb(len) => ABSORB_FLAG | len,
+            Self::Squeeze(len) => *len,
+        }
+    }
+}
+
+/// Sequence of operations of a sponge. Consecutive operations of the same
+/// kind are merged and empty ones are dropped, so `Absorb(1), Absorb(2)`
+/// and `Absorb(3)` are the same pattern.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct IOPattern(Vec<SpongeOp>);
+
+impl IOPattern {
+    /// Panics if a merged operation has `2^31` or more elements.
+    pub fn new(ops: &[SpongeOp]) -> Self {
+        let mut merged: Vec<SpongeOp> = Vec::with_capacity(ops.len());
+        for op in ops.iter().filter(|op| op.len() > 0) {
+            match merged.last_mut() {
+                Some(last) if last.same_kind(op) => {
+                    *last = last.with_len(last.len().checked_add(op.len()).expect("too many elements"));
+                }
+                _ => merged.push(*op),
+            }
+        }
+        assert!(
+            merged.iter().all(|op| op.len() < ABSORB_FLAG),
+            "an operation of IO pattern has too many elements"
+        );
+
+        Self(merged)
+    }
+
+    pub fn ops(&self) -> &[SpongeOp] {
+        &self.0
+    }
+
+    /// Tag of the pattern within `domain_separator`.
+    pub fn tag<E: Engine>(&self, domain_separator: &[u8]) -> E::Fr {
+        use sha3::{Digest, Sha3_256};
+
+        let mut hasher = Sha3_256::new();
+        for op in self.0.iter() {
+            hasher.update(op.encode().to_be_bytes());
+        }
+        hasher.update(domain_separator);
+        let digest = hasher.finalize();
+
+        let mut repr = <E::Fr as PrimeField>::Repr::default();
+        for (limb, chunk) in repr.as_mut().iter_mut().zip(digest[..16].chunks_exact(8)) {
+            *limb = u64::from_le_bytes(chunk.try_into().unwrap());
+        }
+
+        E::Fr::from_repr(repr).expect("128 bits fit into a field element")
+    }
+}
+
+/// Errors of calls not following the IO pattern.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SafeSpongeError {
+    /// `expected` is the rest of the current operation of the pattern, none
+    /// if the pattern is exhausted.
+    UnexpectedOp {
+        expected: Option<SpongeOp>,
+        actual: SpongeOp,
+    },
+    /// `finish` is called before the pattern is exhausted.
+    Unfinished { remaining: SpongeOp },
+}
+
+impl core::fmt::Display for SafeSpongeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::UnexpectedOp { expected: Some(expected), actual } => {
+                write!(f, "IO pattern expects {:?}, got {:?}", expected, actual)
+            }
+            Self::UnexpectedOp { expected: None, actual } => {
+                write!(f, "IO pattern is exhausted, got {:?}", actual)
+            }
+            Self::Unfinished { remaining } => write!(f, "IO pattern is not exhausted, {:?} remains", remaining),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SafeSpongeError {}
+
+// Position of the calls within the pattern, shared by native and circuit
+// sponges. An operation of the pattern may be split across several calls.
+#[derive(Clone, Debug)]
+pub(crate) struct IOTracker {
+    pattern: IOPattern,
+    op: usize,
+    used: u32,
+}
+
+impl IOTracker {
+    pub(crate) fn new(pattern: IOPattern) -> Self {
+        Self { pattern, op: 0, used: 0 }
+    }
+
+    fn remaining(&self) -> Option<SpongeOp> {
+        self.pattern
+            .0
+            .get(self.op)
+            .map(|op| op.with_len(op.len() - self.used))
+    }
+
+    pub(crate) fn consume(&mut self, actual: SpongeOp) -> Result<(), SafeSpongeError> {
+        if actual.len() == 0 {
+            return Ok(());
+        }
+        match self.remaining() {
+            Some(expected) if expected.same_kind(&actual) && actual.len() <= expected.len() => {
+                self.used += actual.len();
+                if actual.len() == expected.len() {
+                    self.op += 1;
+                    self.used = 0;
+                }
+
+                Ok(())
+            }
+            expected => Err(SafeSpongeError::UnexpectedOp { expected, actual }),
+        }
+    }
+
+    pub(crate) fn finish(&self) -> Result<(), SafeSpongeError> {
+        match self.remaining() {
+            Some(remaining) => Err(SafeSpongeError::Unfinished { remaining }),
+            None => Ok(()),
+        }
+    }
+}
+
+pub(crate) fn op_len(len: usize) -> u32 {
+    u32::try_from(len).expect("too many elements")
+}
+
+/// Sponge following the SAFE calling convention over any hash of the crate.
+/// Absorbed elements are added to the rate part.
+pub struct SafeSponge<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize> {
+    state: State<E, WIDTH>,
+    params: P,
+    tracker: IOTracker,
+    absorb_pos: usize,
+    squeeze_pos: usize,
+}
+
+impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize> SafeSponge<E, P, RATE, WIDTH> {
+    /// SAFE `START`.
+    pub fn new(params: P, pattern: IOPattern, domain_separator: &[u8]) -> Self {
+        assert!(RATE < WIDTH, "SAFE requires a capacity element");
+        let mut state = State::<E, WIDTH>::new();
+        state[RATE] = pattern.tag::<E>(domain_separator);
+
+        Self {
+            state,
+            params,
+            tracker: IOTracker::new(pattern),
+            absorb_pos: 0,
+            squeeze_pos: 0,
+        }
+    }
+
+    /// SAFE `ABSORB` of `input.len()` elements.
+    pub fn absorb(&mut self, input: &[E::Fr]) -> Result<(), SafeSpongeError> {
+        self.tracker.consume(SpongeOp::Absorb(op_len(input.len())))?;
+
+        for el in input.iter() {
+            if self.absorb_pos == RATE {
+                generic_round_function(&self.params, &mut *self.state);
+                self.absorb_pos = 0;
+            }
+            self.state[self.absorb_pos].add_assign(el);
+            self.absorb_pos += 1;
+        }
+        // next squeeze permutes
+        self.squeeze_pos = RATE;
+
+        Ok(())
+    }
+
+    /// SAFE `SQUEEZE` of `length` elements.
+    pub fn squeeze(&mut self, length: usize) -> Result<Vec<E::Fr>, SafeSpongeError> {
+        self.tracker.consume(SpongeOp::Squeeze(op_len(length)))?;
+
+        let mut output = Vec::with_capacity(length);
+        for _ in 0..length {
+            if self.squeeze_pos == RATE {
+                generic_round_function(&self.params, &mut *self.state);
+                self.squeeze_pos = 0;
+                self.absorb_pos = 0;
+            }
+            output.push(self.state[self.squeeze_pos]);
+            self.squeeze_pos += 1;
+        }
+
+        Ok(output)
+    }
+
+    /// SAFE `FINISH`, fails unless every operation of the pattern is done.
+    pub fn finish(self) -> Result<(), SafeSpongeError> {
+        self.tracker.finish()
+    }
+}
@@ -0,0 +1,205 @@
+//! Cheap runtime checks of the implementation, suitable for calling at
+//! service startup to catch platform specific miscompilations.
+use crate::common::sbox::sbox;
+use crate::common::utils::fe_from_u64;
+use crate::griffin::GriffinParams;
+use crate::poseidon2::Poseidon2Params;
+use crate::sponge::{generic_round_function, GenericSponge};
+use crate::traits::HashParams;
+use crate::{
+    poseidon_round_function_reference, CircuitGenericSponge, PoseidonParams, RescueParams, RescuePrimeParams,
+};
+use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+use franklin_crypto::bellman::plonk::better_better_cs::cs::{
+    ConstraintSystem, TrivialAssembly, Width4MainGateWithDNext,
+};
+use franklin_crypto::bellman::{Field, PrimeField, SynthesisError};
+use franklin_crypto::plonk::circuit::allocated_num::{AllocatedNum, Num};
+use franklin_crypto::plonk::circuit::Width4WithCustomGates;
+
+const RATE: usize = 2;
+const WIDTH: usize = 3;
+
+// First round constants of RescuePrime over Bn256 with rate=2, width=3.
+const RESCUE_PRIME_FIRST_ROUND_CONSTANTS: [&str; WIDTH] = [
+    "25fa60d3d93901eabe9b6cc8682b1c141261bf7e9355e4565a7d6a79efaa1272",
+    "0dae0e024afc871a113d48c03c92cc288bbd15a178b9d93774b3eaf2c1a2605f",
+    "05fe864da3b3c5521bb6bebac86069db232d11b78333ee611765389683dcf3c6",
+];
+
+// Output of fixed length hash of [1, 2, 3] with default params over Bn256
+// with rate=2 and width=3.
+const RESCUE_DIGEST: [&str; RATE] = [
+    "09d18612a2755fb856a491800a8096a9b7d26711563002e088ea706a7ce741b2",
+    "101c0c863b70783d2cf571a77d5ba88c189e38285323c4210fb31f7872c2ced5",
+];
+const POSEIDON_DIGEST: [&str; RATE] = [
+    "138c969509bd98822a310b9670ab9b6694bbaa3dd1b46e0dae9106fdb97ef378",
+    "0a8450518e6936cbd867d254c691e1585124f03bffb4d4bcb3a2141cc9d1253e",
+];
+const RESCUE_PRIME_DIGEST: [&str; RATE] = [
+    "2a10655816a2727a6f38e49ee0ac360a0a26ea42982de7394b53f6328add3890",
+    "1f4bc79a15d399a0724a5e418f20af27f0e1c2f937e9053b2ed1043ab4bc21f8",
+];
+const POSEIDON2_DIGEST: [&str; RATE] = [
+    "17e2942ec7c9fb415a2bcd4f8af51cad50d19ac18f49095b5dcf4e98fdccc150",
+    "145b541c33d69a1e5f94b0f263eff92a61e1cb2f858bd0b0008430381978b854",
+];
+const GRIFFIN_DIGEST: [&str; RATE] = [
+    "1543fe6714a475600a8e11e6204729462945eab22b99a1eb089799a1c88824d0",
+    "0b6bf13d3db9f9ba46ea1aee4d0d8bf66b725f5d9381581d326b379da38aa404",
+];
+
+/// Outcome of a single self test check.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SelfTestCheck {
+    pub name: &'static str,
+    pub passed: bool,
+}
+
+/// Report of `self_test`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SelfTestReport {
+    pub checks: Vec<SelfTestCheck>,
+}
+
+impl SelfTestReport {
+    pub fn is_ok(&self) -> bool {
+        self.checks.iter().all(|check| check.passed)
+    }
+
+    pub fn failed(&self) -> impl Iterator<Item = &SelfTestCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+
+    fn record(&mut self, name: &'static str, passed: bool) {
+        self.checks.push(SelfTestCheck { name, passed });
+    }
+}
+
+/// Runs known answer and consistency checks for each hash family over Bn256
+/// with rate=2 and width=3. If `check_circuits` is set, also synthesizes a
+/// tiny circuit per family and checks its satisfiability and output.
+pub fn self_test(check_circuits: bool) -> SelfTestReport {
+    let mut report = SelfTestReport::default();
+
+    let rescue = RescueParams::<Bn256, RATE, WIDTH>::default();
+    let poseidon = PoseidonParams::<Bn256, RATE, WIDTH>::default();
+    let rescue_prime = RescuePrimeParams::<Bn256, RATE, WIDTH>::default();
+    let poseidon2 = Poseidon2Params::<Bn256, RATE, WIDTH>::default();
+    let griffin = GriffinParams::<Bn256, RATE, WIDTH>::default();
+
+    report.record("rescue prime round constants", check_rescue_prime_constants(&rescue_prime));
+    report.record("rescue inverse sbox", check_inverse_sbox(&rescue));
+    report.record("poseidon optimized rounds", check_poseidon_schedule(&poseidon));
+
+    report.record("rescue sponge", check_sponge(&rescue, &RESCUE_DIGEST));
+    report.record("poseidon sponge", check_sponge(&poseidon, &POSEIDON_DIGEST));
+    report.record("rescue prime sponge", check_sponge(&rescue_prime, &RESCUE_PRIME_DIGEST));
+    report.record("poseidon2 sponge", check_sponge(&poseidon2, &POSEIDON2_DIGEST));
+    report.record("griffin sponge", check_sponge(&griffin, &GRIFFIN_DIGEST));
+
+    if check_circuits {
+        report.record("rescue circuit", check_circuit(&rescue, &RESCUE_DIGEST));
+        report.record("poseidon circuit", check_circuit(&poseidon, &POSEIDON_DIGEST));
+        report.record("rescue prime circuit", check_circuit(&rescue_prime, &RESCUE_PRIME_DIGEST));
+        report.record("poseidon2 circuit", check_circuit(&poseidon2, &POSEIDON2_DIGEST));
+        report.record("griffin circuit", check_circuit(&griffin, &GRIFFIN_DIGEST));
+    }
+
+    report
+}
+
+fn fe_from_hex(value: &str) -> Option<Fr> {
+    let mut repr = <Fr as PrimeField>::Repr::default();
+    let num_limbs = repr.as_ref().len();
+    if value.len() != num_limbs * 16 {
+        return None;
+    }
+    for (limb, chunk) in repr.as_mut().iter_mut().rev().zip(value.as_bytes().chunks(16)) {
+        *limb = u64::from_str_radix(std::str::from_utf8(chunk).ok()?, 16).ok()?;
+    }
+
+    Fr::from_repr(repr).ok()
+}
+
+fn test_input() -> [Fr; 3] {
+    [1, 2, 3].map(fe_from_u64::<Bn256>)
+}
+
+fn check_rescue_prime_constants(params: &RescuePrimeParams<Bn256, RATE, WIDTH>) -> bool {
+    params
+        .constants_of_round(0)
+        .iter()
+        .zip(RESCUE_PRIME_FIRST_ROUND_CONSTANTS.iter())
+        .all(|(actual, expected)| fe_from_hex(expected) == Some(*actual))
+}
+
+fn check_inverse_sbox(params: &RescueParams<Bn256, RATE, WIDTH>) -> bool {
+    let input = test_input();
+    let mut state = input;
+    sbox::<Bn256>(params.alpha(), &mut state);
+    sbox::<Bn256>(params.alpha_inv(), &mut state);
+
+    state == input
+}
+
+// Optimized permutation should match the permutation over the original schedule.
+fn check_poseidon_schedule(params: &PoseidonParams<Bn256, RATE, WIDTH>) -> bool {
+    let mut expected = test_input();
+    poseidon_round_function_reference(params, &mut expected);
+
+    let mut actual = test_input();
+    generic_round_function(params, &mut actual);
+
+    actual == expected
+}
+
+// Fixed length hash should give the known digest.
+fn check_sponge<P: HashParams<Bn256, RATE, WIDTH>>(params: &P, expected: &[&str; RATE]) -> bool {
+    let actual = GenericSponge::<Bn256, RATE, WIDTH>::hash(&test_input(), params, None);
+
+    matches_digest(&actual, expected)
+}
+
+fn check_circuit<P: HashParams<Bn256, RATE, WIDTH>>(params: &P, expected: &[&str; RATE]) -> bool {
+    let cs = &mut TrivialAssembly::<Bn256, Width4WithCustomGates, Width4MainGateWithDNext>::new();
+    match synthesize_hash(cs, params) {
+        Ok(actual) => {
+            let actual: Option<Vec<Fr>> = actual.iter().map(|num| num.get_value()).collect();
+            cs.is_satisfied() && actual.map_or(false, |actual| matches_digest(&actual, expected))
+        }
+        Err(_) => false,
+    }
+}
+
+fn matches_digest(actual: &[Fr], expected: &[&str; RATE]) -> bool {
+    actual.len() == RATE
+        && actual
+            .iter()
+            .zip(expected.iter())
+            .all(|(actual, expected)| fe_from_hex(expected) == Some(*actual))
+}
+
+fn synthesize_hash<CS: ConstraintSystem<Bn256>, P: HashParams<Bn256, RATE, WIDTH>>(
+    cs: &mut CS,
+    params: &P,
+) -> Result<[Num<Bn256>; RATE], SynthesisError> {
+    let mut input = [Num::Constant(Fr::zero()); 3];
+    for (num, el) in input.iter_mut().zip(test_input().iter()) {
+        *num = Num::Variable(AllocatedNum::alloc(cs, || Ok(*el))?);
+    }
+
+    CircuitGenericSponge::<Bn256, RATE, WIDTH>::hash_num(cs, &input, params, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_self_test() {
+        let report = self_test(true);
+        assert!(report.is_ok(), "failed checks: {:?}", report.failed().collect::<Vec<_>>());
+    }
+}
@@ -0,0 +1,368 @@
+//! Specification of a parameter set rendered from the params themselves, so
+//! reviewed document always describes the configuration which is deployed.
+
+use crate::common::params::{MDS_TAG, ROUND_CONSTANTS_TAG};
+use crate::traits::{HashFamily, HashParams};
+use franklin_crypto::bellman::{Engine, PrimeField};
+use std::fmt::Write;
+
+/// Renders specification of `params` as markdown: field, state shape, round
+/// structure, sboxes, linear layer, all round constants, derivation of
+/// default constants and padding rules. Output is deterministic, so two
+/// documents can be diffed.
+///
+/// Panics for Poseidon params deserialized from a blob which predates storing
+/// original round constants.
+pub fn render_markdown<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+) -> String {
+    let family = params.hash_family();
+    let mut out = String::new();
+
+    writeln!(out, "# {} over a {}-bit prime field", family, E::Fr::NUM_BITS).unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "Fingerprint: `{}`", to_hex(&params.fingerprint())).unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "## Field").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "- modulus: `{}`", E::Fr::char()).unwrap();
+    writeln!(out, "- elements are written as big endian hex of their canonical representation").unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "## State").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "- width: {}", WIDTH).unwrap();
+    writeln!(out, "- rate: {}, elements `0..{}`", RATE, RATE).unwrap();
+    writeln!(out, "- capacity: {}, elements `{}..{}`", WIDTH - RATE, RATE, WIDTH).unwrap();
+    writeln!(out).unwrap();
+
+    writeln!(out, "## Rounds").unwrap();
+    writeln!(out).unwrap();
+    render_rounds(&mut out, params);
+    writeln!(out).unwrap();
+
+    writeln!(out, "## S-box").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "- forward: {:?}", params.alpha()).unwrap();
+    match family {
+        HashFamily::Rescue | HashFamily::RescuePrime | HashFamily::Griffin | HashFamily::Anemoi => {
+            writeln!(out, "- inverse: {:?}", params.alpha_inv()).unwrap();
+        }
+        HashFamily::Poseidon | HashFamily::Poseidon2 => (),
+        HashFamily::Monolith => unreachable!("monolith params don't implement HashParams"),
+    }
+    writeln!(out).unwrap();
+
+    writeln!(out, "## Linear layer").unwrap();
+    writeln!(out).unwrap();
+    render_linear_layer(&mut out, params);
+    writeln!(out).unwrap();
+
+    writeln!(out, "## Round constants").unwrap();
+    writeln!(out).unwrap();
+    writeln!(out, "| round | constants |").unwrap();
+    writeln!(out, "| --- | --- |").unwrap();
+    for round in 0..number_of_round_constants(params) {
+        let constants: Vec<_> = params.constants_of_round(round).iter().map(fr_to_hex::<E>).collect();
+        writeln!(out, "| {} | {} |", round, constants.join(" ")).unwrap();
+    }
+    writeln!(out).unwrap();
+
+    writeln!(out, "## Derivation of default constants").unwrap();
+    writeln!(out).unwrap();
+    render_derivation(&mut out, family);
+    writeln!(out).unwrap();
+
+    writeln!(out, "## Padding and domain separation").unwrap();
+    writeln!(out).unwrap();
+    writeln!(
+        out,
+        "- fixed length hash: last state element is set to the input length, input is padded \
+         with ones up to a multiple of the rate, output is `state[0..{}]`",
+        RATE
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "- sponge: capacity starts at zero, absorbed message is padded with ones up to a \
+         multiple of the rate unless its length is already a multiple of it"
+    )
+    .unwrap();
+    writeln!(out, "- input is added to the rate part of the state before each permutation").unwrap();
+
+    out
+}
+
+fn render_rounds<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    out: &mut String,
+    params: &P,
+) {
+    let full_rounds = params.number_of_full_rounds();
+    match params.hash_family() {
+        HashFamily::Rescue => {
+            writeln!(out, "- rounds: {}", full_rounds).unwrap();
+            writeln!(out, "- constants 0 are added to the state first").unwrap();
+            writeln!(
+                out,
+                "- step `i` in `0..{}` applies inverse S-box if `i` is even and forward S-box \
+                 otherwise, then the MDS matrix, then constants `i + 1`",
+                2 * full_rounds
+            )
+            .unwrap();
+        }
+        HashFamily::Poseidon => {
+            let partial_rounds = params.number_of_partial_rounds();
+            writeln!(out, "- full rounds: {}, half before and half after partial rounds", full_rounds).unwrap();
+            writeln!(out, "- partial rounds: {}", partial_rounds).unwrap();
+            writeln!(
+                out,
+                "- round `r` adds constants `r`, applies S-box to the whole state in full rounds \
+                 and to element 0 in partial rounds, then multiplies by the linear layer"
+            )
+            .unwrap();
+        }
+        HashFamily::RescuePrime => {
+            writeln!(out, "- rounds: {}", full_rounds - 1).unwrap();
+            writeln!(
+                out,
+                "- round `r` applies forward S-box, MDS matrix, constants `r`, inverse S-box, \
+                 MDS matrix and constants `r + 1`"
+            )
+            .unwrap();
+        }
+        HashFamily::Poseidon2 => {
+            let partial_rounds = params.number_of_partial_rounds();
+            writeln!(out, "- full rounds: {}, half before and half after partial rounds", full_rounds).unwrap();
+            writeln!(out, "- partial rounds: {}", partial_rounds).unwrap();
+            writeln!(out, "- state is multiplied by the external matrix first").unwrap();
+            writeln!(
+                out,
+                "- full round `r` adds constants `r`, applies S-box to the whole state and \
+                 multiplies by the external matrix"
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "- partial round `r` adds element 0 of constants `r` to element 0, applies \
+                 S-box to it and multiplies by the internal matrix"
+            )
+            .unwrap();
+        }
+        HashFamily::Griffin => {
+            writeln!(out, "- rounds: {}", full_rounds).unwrap();
+            writeln!(out, "- state is multiplied by the MDS matrix first").unwrap();
+            writeln!(
+                out,
+                "- round `r` maps `(x0, x1, x2)` to `(y0, y1, x2 * (L^2 + alpha * L + beta))` \
+                 where `y0 = x0^(1/d)`, `y1 = x1^d` and `L = y0 + y1`, multiplies by the MDS \
+                 matrix and adds constants `r` except in the last round"
+            )
+            .unwrap();
+        }
+        HashFamily::Anemoi => {
+            writeln!(out, "- rounds: {}", full_rounds).unwrap();
+            writeln!(
+                out,
+                "- round `r` adds constants `r`, multiplies by the linear layer and maps each \
+                 column `(x, y) = (state[i], state[{} + i])` by the open Flystel: \
+                 `u = x - beta * y^2 - gamma`, `v = y - u^(1/d)`, `(u + beta * v^2 + delta, v)`",
+                WIDTH / 2
+            )
+            .unwrap();
+            writeln!(out, "- state is multiplied by the linear layer once more after the last round").unwrap();
+        }
+        HashFamily::Monolith => unreachable!("monolith params don't implement HashParams"),
+    }
+}
+
+fn render_linear_layer<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    out: &mut String,
+    params: &P,
+) {
+    let render_matrix = |out: &mut String, matrix: &[[E::Fr; WIDTH]; WIDTH]| {
+        writeln!(out, "```").unwrap();
+        for row in matrix.iter() {
+            let row: Vec<_> = row.iter().map(fr_to_hex::<E>).collect();
+            writeln!(out, "{}", row.join(" ")).unwrap();
+        }
+        writeln!(out, "```").unwrap();
+    };
+
+    match params.hash_family() {
+        HashFamily::Poseidon2 => {
+            let params = params.try_to_poseidon2_params().expect("poseidon2 params");
+            writeln!(out, "External matrix, row by row:").unwrap();
+            writeln!(out).unwrap();
+            render_matrix(out, &params.mds_external_matrix);
+            writeln!(out).unwrap();
+            let diagonal: Vec<_> = params.diag_internal_matrix.iter().map(fr_to_hex::<E>).collect();
+            writeln!(out, "Internal matrix has ones off the diagonal and `{}` on it.", diagonal.join(" ")).unwrap();
+        }
+        _ => {
+            if params.uses_circular_full_rounds_mds() {
+                writeln!(out, "Full rounds use circ(2, 1, 1), partial rounds use the matrix below.").unwrap();
+                writeln!(out).unwrap();
+            }
+            writeln!(out, "MDS matrix, row by row:").unwrap();
+            writeln!(out).unwrap();
+            render_matrix(out, params.mds_matrix());
+        }
+    }
+
+    if let Some(params) = params.try_to_griffin_params() {
+        let (alpha, beta) = params.alpha_beta();
+        writeln!(out).unwrap();
+        writeln!(out, "- alpha: `{}`", fr_to_hex::<E>(alpha)).unwrap();
+        writeln!(out, "- beta: `{}`", fr_to_hex::<E>(beta)).unwrap();
+    }
+
+    if let Some(params) = params.try_to_anemoi_params() {
+        let (beta, gamma, delta) = params.flystel_constants();
+        writeln!(out).unwrap();
+        writeln!(out, "- beta: `{}`", fr_to_hex::<E>(beta)).unwrap();
+        writeln!(out, "- gamma: `{}`", fr_to_hex::<E>(gamma)).unwrap();
+        writeln!(out, "- delta: `{}`", fr_to_hex::<E>(delta)).unwrap();
+    }
+}
+
+fn render_derivation(out: &mut String, family: HashFamily) {
+    let tag = |tag: &[u8]| String::from_utf8_lossy(tag).into_owned();
+    let blake_constants = |out: &mut String, tag: &str| {
+        writeln!(
+            out,
+            "- round constants: blake2s personalized with `{}` over `GH_FIRST_BLOCK || nonce` \
+             with big endian u32 nonce starting from 0, digest is read as a little endian \
+             integer and rejected if it is zero or not less than the modulus",
+            tag
+        )
+        .unwrap();
+    };
+    let cauchy_mds = |out: &mut String| {
+        writeln!(
+            out,
+            "- MDS matrix: Cauchy matrix sampled by ChaCha rng seeded with blake2s \
+             personalized with `{}` over `GH_FIRST_BLOCK`",
+            tag(MDS_TAG)
+        )
+        .unwrap();
+    };
+
+    match family {
+        HashFamily::Rescue | HashFamily::Poseidon => {
+            blake_constants(out, &tag(ROUND_CONSTANTS_TAG));
+            cauchy_mds(out);
+        }
+        HashFamily::Poseidon2 => {
+            blake_constants(out, &tag(ROUND_CONSTANTS_TAG));
+            writeln!(out, "- constants of partial rounds are zero except element 0").unwrap();
+            writeln!(out, "- external and internal matrices are fixed by the Poseidon2 paper").unwrap();
+        }
+        HashFamily::RescuePrime => {
+            writeln!(
+                out,
+                "- round constants: SHAKE256 of `{}(p,m,c,s)` with modulus, width, capacity \
+                 and security level {} in decimal, read in little endian chunks of \
+                 `bits(p) / 8 + 1` bytes reduced by the modulus",
+                crate::rescue_prime::params::ROUND_CONSTANTS_SEED_PREFIX,
+                crate::rescue_prime::params::SECURITY_LEVEL
+            )
+            .unwrap();
+            cauchy_mds(out);
+        }
+        HashFamily::Griffin => {
+            writeln!(
+                out,
+                "- round constants, then alpha and beta: SHAKE128 of `{}` followed by the modulus \
+                 as little endian u64 limbs, read in little endian chunks of `ceil(bits(p) / 8)` \
+                 bytes with the unused top bits masked and rejected if not less than the modulus",
+                tag(crate::griffin::params::CONSTANTS_SEED_PREFIX)
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "- alpha and beta: the first pair of distinct nonzero elements whose \
+                 `alpha^2 - 4 * beta` is a non-residue"
+            )
+            .unwrap();
+            writeln!(out, "- MDS matrix: circ(2, 1, 1)").unwrap();
+        }
+        HashFamily::Anemoi => {
+            writeln!(
+                out,
+                "- round constants: `C[r][i] = g * pi_0^(2r) + (pi_0^r + pi_1^i)^alpha` for `X` and \
+                 `D[r][i] = g * pi_1^(2i) + (pi_0^r + pi_1^i)^alpha + g^-1` for `Y`, where `pi_0` \
+                 and `pi_1` are the first and second hundred decimal digits of pi after the point"
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "- linear layer: halves are multiplied by [[1, g], [g, g^2 + 1]] (identity for a \
+                 single column) with `Y` rotated left by one, then `Y += X` and `X += Y`"
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "- beta is the smallest primitive root `g` (5 for BN254), gamma is zero, delta is `g^-1`"
+            )
+            .unwrap();
+        }
+        HashFamily::Monolith => unreachable!("monolith params don't implement HashParams"),
+    }
+    writeln!(out, "- constants listed above take precedence if params were constructed differently").unwrap();
+}
+
+fn number_of_round_constants<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+) -> usize {
+    let full_rounds = params.number_of_full_rounds();
+    match params.hash_family() {
+        HashFamily::Rescue => 2 * full_rounds + 1,
+        HashFamily::Poseidon | HashFamily::Poseidon2 => full_rounds + params.number_of_partial_rounds(),
+        HashFamily::RescuePrime | HashFamily::Anemoi => full_rounds,
+        HashFamily::Griffin => full_rounds - 1,
+        HashFamily::Monolith => unreachable!("monolith params don't implement HashParams"),
+    }
+}
+
+fn fr_to_hex<E: Engine>(el: &E::Fr) -> String {
+    el.into_repr().to_string()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::{GriffinParams, PoseidonParams, RescueParams, RescuePrimeParams};
+    use franklin_crypto::bellman::pairing::bn256::Bn256;
+
+    fn check_spec<P: HashParams<Bn256, 2, 3>>(params: &P) {
+        let spec = render_markdown(params);
+        assert_eq!(spec, render_markdown(params));
+        assert!(spec.contains(&to_hex(&params.fingerprint())));
+        assert!(spec.contains(&fr_to_hex::<Bn256>(&params.constants_of_round(0)[1])));
+        assert!(spec.contains(&fr_to_hex::<Bn256>(&params.mds_matrix()[0][1])));
+    }
+
+    #[test]
+    fn test_render_markdown() {
+        check_spec(&RescueParams::<Bn256, 2, 3>::default());
+        check_spec(&PoseidonParams::<Bn256, 2, 3>::default());
+        check_spec(&RescuePrimeParams::<Bn256, 2, 3>::default());
+        check_spec(&Poseidon2Params::<Bn256, 2, 3>::default());
+        check_spec(&GriffinParams::<Bn256, 2, 3>::default());
+
+        let anemoi = render_markdown(&crate::AnemoiParams::<Bn256, 1, 2>::default());
+        assert!(anemoi.contains("Flystel"));
+
+        // circular full rounds change the document
+        assert_ne!(
+            render_markdown(&PoseidonParams::<Bn256, 2, 3>::default()),
+            render_markdown(&PoseidonParams::<Bn256, 2, 3>::with_circular_mds())
+        );
+    }
+}
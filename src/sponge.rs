@@ -1,7 +1,10 @@
-use crate::{common::domain_strategy::DomainStrategy, traits::HashParams};
+use crate::common::misuse::MisuseTracker;
+use crate::common::utils::try_slice_into_fixed;
+use crate::state::State;
+use crate::{common::domain_strategy::DomainStrategy, traits::{HashParams, NoopMiddleware, RoundFunctionMiddleware}};
+use alloc::vec::Vec;
 use franklin_crypto::bellman::Engine;
-use franklin_crypto::bellman::Field;
-use std::convert::TryInto;
+use franklin_crypto::bellman::{Field, PrimeField};
 
 pub fn generic_hash<
     E: Engine,
@@ -17,25 +20,173 @@ pub fn generic_hash<
     GenericSponge::hash(input, params, domain_strategy)
 }
 
-#[derive(Clone)]
-enum SpongeMode<E: Engine, const RATE: usize> {
+/// Hashes each of `inputs` as a fixed length input with default domain
+/// strategy. Hashes are independent, with `rayon` feature they are computed
+/// on the global thread pool. Output order matches order of `inputs`.
+pub fn generic_hash_batch<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    params: &P,
+    inputs: &[&[E::Fr]],
+) -> Vec<[E::Fr; RATE]> {
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+        inputs
+            .par_iter()
+            .map(|input| GenericSponge::hash(input, params, None))
+            .collect()
+    }
+    #[cfg(not(feature = "rayon"))]
+    {
+        inputs
+            .iter()
+            .map(|input| GenericSponge::hash(input, params, None))
+            .collect()
+    }
+}
+
+/// Same as `generic_hash` but output elements are taken from the state
+/// according to the given convention.
+pub fn generic_hash_with_output_convention<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+    const LENGTH: usize,
+>(
+    params: &P,
+    input: &[E::Fr; LENGTH],
+    domain_strategy: Option<DomainStrategy>,
+    output_convention: OutputConvention,
+) -> [E::Fr; RATE] {
+    GenericSponge::hash_with_output_convention(input, params, domain_strategy, output_convention)
+}
+
+/// Defines which state elements form the output of a fixed length hash and in
+/// which order. Other implementations may keep capacity element in front of
+/// the state or output rate elements in reverse order.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputConvention {
+    /// `state[..RATE]`, used by this crate.
+    #[default]
+    Rate,
+    /// `state[WIDTH - RATE..]`, i.e. `state[1..=RATE]` when there is a single
+    /// capacity element.
+    Tail,
+    /// `state[..RATE]` in reverse order.
+    ReversedRate,
+}
+
+impl OutputConvention {
+    // Index of the state element which becomes i-th output element.
+    pub(crate) fn state_index(&self, i: usize, rate: usize, width: usize) -> usize {
+        debug_assert!(i < rate && rate <= width);
+        match self {
+            Self::Rate => i,
+            Self::Tail => width - rate + i,
+            Self::ReversedRate => rate - 1 - i,
+        }
+    }
+}
+
+/// Mode of the sponge together with its pending buffer.
+/// Absorbing buffer is filled from the beginning, squeezing buffer is
+/// drained from the beginning.
+#[derive(Clone, Debug)]
+pub enum SpongeMode<E: Engine, const RATE: usize> {
     Absorb([Option<E::Fr>; RATE]),
     Squeeze([Option<E::Fr>; RATE]),
 }
 
+impl<E: Engine, const RATE: usize> SpongeMode<E, RATE> {
+    // Checks that buffer has a shape which could be reached by the sponge itself.
+    fn validate(&self) -> Result<(), SpongeStateError> {
+        match self {
+            SpongeMode::Absorb(buf) => {
+                let filled = buf.iter().take_while(|el| el.is_some()).count();
+                if buf[filled..].iter().any(|el| el.is_some()) {
+                    return Err(SpongeStateError::NonContiguousAbsorbBuffer);
+                }
+            }
+            SpongeMode::Squeeze(buf) => {
+                let taken = buf.iter().take_while(|el| el.is_none()).count();
+                if buf[taken..].iter().any(|el| el.is_none()) {
+                    return Err(SpongeStateError::NonContiguousSqueezeBuffer);
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Errors of raw sponge state import.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpongeStateError {
+    NonContiguousAbsorbBuffer,
+    NonContiguousSqueezeBuffer,
+    UnsupportedDomainStrategy,
+    /// Initial state has a non-zero element where domain strategy specializes capacity.
+    CapacityIsSpecialized,
+}
+
+impl core::fmt::Display for SpongeStateError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NonContiguousAbsorbBuffer => write!(f, "absorbing buffer has gaps"),
+            Self::NonContiguousSqueezeBuffer => write!(f, "squeezing buffer has gaps"),
+            Self::UnsupportedDomainStrategy => write!(f, "only variable length domain strategies allowed"),
+            Self::CapacityIsSpecialized => write!(f, "initial state overlaps with specialized capacity element"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SpongeStateError {}
+
+/// Errors of squeezing.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SqueezeError {
+    /// Absorbing buffer is not full, `pad_if_necessary` should be called first.
+    NotPadded,
+    /// Nothing is absorbed since the sponge was created.
+    EmptyInput,
+    /// All `RATE` elements of the current permutation are already squeezed.
+    Exhausted,
+}
+
+impl core::fmt::Display for SqueezeError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::NotPadded => write!(f, "absorbing buffer should be padded before squeezing"),
+            Self::EmptyInput => write!(f, "nothing is absorbed before squeezing"),
+            Self::Exhausted => write!(f, "squeezing buffer is exhausted"),
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for SqueezeError {}
+
 #[derive(Clone)]
 pub struct GenericSponge<E: Engine, const RATE: usize, const WIDTH: usize> {
-    state: [E::Fr; WIDTH],
+    state: State<E, WIDTH>,
     mode: SpongeMode<E, RATE>,
     domain_strategy: DomainStrategy,
+    misuse: MisuseTracker,
 }
 
 impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> GenericSponge<E, RATE, WIDTH> {
     pub fn new() -> Self {
         Self {
-            state: [E::Fr::zero(); WIDTH],
+            state: State::new(),
             mode: SpongeMode::Absorb([None; RATE]),
             domain_strategy: DomainStrategy::CustomVariableLength,
+            misuse: MisuseTracker::default(),
         }
     }
 
@@ -46,23 +197,138 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> GenericSponge<E, RATE
         }
 
         Self {
-            state: [E::Fr::zero(); WIDTH],
+            state: State::new(),
             mode: SpongeMode::Absorb([None; RATE]),
             domain_strategy: domain_strategy,
+            misuse: MisuseTracker::default(),
         }
     }
 
+    /// Creates sponge whose state starts from `initial_state` instead of zeros,
+    /// e.g. to personalize hash instances without absorbing extra elements.
+    /// Last element must be zero if domain strategy specializes capacity.
+    pub fn new_with_initial_state(
+        initial_state: [E::Fr; WIDTH],
+        domain_strategy: DomainStrategy,
+    ) -> Result<Self, SpongeStateError> {
+        validate_initial_state::<E, RATE, WIDTH>(&initial_state, &domain_strategy)?;
+
+        Ok(Self {
+            state: State::from(initial_state),
+            mode: SpongeMode::Absorb([None; RATE]),
+            domain_strategy,
+            misuse: MisuseTracker::default(),
+        })
+    }
+
+    /// Restores sponge from a raw state, e.g. produced by another implementation.
+    /// Mode is validated to be reachable by the sponge itself.
+    pub fn from_raw_state(
+        state: [E::Fr; WIDTH],
+        mode: SpongeMode<E, RATE>,
+        domain_strategy: DomainStrategy,
+    ) -> Result<Self, SpongeStateError> {
+        match domain_strategy {
+            DomainStrategy::CustomVariableLength | DomainStrategy::VariableLength => (),
+            _ => return Err(SpongeStateError::UnsupportedDomainStrategy),
+        }
+        mode.validate()?;
+
+        Ok(Self {
+            state: State::from(state),
+            mode,
+            domain_strategy,
+            misuse: MisuseTracker::default(),
+        })
+    }
+
+    pub fn rate(&self) -> usize {
+        RATE
+    }
+
+    pub fn capacity(&self) -> usize {
+        WIDTH - RATE
+    }
+
+    /// Exports raw state of the sponge so it can be continued elsewhere.
+    pub fn into_raw_state(self) -> ([E::Fr; WIDTH], SpongeMode<E, RATE>, DomainStrategy) {
+        (self.state.into_array(), self.mode, self.domain_strategy)
+    }
+
+    /// Hashes fixed length input. Input is processed in place and only the last
+    /// incomplete chunk is copied on the stack for padding, so no heap allocation
+    /// happens regardless of input length.
     pub fn hash<P: HashParams<E, RATE, WIDTH>>(
         input: &[E::Fr],
         params: &P,
         domain_strategy: Option<DomainStrategy>,
     ) -> [E::Fr; RATE] {
+        Self::hash_with_output_convention(input, params, domain_strategy, OutputConvention::Rate)
+    }
+
+    /// Same as `hash` but output elements are taken from the state according
+    /// to the given convention.
+    pub fn hash_with_output_convention<P: HashParams<E, RATE, WIDTH>>(
+        input: &[E::Fr],
+        params: &P,
+        domain_strategy: Option<DomainStrategy>,
+        output_convention: OutputConvention,
+    ) -> [E::Fr; RATE] {
+        Self::hash_with_permutation(input, domain_strategy, output_convention, |state| {
+            generic_round_function(params, state)
+        })
+    }
+
+    /// Same as `hash` but the result is written into `output` and `state` is
+    /// used as the permutation state, so tight loops, e.g. building Merkle
+    /// trees, can reuse both buffers. Previous content of `state` is ignored
+    /// and it holds the final state afterwards.
+    pub fn hash_into<P: HashParams<E, RATE, WIDTH>>(
+        input: &[E::Fr],
+        params: &P,
+        domain_strategy: Option<DomainStrategy>,
+        state: &mut [E::Fr; WIDTH],
+        output: &mut [E::Fr; RATE],
+    ) {
+        Self::hash_with_permutation_into(
+            input,
+            domain_strategy,
+            OutputConvention::Rate,
+            state,
+            output,
+            |state| generic_round_function(params, state),
+        )
+    }
+
+    // Fixed length hashing where each permutation is done by `permute`.
+    pub(crate) fn hash_with_permutation<F: FnMut(&mut [E::Fr; WIDTH])>(
+        input: &[E::Fr],
+        domain_strategy: Option<DomainStrategy>,
+        output_convention: OutputConvention,
+        permute: F,
+    ) -> [E::Fr; RATE] {
+        let mut state = State::<E, WIDTH>::new();
+        let mut output = [E::Fr::zero(); RATE];
+        Self::hash_with_permutation_into(input, domain_strategy, output_convention, &mut state, &mut output, permute);
+
+        output
+    }
+
+    fn hash_with_permutation_into<F: FnMut(&mut [E::Fr; WIDTH])>(
+        input: &[E::Fr],
+        domain_strategy: Option<DomainStrategy>,
+        output_convention: OutputConvention,
+        state: &mut [E::Fr; WIDTH],
+        output: &mut [E::Fr; RATE],
+        mut permute: F,
+    ) {
+        assert!(!input.is_empty(), "empty input");
         // init state
-        let mut state = [E::Fr::zero(); WIDTH];
+        state.iter_mut().for_each(|s| *s = E::Fr::zero());
 
         let domain_strategy = domain_strategy.unwrap_or(DomainStrategy::CustomFixedLength);
         match domain_strategy {
-            DomainStrategy::CustomFixedLength | DomainStrategy::FixedLength => (),
+            DomainStrategy::CustomFixedLength | DomainStrategy::FixedLength | DomainStrategy::NoPadding => (),
             _ => panic!("only fixed length domain strategies allowed"),
         }
 
@@ -72,35 +338,43 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> GenericSponge<E, RATE
             .unwrap_or(E::Fr::zero());
         *state.last_mut().expect("last element") = capacity_value;
 
-        // compute padding values
-        let padding_values = domain_strategy.generate_padding_values::<E>(input.len(), RATE);
-
-        // chain all values
-        let mut padded_input = smallvec::SmallVec::<[_; 9]>::new();
-        padded_input.extend_from_slice(input);
-        padded_input.extend_from_slice(&padding_values);
-
-        assert!(padded_input.len() % RATE == 0);
+        // process each full chunk of input directly, no intermediate buffer needed
+        let mut chunks = input.chunks_exact(RATE);
+        for values in &mut chunks {
+            absorb_into_rate::<_, _, RATE, WIDTH>(state, values, |s, i| s.add_assign(i));
+            permute(state);
+        }
 
-        // process each chunk of input
-        for values in padded_input.chunks_exact(RATE) {
-            absorb::<E, _, RATE, WIDTH>(
-                &mut state,
-                &values.try_into().expect("constant array"),
-                params,
+        // last chunk is completed with padding values on the stack
+        let remainder = chunks.remainder();
+        if !remainder.is_empty() {
+            let mut last_chunk = [E::Fr::zero(); RATE];
+            last_chunk[..remainder.len()].copy_from_slice(remainder);
+            domain_strategy.fill_padding_values::<E>(
+                input.len(),
+                RATE,
+                &mut last_chunk[remainder.len()..],
             );
+            absorb_into_rate::<_, _, RATE, WIDTH>(state, &last_chunk, |s, i| s.add_assign(i));
+            permute(state);
         }
+
         // prepare output
-        let mut output = [E::Fr::zero(); RATE];
-        for (o, s) in output.iter_mut().zip(state[..RATE].iter()) {
-            *o = *s;
+        for (i, o) in output.iter_mut().enumerate() {
+            *o = state[output_convention.state_index(i, RATE, WIDTH)];
         }
-
-        output
+        crate::common::misuse::record_fixed_length_output::<E>(output);
     }
 
+    /// Absorbs input as a complete message. Input whose length is not a
+    /// multiple of `RATE` is followed by padding values of the domain strategy,
+    /// rate-aligned input is absorbed as is. Empty input is ignored. Circuit
+    /// sponge follows the same rule.
     pub fn absorb_multiple<P: HashParams<E, RATE, WIDTH>>(&mut self, input: &[E::Fr], params: &P) {
-        // compute padding values        
+        if input.is_empty() {
+            return;
+        }
+        // compute padding values
         let padding_values = self.domain_strategy.generate_padding_values::<E>(input.len(), RATE);
 
         for inp in input.iter().chain(padding_values.iter()) {
@@ -109,6 +383,7 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> GenericSponge<E, RATE
     }
 
     pub fn absorb<P: HashParams<E, RATE, WIDTH>>(&mut self, input: E::Fr, params: &P) {
+        self.misuse.on_absorb::<E>(&input, params.hash_family());
         match self.mode {
             SpongeMode::Absorb(ref mut buf) => {
                 // push value into buffer
@@ -144,10 +419,47 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> GenericSponge<E, RATE
         }
     }
 
+    /// Absorbs 256-bit value given as little-endian u64 limbs. Value is always
+    /// split into low and high 128-bit halves, so values exceeding the modulus
+    /// are absorbed canonically as well.
+    pub fn absorb_u256<P: HashParams<E, RATE, WIDTH>>(&mut self, limbs: [u64; 4], params: &P) {
+        for half in limbs.chunks_exact(2) {
+            let mut repr = <E::Fr as PrimeField>::Repr::default();
+            repr.as_mut()[0] = half[0];
+            repr.as_mut()[1] = half[1];
+            let el = E::Fr::from_repr(repr).expect("128 bits always fit into the field");
+            self.absorb(el, params);
+        }
+    }
+
+    /// Absorbs bitmap packed into field elements, each element holds up to
+    /// `CAPACITY` bits in little-endian order. Length of the bitmap is not
+    /// absorbed, so variable length bitmaps should be prefixed by their length.
+    pub fn absorb_bits<P: HashParams<E, RATE, WIDTH>>(&mut self, bits: &[bool], params: &P) {
+        let capacity = E::Fr::CAPACITY as usize;
+        for chunk in bits.chunks(capacity) {
+            let mut repr = <E::Fr as PrimeField>::Repr::default();
+            for (i, bit) in chunk.iter().enumerate() {
+                if *bit {
+                    repr.as_mut()[i / 64] |= 1u64 << (i % 64);
+                }
+            }
+            let el = E::Fr::from_repr(repr).expect("capacity bits always fit into the field");
+            self.absorb(el, params);
+        }
+    }
+
+    /// Pads partially filled absorbing buffer. Empty buffer is left as is:
+    /// a full block of padding would make the digest of empty input equal
+    /// to the digest of input consisting of padding values only. So squeezing
+    /// from a sponge which absorbed nothing fails with `EmptyInput`.
     pub fn pad_if_necessary(&mut self) {
         match self.mode {
             SpongeMode::Absorb(ref mut buf) => {
                 let unwrapped_buffer_len = buf.iter().filter(|el| el.is_some()).count();
+                if unwrapped_buffer_len == 0 {
+                    return;
+                }
                 // compute padding values                
                 let padding_values =
                     self.domain_strategy.generate_padding_values::<E>(unwrapped_buffer_len, RATE);
@@ -164,7 +476,73 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> GenericSponge<E, RATE
         }
     }
 
+    /// Irreversibly mixes the state as RATCHET operation of Strobe: pending
+    /// input is padded and absorbed, state is permuted and its rate part is
+    /// zeroized. Only the capacity carries the history further, so leaked
+    /// state doesn't allow to recover the state before ratchet without
+    /// guessing erased rate elements. Pending squeezed elements are dropped
+    /// and sponge should absorb again before squeezing.
+    pub fn ratchet<P: HashParams<E, RATE, WIDTH>>(&mut self, params: &P) {
+        self.pad_if_necessary();
+        let mut input = [E::Fr::zero(); RATE];
+        if let SpongeMode::Absorb(ref buf) = self.mode {
+            for (i, b) in input.iter_mut().zip(buf.iter()) {
+                if let Some(value) = b {
+                    *i = *value;
+                }
+            }
+        }
+        absorb::<E, _, RATE, WIDTH>(&mut self.state, &input, params);
+        self.state[..RATE].iter_mut().for_each(|s| *s = E::Fr::zero());
+        self.mode = SpongeMode::Absorb([None; RATE]);
+    }
+
+    /// Duplex call: absorbs up to `RATE` elements with a single permutation
+    /// and returns the rate part of the permuted state. Shorter input is
+    /// padded by a single `one` followed by zeros, while a full block instead
+    /// adds `one` to the last capacity element, so different inputs never
+    /// result in the same permutation input. Padding doesn't depend on the
+    /// domain strategy. Empty input is allowed and only advances the state.
+    /// Sponge shouldn't have pending absorbed elements, pending squeezed
+    /// elements are dropped.
+    pub fn duplex_call<P: HashParams<E, RATE, WIDTH>>(&mut self, input: &[E::Fr], params: &P) -> [E::Fr; RATE] {
+        assert!(input.len() <= RATE, "at most {} elements are absorbed per duplex call", RATE);
+        if let SpongeMode::Absorb(ref buf) = self.mode {
+            assert!(buf.iter().all(|el| el.is_none()), "duplex call with pending absorbed elements");
+        }
+        for el in input.iter() {
+            self.misuse.on_absorb::<E>(el, params.hash_family());
+        }
+        self.misuse.on_squeeze(params.hash_family());
+
+        let mut block = [E::Fr::zero(); RATE];
+        block[..input.len()].copy_from_slice(input);
+        if input.len() < RATE {
+            block[input.len()] = E::Fr::one();
+        } else {
+            self.state[WIDTH - 1].add_assign(&E::Fr::one());
+        }
+        absorb::<E, _, RATE, WIDTH>(&mut self.state, &block, params);
+        self.mode = SpongeMode::Absorb([None; RATE]);
+
+        try_slice_into_fixed(&self.state[..RATE]).expect("rate part")
+    }
+
+    /// Squeezes an element, returns `None` either if buffer is not padded or
+    /// all `RATE` elements are already squeezed. See `try_squeeze` and
+    /// `squeeze_or_permute` for a well-defined behavior.
     pub fn squeeze<P: HashParams<E, RATE, WIDTH>>(&mut self, params: &P) -> Option<E::Fr> {
+        self.try_squeeze(params).ok()
+    }
+
+    /// Squeezes an element, errors if nothing is absorbed, buffer is not
+    /// padded or all `RATE` elements are already squeezed after the last
+    /// absorption.
+    pub fn try_squeeze<P: HashParams<E, RATE, WIDTH>>(
+        &mut self,
+        params: &P,
+    ) -> Result<E::Fr, SqueezeError> {
+        self.misuse.on_squeeze(params.hash_family());
         loop {
             match self.mode {
                 SpongeMode::Absorb(ref mut buf) => {
@@ -176,9 +554,12 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> GenericSponge<E, RATE
                         }
                     }
 
+                    if unwrapped_buffer.is_empty() {
+                        return Err(SqueezeError::EmptyInput);
+                    }
                     if unwrapped_buffer.len() != RATE {
                         // processing buffer was done and we need padding
-                        return None;
+                        return Err(SqueezeError::NotPadded);
                     }
 
                     // make input array
@@ -188,7 +569,7 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> GenericSponge<E, RATE
                     }
 
                     // permute state
-                    absorb(&mut self.state, &all_inputs, params);
+                    absorb(&mut *self.state, &all_inputs, params);
 
                     // push values into squeezing buffer for later squeezing
                     let mut squeeze_buffer = [None; RATE];
@@ -202,14 +583,52 @@ impl<'a, E: Engine, const RATE: usize, const WIDTH: usize> GenericSponge<E, RATE
                 SpongeMode::Squeeze(ref mut buf) => {
                     for el in buf {
                         if let Some(value) = el.take() {
-                            return Some(value);
+                            return Ok(value);
                         }
                     }
-                    return None;
+                    return Err(SqueezeError::Exhausted);
                 }
             };
         }
     }
+
+    /// Squeezes an element, permuting the state again once all `RATE`
+    /// elements are squeezed, so any number of elements can be squeezed.
+    /// Errors only if buffer is not padded.
+    pub fn squeeze_or_permute<P: HashParams<E, RATE, WIDTH>>(
+        &mut self,
+        params: &P,
+    ) -> Result<E::Fr, SqueezeError> {
+        match self.try_squeeze(params) {
+            Err(SqueezeError::Exhausted) => {
+                generic_round_function(params, &mut *self.state);
+                let mut squeeze_buffer = [None; RATE];
+                for (s, b) in self.state[..RATE].iter().zip(squeeze_buffer.iter_mut()) {
+                    *b = Some(*s)
+                }
+                self.mode = SpongeMode::Squeeze(squeeze_buffer);
+
+                self.try_squeeze(params)
+            }
+            result => result,
+        }
+    }
+}
+
+pub(crate) fn validate_initial_state<E: Engine, const RATE: usize, const WIDTH: usize>(
+    initial_state: &[E::Fr; WIDTH],
+    domain_strategy: &DomainStrategy,
+) -> Result<(), SpongeStateError> {
+    match domain_strategy {
+        DomainStrategy::CustomVariableLength | DomainStrategy::VariableLength => (),
+        _ => return Err(SpongeStateError::UnsupportedDomainStrategy),
+    }
+    let specializes_capacity = matches!(domain_strategy.try_compute_capacity::<E>(1, RATE), Ok(Some(_)));
+    if specializes_capacity && !initial_state[WIDTH - 1].is_zero() {
+        return Err(SpongeStateError::CapacityIsSpecialized);
+    }
+
+    Ok(())
 }
 
 fn absorb<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
@@ -217,12 +636,34 @@ fn absorb<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WID
     input: &[E::Fr; RATE],
     params: &P,
 ) {
-    for (i, s) in input.iter().zip(state.iter_mut()) {
-        s.add_assign(i);
-    }
+    absorb_into_rate::<_, _, RATE, WIDTH>(state, input, |s, i| s.add_assign(i));
     generic_round_function(params, state);
 }
 
+/// Adds a block of input into the rate part of the state. Capacity part is
+/// never touched and debug builds check that no more than `RATE` elements
+/// are absorbed per permutation, since longer input would be silently
+/// truncated otherwise.
+pub(crate) fn absorb_into_rate<T, I, const RATE: usize, const WIDTH: usize>(
+    state: &mut [T; WIDTH],
+    input: &[I],
+    mut add: impl FnMut(&mut T, &I),
+) {
+    debug_assert!(RATE < WIDTH, "rate {} leaves no capacity in width {}", RATE, WIDTH);
+    debug_assert!(
+        input.len() <= RATE,
+        "{} elements are absorbed per permutation, rate is {}",
+        input.len(),
+        RATE
+    );
+    for (s, i) in state[..RATE].iter_mut().zip(input.iter()) {
+        add(s, i);
+    }
+}
+
+/// Permutation of `params` applied to `state`. With the `asm` feature Poseidon
+/// and Rescue over Bn256 use the assembly field arithmetic on x86_64 CPUs with
+/// ADX and BMI2, the result is the same.
 pub fn generic_round_function<
     E: Engine,
     P: HashParams<E, RATE, WIDTH>,
@@ -231,22 +672,57 @@ pub fn generic_round_function<
 >(
     params: &P,
     state: &mut [E::Fr; WIDTH],
+) {
+    #[cfg(all(feature = "asm", target_arch = "x86_64"))]
+    if crate::asm::try_round_function(params, state) {
+        return;
+    }
+    generic_round_function_with_middleware(params, state, &mut NoopMiddleware)
+}
+
+/// Same as `generic_round_function` but calls `middleware` after each round.
+pub fn generic_round_function_with_middleware<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    M: RoundFunctionMiddleware<E, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+>(
+    params: &P,
+    state: &mut [E::Fr; WIDTH],
+    middleware: &mut M,
 ) {
     match params.hash_family() {
         crate::traits::HashFamily::Rescue => {
-            crate::rescue::rescue_round_function(params, state)
+            crate::rescue::rescue_round_function_with_middleware(params, state, middleware)
         }
         crate::traits::HashFamily::Poseidon => {
-            crate::poseidon::poseidon_round_function(params, state)
+            crate::poseidon::poseidon_round_function_with_middleware(params, state, middleware)
         }
         crate::traits::HashFamily::RescuePrime => {
-            crate::rescue_prime::rescue_prime_round_function(params, state)
+            crate::rescue_prime::rescue_prime_round_function_with_middleware(params, state, middleware)
         }
         crate::traits::HashFamily::Poseidon2 => {
-            crate::poseidon2::poseidon2_round_function(
+            crate::poseidon2::poseidon2_round_function_with_middleware(
                 state, 
-                params.try_to_poseidon2_params().unwrap()
+                params.try_to_poseidon2_params().unwrap(),
+                middleware,
+            )
+        }
+        crate::traits::HashFamily::Griffin => {
+            crate::griffin::griffin_round_function_with_middleware(
+                state,
+                params.try_to_griffin_params().unwrap(),
+                middleware,
+            )
+        }
+        crate::traits::HashFamily::Anemoi => {
+            crate::anemoi::anemoi_round_function_with_middleware(
+                state,
+                params.try_to_anemoi_params().unwrap(),
+                middleware,
             )
         }
+        crate::traits::HashFamily::Monolith => unreachable!("monolith params don't implement HashParams"),
     }
 }
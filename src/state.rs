@@ -0,0 +1,165 @@
+use crate::sponge::generic_round_function;
+use crate::traits::HashParams;
+use franklin_crypto::bellman::{Engine, Field};
+
+/// Size of the alignment of `State`, a cache line of common CPUs.
+pub const STATE_ALIGNMENT: usize = 64;
+
+/// Permutation state aligned to a cache line, so a state never straddles more
+/// lines than its size requires, e.g. 6 lines for width 12 over a 254 bit
+/// field instead of 7. Size is rounded up to a multiple of the alignment.
+///
+/// Dereferences to `[E::Fr; WIDTH]`, so it can be passed to round functions
+/// by reference without copying, and sponges keep it in place for their
+/// whole life.
+#[repr(C, align(64))]
+pub struct State<E: Engine, const WIDTH: usize>([E::Fr; WIDTH]);
+
+impl<E: Engine, const WIDTH: usize> State<E, WIDTH> {
+    /// All zero state.
+    pub fn new() -> Self {
+        Self([E::Fr::zero(); WIDTH])
+    }
+
+    pub fn from_array(elements: [E::Fr; WIDTH]) -> Self {
+        Self(elements)
+    }
+
+    pub fn into_array(self) -> [E::Fr; WIDTH] {
+        self.0
+    }
+
+    pub fn as_array(&self) -> &[E::Fr; WIDTH] {
+        &self.0
+    }
+
+    pub fn as_array_mut(&mut self) -> &mut [E::Fr; WIDTH] {
+        &mut self.0
+    }
+
+    /// First `RATE` elements, which input is absorbed into.
+    pub fn rate<const RATE: usize>(&self) -> &[E::Fr] {
+        &self.0[..RATE]
+    }
+
+    /// Elements after the first `RATE` ones.
+    pub fn capacity<const RATE: usize>(&self) -> &[E::Fr] {
+        &self.0[RATE..]
+    }
+
+    /// Sets every element to zero.
+    pub fn reset(&mut self) {
+        self.0.iter_mut().for_each(|el| *el = E::Fr::zero());
+    }
+
+    /// Applies the round function of `params` in place.
+    pub fn permute<P: HashParams<E, RATE, WIDTH>, const RATE: usize>(&mut self, params: &P) {
+        generic_round_function(params, &mut self.0)
+    }
+}
+
+impl<E: Engine, const WIDTH: usize> Default for State<E, WIDTH> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<E: Engine, const WIDTH: usize> Clone for State<E, WIDTH> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<E: Engine, const WIDTH: usize> Copy for State<E, WIDTH> {}
+
+impl<E: Engine, const WIDTH: usize> PartialEq for State<E, WIDTH> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<E: Engine, const WIDTH: usize> Eq for State<E, WIDTH> {}
+
+impl<E: Engine, const WIDTH: usize> core::fmt::Debug for State<E, WIDTH> {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("State").field(&self.0).finish()
+    }
+}
+
+impl<E: Engine, const WIDTH: usize> core::ops::Deref for State<E, WIDTH> {
+    type Target = [E::Fr; WIDTH];
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<E: Engine, const WIDTH: usize> core::ops::DerefMut for State<E, WIDTH> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<E: Engine, const WIDTH: usize> From<[E::Fr; WIDTH]> for State<E, WIDTH> {
+    fn from(elements: [E::Fr; WIDTH]) -> Self {
+        Self(elements)
+    }
+}
+
+impl<E: Engine, const WIDTH: usize> AsRef<[E::Fr]> for State<E, WIDTH> {
+    fn as_ref(&self) -> &[E::Fr] {
+        &self.0
+    }
+}
+
+impl<E: Engine, const WIDTH: usize> AsMut<[E::Fr]> for State<E, WIDTH> {
+    fn as_mut(&mut self) -> &mut [E::Fr] {
+        &mut self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::init_rng;
+    use crate::PoseidonParams;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_state_layout() {
+        assert_eq!(core::mem::align_of::<State<Bn256, 3>>(), STATE_ALIGNMENT);
+        assert_eq!(core::mem::size_of::<State<Bn256, 3>>(), 2 * STATE_ALIGNMENT);
+        assert_eq!(core::mem::size_of::<State<Bn256, 12>>(), 6 * STATE_ALIGNMENT);
+
+        let states = [State::<Bn256, 3>::new(); 4];
+        for state in states.iter() {
+            assert_eq!(state.as_array().as_ptr() as usize % STATE_ALIGNMENT, 0);
+        }
+    }
+
+    #[test]
+    fn test_state_permutation() {
+        let rng = &mut init_rng();
+        let elements: [Fr; 12] = core::array::from_fn(|_| Fr::rand(rng));
+
+        let params = PoseidonParams::<Bn256, 11, 12>::default();
+        let mut expected = elements;
+        generic_round_function(&params, &mut expected);
+        let mut state = State::<Bn256, 12>::from(elements);
+        state.permute(&params);
+        assert_eq!(state.into_array(), expected);
+
+        let mut expected = elements;
+        generic_round_function(&params, &mut expected);
+        // round functions take the state in place through deref
+        let mut state = State::<Bn256, 12>::from_array(elements);
+        generic_round_function(&params, &mut *state);
+        assert_eq!(*state, expected);
+        assert_eq!(state.rate::<11>(), &expected[..11]);
+        assert_eq!(state.capacity::<11>(), &expected[11..]);
+
+        state.reset();
+        assert_eq!(state, State::default());
+    }
+}
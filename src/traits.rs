@@ -1,11 +1,18 @@
+use alloc::vec::Vec;
 use franklin_crypto::bellman::Engine;
+use franklin_crypto::bellman::plonk::better_better_cs::cs::{ConstraintSystem, PlonkConstraintSystemParams};
 
-#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
 pub enum HashFamily {
     Rescue,
     Poseidon,
     RescuePrime,
-    Poseidon2
+    Poseidon2,
+    Griffin,
+    /// Goldilocks only, see `monolith` module. Has no `HashParams`
+    /// implementation, so never returned by big field params.
+    Monolith,
+    Anemoi,
 }
 
 #[derive(Copy, Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -15,6 +22,28 @@ pub enum CustomGate {
     None,
 }
 
+impl CustomGate {
+    /// Returns the best custom gate supported by the constraint system.
+    /// Width-3 quintic gate is never auto-selected since it requires a dedicated
+    /// gate to be registered in the constraint system.
+    pub fn select_for<E: Engine, CS: ConstraintSystem<E>>() -> Self {
+        if CS::Params::HAS_CUSTOM_GATES && CS::Params::STATE_WIDTH >= 4 {
+            CustomGate::QuinticWidth4
+        } else {
+            CustomGate::None
+        }
+    }
+
+    /// Checks whether constraint system is able to use this gate.
+    pub fn is_supported_by<E: Engine, CS: ConstraintSystem<E>>(&self) -> bool {
+        match self {
+            CustomGate::None => true,
+            CustomGate::QuinticWidth4 => CS::Params::HAS_CUSTOM_GATES && CS::Params::STATE_WIDTH >= 4,
+            CustomGate::QuinticWidth3 => CS::Params::HAS_CUSTOM_GATES && CS::Params::STATE_WIDTH >= 3,
+        }
+    }
+}
+
 #[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
 pub enum Step {
     Double {
@@ -42,8 +71,8 @@ impl From<addchain::Step> for Step {
     }
 }
 
-impl std::fmt::Debug for Sbox {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+impl core::fmt::Debug for Sbox {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         match self {
             Self::Alpha(alpha) => write!(f, "sbox x^{}", alpha),
             Self::AlphaInverse(vec, alpha) => write!(f, "inverse sbox [u64; {}] for x^{}", vec.len(), alpha),
@@ -52,6 +81,25 @@ impl std::fmt::Debug for Sbox {
     }
 }
 
+/// Hook which is called by native round functions after each round.
+/// Allows to observe or transform intermediate state, e.g. for masked
+/// implementations or instrumentation. Default methods are no-op so unused
+/// middleware is optimized away after monomorphization.
+pub trait RoundFunctionMiddleware<E: Engine, const WIDTH: usize> {
+    #[inline(always)]
+    fn after_round(&mut self, _round: usize, _state: &mut [E::Fr; WIDTH]) {}
+    /// Called with outputs of each inverse sbox application, these are the
+    /// values circuits allocate as witnesses of the inverse sbox.
+    #[inline(always)]
+    fn after_inverse_sbox(&mut self, _round: usize, _outputs: &[E::Fr]) {}
+}
+
+/// Middleware that does nothing.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct NoopMiddleware;
+
+impl<E: Engine, const WIDTH: usize> RoundFunctionMiddleware<E, WIDTH> for NoopMiddleware {}
+
 pub trait HashParams<E: Engine, const RATE: usize, const WIDTH: usize>:
     Clone + Send + Sync + serde::Serialize + serde::de::DeserializeOwned
 {
@@ -70,6 +118,37 @@ pub trait HashParams<E: Engine, const RATE: usize, const WIDTH: usize>:
     fn optimized_mds_matrixes(&self) -> (&[[E::Fr; WIDTH]; WIDTH], &[[[E::Fr; WIDTH]; WIDTH]]);
     fn custom_gate(&self) -> CustomGate;
     fn use_custom_gate(&mut self, gate: CustomGate);
+    /// Number of state elements input is absorbed into per permutation.
+    fn rate(&self) -> usize {
+        RATE
+    }
+    /// Number of state elements input is never absorbed into.
+    fn capacity(&self) -> usize {
+        WIDTH - RATE
+    }
+    /// Full rounds multiply by circ(2, 1, 1) instead of `mds_matrix`.
+    fn uses_circular_full_rounds_mds(&self) -> bool {
+        false
+    }
+    /// Canonical digest of the whole parameter set. Two parameter sets with
+    /// equal fingerprints define the same permutation.
+    ///
+    /// By default covers round numbers, round constants, MDS matrix and sbox,
+    /// implementors with more knobs affecting the permutation must override it.
+    fn fingerprint(&self) -> [u8; 32] {
+        let mut fingerprint = crate::common::fingerprint::Fingerprint::new(self.hash_family(), RATE, WIDTH);
+        let full_rounds = self.number_of_full_rounds();
+        let partial_rounds = self.number_of_partial_rounds();
+        fingerprint.update_usize(full_rounds);
+        fingerprint.update_usize(partial_rounds);
+        for round in 0..full_rounds + partial_rounds {
+            fingerprint.update_frs::<E>(self.constants_of_round(round));
+        }
+        fingerprint.update_matrix::<E, WIDTH>(self.mds_matrix());
+        fingerprint.update_sbox(self.alpha());
+
+        fingerprint.finalize()
+    }
     fn specialized_affine_transformation_for_round(&self, _state: &mut [E::Fr; WIDTH], _round_constants: &[E::Fr; WIDTH]) {
         unimplemented!("not implemented by default");
     }
@@ -77,4 +156,12 @@ pub trait HashParams<E: Engine, const RATE: usize, const WIDTH: usize>:
     fn try_to_poseidon2_params(&self) -> Option<&crate::poseidon2::Poseidon2Params<E, RATE, WIDTH>> {
         None
     }
+
+    fn try_to_griffin_params(&self) -> Option<&crate::griffin::GriffinParams<E, RATE, WIDTH>> {
+        None
+    }
+
+    fn try_to_anemoi_params(&self) -> Option<&crate::anemoi::AnemoiParams<E, RATE, WIDTH>> {
+        None
+    }
 }
@@ -0,0 +1,352 @@
+//! Fixed absorption order of our PLONK protocol over field element transcripts.
+//!
+//! Prover and verifier should drive the transcript through the following
+//! sequence, each item being a method of `TranscriptProtocol`:
+//!
+//! 1. `commit_public_inputs`
+//! 2. `commit_proof_element` for each witness polynomial commitment
+//! 3. `challenge_beta`, `challenge_gamma`
+//! 4. `commit_proof_element` for grand product commitment
+//! 5. `challenge_alpha`
+//! 6. `commit_proof_element` for each quotient chunk commitment
+//! 7. `challenge_z`
+//! 8. `commit_evaluation` for each evaluation in proof order
+//! 9. `challenge_v`
+//! 10. `commit_proof_element` for each opening proof
+//! 11. `challenge_u`
+//!
+//! Every item absorbs its own label first, so a caller deviating from the
+//! sequence gets different challenges instead of silently matching ones.
+use crate::common::utils::fe_from_u64;
+use crate::sponge::GenericSponge;
+use crate::traits::HashParams;
+use franklin_crypto::bellman::{Engine, PrimeField};
+
+/// Labels absorbed before each item of the protocol.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u64)]
+pub enum ProtocolLabel {
+    PublicInputs = 1,
+    ProofElement = 2,
+    Evaluation = 3,
+    Beta = 4,
+    Gamma = 5,
+    Alpha = 6,
+    Z = 7,
+    V = 8,
+    U = 9,
+}
+
+impl ProtocolLabel {
+    pub(crate) fn as_fe<E: Engine>(&self) -> E::Fr {
+        let mut repr = <E::Fr as PrimeField>::Repr::default();
+        repr.as_mut()[0] = u64::from_be_bytes(*b"PlonkTr\0") | *self as u64;
+
+        E::Fr::from_repr(repr).expect("a field element")
+    }
+}
+
+/// Typed methods of the protocol on top of a transcript which absorbs and
+/// squeezes field elements. Only `commit_field_element` and `get_challenge`
+/// are required, the rest define the protocol and shouldn't be overridden.
+pub trait TranscriptProtocol<E: Engine> {
+    fn commit_field_element(&mut self, element: &E::Fr);
+
+    fn get_challenge(&mut self) -> E::Fr;
+
+    /// Challenge truncated to its lower 128 bits for protocols using short
+    /// scalars. Full challenge is uniform over the field, so its lower 128
+    /// bits are uniform up to statistical distance `2^128 / modulus`, which
+    /// is about `2^-126` for BN254. A check that holds for at most `d`
+    /// values of the challenge has soundness error `d / 2^128` instead of
+    /// `d / modulus`, so short challenges should be used only where 128 bits
+    /// of security are enough.
+    fn get_challenge_128(&mut self) -> E::Fr {
+        truncate_challenge_128::<E>(&self.get_challenge())
+    }
+
+    /// Public inputs are prefixed by their number.
+    fn commit_public_inputs(&mut self, inputs: &[E::Fr]) {
+        self.commit_field_element(&ProtocolLabel::PublicInputs.as_fe::<E>());
+        self.commit_field_element(&fe_from_u64::<E>(inputs.len() as u64));
+        for input in inputs.iter() {
+            self.commit_field_element(input);
+        }
+    }
+
+    /// Commits a proof element, e.g. a point, encoded as field elements.
+    /// Encoding is prefixed by its length.
+    fn commit_proof_element(&mut self, encoding: &[E::Fr]) {
+        self.commit_field_element(&ProtocolLabel::ProofElement.as_fe::<E>());
+        self.commit_field_element(&fe_from_u64::<E>(encoding.len() as u64));
+        for el in encoding.iter() {
+            self.commit_field_element(el);
+        }
+    }
+
+    fn commit_evaluation(&mut self, evaluation: &E::Fr) {
+        self.commit_field_element(&ProtocolLabel::Evaluation.as_fe::<E>());
+        self.commit_field_element(evaluation);
+    }
+
+    fn challenge_beta(&mut self) -> E::Fr {
+        labeled_challenge(self, ProtocolLabel::Beta)
+    }
+
+    fn challenge_gamma(&mut self) -> E::Fr {
+        labeled_challenge(self, ProtocolLabel::Gamma)
+    }
+
+    fn challenge_alpha(&mut self) -> E::Fr {
+        labeled_challenge(self, ProtocolLabel::Alpha)
+    }
+
+    fn challenge_z(&mut self) -> E::Fr {
+        labeled_challenge(self, ProtocolLabel::Z)
+    }
+
+    fn challenge_v(&mut self) -> E::Fr {
+        labeled_challenge(self, ProtocolLabel::V)
+    }
+
+    fn challenge_u(&mut self) -> E::Fr {
+        labeled_challenge(self, ProtocolLabel::U)
+    }
+}
+
+fn labeled_challenge<E: Engine, T: TranscriptProtocol<E> + ?Sized>(
+    transcript: &mut T,
+    label: ProtocolLabel,
+) -> E::Fr {
+    transcript.commit_field_element(&label.as_fe::<E>());
+
+    transcript.get_challenge()
+}
+
+pub(crate) fn truncate_challenge_128<E: Engine>(challenge: &E::Fr) -> E::Fr {
+    let challenge_repr = challenge.into_repr();
+    let mut repr = <E::Fr as PrimeField>::Repr::default();
+    repr.as_mut()[..2].copy_from_slice(&challenge_repr.as_ref()[..2]);
+
+    E::Fr::from_repr(repr).expect("a field element")
+}
+
+/// Transcript over the variable length sponge. Pending elements are padded
+/// before each challenge.
+#[derive(Clone)]
+pub struct SpongeTranscript<
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    const RATE: usize,
+    const WIDTH: usize,
+> {
+    sponge: GenericSponge<E, RATE, WIDTH>,
+    params: P,
+}
+
+impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>
+    SpongeTranscript<E, P, RATE, WIDTH>
+{
+    pub fn new(params: P) -> Self {
+        Self {
+            sponge: GenericSponge::new(),
+            params,
+        }
+    }
+}
+
+impl<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>
+    TranscriptProtocol<E> for SpongeTranscript<E, P, RATE, WIDTH>
+{
+    fn commit_field_element(&mut self, element: &E::Fr) {
+        self.sponge.absorb(*element, &self.params);
+    }
+
+    fn get_challenge(&mut self) -> E::Fr {
+        self.sponge.pad_if_necessary();
+
+        self.sponge
+            .squeeze_or_permute(&self.params)
+            .expect("buffer is padded")
+    }
+}
+
+/// Transcript over Rescue with state-width=3 and rate=2.
+pub type RescueTranscript<E> = SpongeTranscript<E, crate::RescueParams<E, 2, 3>, 2, 3>;
+
+/// Transcript over Poseidon with state-width=3 and rate=2.
+pub type PoseidonTranscript<E> = SpongeTranscript<E, crate::PoseidonParams<E, 2, 3>, 2, 3>;
+
+// Kept apart so that method names of bellman traits don't clash with
+// `TranscriptProtocol` where the latter is in scope.
+#[cfg(feature = "std")]
+mod bellman_transcript {
+    use super::{SpongeTranscript, TranscriptProtocol};
+    use crate::common::utils::fe_from_u64;
+    use crate::traits::HashParams;
+    use franklin_crypto::bellman::plonk::commitments::transcript::{Prng, Transcript};
+    use franklin_crypto::bellman::{Engine, PrimeField, PrimeFieldRepr};
+
+    /// Transcript for `better_better_cs` provers and verifiers. Default params
+    /// are used by `new`, custom ones are passed as initialization parameters.
+    impl<
+            E: Engine,
+            P: HashParams<E, RATE, WIDTH> + Default + 'static,
+            const RATE: usize,
+            const WIDTH: usize,
+        > Prng<E::Fr> for SpongeTranscript<E, P, RATE, WIDTH>
+    {
+        type Input = E::Fr;
+        type InitializationParameters = P;
+
+        fn new() -> Self {
+            Self::new(P::default())
+        }
+
+        fn new_from_params(params: Self::InitializationParameters) -> Self {
+            Self::new(params)
+        }
+
+        fn commit_input(&mut self, input: &Self::Input) {
+            TranscriptProtocol::commit_field_element(self, input)
+        }
+
+        fn get_challenge(&mut self) -> E::Fr {
+            TranscriptProtocol::get_challenge(self)
+        }
+    }
+
+    impl<
+            E: Engine,
+            P: HashParams<E, RATE, WIDTH> + Default + 'static,
+            const RATE: usize,
+            const WIDTH: usize,
+        > Transcript<E::Fr> for SpongeTranscript<E, P, RATE, WIDTH>
+    {
+        /// Bytes are prefixed by their length and packed by `bytes_to_field_elements`.
+        fn commit_bytes(&mut self, bytes: &[u8]) {
+            TranscriptProtocol::commit_field_element(self, &fe_from_u64::<E>(bytes.len() as u64));
+            for el in crate::encoding::bytes_to_field_elements::<E>(bytes).iter() {
+                TranscriptProtocol::commit_field_element(self, el);
+            }
+        }
+
+        fn commit_field_element(&mut self, element: &E::Fr) {
+            TranscriptProtocol::commit_field_element(self, element)
+        }
+
+        /// Big endian representation of a field challenge.
+        fn get_challenge_bytes(&mut self) -> Vec<u8> {
+            let challenge = TranscriptProtocol::get_challenge(self);
+            repr_to_be_bytes::<E::Fr>(&challenge)
+        }
+
+        /// Elements of other fields are committed as big endian bytes.
+        fn commit_fe<FF: PrimeField>(&mut self, element: &FF) {
+            self.commit_bytes(&repr_to_be_bytes(element));
+        }
+    }
+
+    fn repr_to_be_bytes<F: PrimeField>(element: &F) -> Vec<u8> {
+        let mut bytes = vec![];
+        element
+            .into_repr()
+            .write_be(&mut bytes)
+            .expect("write into vector");
+
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::tests::init_rng;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_transcript_protocol_order() {
+        let rng = &mut init_rng();
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        let inputs: Vec<_> = (0..2).map(|_| Fr::rand(rng)).collect();
+        let point: Vec<_> = (0..4).map(|_| Fr::rand(rng)).collect();
+
+        let run = |swap_challenges: bool| {
+            let mut transcript = SpongeTranscript::<Bn256, _, 2, 3>::new(params.clone());
+            transcript.commit_public_inputs(&inputs);
+            transcript.commit_proof_element(&point);
+            let (beta, gamma) = if swap_challenges {
+                let gamma = transcript.challenge_gamma();
+                (transcript.challenge_beta(), gamma)
+            } else {
+                (transcript.challenge_beta(), transcript.challenge_gamma())
+            };
+            transcript.commit_evaluation(&inputs[0]);
+            (beta, gamma, transcript.challenge_v())
+        };
+
+        let (beta, gamma, v) = run(false);
+        assert_eq!((beta, gamma, v), run(false));
+        assert_ne!(beta, gamma);
+
+        let (swapped_beta, swapped_gamma, swapped_v) = run(true);
+        assert_ne!(beta, swapped_beta);
+        assert_ne!(gamma, swapped_gamma);
+        assert_ne!(v, swapped_v);
+    }
+
+    #[test]
+    fn test_bellman_transcript() {
+        use franklin_crypto::bellman::plonk::commitments::transcript::{Prng, Transcript};
+
+        let rng = &mut init_rng();
+        let element = Fr::rand(rng);
+
+        let mut transcript = <RescueTranscript<Bn256> as Prng<Fr>>::new();
+        let mut expected = RescueTranscript::<Bn256>::new(crate::RescueParams::default());
+        Transcript::commit_field_element(&mut transcript, &element);
+        TranscriptProtocol::commit_field_element(&mut expected, &element);
+        assert_eq!(
+            Prng::get_challenge(&mut transcript),
+            TranscriptProtocol::get_challenge(&mut expected)
+        );
+
+        // byte commitments are length prefixed
+        let mut first = <PoseidonTranscript<Bn256> as Prng<Fr>>::new_from_params(
+            crate::PoseidonParams::default(),
+        );
+        let mut second = first.clone();
+        first.commit_bytes(&[1, 2, 3]);
+        second.commit_bytes(&[1, 2]);
+        second.commit_bytes(&[3]);
+        let challenge = first.get_challenge_bytes();
+        assert_eq!(challenge.len(), 32);
+        assert_ne!(challenge, second.get_challenge_bytes());
+    }
+
+    #[test]
+    fn test_get_challenge_128() {
+        let rng = &mut init_rng();
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        let element = Fr::rand(rng);
+
+        let mut transcript = SpongeTranscript::<Bn256, _, 2, 3>::new(params.clone());
+        let mut expected_transcript = transcript.clone();
+        transcript.commit_field_element(&element);
+        expected_transcript.commit_field_element(&element);
+
+        let challenge = transcript.get_challenge_128();
+        let full_challenge = expected_transcript.get_challenge();
+        let repr = challenge.into_repr();
+        assert!(repr.as_ref()[2..].iter().all(|limb| *limb == 0));
+        assert_eq!(repr.as_ref()[..2], full_challenge.into_repr().as_ref()[..2]);
+
+        // transcripts stay in sync after a short challenge
+        assert_eq!(
+            transcript.get_challenge(),
+            expected_transcript.get_challenge()
+        );
+    }
+}
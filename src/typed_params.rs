@@ -0,0 +1,102 @@
+use crate::traits::HashParams;
+use alloc::format;
+use franklin_crypto::bellman::Engine;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Marker which ties serialized params to `RATE` and `WIDTH`. It is
+/// serialized as `(RATE, WIDTH)` and deserialization fails if stored values
+/// don't match the instantiation params are loaded into.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParamsFor<const RATE: usize, const WIDTH: usize>;
+
+impl<const RATE: usize, const WIDTH: usize> Serialize for ParamsFor<RATE, WIDTH> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (RATE as u64, WIDTH as u64).serialize(serializer)
+    }
+}
+
+impl<'de, const RATE: usize, const WIDTH: usize> Deserialize<'de> for ParamsFor<RATE, WIDTH> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let (rate, width) = <(u64, u64)>::deserialize(deserializer)?;
+        if rate != RATE as u64 || width != WIDTH as u64 {
+            return Err(D::Error::custom(format!(
+                "params are for rate={} width={}, but loaded into rate={} width={}",
+                rate, width, RATE, WIDTH
+            )));
+        }
+
+        Ok(ParamsFor)
+    }
+}
+
+/// Params together with their `ParamsFor` marker. Can only be constructed
+/// from params of the same `RATE` and `WIDTH`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TypedParams<P, const RATE: usize, const WIDTH: usize> {
+    params_for: ParamsFor<RATE, WIDTH>,
+    params: P,
+}
+
+impl<P, const RATE: usize, const WIDTH: usize> TypedParams<P, RATE, WIDTH> {
+    pub fn new<E: Engine>(params: P) -> Self
+    where
+        P: HashParams<E, RATE, WIDTH>,
+    {
+        Self {
+            params_for: ParamsFor,
+            params,
+        }
+    }
+
+    pub fn params(&self) -> &P {
+        &self.params
+    }
+
+    pub fn into_params(self) -> P {
+        self.params
+    }
+}
+
+/// Serializes params with their `ParamsFor` marker.
+pub fn store_params<E, P, S, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    serializer: S,
+) -> Result<S::Ok, S::Error>
+where
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    S: Serializer,
+{
+    (ParamsFor::<RATE, WIDTH>, params).serialize(serializer)
+}
+
+/// Deserializes params stored by `store_params`, errors if they were stored
+/// for another `RATE` or `WIDTH`.
+pub fn load_params<'de, E, P, D, const RATE: usize, const WIDTH: usize>(
+    deserializer: D,
+) -> Result<P, D::Error>
+where
+    E: Engine,
+    P: HashParams<E, RATE, WIDTH>,
+    D: Deserializer<'de>,
+{
+    let (_, params) = <(ParamsFor<RATE, WIDTH>, P)>::deserialize(deserializer)?;
+
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::de::value::{Error as ValueError, SeqDeserializer};
+
+    #[test]
+    fn test_params_for_marker() {
+        let deserializer = SeqDeserializer::<_, ValueError>::new(vec![2u64, 3u64].into_iter());
+        assert!(ParamsFor::<2, 3>::deserialize(deserializer).is_ok());
+
+        let deserializer = SeqDeserializer::<_, ValueError>::new(vec![2u64, 3u64].into_iter());
+        assert!(ParamsFor::<4, 5>::deserialize(deserializer).is_err());
+    }
+}
@@ -0,0 +1,126 @@
+use crate::common::utils::fe_from_u64;
+use crate::merkle::{hash_leaf, verify_path, MerkleLayers};
+use crate::traits::HashParams;
+use alloc::vec::Vec;
+use franklin_crypto::bellman::Engine;
+
+/// Merkle commitment to a vector of field elements.
+/// Each leaf is hashed together with its index so openings can not be
+/// replayed at a different position, which is what FRI-style query
+/// answering needs. Length of the committed vector must be a power of two.
+#[derive(Clone, Debug)]
+pub struct VectorCommitment<E: Engine, const RATE: usize, const WIDTH: usize> {
+    tree: MerkleLayers<E>,
+    values: Vec<E::Fr>,
+}
+
+/// Opening of a single position of a committed vector.
+#[derive(Clone, Debug)]
+pub struct VectorOpening<E: Engine> {
+    pub index: usize,
+    pub value: E::Fr,
+    pub path: Vec<E::Fr>,
+}
+
+impl<E: Engine, const RATE: usize, const WIDTH: usize> VectorCommitment<E, RATE, WIDTH> {
+    pub fn new<P: HashParams<E, RATE, WIDTH>>(values: &[E::Fr], params: &P) -> Self {
+        assert!(values.len().is_power_of_two(), "vector length should be a power of two");
+
+        let leaves = values
+            .iter()
+            .enumerate()
+            .map(|(index, value)| hash_indexed_value::<E, P, RATE, WIDTH>(index, value, params))
+            .collect();
+
+        Self {
+            tree: MerkleLayers::new(leaves, params),
+            values: values.to_vec(),
+        }
+    }
+
+    pub fn root(&self) -> E::Fr {
+        self.tree.root()
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn open(&self, index: usize) -> VectorOpening<E> {
+        VectorOpening {
+            index,
+            path: self.tree.path(index),
+            value: self.values[index],
+        }
+    }
+
+    /// Produces openings for a set of query indexes in the given order.
+    pub fn open_many(&self, indexes: &[usize]) -> Vec<VectorOpening<E>> {
+        indexes.iter().map(|index| self.open(*index)).collect()
+    }
+}
+
+impl<E: Engine> VectorOpening<E> {
+    pub fn verify<P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+        &self,
+        root: &E::Fr,
+        params: &P,
+    ) -> bool {
+        let leaf_hash = hash_indexed_value::<E, P, RATE, WIDTH>(self.index, &self.value, params);
+
+        verify_path::<E, P, RATE, WIDTH>(root, leaf_hash, self.index, &self.path, params)
+    }
+}
+
+/// Verifies a batch of openings against the same root.
+pub fn verify_openings<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    root: &E::Fr,
+    openings: &[VectorOpening<E>],
+    params: &P,
+) -> bool {
+    openings
+        .iter()
+        .all(|opening| opening.verify::<P, RATE, WIDTH>(root, params))
+}
+
+// Leaf is hashed as (index, value) so that index acts as a domain separator.
+fn hash_indexed_value<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    index: usize,
+    value: &E::Fr,
+    params: &P,
+) -> E::Fr {
+    hash_leaf::<E, P, RATE, WIDTH>(&[fe_from_u64::<E>(index as u64), *value], params)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::poseidon2::Poseidon2Params;
+    use crate::tests::init_rng;
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    #[test]
+    fn test_vector_commitment_openings() {
+        let rng = &mut init_rng();
+        let params = Poseidon2Params::<Bn256, 2, 3>::default();
+        let values: Vec<_> = (0..16).map(|_| Fr::rand(rng)).collect();
+
+        let commitment = VectorCommitment::<Bn256, 2, 3>::new(&values, &params);
+        let root = commitment.root();
+        assert_eq!(commitment.len(), 16);
+        assert!(!commitment.is_empty());
+
+        let openings = commitment.open_many(&[0, 5, 15]);
+        assert!(verify_openings(&root, &openings, &params));
+
+        // opening is bound to its index
+        let mut moved = openings[1].clone();
+        moved.index = 4;
+        assert!(!moved.verify(&root, &params));
+    }
+}
@@ -0,0 +1,103 @@
+use crate::sponge::{generic_round_function_with_middleware, GenericSponge, OutputConvention};
+use crate::traits::{HashParams, RoundFunctionMiddleware};
+use alloc::{vec, vec::Vec};
+use franklin_crypto::bellman::Engine;
+
+/// Natively computed outputs of inverse sboxes of a fixed length hash,
+/// grouped by permutation. Inverse sbox is an exponentiation by a full size
+/// exponent while forward sbox takes few multiplications, so only the former
+/// is worth computing ahead of synthesis. Hashes of Poseidon families have no
+/// inverse sbox and their witness is empty for each permutation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HashWitness<E: Engine> {
+    permutations: Vec<Vec<E::Fr>>,
+    output: Vec<E::Fr>,
+}
+
+impl<E: Engine> HashWitness<E> {
+    pub fn num_permutations(&self) -> usize {
+        self.permutations.len()
+    }
+
+    /// Inverse sbox outputs of `index`-th permutation in the order circuit
+    /// allocates them.
+    pub fn permutation(&self, index: usize) -> &[E::Fr] {
+        &self.permutations[index]
+    }
+
+    /// Output of the hash, same as of `GenericSponge::hash`.
+    pub fn output(&self) -> &[E::Fr] {
+        &self.output
+    }
+}
+
+struct InverseSboxRecorder<'a, E: Engine> {
+    outputs: &'a mut Vec<E::Fr>,
+}
+
+impl<'a, E: Engine, const WIDTH: usize> RoundFunctionMiddleware<E, WIDTH> for InverseSboxRecorder<'a, E> {
+    fn after_inverse_sbox(&mut self, _round: usize, outputs: &[E::Fr]) {
+        self.outputs.extend_from_slice(outputs);
+    }
+}
+
+/// Computes hash of `input` with default domain strategy and records all
+/// inverse sbox outputs, so `circuit_generic_hash_with_witness` doesn't
+/// recompute them while allocating witnesses.
+pub fn precompute_witnesses<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    input: &[E::Fr],
+) -> HashWitness<E> {
+    let mut permutations = vec![];
+    let output = GenericSponge::<E, RATE, WIDTH>::hash_with_permutation(
+        input,
+        None,
+        OutputConvention::Rate,
+        |state| {
+            let mut outputs = vec![];
+            generic_round_function_with_middleware(
+                params,
+                state,
+                &mut InverseSboxRecorder { outputs: &mut outputs },
+            );
+            permutations.push(outputs);
+        },
+    );
+
+    HashWitness {
+        permutations,
+        output: output.to_vec(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::init_rng;
+    use crate::{GriffinParams, PoseidonParams, RescueParams, RescuePrimeParams};
+    use franklin_crypto::bellman::pairing::bn256::{Bn256, Fr};
+    use rand::Rand;
+
+    fn check_witness<P: HashParams<Bn256, 2, 3>>(params: &P, sboxes_per_permutation: usize) {
+        let rng = &mut init_rng();
+        let input: Vec<_> = (0..5).map(|_| Fr::rand(rng)).collect();
+
+        let witness = precompute_witnesses(params, &input);
+        assert_eq!(witness.output(), &GenericSponge::hash(&input, params, None)[..]);
+        assert_eq!(witness.num_permutations(), 3);
+        for i in 0..witness.num_permutations() {
+            assert_eq!(witness.permutation(i).len(), sboxes_per_permutation);
+        }
+    }
+
+    #[test]
+    fn test_precompute_witnesses() {
+        let params = RescueParams::<Bn256, 2, 3>::default();
+        check_witness(&params, params.number_of_full_rounds() * 3);
+        let params = RescuePrimeParams::<Bn256, 2, 3>::default();
+        check_witness(&params, (params.number_of_full_rounds() - 1) * 3);
+        let params = GriffinParams::<Bn256, 2, 3>::default();
+        check_witness(&params, params.number_of_full_rounds());
+        check_witness(&PoseidonParams::<Bn256, 2, 3>::default(), 0);
+    }
+}
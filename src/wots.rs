@@ -0,0 +1,32 @@
+//! Winternitz chain helpers for hash-based one-time signatures.
+//!
+//! Each step of a chain is a fixed length hash of a single element, so
+//! `chain(params, chain(params, x, a), b) == chain(params, x, a + b)`.
+use crate::common::utils::ct_eq_digest;
+use crate::sponge::GenericSponge;
+use crate::traits::HashParams;
+use franklin_crypto::bellman::Engine;
+
+/// Applies `steps` chain steps starting from `start`.
+pub fn chain<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    start: &E::Fr,
+    steps: usize,
+) -> E::Fr {
+    let mut current = *start;
+    for _ in 0..steps {
+        current = GenericSponge::<E, RATE, WIDTH>::hash(&[current], params, None)[0];
+    }
+
+    current
+}
+
+/// Checks that `end` is reached from `value` after exactly `steps` steps.
+pub fn verify_chain<E: Engine, P: HashParams<E, RATE, WIDTH>, const RATE: usize, const WIDTH: usize>(
+    params: &P,
+    value: &E::Fr,
+    steps: usize,
+    end: &E::Fr,
+) -> bool {
+    ct_eq_digest::<E, 1>(&[chain::<E, P, RATE, WIDTH>(params, value, steps)], &[*end])
+}